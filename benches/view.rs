@@ -0,0 +1,89 @@
+// Compares ClassFileView against ClassFileLoader on the same scan: the name of every class and the
+// total bytecode size of its methods.
+//
+//   cargo bench --bench view -- [directory or class file...]
+//
+// Defaults to the class files in the repository root.
+
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{env, fs};
+
+use jvmmy::class::loading::ClassFileLoader;
+use jvmmy::class::view::ClassFileView;
+use jvmmy::attr::AttributeInfo;
+
+fn main() {
+    let mut paths: Vec<PathBuf> = env::args().skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .map(PathBuf::from)
+        .collect();
+    if paths.is_empty() {
+        paths = vec![PathBuf::from("HelloWorld.class"), PathBuf::from("OnePlusOne.class")];
+    }
+    let mut files = Vec::new();
+    for path in &paths {
+        collect_class_files(path, &mut files);
+    }
+    let classes: Vec<Vec<u8>> = files.iter().map(|file| fs::read(file).expect("readable class file")).collect();
+    let total_bytes: usize = classes.iter().map(Vec::len).sum();
+    println!("{} classes, {} bytes", classes.len(), total_bytes);
+
+    let loader = bench("ClassFileLoader", || {
+        for bytes in &classes {
+            let class_file = ClassFileLoader::new(bytes).load().expect("valid class file");
+            let name = class_file.find_class_name(class_file.this_class).expect("valid this_class");
+            let code_length: usize = class_file.methods.iter()
+                .flat_map(|method| &method.attributes)
+                .map(|attribute| match &attribute.info {
+                    AttributeInfo::Code { code, .. } => code.len(),
+                    _ => 0,
+                })
+                .sum();
+            black_box((name, code_length));
+        }
+    });
+    let view = bench("ClassFileView", || {
+        for bytes in &classes {
+            let view = ClassFileView::parse(bytes).expect("valid class file");
+            let name = view.this_class_name().expect("valid this_class");
+            let mut code_length = 0;
+            for method in &view.methods {
+                if let Some(code) = view.code(method).expect("valid Code attribute") {
+                    code_length += code.code.len();
+                }
+            }
+            black_box((name, code_length));
+        }
+    });
+    println!("ClassFileView is {:.1}x faster", loader.as_secs_f64() / view.as_secs_f64());
+}
+
+// Runs f until at least a second has passed and reports the average time per run
+fn bench(name: &str, mut f: impl FnMut()) -> Duration {
+    f();
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        f();
+        runs += 1;
+    }
+    let average = start.elapsed() / runs;
+    println!("{:>16}: {:?} per run ({} runs)", name, average, runs);
+    average
+}
+
+fn collect_class_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        let mut entries: Vec<PathBuf> = fs::read_dir(path).expect("readable directory")
+            .map(|entry| entry.expect("readable directory entry").path())
+            .collect();
+        entries.sort();
+        for entry in entries {
+            collect_class_files(&entry, files);
+        }
+    } else if path.extension().is_some_and(|extension| extension == "class") {
+        files.push(path.to_path_buf());
+    }
+}
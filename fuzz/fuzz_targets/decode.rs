@@ -0,0 +1,28 @@
+// Decodes arbitrary bytes as a code array. Whatever decodes has to encode again, and decode to the same
+// instructions at the same pcs.
+//
+//   cargo +nightly fuzz run decode
+//
+// corpus/decode starts out with the code arrays of the methods in the class files in the repository root.
+
+#![no_main]
+
+use jvmmy::bytecode::{decode, encode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let instructions = match decode(data) {
+        Ok(instructions) => instructions,
+        Err(_) => return,
+    };
+    // Each instruction ends where the next one starts, the last one at the end of the code
+    let ends = instructions.iter().skip(1).map(|&(pc, _)| pc).chain([data.len() as u32]);
+    for ((pc, instruction), end) in instructions.iter().zip(ends) {
+        assert_eq!(pc + instruction.length(*pc), end);
+    }
+    let code = encode(&instructions.iter().map(|(_, instruction)| instruction.clone()).collect::<Vec<_>>())
+        .expect("decoded instructions should encode");
+    // Switch padding is the only thing that may differ, the encoder writes it as zeros
+    assert_eq!(code.len(), data.len());
+    assert_eq!(decode(&code).expect("encoded instructions should decode"), instructions);
+});
@@ -0,0 +1,24 @@
+// Loads arbitrary bytes as a class file. Whatever loads has to get through the format check without
+// panicking, and has to load again, unchanged, after being written back out.
+//
+//   cargo +nightly fuzz run load
+//
+// corpus/load starts out with the class files in the repository root.
+
+#![no_main]
+
+use jvmmy::class::format_check::check_format;
+use jvmmy::class::loading::ClassFileLoader;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let class_file = match ClassFileLoader::new(data).enable_preview(true).load() {
+        Ok(class_file) => class_file,
+        Err(_) => return,
+    };
+    let _ = check_format(&class_file);
+    let bytes = class_file.to_bytes().expect("a loaded class file should be writable");
+    let reloaded = ClassFileLoader::new(&bytes).enable_preview(true).load()
+        .expect("a written class file should load");
+    assert_eq!(class_file, reloaded);
+});
@@ -0,0 +1,22 @@
+// Reads arbitrary bytes as a zip file, and every entry in it. Whatever the bytes, reading has to fail
+// with an error rather than panic, and a small archive can't decompress into a huge entry.
+//
+//   cargo +nightly fuzz run zip
+//
+// corpus/zip starts out with the class files in the repository root in a deflated and a stored JAR.
+
+#![no_main]
+
+use jvmmy::zip::ZipArchive;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(archive) = ZipArchive::new(data) else {
+        return;
+    };
+    for entry in &archive.entries {
+        if let Ok(contents) = archive.read_entry(entry) {
+            assert_eq!(contents.len() as u64, entry.uncompressed_size);
+        }
+    }
+});
@@ -0,0 +1,102 @@
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.16
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub type_index: u16, // Points at a field descriptor in constant_pool
+    pub element_value_pairs: Vec<ElementValuePair>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementValuePair {
+    pub element_name_index: u16,
+    pub value: ElementValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElementValue {
+    // tag=B, C, D, F, I, J, S, Z or s, which also tells the kind of constant
+    Const {
+        tag: u8,
+        const_value_index: u16,
+    },
+    // tag=e
+    Enum {
+        type_name_index: u16,
+        const_name_index: u16,
+    },
+    // tag=c, points at a return descriptor, so void.class is V
+    Class {
+        class_info_index: u16,
+    },
+    // tag=@
+    Annotation(Annotation),
+    // tag=[
+    Array(Vec<ElementValue>),
+}
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.20
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeAnnotation {
+    // Which kind of target_info follows, and what exactly it annotates
+    pub target_type: u8,
+    pub target_info: TargetInfo,
+    pub target_path: Vec<TypePathEntry>,
+    pub annotation: Annotation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TargetInfo {
+    // target_type=0x00, 0x01
+    TypeParameter {
+        type_parameter_index: u8,
+    },
+    // target_type=0x10, 65535 for the superclass, otherwise an index into interfaces
+    Supertype {
+        supertype_index: u16,
+    },
+    // target_type=0x11, 0x12
+    TypeParameterBound {
+        type_parameter_index: u8,
+        bound_index: u8,
+    },
+    // target_type=0x13-0x15, the type of a field, a return type or a receiver type
+    Empty,
+    // target_type=0x16
+    FormalParameter {
+        formal_parameter_index: u8,
+    },
+    // target_type=0x17, an index into the Exceptions attribute
+    Throws {
+        throws_type_index: u16,
+    },
+    // target_type=0x40, 0x41
+    Localvar {
+        table: Vec<LocalvarTarget>,
+    },
+    // target_type=0x42
+    Catch {
+        exception_table_index: u16,
+    },
+    // target_type=0x43-0x46, the pc of an instanceof, new or method reference instruction
+    Offset {
+        offset: u16,
+    },
+    // target_type=0x47-0x4B, a cast or an explicit type argument
+    TypeArgument {
+        offset: u16,
+        type_argument_index: u8,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalvarTarget {
+    pub start_pc: u16,
+    pub length: u16,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypePathEntry {
+    // 0: deeper in an array type, 1: deeper in a nested type, 2: on a wildcard bound, 3: on a type argument
+    pub type_path_kind: u8,
+    pub type_argument_index: u8,
+}
@@ -1,4 +1,19 @@
-#[derive(Debug)]
+pub mod annotation;
+pub mod module;
+
+use annotation::{Annotation, ElementValue, TypeAnnotation};
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7
+// An attribute with the constant pool entry holding its name. A pool can have the same string in more
+// than one Utf8 entry, so the index is kept to write the attribute back exactly as it was read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    // Zero for attributes made in code, whose name the writer looks up in the constant pool
+    pub name_index: u16,
+    pub info: AttributeInfo,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum AttributeInfo {
     ConstantValue {
         constantvalue_index: u16, // Points at constant_pool
@@ -8,23 +23,297 @@ pub enum AttributeInfo {
         max_locals: u16,
         code: Vec<u8>,
         exception_tables: Vec<ExceptionTable>,
-        attributes: Vec<AttributeInfo>,
+        attributes: Vec<Attribute>,
     },
     LineNumberTable {
         entries: Vec<LineNumberTableEntry>,
     },
+    LocalVariableTable {
+        entries: Vec<LocalVariableTableEntry>,
+    },
+    LocalVariableTypeTable {
+        entries: Vec<LocalVariableTypeTableEntry>,
+    },
+    StackMapTable {
+        entries: Vec<StackMapFrame>,
+    },
     SourceFile {
         sourcefile_index: u16, // Points at constant_pool
     },
+    BootstrapMethods {
+        bootstrap_methods: Vec<BootstrapMethod>,
+    },
+    NestHost {
+        host_class_index: u16, // Points at constant_pool
+    },
+    NestMembers {
+        classes: Vec<u16>, // Point at constant_pool
+    },
+    PermittedSubclasses {
+        classes: Vec<u16>, // Point at constant_pool
+    },
+    Record {
+        components: Vec<RecordComponentInfo>,
+    },
+    InnerClasses {
+        classes: Vec<InnerClass>,
+    },
+    EnclosingMethod {
+        class_index: u16, // Points at constant_pool
+        // Zero if the class is not enclosed by a method or constructor, e.g. in a field initializer
+        method_index: u16,
+    },
+    Signature {
+        signature_index: u16, // Points at constant_pool
+    },
+    Exceptions {
+        exception_index_table: Vec<u16>, // Point at constant_pool
+    },
+    Synthetic,
+    Deprecated,
+    MethodParameters {
+        parameters: Vec<MethodParameter>,
+    },
+    SourceDebugExtension {
+        // Spec says modified UTF-8, but the JVM never checks it, so keep the bytes as they are
+        debug_extension: Vec<u8>,
+    },
+    RuntimeVisibleAnnotations {
+        annotations: Vec<Annotation>,
+    },
+    RuntimeInvisibleAnnotations {
+        annotations: Vec<Annotation>,
+    },
+    // One list of annotations per parameter
+    RuntimeVisibleParameterAnnotations {
+        parameter_annotations: Vec<Vec<Annotation>>,
+    },
+    RuntimeInvisibleParameterAnnotations {
+        parameter_annotations: Vec<Vec<Annotation>>,
+    },
+    RuntimeVisibleTypeAnnotations {
+        annotations: Vec<TypeAnnotation>,
+    },
+    RuntimeInvisibleTypeAnnotations {
+        annotations: Vec<TypeAnnotation>,
+    },
+    // The default value of an annotation interface element
+    AnnotationDefault {
+        default_value: ElementValue,
+    },
+    Module {
+        module: module::Module,
+    },
+    ModulePackages {
+        package_index: Vec<u16>, // Point at Packages in constant_pool
+    },
+    ModuleMainClass {
+        main_class_index: u16, // Points at constant_pool
+    },
+    // Any attribute we don't know, e.g. ones added by other compilers or tools
+    Unknown {
+        name: String,
+        bytes: Vec<u8>,
+    },
+}
+
+impl Attribute {
+    // An attribute whose name is yet to be looked up
+    pub fn new(info: AttributeInfo) -> Attribute {
+        Attribute { name_index: 0, info }
+    }
+
+    pub fn name(&self) -> &str {
+        self.info.name()
+    }
+}
+
+impl AttributeInfo {
+    // The name the attribute goes by in the class file
+    pub fn name(&self) -> &str {
+        match self {
+            AttributeInfo::ConstantValue { .. } => "ConstantValue",
+            AttributeInfo::Code { .. } => "Code",
+            AttributeInfo::LineNumberTable { .. } => "LineNumberTable",
+            AttributeInfo::LocalVariableTable { .. } => "LocalVariableTable",
+            AttributeInfo::LocalVariableTypeTable { .. } => "LocalVariableTypeTable",
+            AttributeInfo::StackMapTable { .. } => "StackMapTable",
+            AttributeInfo::SourceFile { .. } => "SourceFile",
+            AttributeInfo::BootstrapMethods { .. } => "BootstrapMethods",
+            AttributeInfo::NestHost { .. } => "NestHost",
+            AttributeInfo::NestMembers { .. } => "NestMembers",
+            AttributeInfo::PermittedSubclasses { .. } => "PermittedSubclasses",
+            AttributeInfo::Record { .. } => "Record",
+            AttributeInfo::InnerClasses { .. } => "InnerClasses",
+            AttributeInfo::EnclosingMethod { .. } => "EnclosingMethod",
+            AttributeInfo::Signature { .. } => "Signature",
+            AttributeInfo::Exceptions { .. } => "Exceptions",
+            AttributeInfo::Synthetic => "Synthetic",
+            AttributeInfo::Deprecated => "Deprecated",
+            AttributeInfo::MethodParameters { .. } => "MethodParameters",
+            AttributeInfo::SourceDebugExtension { .. } => "SourceDebugExtension",
+            AttributeInfo::RuntimeVisibleAnnotations { .. } => "RuntimeVisibleAnnotations",
+            AttributeInfo::RuntimeInvisibleAnnotations { .. } => "RuntimeInvisibleAnnotations",
+            AttributeInfo::RuntimeVisibleParameterAnnotations { .. } => "RuntimeVisibleParameterAnnotations",
+            AttributeInfo::RuntimeInvisibleParameterAnnotations { .. } => "RuntimeInvisibleParameterAnnotations",
+            AttributeInfo::RuntimeVisibleTypeAnnotations { .. } => "RuntimeVisibleTypeAnnotations",
+            AttributeInfo::RuntimeInvisibleTypeAnnotations { .. } => "RuntimeInvisibleTypeAnnotations",
+            AttributeInfo::AnnotationDefault { .. } => "AnnotationDefault",
+            AttributeInfo::Module { .. } => "Module",
+            AttributeInfo::ModulePackages { .. } => "ModulePackages",
+            AttributeInfo::ModuleMainClass { .. } => "ModuleMainClass",
+            AttributeInfo::Unknown { name, .. } => name,
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LineNumberTableEntry {
     pub start_pc: u16,
     pub line_number: u16,
 }
 
-#[derive(Debug)]
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.4
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalVariableTableEntry {
+    // The variable is live from start_pc up to, but not including, start_pc + length
+    pub start_pc: u16,
+    pub length: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub index: u16, // Into the local variables of the frame
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalVariableTypeTableEntry {
+    pub start_pc: u16,
+    pub length: u16,
+    pub name_index: u16,
+    // Like LocalVariableTableEntry, but with a generic signature instead of a descriptor
+    pub signature_index: u16,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodParameter {
+    // Zero for a parameter without a name
+    pub name_index: u16,
+    pub access_flags: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackMapFrame {
+    pub offset_delta: u16,
+    // Absolute bytecode offset this frame applies to.
+    // The first frame is at offset_delta, every next one at previous offset + offset_delta + 1.
+    pub offset: u32,
+    pub kind: StackMapFrameKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StackMapFrameKind {
+    // frame_type=0-63, offset_delta is the frame_type itself
+    Same,
+    // frame_type=64-127, offset_delta is frame_type - 64
+    SameLocals1StackItem {
+        stack: VerificationTypeInfo,
+    },
+    // frame_type=247
+    SameLocals1StackItemExtended {
+        stack: VerificationTypeInfo,
+    },
+    // frame_type=248-250, the last 251 - frame_type locals are absent
+    Chop {
+        absent_locals: u8,
+    },
+    // frame_type=251
+    SameExtended,
+    // frame_type=252-254, frame_type - 251 locals are added
+    Append {
+        locals: Vec<VerificationTypeInfo>,
+    },
+    // frame_type=255
+    Full {
+        locals: Vec<VerificationTypeInfo>,
+        stack: Vec<VerificationTypeInfo>,
+    },
+}
+
+impl StackMapFrameKind {
+    // The types the frame holds, locals first
+    pub fn verification_types(&self) -> Vec<&VerificationTypeInfo> {
+        match self {
+            StackMapFrameKind::SameLocals1StackItem { stack } |
+            StackMapFrameKind::SameLocals1StackItemExtended { stack } => vec![stack],
+            StackMapFrameKind::Append { locals } => locals.iter().collect(),
+            StackMapFrameKind::Full { locals, stack } => locals.iter().chain(stack).collect(),
+            StackMapFrameKind::Same | StackMapFrameKind::SameExtended | StackMapFrameKind::Chop { .. } => Vec::new(),
+        }
+    }
+
+    // The same types as verification_types, for changing them in place
+    pub fn verification_types_mut(&mut self) -> Vec<&mut VerificationTypeInfo> {
+        match self {
+            StackMapFrameKind::SameLocals1StackItem { stack } |
+            StackMapFrameKind::SameLocals1StackItemExtended { stack } => vec![stack],
+            StackMapFrameKind::Append { locals } => locals.iter_mut().collect(),
+            StackMapFrameKind::Full { locals, stack } => locals.iter_mut().chain(stack).collect(),
+            StackMapFrameKind::Same | StackMapFrameKind::SameExtended | StackMapFrameKind::Chop { .. } => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationTypeInfo {
+    // tag=0
+    Top,
+    // tag=1
+    Integer,
+    // tag=2
+    Float,
+    // tag=3
+    Double,
+    // tag=4
+    Long,
+    // tag=5
+    Null,
+    // tag=6
+    UninitializedThis,
+    // tag=7
+    Object {
+        cpool_index: u16, // Points at constant_pool
+    },
+    // tag=8
+    Uninitialized {
+        // Offset of the new instruction that created the object
+        offset: u16,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BootstrapMethod {
+    pub bootstrap_method_ref: u16, // Points at a MethodHandle in constant_pool
+    pub bootstrap_arguments: Vec<u16>, // Point at loadable constants in constant_pool
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordComponentInfo {
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InnerClass {
+    pub inner_class_info_index: u16,
+    // Zero for top-level, local and anonymous classes
+    pub outer_class_info_index: u16,
+    // Zero for anonymous classes
+    pub inner_name_index: u16,
+    pub inner_class_access_flags: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ExceptionTable {
     pub start_pc: u16,
     pub end_pc: u16,
@@ -32,6 +321,17 @@ pub struct ExceptionTable {
     pub catch_type: u16,
 }
 
+pub fn signature_index(attributes: &[Attribute]) -> Option<u16> {
+    attributes.iter().find_map(|attribute| match attribute.info {
+        AttributeInfo::Signature { signature_index } => Some(signature_index),
+        _ => None
+    })
+}
+
+pub fn is_deprecated(attributes: &[Attribute]) -> bool {
+    attributes.iter().any(|attribute| matches!(attribute.info, AttributeInfo::Deprecated))
+}
+
 // Required:
 // - ConstantValue
 // - Code
@@ -0,0 +1,43 @@
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.25
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub module_name_index: u16, // Points at a Module in constant_pool
+    pub module_flags: u16,
+    // Zero if there is no version information
+    pub module_version_index: u16,
+    pub requires: Vec<Requires>,
+    pub exports: Vec<Exports>,
+    pub opens: Vec<Opens>,
+    pub uses_index: Vec<u16>, // Point at service interface Class entries in constant_pool
+    pub provides: Vec<Provides>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Requires {
+    pub requires_index: u16, // Points at a Module in constant_pool
+    pub requires_flags: u16,
+    // Zero if there is no version information
+    pub requires_version_index: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exports {
+    pub exports_index: u16, // Points at a Package in constant_pool
+    pub exports_flags: u16,
+    // Empty for an unqualified export
+    pub exports_to_index: Vec<u16>, // Point at Modules in constant_pool
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Opens {
+    pub opens_index: u16, // Points at a Package in constant_pool
+    pub opens_flags: u16,
+    // Empty for an unqualified open
+    pub opens_to_index: Vec<u16>, // Point at Modules in constant_pool
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provides {
+    pub provides_index: u16, // Points at the service interface Class in constant_pool
+    pub provides_with_index: Vec<u16>, // Point at implementation Class entries in constant_pool
+}
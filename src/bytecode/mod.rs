@@ -0,0 +1,1521 @@
+use std::error::Error;
+use std::fmt;
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5
+// One variant per opcode, with its operands. Branch targets are absolute pcs rather than the offsets
+// in the code array; everything else is as it is in the code array.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    // 0x00
+    Nop,
+    // 0x01
+    AconstNull,
+    // 0x02
+    IconstM1,
+    // 0x03
+    Iconst0,
+    // 0x04
+    Iconst1,
+    // 0x05
+    Iconst2,
+    // 0x06
+    Iconst3,
+    // 0x07
+    Iconst4,
+    // 0x08
+    Iconst5,
+    // 0x09
+    Lconst0,
+    // 0x0a
+    Lconst1,
+    // 0x0b
+    Fconst0,
+    // 0x0c
+    Fconst1,
+    // 0x0d
+    Fconst2,
+    // 0x0e
+    Dconst0,
+    // 0x0f
+    Dconst1,
+    // 0x10
+    Bipush(i8),
+    // 0x11
+    Sipush(i16),
+    // 0x12
+    Ldc(u8),
+    // 0x13
+    LdcW(u16),
+    // 0x14
+    Ldc2W(u16),
+    // 0x15
+    Iload(u8),
+    // 0x16
+    Lload(u8),
+    // 0x17
+    Fload(u8),
+    // 0x18
+    Dload(u8),
+    // 0x19
+    Aload(u8),
+    // 0x1a
+    Iload0,
+    // 0x1b
+    Iload1,
+    // 0x1c
+    Iload2,
+    // 0x1d
+    Iload3,
+    // 0x1e
+    Lload0,
+    // 0x1f
+    Lload1,
+    // 0x20
+    Lload2,
+    // 0x21
+    Lload3,
+    // 0x22
+    Fload0,
+    // 0x23
+    Fload1,
+    // 0x24
+    Fload2,
+    // 0x25
+    Fload3,
+    // 0x26
+    Dload0,
+    // 0x27
+    Dload1,
+    // 0x28
+    Dload2,
+    // 0x29
+    Dload3,
+    // 0x2a
+    Aload0,
+    // 0x2b
+    Aload1,
+    // 0x2c
+    Aload2,
+    // 0x2d
+    Aload3,
+    // 0x2e
+    Iaload,
+    // 0x2f
+    Laload,
+    // 0x30
+    Faload,
+    // 0x31
+    Daload,
+    // 0x32
+    Aaload,
+    // 0x33
+    Baload,
+    // 0x34
+    Caload,
+    // 0x35
+    Saload,
+    // 0x36
+    Istore(u8),
+    // 0x37
+    Lstore(u8),
+    // 0x38
+    Fstore(u8),
+    // 0x39
+    Dstore(u8),
+    // 0x3a
+    Astore(u8),
+    // 0x3b
+    Istore0,
+    // 0x3c
+    Istore1,
+    // 0x3d
+    Istore2,
+    // 0x3e
+    Istore3,
+    // 0x3f
+    Lstore0,
+    // 0x40
+    Lstore1,
+    // 0x41
+    Lstore2,
+    // 0x42
+    Lstore3,
+    // 0x43
+    Fstore0,
+    // 0x44
+    Fstore1,
+    // 0x45
+    Fstore2,
+    // 0x46
+    Fstore3,
+    // 0x47
+    Dstore0,
+    // 0x48
+    Dstore1,
+    // 0x49
+    Dstore2,
+    // 0x4a
+    Dstore3,
+    // 0x4b
+    Astore0,
+    // 0x4c
+    Astore1,
+    // 0x4d
+    Astore2,
+    // 0x4e
+    Astore3,
+    // 0x4f
+    Iastore,
+    // 0x50
+    Lastore,
+    // 0x51
+    Fastore,
+    // 0x52
+    Dastore,
+    // 0x53
+    Aastore,
+    // 0x54
+    Bastore,
+    // 0x55
+    Castore,
+    // 0x56
+    Sastore,
+    // 0x57
+    Pop,
+    // 0x58
+    Pop2,
+    // 0x59
+    Dup,
+    // 0x5a
+    DupX1,
+    // 0x5b
+    DupX2,
+    // 0x5c
+    Dup2,
+    // 0x5d
+    Dup2X1,
+    // 0x5e
+    Dup2X2,
+    // 0x5f
+    Swap,
+    // 0x60
+    Iadd,
+    // 0x61
+    Ladd,
+    // 0x62
+    Fadd,
+    // 0x63
+    Dadd,
+    // 0x64
+    Isub,
+    // 0x65
+    Lsub,
+    // 0x66
+    Fsub,
+    // 0x67
+    Dsub,
+    // 0x68
+    Imul,
+    // 0x69
+    Lmul,
+    // 0x6a
+    Fmul,
+    // 0x6b
+    Dmul,
+    // 0x6c
+    Idiv,
+    // 0x6d
+    Ldiv,
+    // 0x6e
+    Fdiv,
+    // 0x6f
+    Ddiv,
+    // 0x70
+    Irem,
+    // 0x71
+    Lrem,
+    // 0x72
+    Frem,
+    // 0x73
+    Drem,
+    // 0x74
+    Ineg,
+    // 0x75
+    Lneg,
+    // 0x76
+    Fneg,
+    // 0x77
+    Dneg,
+    // 0x78
+    Ishl,
+    // 0x79
+    Lshl,
+    // 0x7a
+    Ishr,
+    // 0x7b
+    Lshr,
+    // 0x7c
+    Iushr,
+    // 0x7d
+    Lushr,
+    // 0x7e
+    Iand,
+    // 0x7f
+    Land,
+    // 0x80
+    Ior,
+    // 0x81
+    Lor,
+    // 0x82
+    Ixor,
+    // 0x83
+    Lxor,
+    // 0x84
+    Iinc {
+        index: u8,
+        constant: i8,
+    },
+    // 0x85
+    I2l,
+    // 0x86
+    I2f,
+    // 0x87
+    I2d,
+    // 0x88
+    L2i,
+    // 0x89
+    L2f,
+    // 0x8a
+    L2d,
+    // 0x8b
+    F2i,
+    // 0x8c
+    F2l,
+    // 0x8d
+    F2d,
+    // 0x8e
+    D2i,
+    // 0x8f
+    D2l,
+    // 0x90
+    D2f,
+    // 0x91
+    I2b,
+    // 0x92
+    I2c,
+    // 0x93
+    I2s,
+    // 0x94
+    Lcmp,
+    // 0x95
+    Fcmpl,
+    // 0x96
+    Fcmpg,
+    // 0x97
+    Dcmpl,
+    // 0x98
+    Dcmpg,
+    // 0x99
+    Ifeq(u32),
+    // 0x9a
+    Ifne(u32),
+    // 0x9b
+    Iflt(u32),
+    // 0x9c
+    Ifge(u32),
+    // 0x9d
+    Ifgt(u32),
+    // 0x9e
+    Ifle(u32),
+    // 0x9f
+    IfIcmpeq(u32),
+    // 0xa0
+    IfIcmpne(u32),
+    // 0xa1
+    IfIcmplt(u32),
+    // 0xa2
+    IfIcmpge(u32),
+    // 0xa3
+    IfIcmpgt(u32),
+    // 0xa4
+    IfIcmple(u32),
+    // 0xa5
+    IfAcmpeq(u32),
+    // 0xa6
+    IfAcmpne(u32),
+    // 0xa7
+    Goto(u32),
+    // 0xa8
+    Jsr(u32),
+    // 0xa9
+    Ret(u8),
+    // 0xaa
+    // Spec: padded so that default starts at a multiple of four; targets has high - low + 1 entries
+    Tableswitch {
+        default: u32,
+        low: i32,
+        high: i32,
+        targets: Vec<u32>,
+    },
+    // 0xab
+    // Spec: padded like tableswitch, the pairs are sorted by key
+    Lookupswitch {
+        default: u32,
+        pairs: Vec<(i32, u32)>,
+    },
+    // 0xac
+    Ireturn,
+    // 0xad
+    Lreturn,
+    // 0xae
+    Freturn,
+    // 0xaf
+    Dreturn,
+    // 0xb0
+    Areturn,
+    // 0xb1
+    Return,
+    // 0xb2
+    Getstatic(u16),
+    // 0xb3
+    Putstatic(u16),
+    // 0xb4
+    Getfield(u16),
+    // 0xb5
+    Putfield(u16),
+    // 0xb6
+    Invokevirtual(u16),
+    // 0xb7
+    Invokespecial(u16),
+    // 0xb8
+    Invokestatic(u16),
+    // 0xb9
+    // count is redundant with the descriptor, but has to be there; a zero byte follows
+    Invokeinterface {
+        index: u16,
+        count: u8,
+    },
+    // 0xba
+    // Followed by two zero bytes
+    Invokedynamic(u16),
+    // 0xbb
+    New(u16),
+    // 0xbc
+    Newarray(u8),
+    // 0xbd
+    Anewarray(u16),
+    // 0xbe
+    Arraylength,
+    // 0xbf
+    Athrow,
+    // 0xc0
+    Checkcast(u16),
+    // 0xc1
+    Instanceof(u16),
+    // 0xc2
+    Monitorenter,
+    // 0xc3
+    Monitorexit,
+    // 0xc4
+    Wide(WideInstruction),
+    // 0xc5
+    Multianewarray {
+        index: u16,
+        dimensions: u8,
+    },
+    // 0xc6
+    Ifnull(u32),
+    // 0xc7
+    Ifnonnull(u32),
+    // 0xc8
+    GotoW(u32),
+    // 0xc9
+    JsrW(u32),
+}
+
+// The instructions wide can modify, with their operands widened to 16 bits.
+// Kept apart from the plain ones so a decoded instruction encodes back to the same bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WideInstruction {
+    Iload(u16),
+    Lload(u16),
+    Fload(u16),
+    Dload(u16),
+    Aload(u16),
+    Istore(u16),
+    Lstore(u16),
+    Fstore(u16),
+    Dstore(u16),
+    Astore(u16),
+    Ret(u16),
+    Iinc {
+        index: u16,
+        constant: i16,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct BytecodeError {
+    // pc of the instruction that could not be decoded or encoded
+    pub pc: u32,
+    pub kind: BytecodeErrorKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BytecodeErrorKind {
+    UnknownOpcode(u8),
+    // The instruction runs past the end of the code
+    Truncated,
+    // wide followed by an instruction it can't modify
+    BadWideOpcode(u8),
+    // A branch to somewhere outside the code
+    BranchOutOfRange {
+        target: i64,
+    },
+    // A branch target further away than the instruction's offset can reach, when encoding
+    BranchOffsetOverflow {
+        target: u32,
+    },
+    BadSwitch(&'static str),
+    // An operand with a value the spec rules out
+    BadOperand(&'static str),
+}
+
+// Decodes a whole code array into instructions and the pc each one starts at
+pub fn decode(code: &[u8]) -> Result<Vec<(u32, Instruction)>, BytecodeError> {
+    let mut decoder = Decoder { code, pc: 0, index: 0 };
+    let mut instructions = Vec::new();
+    while decoder.index < code.len() {
+        decoder.pc = decoder.index;
+        let instruction = decoder.instruction()?;
+        instructions.push((decoder.pc as u32, instruction));
+    }
+    Ok(instructions)
+}
+
+// Encodes instructions one after the other, starting at pc 0. Branch targets are taken to be pcs in
+// the encoded code, so decoding and encoding again gives back the same bytes.
+pub fn encode(instructions: &[Instruction]) -> Result<Vec<u8>, BytecodeError> {
+    let mut code = Vec::new();
+    for instruction in instructions {
+        instruction.encode(&mut code)?;
+    }
+    Ok(code)
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> u8 {
+        self.info().0
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        self.info().1
+    }
+
+    // The pcs this instruction may jump to, besides the next instruction
+    pub fn branch_targets(&self) -> Vec<u32> {
+        match self {
+            Instruction::Ifeq(target) |
+            Instruction::Ifne(target) |
+            Instruction::Iflt(target) |
+            Instruction::Ifge(target) |
+            Instruction::Ifgt(target) |
+            Instruction::Ifle(target) |
+            Instruction::IfIcmpeq(target) |
+            Instruction::IfIcmpne(target) |
+            Instruction::IfIcmplt(target) |
+            Instruction::IfIcmpge(target) |
+            Instruction::IfIcmpgt(target) |
+            Instruction::IfIcmple(target) |
+            Instruction::IfAcmpeq(target) |
+            Instruction::IfAcmpne(target) |
+            Instruction::Goto(target) |
+            Instruction::Jsr(target) |
+            Instruction::Ifnull(target) |
+            Instruction::Ifnonnull(target) |
+            Instruction::GotoW(target) |
+            Instruction::JsrW(target) => vec![*target],
+            Instruction::Tableswitch { default, targets, .. } =>
+                std::iter::once(*default).chain(targets.iter().copied()).collect(),
+            Instruction::Lookupswitch { default, pairs } =>
+                std::iter::once(*default).chain(pairs.iter().map(|&(_, target)| target)).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // The same targets as branch_targets, for changing them in place
+    pub fn branch_targets_mut(&mut self) -> Vec<&mut u32> {
+        match self {
+            Instruction::Ifeq(target) |
+            Instruction::Ifne(target) |
+            Instruction::Iflt(target) |
+            Instruction::Ifge(target) |
+            Instruction::Ifgt(target) |
+            Instruction::Ifle(target) |
+            Instruction::IfIcmpeq(target) |
+            Instruction::IfIcmpne(target) |
+            Instruction::IfIcmplt(target) |
+            Instruction::IfIcmpge(target) |
+            Instruction::IfIcmpgt(target) |
+            Instruction::IfIcmple(target) |
+            Instruction::IfAcmpeq(target) |
+            Instruction::IfAcmpne(target) |
+            Instruction::Goto(target) |
+            Instruction::Jsr(target) |
+            Instruction::Ifnull(target) |
+            Instruction::Ifnonnull(target) |
+            Instruction::GotoW(target) |
+            Instruction::JsrW(target) => vec![target],
+            Instruction::Tableswitch { default, targets, .. } =>
+                std::iter::once(default).chain(targets.iter_mut()).collect(),
+            Instruction::Lookupswitch { default, pairs } =>
+                std::iter::once(default).chain(pairs.iter_mut().map(|(_, target)| target)).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // Number of bytes the instruction takes up when encoded at pc, which only the switches' padding depends on
+    pub fn length(&self, pc: u32) -> u32 {
+        let padding = 3 - pc % 4;
+        match self {
+            Instruction::Tableswitch { targets, .. } => 1 + padding + 12 + 4 * targets.len() as u32,
+            Instruction::Lookupswitch { pairs, .. } => 1 + padding + 8 + 8 * pairs.len() as u32,
+            Instruction::Wide(WideInstruction::Iinc { .. }) => 6,
+            Instruction::Wide(_) => 4,
+            Instruction::Invokeinterface { .. } |
+            Instruction::Invokedynamic(_) |
+            Instruction::GotoW(_) |
+            Instruction::JsrW(_) => 5,
+            Instruction::Multianewarray { .. } => 4,
+            Instruction::Sipush(_) |
+            Instruction::LdcW(_) |
+            Instruction::Ldc2W(_) |
+            Instruction::Getstatic(_) |
+            Instruction::Putstatic(_) |
+            Instruction::Getfield(_) |
+            Instruction::Putfield(_) |
+            Instruction::Invokevirtual(_) |
+            Instruction::Invokespecial(_) |
+            Instruction::Invokestatic(_) |
+            Instruction::New(_) |
+            Instruction::Anewarray(_) |
+            Instruction::Checkcast(_) |
+            Instruction::Instanceof(_) |
+            Instruction::Iinc { .. } => 3,
+            Instruction::Bipush(_) |
+            Instruction::Ldc(_) |
+            Instruction::Iload(_) |
+            Instruction::Lload(_) |
+            Instruction::Fload(_) |
+            Instruction::Dload(_) |
+            Instruction::Aload(_) |
+            Instruction::Istore(_) |
+            Instruction::Lstore(_) |
+            Instruction::Fstore(_) |
+            Instruction::Dstore(_) |
+            Instruction::Astore(_) |
+            Instruction::Ret(_) |
+            Instruction::Newarray(_) => 2,
+            instruction => match instruction.branch_targets().is_empty() {
+                true => 1,
+                false => 3,
+            },
+        }
+    }
+
+    // Appends the instruction to code, at the pc code.len()
+    fn encode(&self, code: &mut Vec<u8>) -> Result<(), BytecodeError> {
+        let pc = code.len() as u32;
+        code.push(self.opcode());
+        match self {
+            Instruction::Nop |
+            Instruction::AconstNull |
+            Instruction::IconstM1 |
+            Instruction::Iconst0 |
+            Instruction::Iconst1 |
+            Instruction::Iconst2 |
+            Instruction::Iconst3 |
+            Instruction::Iconst4 |
+            Instruction::Iconst5 |
+            Instruction::Lconst0 |
+            Instruction::Lconst1 |
+            Instruction::Fconst0 |
+            Instruction::Fconst1 |
+            Instruction::Fconst2 |
+            Instruction::Dconst0 |
+            Instruction::Dconst1 |
+            Instruction::Iload0 |
+            Instruction::Iload1 |
+            Instruction::Iload2 |
+            Instruction::Iload3 |
+            Instruction::Lload0 |
+            Instruction::Lload1 |
+            Instruction::Lload2 |
+            Instruction::Lload3 |
+            Instruction::Fload0 |
+            Instruction::Fload1 |
+            Instruction::Fload2 |
+            Instruction::Fload3 |
+            Instruction::Dload0 |
+            Instruction::Dload1 |
+            Instruction::Dload2 |
+            Instruction::Dload3 |
+            Instruction::Aload0 |
+            Instruction::Aload1 |
+            Instruction::Aload2 |
+            Instruction::Aload3 |
+            Instruction::Iaload |
+            Instruction::Laload |
+            Instruction::Faload |
+            Instruction::Daload |
+            Instruction::Aaload |
+            Instruction::Baload |
+            Instruction::Caload |
+            Instruction::Saload |
+            Instruction::Istore0 |
+            Instruction::Istore1 |
+            Instruction::Istore2 |
+            Instruction::Istore3 |
+            Instruction::Lstore0 |
+            Instruction::Lstore1 |
+            Instruction::Lstore2 |
+            Instruction::Lstore3 |
+            Instruction::Fstore0 |
+            Instruction::Fstore1 |
+            Instruction::Fstore2 |
+            Instruction::Fstore3 |
+            Instruction::Dstore0 |
+            Instruction::Dstore1 |
+            Instruction::Dstore2 |
+            Instruction::Dstore3 |
+            Instruction::Astore0 |
+            Instruction::Astore1 |
+            Instruction::Astore2 |
+            Instruction::Astore3 |
+            Instruction::Iastore |
+            Instruction::Lastore |
+            Instruction::Fastore |
+            Instruction::Dastore |
+            Instruction::Aastore |
+            Instruction::Bastore |
+            Instruction::Castore |
+            Instruction::Sastore |
+            Instruction::Pop |
+            Instruction::Pop2 |
+            Instruction::Dup |
+            Instruction::DupX1 |
+            Instruction::DupX2 |
+            Instruction::Dup2 |
+            Instruction::Dup2X1 |
+            Instruction::Dup2X2 |
+            Instruction::Swap |
+            Instruction::Iadd |
+            Instruction::Ladd |
+            Instruction::Fadd |
+            Instruction::Dadd |
+            Instruction::Isub |
+            Instruction::Lsub |
+            Instruction::Fsub |
+            Instruction::Dsub |
+            Instruction::Imul |
+            Instruction::Lmul |
+            Instruction::Fmul |
+            Instruction::Dmul |
+            Instruction::Idiv |
+            Instruction::Ldiv |
+            Instruction::Fdiv |
+            Instruction::Ddiv |
+            Instruction::Irem |
+            Instruction::Lrem |
+            Instruction::Frem |
+            Instruction::Drem |
+            Instruction::Ineg |
+            Instruction::Lneg |
+            Instruction::Fneg |
+            Instruction::Dneg |
+            Instruction::Ishl |
+            Instruction::Lshl |
+            Instruction::Ishr |
+            Instruction::Lshr |
+            Instruction::Iushr |
+            Instruction::Lushr |
+            Instruction::Iand |
+            Instruction::Land |
+            Instruction::Ior |
+            Instruction::Lor |
+            Instruction::Ixor |
+            Instruction::Lxor |
+            Instruction::I2l |
+            Instruction::I2f |
+            Instruction::I2d |
+            Instruction::L2i |
+            Instruction::L2f |
+            Instruction::L2d |
+            Instruction::F2i |
+            Instruction::F2l |
+            Instruction::F2d |
+            Instruction::D2i |
+            Instruction::D2l |
+            Instruction::D2f |
+            Instruction::I2b |
+            Instruction::I2c |
+            Instruction::I2s |
+            Instruction::Lcmp |
+            Instruction::Fcmpl |
+            Instruction::Fcmpg |
+            Instruction::Dcmpl |
+            Instruction::Dcmpg |
+            Instruction::Ireturn |
+            Instruction::Lreturn |
+            Instruction::Freturn |
+            Instruction::Dreturn |
+            Instruction::Areturn |
+            Instruction::Return |
+            Instruction::Arraylength |
+            Instruction::Athrow |
+            Instruction::Monitorenter |
+            Instruction::Monitorexit => {}
+            Instruction::Bipush(value) => code.push(*value as u8),
+            Instruction::Sipush(value) => code.extend_from_slice(&value.to_be_bytes()),
+            Instruction::Ldc(index) |
+            Instruction::Iload(index) |
+            Instruction::Lload(index) |
+            Instruction::Fload(index) |
+            Instruction::Dload(index) |
+            Instruction::Aload(index) |
+            Instruction::Istore(index) |
+            Instruction::Lstore(index) |
+            Instruction::Fstore(index) |
+            Instruction::Dstore(index) |
+            Instruction::Astore(index) |
+            Instruction::Ret(index) |
+            Instruction::Newarray(index) => code.push(*index),
+            Instruction::LdcW(index) |
+            Instruction::Ldc2W(index) |
+            Instruction::Getstatic(index) |
+            Instruction::Putstatic(index) |
+            Instruction::Getfield(index) |
+            Instruction::Putfield(index) |
+            Instruction::Invokevirtual(index) |
+            Instruction::Invokespecial(index) |
+            Instruction::Invokestatic(index) |
+            Instruction::New(index) |
+            Instruction::Anewarray(index) |
+            Instruction::Checkcast(index) |
+            Instruction::Instanceof(index) => code.extend_from_slice(&index.to_be_bytes()),
+            Instruction::Iinc { index, constant } => {
+                code.push(*index);
+                code.push(*constant as u8);
+            }
+            Instruction::Ifeq(target) |
+            Instruction::Ifne(target) |
+            Instruction::Iflt(target) |
+            Instruction::Ifge(target) |
+            Instruction::Ifgt(target) |
+            Instruction::Ifle(target) |
+            Instruction::IfIcmpeq(target) |
+            Instruction::IfIcmpne(target) |
+            Instruction::IfIcmplt(target) |
+            Instruction::IfIcmpge(target) |
+            Instruction::IfIcmpgt(target) |
+            Instruction::IfIcmple(target) |
+            Instruction::IfAcmpeq(target) |
+            Instruction::IfAcmpne(target) |
+            Instruction::Goto(target) |
+            Instruction::Jsr(target) |
+            Instruction::Ifnull(target) |
+            Instruction::Ifnonnull(target) => {
+                let offset = i16::try_from(*target as i64 - pc as i64)
+                    .map_err(|_| BytecodeError { pc, kind: BytecodeErrorKind::BranchOffsetOverflow { target: *target } })?;
+                code.extend_from_slice(&offset.to_be_bytes());
+            }
+            Instruction::GotoW(target) |
+            Instruction::JsrW(target) => {
+                code.extend_from_slice(&branch_offset_w(pc, *target)?.to_be_bytes());
+            }
+            Instruction::Tableswitch { default, low, high, targets } => {
+                if *low as i64 + targets.len() as i64 - 1 != *high as i64 {
+                    return Err(BytecodeError { pc, kind: BytecodeErrorKind::BadSwitch("tableswitch needs high - low + 1 targets") });
+                }
+                pad(code);
+                code.extend_from_slice(&branch_offset_w(pc, *default)?.to_be_bytes());
+                code.extend_from_slice(&low.to_be_bytes());
+                code.extend_from_slice(&high.to_be_bytes());
+                for &target in targets {
+                    code.extend_from_slice(&branch_offset_w(pc, target)?.to_be_bytes());
+                }
+            }
+            Instruction::Lookupswitch { default, pairs } => {
+                pad(code);
+                code.extend_from_slice(&branch_offset_w(pc, *default)?.to_be_bytes());
+                code.extend_from_slice(&(pairs.len() as i32).to_be_bytes());
+                for &(key, target) in pairs {
+                    code.extend_from_slice(&key.to_be_bytes());
+                    code.extend_from_slice(&branch_offset_w(pc, target)?.to_be_bytes());
+                }
+            }
+            Instruction::Invokeinterface { index, count } => {
+                code.extend_from_slice(&index.to_be_bytes());
+                code.push(*count);
+                code.push(0);
+            }
+            Instruction::Invokedynamic(index) => {
+                code.extend_from_slice(&index.to_be_bytes());
+                code.extend_from_slice(&[0, 0]);
+            }
+            Instruction::Wide(instruction) => {
+                code.push(instruction.opcode());
+                match instruction {
+                    WideInstruction::Iload(index) | WideInstruction::Lload(index) | WideInstruction::Fload(index) |
+                    WideInstruction::Dload(index) | WideInstruction::Aload(index) | WideInstruction::Istore(index) |
+                    WideInstruction::Lstore(index) | WideInstruction::Fstore(index) | WideInstruction::Dstore(index) |
+                    WideInstruction::Astore(index) | WideInstruction::Ret(index) =>
+                        code.extend_from_slice(&index.to_be_bytes()),
+                    WideInstruction::Iinc { index, constant } => {
+                        code.extend_from_slice(&index.to_be_bytes());
+                        code.extend_from_slice(&constant.to_be_bytes());
+                    }
+                }
+            }
+            Instruction::Multianewarray { index, dimensions } => {
+                code.extend_from_slice(&index.to_be_bytes());
+                code.push(*dimensions);
+            }
+        }
+        Ok(())
+    }
+
+    fn info(&self) -> (u8, &'static str) {
+        match self {
+            Instruction::Nop => (0x00, "nop"),
+            Instruction::AconstNull => (0x01, "aconst_null"),
+            Instruction::IconstM1 => (0x02, "iconst_m1"),
+            Instruction::Iconst0 => (0x03, "iconst_0"),
+            Instruction::Iconst1 => (0x04, "iconst_1"),
+            Instruction::Iconst2 => (0x05, "iconst_2"),
+            Instruction::Iconst3 => (0x06, "iconst_3"),
+            Instruction::Iconst4 => (0x07, "iconst_4"),
+            Instruction::Iconst5 => (0x08, "iconst_5"),
+            Instruction::Lconst0 => (0x09, "lconst_0"),
+            Instruction::Lconst1 => (0x0a, "lconst_1"),
+            Instruction::Fconst0 => (0x0b, "fconst_0"),
+            Instruction::Fconst1 => (0x0c, "fconst_1"),
+            Instruction::Fconst2 => (0x0d, "fconst_2"),
+            Instruction::Dconst0 => (0x0e, "dconst_0"),
+            Instruction::Dconst1 => (0x0f, "dconst_1"),
+            Instruction::Bipush(_) => (0x10, "bipush"),
+            Instruction::Sipush(_) => (0x11, "sipush"),
+            Instruction::Ldc(_) => (0x12, "ldc"),
+            Instruction::LdcW(_) => (0x13, "ldc_w"),
+            Instruction::Ldc2W(_) => (0x14, "ldc2_w"),
+            Instruction::Iload(_) => (0x15, "iload"),
+            Instruction::Lload(_) => (0x16, "lload"),
+            Instruction::Fload(_) => (0x17, "fload"),
+            Instruction::Dload(_) => (0x18, "dload"),
+            Instruction::Aload(_) => (0x19, "aload"),
+            Instruction::Iload0 => (0x1a, "iload_0"),
+            Instruction::Iload1 => (0x1b, "iload_1"),
+            Instruction::Iload2 => (0x1c, "iload_2"),
+            Instruction::Iload3 => (0x1d, "iload_3"),
+            Instruction::Lload0 => (0x1e, "lload_0"),
+            Instruction::Lload1 => (0x1f, "lload_1"),
+            Instruction::Lload2 => (0x20, "lload_2"),
+            Instruction::Lload3 => (0x21, "lload_3"),
+            Instruction::Fload0 => (0x22, "fload_0"),
+            Instruction::Fload1 => (0x23, "fload_1"),
+            Instruction::Fload2 => (0x24, "fload_2"),
+            Instruction::Fload3 => (0x25, "fload_3"),
+            Instruction::Dload0 => (0x26, "dload_0"),
+            Instruction::Dload1 => (0x27, "dload_1"),
+            Instruction::Dload2 => (0x28, "dload_2"),
+            Instruction::Dload3 => (0x29, "dload_3"),
+            Instruction::Aload0 => (0x2a, "aload_0"),
+            Instruction::Aload1 => (0x2b, "aload_1"),
+            Instruction::Aload2 => (0x2c, "aload_2"),
+            Instruction::Aload3 => (0x2d, "aload_3"),
+            Instruction::Iaload => (0x2e, "iaload"),
+            Instruction::Laload => (0x2f, "laload"),
+            Instruction::Faload => (0x30, "faload"),
+            Instruction::Daload => (0x31, "daload"),
+            Instruction::Aaload => (0x32, "aaload"),
+            Instruction::Baload => (0x33, "baload"),
+            Instruction::Caload => (0x34, "caload"),
+            Instruction::Saload => (0x35, "saload"),
+            Instruction::Istore(_) => (0x36, "istore"),
+            Instruction::Lstore(_) => (0x37, "lstore"),
+            Instruction::Fstore(_) => (0x38, "fstore"),
+            Instruction::Dstore(_) => (0x39, "dstore"),
+            Instruction::Astore(_) => (0x3a, "astore"),
+            Instruction::Istore0 => (0x3b, "istore_0"),
+            Instruction::Istore1 => (0x3c, "istore_1"),
+            Instruction::Istore2 => (0x3d, "istore_2"),
+            Instruction::Istore3 => (0x3e, "istore_3"),
+            Instruction::Lstore0 => (0x3f, "lstore_0"),
+            Instruction::Lstore1 => (0x40, "lstore_1"),
+            Instruction::Lstore2 => (0x41, "lstore_2"),
+            Instruction::Lstore3 => (0x42, "lstore_3"),
+            Instruction::Fstore0 => (0x43, "fstore_0"),
+            Instruction::Fstore1 => (0x44, "fstore_1"),
+            Instruction::Fstore2 => (0x45, "fstore_2"),
+            Instruction::Fstore3 => (0x46, "fstore_3"),
+            Instruction::Dstore0 => (0x47, "dstore_0"),
+            Instruction::Dstore1 => (0x48, "dstore_1"),
+            Instruction::Dstore2 => (0x49, "dstore_2"),
+            Instruction::Dstore3 => (0x4a, "dstore_3"),
+            Instruction::Astore0 => (0x4b, "astore_0"),
+            Instruction::Astore1 => (0x4c, "astore_1"),
+            Instruction::Astore2 => (0x4d, "astore_2"),
+            Instruction::Astore3 => (0x4e, "astore_3"),
+            Instruction::Iastore => (0x4f, "iastore"),
+            Instruction::Lastore => (0x50, "lastore"),
+            Instruction::Fastore => (0x51, "fastore"),
+            Instruction::Dastore => (0x52, "dastore"),
+            Instruction::Aastore => (0x53, "aastore"),
+            Instruction::Bastore => (0x54, "bastore"),
+            Instruction::Castore => (0x55, "castore"),
+            Instruction::Sastore => (0x56, "sastore"),
+            Instruction::Pop => (0x57, "pop"),
+            Instruction::Pop2 => (0x58, "pop2"),
+            Instruction::Dup => (0x59, "dup"),
+            Instruction::DupX1 => (0x5a, "dup_x1"),
+            Instruction::DupX2 => (0x5b, "dup_x2"),
+            Instruction::Dup2 => (0x5c, "dup2"),
+            Instruction::Dup2X1 => (0x5d, "dup2_x1"),
+            Instruction::Dup2X2 => (0x5e, "dup2_x2"),
+            Instruction::Swap => (0x5f, "swap"),
+            Instruction::Iadd => (0x60, "iadd"),
+            Instruction::Ladd => (0x61, "ladd"),
+            Instruction::Fadd => (0x62, "fadd"),
+            Instruction::Dadd => (0x63, "dadd"),
+            Instruction::Isub => (0x64, "isub"),
+            Instruction::Lsub => (0x65, "lsub"),
+            Instruction::Fsub => (0x66, "fsub"),
+            Instruction::Dsub => (0x67, "dsub"),
+            Instruction::Imul => (0x68, "imul"),
+            Instruction::Lmul => (0x69, "lmul"),
+            Instruction::Fmul => (0x6a, "fmul"),
+            Instruction::Dmul => (0x6b, "dmul"),
+            Instruction::Idiv => (0x6c, "idiv"),
+            Instruction::Ldiv => (0x6d, "ldiv"),
+            Instruction::Fdiv => (0x6e, "fdiv"),
+            Instruction::Ddiv => (0x6f, "ddiv"),
+            Instruction::Irem => (0x70, "irem"),
+            Instruction::Lrem => (0x71, "lrem"),
+            Instruction::Frem => (0x72, "frem"),
+            Instruction::Drem => (0x73, "drem"),
+            Instruction::Ineg => (0x74, "ineg"),
+            Instruction::Lneg => (0x75, "lneg"),
+            Instruction::Fneg => (0x76, "fneg"),
+            Instruction::Dneg => (0x77, "dneg"),
+            Instruction::Ishl => (0x78, "ishl"),
+            Instruction::Lshl => (0x79, "lshl"),
+            Instruction::Ishr => (0x7a, "ishr"),
+            Instruction::Lshr => (0x7b, "lshr"),
+            Instruction::Iushr => (0x7c, "iushr"),
+            Instruction::Lushr => (0x7d, "lushr"),
+            Instruction::Iand => (0x7e, "iand"),
+            Instruction::Land => (0x7f, "land"),
+            Instruction::Ior => (0x80, "ior"),
+            Instruction::Lor => (0x81, "lor"),
+            Instruction::Ixor => (0x82, "ixor"),
+            Instruction::Lxor => (0x83, "lxor"),
+            Instruction::Iinc { .. } => (0x84, "iinc"),
+            Instruction::I2l => (0x85, "i2l"),
+            Instruction::I2f => (0x86, "i2f"),
+            Instruction::I2d => (0x87, "i2d"),
+            Instruction::L2i => (0x88, "l2i"),
+            Instruction::L2f => (0x89, "l2f"),
+            Instruction::L2d => (0x8a, "l2d"),
+            Instruction::F2i => (0x8b, "f2i"),
+            Instruction::F2l => (0x8c, "f2l"),
+            Instruction::F2d => (0x8d, "f2d"),
+            Instruction::D2i => (0x8e, "d2i"),
+            Instruction::D2l => (0x8f, "d2l"),
+            Instruction::D2f => (0x90, "d2f"),
+            Instruction::I2b => (0x91, "i2b"),
+            Instruction::I2c => (0x92, "i2c"),
+            Instruction::I2s => (0x93, "i2s"),
+            Instruction::Lcmp => (0x94, "lcmp"),
+            Instruction::Fcmpl => (0x95, "fcmpl"),
+            Instruction::Fcmpg => (0x96, "fcmpg"),
+            Instruction::Dcmpl => (0x97, "dcmpl"),
+            Instruction::Dcmpg => (0x98, "dcmpg"),
+            Instruction::Ifeq(_) => (0x99, "ifeq"),
+            Instruction::Ifne(_) => (0x9a, "ifne"),
+            Instruction::Iflt(_) => (0x9b, "iflt"),
+            Instruction::Ifge(_) => (0x9c, "ifge"),
+            Instruction::Ifgt(_) => (0x9d, "ifgt"),
+            Instruction::Ifle(_) => (0x9e, "ifle"),
+            Instruction::IfIcmpeq(_) => (0x9f, "if_icmpeq"),
+            Instruction::IfIcmpne(_) => (0xa0, "if_icmpne"),
+            Instruction::IfIcmplt(_) => (0xa1, "if_icmplt"),
+            Instruction::IfIcmpge(_) => (0xa2, "if_icmpge"),
+            Instruction::IfIcmpgt(_) => (0xa3, "if_icmpgt"),
+            Instruction::IfIcmple(_) => (0xa4, "if_icmple"),
+            Instruction::IfAcmpeq(_) => (0xa5, "if_acmpeq"),
+            Instruction::IfAcmpne(_) => (0xa6, "if_acmpne"),
+            Instruction::Goto(_) => (0xa7, "goto"),
+            Instruction::Jsr(_) => (0xa8, "jsr"),
+            Instruction::Ret(_) => (0xa9, "ret"),
+            Instruction::Tableswitch { .. } => (0xaa, "tableswitch"),
+            Instruction::Lookupswitch { .. } => (0xab, "lookupswitch"),
+            Instruction::Ireturn => (0xac, "ireturn"),
+            Instruction::Lreturn => (0xad, "lreturn"),
+            Instruction::Freturn => (0xae, "freturn"),
+            Instruction::Dreturn => (0xaf, "dreturn"),
+            Instruction::Areturn => (0xb0, "areturn"),
+            Instruction::Return => (0xb1, "return"),
+            Instruction::Getstatic(_) => (0xb2, "getstatic"),
+            Instruction::Putstatic(_) => (0xb3, "putstatic"),
+            Instruction::Getfield(_) => (0xb4, "getfield"),
+            Instruction::Putfield(_) => (0xb5, "putfield"),
+            Instruction::Invokevirtual(_) => (0xb6, "invokevirtual"),
+            Instruction::Invokespecial(_) => (0xb7, "invokespecial"),
+            Instruction::Invokestatic(_) => (0xb8, "invokestatic"),
+            Instruction::Invokeinterface { .. } => (0xb9, "invokeinterface"),
+            Instruction::Invokedynamic(_) => (0xba, "invokedynamic"),
+            Instruction::New(_) => (0xbb, "new"),
+            Instruction::Newarray(_) => (0xbc, "newarray"),
+            Instruction::Anewarray(_) => (0xbd, "anewarray"),
+            Instruction::Arraylength => (0xbe, "arraylength"),
+            Instruction::Athrow => (0xbf, "athrow"),
+            Instruction::Checkcast(_) => (0xc0, "checkcast"),
+            Instruction::Instanceof(_) => (0xc1, "instanceof"),
+            Instruction::Monitorenter => (0xc2, "monitorenter"),
+            Instruction::Monitorexit => (0xc3, "monitorexit"),
+            Instruction::Wide(_) => (0xc4, "wide"),
+            Instruction::Multianewarray { .. } => (0xc5, "multianewarray"),
+            Instruction::Ifnull(_) => (0xc6, "ifnull"),
+            Instruction::Ifnonnull(_) => (0xc7, "ifnonnull"),
+            Instruction::GotoW(_) => (0xc8, "goto_w"),
+            Instruction::JsrW(_) => (0xc9, "jsr_w"),
+        }
+    }
+}
+
+impl WideInstruction {
+    // The opcode of the instruction being modified
+    pub fn opcode(&self) -> u8 {
+        match self {
+            WideInstruction::Iload(_) => 0x15,
+            WideInstruction::Lload(_) => 0x16,
+            WideInstruction::Fload(_) => 0x17,
+            WideInstruction::Dload(_) => 0x18,
+            WideInstruction::Aload(_) => 0x19,
+            WideInstruction::Istore(_) => 0x36,
+            WideInstruction::Lstore(_) => 0x37,
+            WideInstruction::Fstore(_) => 0x38,
+            WideInstruction::Dstore(_) => 0x39,
+            WideInstruction::Astore(_) => 0x3a,
+            WideInstruction::Ret(_) => 0xa9,
+            WideInstruction::Iinc { .. } => 0x84,
+        }
+    }
+
+    // The mnemonic of the instruction being modified
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            WideInstruction::Iload(_) => "iload",
+            WideInstruction::Lload(_) => "lload",
+            WideInstruction::Fload(_) => "fload",
+            WideInstruction::Dload(_) => "dload",
+            WideInstruction::Aload(_) => "aload",
+            WideInstruction::Istore(_) => "istore",
+            WideInstruction::Lstore(_) => "lstore",
+            WideInstruction::Fstore(_) => "fstore",
+            WideInstruction::Dstore(_) => "dstore",
+            WideInstruction::Astore(_) => "astore",
+            WideInstruction::Ret(_) => "ret",
+            WideInstruction::Iinc { .. } => "iinc",
+        }
+    }
+}
+
+// Spec: the bytes between a switch opcode and its default are padding, up to a multiple of four
+fn pad(code: &mut Vec<u8>) {
+    while !code.len().is_multiple_of(4) {
+        code.push(0);
+    }
+}
+
+fn branch_offset_w(pc: u32, target: u32) -> Result<i32, BytecodeError> {
+    i32::try_from(target as i64 - pc as i64)
+        .map_err(|_| BytecodeError { pc, kind: BytecodeErrorKind::BranchOffsetOverflow { target } })
+}
+
+struct Decoder<'a> {
+    code: &'a [u8],
+    // Start of the instruction being decoded, branch offsets are relative to it
+    pc: usize,
+    index: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn instruction(&mut self) -> Result<Instruction, BytecodeError> {
+        let opcode = self.read_u8()?;
+        Ok(match opcode {
+            0x00 => Instruction::Nop,
+            0x01 => Instruction::AconstNull,
+            0x02 => Instruction::IconstM1,
+            0x03 => Instruction::Iconst0,
+            0x04 => Instruction::Iconst1,
+            0x05 => Instruction::Iconst2,
+            0x06 => Instruction::Iconst3,
+            0x07 => Instruction::Iconst4,
+            0x08 => Instruction::Iconst5,
+            0x09 => Instruction::Lconst0,
+            0x0a => Instruction::Lconst1,
+            0x0b => Instruction::Fconst0,
+            0x0c => Instruction::Fconst1,
+            0x0d => Instruction::Fconst2,
+            0x0e => Instruction::Dconst0,
+            0x0f => Instruction::Dconst1,
+            0x10 => Instruction::Bipush(self.read_i8()?),
+            0x11 => Instruction::Sipush(self.read_i16()?),
+            0x12 => Instruction::Ldc(self.read_u8()?),
+            0x13 => Instruction::LdcW(self.read_u16()?),
+            0x14 => Instruction::Ldc2W(self.read_u16()?),
+            0x15 => Instruction::Iload(self.read_u8()?),
+            0x16 => Instruction::Lload(self.read_u8()?),
+            0x17 => Instruction::Fload(self.read_u8()?),
+            0x18 => Instruction::Dload(self.read_u8()?),
+            0x19 => Instruction::Aload(self.read_u8()?),
+            0x1a => Instruction::Iload0,
+            0x1b => Instruction::Iload1,
+            0x1c => Instruction::Iload2,
+            0x1d => Instruction::Iload3,
+            0x1e => Instruction::Lload0,
+            0x1f => Instruction::Lload1,
+            0x20 => Instruction::Lload2,
+            0x21 => Instruction::Lload3,
+            0x22 => Instruction::Fload0,
+            0x23 => Instruction::Fload1,
+            0x24 => Instruction::Fload2,
+            0x25 => Instruction::Fload3,
+            0x26 => Instruction::Dload0,
+            0x27 => Instruction::Dload1,
+            0x28 => Instruction::Dload2,
+            0x29 => Instruction::Dload3,
+            0x2a => Instruction::Aload0,
+            0x2b => Instruction::Aload1,
+            0x2c => Instruction::Aload2,
+            0x2d => Instruction::Aload3,
+            0x2e => Instruction::Iaload,
+            0x2f => Instruction::Laload,
+            0x30 => Instruction::Faload,
+            0x31 => Instruction::Daload,
+            0x32 => Instruction::Aaload,
+            0x33 => Instruction::Baload,
+            0x34 => Instruction::Caload,
+            0x35 => Instruction::Saload,
+            0x36 => Instruction::Istore(self.read_u8()?),
+            0x37 => Instruction::Lstore(self.read_u8()?),
+            0x38 => Instruction::Fstore(self.read_u8()?),
+            0x39 => Instruction::Dstore(self.read_u8()?),
+            0x3a => Instruction::Astore(self.read_u8()?),
+            0x3b => Instruction::Istore0,
+            0x3c => Instruction::Istore1,
+            0x3d => Instruction::Istore2,
+            0x3e => Instruction::Istore3,
+            0x3f => Instruction::Lstore0,
+            0x40 => Instruction::Lstore1,
+            0x41 => Instruction::Lstore2,
+            0x42 => Instruction::Lstore3,
+            0x43 => Instruction::Fstore0,
+            0x44 => Instruction::Fstore1,
+            0x45 => Instruction::Fstore2,
+            0x46 => Instruction::Fstore3,
+            0x47 => Instruction::Dstore0,
+            0x48 => Instruction::Dstore1,
+            0x49 => Instruction::Dstore2,
+            0x4a => Instruction::Dstore3,
+            0x4b => Instruction::Astore0,
+            0x4c => Instruction::Astore1,
+            0x4d => Instruction::Astore2,
+            0x4e => Instruction::Astore3,
+            0x4f => Instruction::Iastore,
+            0x50 => Instruction::Lastore,
+            0x51 => Instruction::Fastore,
+            0x52 => Instruction::Dastore,
+            0x53 => Instruction::Aastore,
+            0x54 => Instruction::Bastore,
+            0x55 => Instruction::Castore,
+            0x56 => Instruction::Sastore,
+            0x57 => Instruction::Pop,
+            0x58 => Instruction::Pop2,
+            0x59 => Instruction::Dup,
+            0x5a => Instruction::DupX1,
+            0x5b => Instruction::DupX2,
+            0x5c => Instruction::Dup2,
+            0x5d => Instruction::Dup2X1,
+            0x5e => Instruction::Dup2X2,
+            0x5f => Instruction::Swap,
+            0x60 => Instruction::Iadd,
+            0x61 => Instruction::Ladd,
+            0x62 => Instruction::Fadd,
+            0x63 => Instruction::Dadd,
+            0x64 => Instruction::Isub,
+            0x65 => Instruction::Lsub,
+            0x66 => Instruction::Fsub,
+            0x67 => Instruction::Dsub,
+            0x68 => Instruction::Imul,
+            0x69 => Instruction::Lmul,
+            0x6a => Instruction::Fmul,
+            0x6b => Instruction::Dmul,
+            0x6c => Instruction::Idiv,
+            0x6d => Instruction::Ldiv,
+            0x6e => Instruction::Fdiv,
+            0x6f => Instruction::Ddiv,
+            0x70 => Instruction::Irem,
+            0x71 => Instruction::Lrem,
+            0x72 => Instruction::Frem,
+            0x73 => Instruction::Drem,
+            0x74 => Instruction::Ineg,
+            0x75 => Instruction::Lneg,
+            0x76 => Instruction::Fneg,
+            0x77 => Instruction::Dneg,
+            0x78 => Instruction::Ishl,
+            0x79 => Instruction::Lshl,
+            0x7a => Instruction::Ishr,
+            0x7b => Instruction::Lshr,
+            0x7c => Instruction::Iushr,
+            0x7d => Instruction::Lushr,
+            0x7e => Instruction::Iand,
+            0x7f => Instruction::Land,
+            0x80 => Instruction::Ior,
+            0x81 => Instruction::Lor,
+            0x82 => Instruction::Ixor,
+            0x83 => Instruction::Lxor,
+            0x84 => Instruction::Iinc { index: self.read_u8()?, constant: self.read_i8()? },
+            0x85 => Instruction::I2l,
+            0x86 => Instruction::I2f,
+            0x87 => Instruction::I2d,
+            0x88 => Instruction::L2i,
+            0x89 => Instruction::L2f,
+            0x8a => Instruction::L2d,
+            0x8b => Instruction::F2i,
+            0x8c => Instruction::F2l,
+            0x8d => Instruction::F2d,
+            0x8e => Instruction::D2i,
+            0x8f => Instruction::D2l,
+            0x90 => Instruction::D2f,
+            0x91 => Instruction::I2b,
+            0x92 => Instruction::I2c,
+            0x93 => Instruction::I2s,
+            0x94 => Instruction::Lcmp,
+            0x95 => Instruction::Fcmpl,
+            0x96 => Instruction::Fcmpg,
+            0x97 => Instruction::Dcmpl,
+            0x98 => Instruction::Dcmpg,
+            0x99 => Instruction::Ifeq(self.read_branch()?),
+            0x9a => Instruction::Ifne(self.read_branch()?),
+            0x9b => Instruction::Iflt(self.read_branch()?),
+            0x9c => Instruction::Ifge(self.read_branch()?),
+            0x9d => Instruction::Ifgt(self.read_branch()?),
+            0x9e => Instruction::Ifle(self.read_branch()?),
+            0x9f => Instruction::IfIcmpeq(self.read_branch()?),
+            0xa0 => Instruction::IfIcmpne(self.read_branch()?),
+            0xa1 => Instruction::IfIcmplt(self.read_branch()?),
+            0xa2 => Instruction::IfIcmpge(self.read_branch()?),
+            0xa3 => Instruction::IfIcmpgt(self.read_branch()?),
+            0xa4 => Instruction::IfIcmple(self.read_branch()?),
+            0xa5 => Instruction::IfAcmpeq(self.read_branch()?),
+            0xa6 => Instruction::IfAcmpne(self.read_branch()?),
+            0xa7 => Instruction::Goto(self.read_branch()?),
+            0xa8 => Instruction::Jsr(self.read_branch()?),
+            0xa9 => Instruction::Ret(self.read_u8()?),
+            0xaa => {
+                self.skip_padding()?;
+                let default = self.read_branch_w()?;
+                let low = self.read_i32()?;
+                let high = self.read_i32()?;
+                if low > high {
+                    return Err(self.error(BytecodeErrorKind::BadSwitch("tableswitch low is greater than high")));
+                }
+                // Check the whole table is there before allocating for it
+                let count = (high as i64 - low as i64 + 1) as usize;
+                if count > self.remaining() / 4 {
+                    return Err(self.error(BytecodeErrorKind::Truncated));
+                }
+                let mut targets = Vec::with_capacity(count);
+                for _ in 0..count {
+                    targets.push(self.read_branch_w()?);
+                }
+                Instruction::Tableswitch { default, low, high, targets }
+            }
+            0xab => {
+                self.skip_padding()?;
+                let default = self.read_branch_w()?;
+                let npairs = self.read_i32()?;
+                if npairs < 0 {
+                    return Err(self.error(BytecodeErrorKind::BadSwitch("lookupswitch npairs is negative")));
+                }
+                if npairs as usize > self.remaining() / 8 {
+                    return Err(self.error(BytecodeErrorKind::Truncated));
+                }
+                let mut pairs: Vec<(i32, u32)> = Vec::with_capacity(npairs as usize);
+                for _ in 0..npairs {
+                    let key = self.read_i32()?;
+                    if pairs.last().is_some_and(|&(previous, _)| previous >= key) {
+                        return Err(self.error(BytecodeErrorKind::BadSwitch("lookupswitch keys are not sorted")));
+                    }
+                    pairs.push((key, self.read_branch_w()?));
+                }
+                Instruction::Lookupswitch { default, pairs }
+            }
+            0xac => Instruction::Ireturn,
+            0xad => Instruction::Lreturn,
+            0xae => Instruction::Freturn,
+            0xaf => Instruction::Dreturn,
+            0xb0 => Instruction::Areturn,
+            0xb1 => Instruction::Return,
+            0xb2 => Instruction::Getstatic(self.read_u16()?),
+            0xb3 => Instruction::Putstatic(self.read_u16()?),
+            0xb4 => Instruction::Getfield(self.read_u16()?),
+            0xb5 => Instruction::Putfield(self.read_u16()?),
+            0xb6 => Instruction::Invokevirtual(self.read_u16()?),
+            0xb7 => Instruction::Invokespecial(self.read_u16()?),
+            0xb8 => Instruction::Invokestatic(self.read_u16()?),
+            0xb9 => {
+                let index = self.read_u16()?;
+                let count = self.read_u8()?;
+                if count == 0 {
+                    return Err(self.error(BytecodeErrorKind::BadOperand("invokeinterface count must not be zero")));
+                }
+                if self.read_u8()? != 0 {
+                    return Err(self.error(BytecodeErrorKind::BadOperand("invokeinterface must end in a zero byte")));
+                }
+                Instruction::Invokeinterface { index, count }
+            }
+            0xba => {
+                let index = self.read_u16()?;
+                if self.read_u16()? != 0 {
+                    return Err(self.error(BytecodeErrorKind::BadOperand("invokedynamic must end in two zero bytes")));
+                }
+                Instruction::Invokedynamic(index)
+            }
+            0xbb => Instruction::New(self.read_u16()?),
+            0xbc => Instruction::Newarray(self.read_u8()?),
+            0xbd => Instruction::Anewarray(self.read_u16()?),
+            0xbe => Instruction::Arraylength,
+            0xbf => Instruction::Athrow,
+            0xc0 => Instruction::Checkcast(self.read_u16()?),
+            0xc1 => Instruction::Instanceof(self.read_u16()?),
+            0xc2 => Instruction::Monitorenter,
+            0xc3 => Instruction::Monitorexit,
+            0xc4 => {
+                let opcode = self.read_u8()?;
+                Instruction::Wide(match opcode {
+                    0x15 => WideInstruction::Iload(self.read_u16()?),
+                    0x16 => WideInstruction::Lload(self.read_u16()?),
+                    0x17 => WideInstruction::Fload(self.read_u16()?),
+                    0x18 => WideInstruction::Dload(self.read_u16()?),
+                    0x19 => WideInstruction::Aload(self.read_u16()?),
+                    0x36 => WideInstruction::Istore(self.read_u16()?),
+                    0x37 => WideInstruction::Lstore(self.read_u16()?),
+                    0x38 => WideInstruction::Fstore(self.read_u16()?),
+                    0x39 => WideInstruction::Dstore(self.read_u16()?),
+                    0x3a => WideInstruction::Astore(self.read_u16()?),
+                    0xa9 => WideInstruction::Ret(self.read_u16()?),
+                    0x84 => WideInstruction::Iinc { index: self.read_u16()?, constant: self.read_i16()? },
+                    _ => return Err(self.error(BytecodeErrorKind::BadWideOpcode(opcode))),
+                })
+            }
+            0xc5 => {
+                let index = self.read_u16()?;
+                let dimensions = self.read_u8()?;
+                if dimensions == 0 {
+                    return Err(self.error(BytecodeErrorKind::BadOperand("multianewarray dimensions must not be zero")));
+                }
+                Instruction::Multianewarray { index, dimensions }
+            }
+            0xc6 => Instruction::Ifnull(self.read_branch()?),
+            0xc7 => Instruction::Ifnonnull(self.read_branch()?),
+            0xc8 => Instruction::GotoW(self.read_branch_w()?),
+            0xc9 => Instruction::JsrW(self.read_branch_w()?),
+            _ => return Err(self.error(BytecodeErrorKind::UnknownOpcode(opcode))),
+        })
+    }
+
+    fn read_branch(&mut self) -> Result<u32, BytecodeError> {
+        let offset = self.read_i16()?;
+        self.branch(offset as i32)
+    }
+
+    fn read_branch_w(&mut self) -> Result<u32, BytecodeError> {
+        let offset = self.read_i32()?;
+        self.branch(offset)
+    }
+
+    // Turns an offset from the current instruction into a pc, which has to be inside the code
+    fn branch(&self, offset: i32) -> Result<u32, BytecodeError> {
+        let target = self.pc as i64 + offset as i64;
+        match target >= 0 && target < self.code.len() as i64 {
+            true => Ok(target as u32),
+            false => Err(self.error(BytecodeErrorKind::BranchOutOfRange { target })),
+        }
+    }
+
+    fn skip_padding(&mut self) -> Result<(), BytecodeError> {
+        let padding = (4 - self.index % 4) % 4;
+        self.read_slice(padding)?;
+        Ok(())
+    }
+
+    fn remaining(&self) -> usize {
+        self.code.len() - self.index
+    }
+
+    fn read_u8(&mut self) -> Result<u8, BytecodeError> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    fn read_i8(&mut self) -> Result<i8, BytecodeError> {
+        Ok(self.read_u8()? as i8)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, BytecodeError> {
+        let bytes = self.read_slice(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_i16(&mut self) -> Result<i16, BytecodeError> {
+        Ok(self.read_u16()? as i16)
+    }
+
+    fn read_i32(&mut self) -> Result<i32, BytecodeError> {
+        let bytes = self.read_slice(4)?;
+        Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_slice(&mut self, length: usize) -> Result<&'a [u8], BytecodeError> {
+        if length > self.remaining() {
+            return Err(self.error(BytecodeErrorKind::Truncated));
+        }
+        let slice = &self.code[self.index..self.index + length];
+        self.index += length;
+        Ok(slice)
+    }
+
+    fn error(&self, kind: BytecodeErrorKind) -> BytecodeError {
+        BytecodeError { pc: self.pc as u32, kind }
+    }
+}
+
+impl fmt::Display for BytecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bad instruction at pc {}: ", self.pc)?;
+        match &self.kind {
+            BytecodeErrorKind::UnknownOpcode(opcode) => write!(f, "unknown opcode 0x{:02x}", opcode),
+            BytecodeErrorKind::Truncated => write!(f, "instruction runs past the end of the code"),
+            BytecodeErrorKind::BadWideOpcode(opcode) => write!(f, "wide cannot modify opcode 0x{:02x}", opcode),
+            BytecodeErrorKind::BranchOutOfRange { target } => write!(f, "branch target {} is outside the code", target),
+            BytecodeErrorKind::BranchOffsetOverflow { target } =>
+                write!(f, "branch target {} is too far away for this instruction", target),
+            BytecodeErrorKind::BadSwitch(rule) => write!(f, "{}", rule),
+            BytecodeErrorKind::BadOperand(rule) => write!(f, "{}", rule),
+        }
+    }
+}
+
+impl Error for BytecodeError {}
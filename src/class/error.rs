@@ -0,0 +1,229 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::bytecode::BytecodeError;
+use crate::descriptor::DescriptorError;
+use crate::util::ReadError;
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.8
+#[derive(Debug)]
+pub struct ClassFormatError {
+    pub kind: ClassFormatErrorKind,
+    // Byte offset into the class file where the problem was found, if the class file is at hand
+    pub offset: Option<usize>,
+    // The structure being parsed, e.g. "method #3 attribute Code exception_table[1]"
+    pub context: String,
+}
+
+#[derive(Debug)]
+pub enum ClassFormatErrorKind {
+    BadMagic(u32),
+    UnsupportedVersion {
+        major_version: u16,
+        minor_version: u16,
+    },
+    // A preview class of the current version, loaded without --enable-preview
+    PreviewNotEnabled {
+        major_version: u16,
+    },
+    Truncated {
+        wanted: usize,
+        available: usize,
+    },
+    InvalidModifiedUtf8 {
+        // Offset of the offending byte within the string
+        offset: usize,
+    },
+    UnknownConstantPoolTag(u8),
+    // Zero, past the end of the constant pool, or the unusable slot after a Long or Double
+    BadConstantPoolIndex(u16),
+    WrongConstantKind {
+        index: u16,
+        expected: &'static str,
+    },
+    // A Long or Double whose second slot would be at constant_pool_count, past the end of the pool
+    ConstantPoolOverrun {
+        index: u16,
+        constant_pool_count: u16,
+    },
+    // A known attribute whose contents don't take up exactly attribute_length bytes
+    AttributeLengthMismatch {
+        attribute_length: u32,
+        consumed: usize,
+    },
+    // Attributes or annotation element values nested deeper than the loader follows them
+    NestedTooDeeply {
+        what: &'static str,
+        max: usize,
+    },
+    // Spec: frame types 128-246 are reserved
+    BadStackMapFrameType(u8),
+    // Writing: a frame that none of the frame types can express. Printing: a frame that isn't at an instruction
+    BadStackMapFrame(&'static str),
+    BadVerificationTypeTag(u8),
+    BadElementValueTag(u8),
+    BadTargetType(u8),
+    // A class with ACC_MODULE set breaks one of the rules for module-info classes
+    BadModuleInfo(&'static str),
+    // A combination of access flags the spec rules out
+    IllegalAccessFlags {
+        flags: u16,
+        rule: &'static str,
+    },
+    // Module and Package entries may only appear in module-info classes
+    MisplacedModuleConstant(u16),
+    InvalidName {
+        name: String,
+        expected: &'static str,
+    },
+    InvalidDescriptor(DescriptorError),
+    // A method's parameters, plus `this` for instance methods, take up more than 255 slots
+    TooManyParameterSlots(u32),
+    BadSuperclass(&'static str),
+    // <init> or <clinit> with the wrong descriptor
+    BadSpecialMethod(&'static str),
+    BadMethodHandle(&'static str),
+    // Points past the end of the BootstrapMethods attribute, or there is none
+    BadBootstrapMethodIndex(u16),
+    // The ConstantValue of a static field has the wrong kind of constant for its type
+    ConstantValueMismatch {
+        descriptor: String,
+    },
+    DuplicateMember {
+        name: String,
+        descriptor: String,
+    },
+    // An attribute that may appear at most once appears more than once
+    DuplicateAttribute(String),
+    BadCode(&'static str),
+    BadInstruction(BytecodeError),
+    PcOutOfRange {
+        pc: u32,
+        code_length: u32,
+    },
+    ExtraBytes(usize),
+    // Writing: an attribute whose name has no Utf8 entry in the constant pool
+    MissingAttributeName(String),
+    // Writing: an attribute whose name_index doesn't point at its name
+    AttributeNameMismatch {
+        index: u16,
+        name: String,
+    },
+    // Writing or adding constants: more entries or bytes than the count or length in front of them can express
+    CountOverflow {
+        count: usize,
+        max: usize,
+    },
+    // Printing: a class the Jasmin format can't write so that it loads again
+    NotPrintable(&'static str),
+}
+
+impl ClassFormatError {
+    pub fn new(kind: ClassFormatErrorKind, offset: Option<usize>, context: impl Into<String>) -> Self {
+        ClassFormatError { kind, offset, context: context.into() }
+    }
+}
+
+impl From<ReadError> for ClassFormatErrorKind {
+    fn from(error: ReadError) -> Self {
+        match error {
+            ReadError::UnexpectedEof { wanted, available } => ClassFormatErrorKind::Truncated { wanted, available },
+            ReadError::InvalidModifiedUtf8 { offset } => ClassFormatErrorKind::InvalidModifiedUtf8 { offset },
+        }
+    }
+}
+
+impl fmt::Display for ClassFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ClassFormatError")?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {:#x}", offset)?;
+        }
+        if !self.context.is_empty() {
+            write!(f, " in {}", self.context)?;
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+impl fmt::Display for ClassFormatErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClassFormatErrorKind::BadMagic(magic) =>
+                write!(f, "bad magic number {:#010x}, expected 0xcafebabe", magic),
+            ClassFormatErrorKind::UnsupportedVersion { major_version, minor_version } =>
+                write!(f, "unsupported class file version {}.{}", major_version, minor_version),
+            ClassFormatErrorKind::PreviewNotEnabled { major_version } =>
+                write!(f, "class file version {}.65535 uses preview features, which need --enable-preview", major_version),
+            ClassFormatErrorKind::Truncated { wanted, available } =>
+                write!(f, "truncated class file, wanted {} bytes but only {} are left", wanted, available),
+            ClassFormatErrorKind::InvalidModifiedUtf8 { offset } =>
+                write!(f, "invalid modified UTF-8 at byte {} of constant pool string", offset),
+            ClassFormatErrorKind::UnknownConstantPoolTag(tag) =>
+                write!(f, "unknown constant pool tag {}", tag),
+            ClassFormatErrorKind::BadConstantPoolIndex(index) =>
+                write!(f, "invalid constant pool index {}", index),
+            ClassFormatErrorKind::WrongConstantKind { index, expected } =>
+                write!(f, "expected {} at constant_pool[{}]", expected, index),
+            ClassFormatErrorKind::ConstantPoolOverrun { index, constant_pool_count } =>
+                write!(f, "constant_pool[{}] takes up two slots, but constant_pool_count is {}", index, constant_pool_count),
+            ClassFormatErrorKind::AttributeLengthMismatch { attribute_length, consumed } =>
+                write!(f, "attribute_length is {} but the attribute takes up {} bytes", attribute_length, consumed),
+            ClassFormatErrorKind::NestedTooDeeply { what, max } =>
+                write!(f, "{} are nested more than {} deep", what, max),
+            ClassFormatErrorKind::BadStackMapFrameType(frame_type) =>
+                write!(f, "reserved stack map frame type {}", frame_type),
+            ClassFormatErrorKind::BadStackMapFrame(rule) =>
+                write!(f, "invalid stack map frame, {}", rule),
+            ClassFormatErrorKind::BadVerificationTypeTag(tag) =>
+                write!(f, "unknown verification type tag {}", tag),
+            ClassFormatErrorKind::BadElementValueTag(tag) =>
+                write!(f, "unknown annotation element value tag '{}'", tag.escape_ascii()),
+            ClassFormatErrorKind::BadTargetType(target_type) =>
+                write!(f, "unknown type annotation target type {:#04x}", target_type),
+            ClassFormatErrorKind::IllegalAccessFlags { flags, rule } =>
+                write!(f, "illegal access flags 0x{:04x}: {}", flags, rule),
+            ClassFormatErrorKind::BadModuleInfo(rule) =>
+                write!(f, "invalid module-info class, {}", rule),
+            ClassFormatErrorKind::MisplacedModuleConstant(index) =>
+                write!(f, "constant_pool[{}] is a Module or Package, but this is not a module-info class", index),
+            ClassFormatErrorKind::InvalidName { name, expected } =>
+                write!(f, "{:?} is not a valid {}", name, expected),
+            ClassFormatErrorKind::InvalidDescriptor(error) =>
+                write!(f, "{}", error),
+            ClassFormatErrorKind::TooManyParameterSlots(slots) =>
+                write!(f, "parameters take up {} slots, at most 255 are allowed", slots),
+            ClassFormatErrorKind::BadSuperclass(rule) =>
+                write!(f, "invalid super_class, {}", rule),
+            ClassFormatErrorKind::BadSpecialMethod(rule) =>
+                write!(f, "{}", rule),
+            ClassFormatErrorKind::BadMethodHandle(rule) =>
+                write!(f, "invalid method handle, {}", rule),
+            ClassFormatErrorKind::BadBootstrapMethodIndex(index) =>
+                write!(f, "bootstrap method {} does not exist", index),
+            ClassFormatErrorKind::ConstantValueMismatch { descriptor } =>
+                write!(f, "ConstantValue does not match field type {}", descriptor),
+            ClassFormatErrorKind::DuplicateMember { name, descriptor } =>
+                write!(f, "duplicate member {} {}", name, descriptor),
+            ClassFormatErrorKind::DuplicateAttribute(name) =>
+                write!(f, "more than one {} attribute", name),
+            ClassFormatErrorKind::BadCode(rule) =>
+                write!(f, "{}", rule),
+            ClassFormatErrorKind::BadInstruction(error) => write!(f, "{}", error),
+            ClassFormatErrorKind::PcOutOfRange { pc, code_length } =>
+                write!(f, "pc {} is outside the code array of length {}", pc, code_length),
+            ClassFormatErrorKind::ExtraBytes(count) =>
+                write!(f, "{} extra bytes after the end of the class file", count),
+            ClassFormatErrorKind::MissingAttributeName(name) =>
+                write!(f, "attribute name {} is not in the constant pool", name),
+            ClassFormatErrorKind::AttributeNameMismatch { index, name } =>
+                write!(f, "attribute name_index {} does not point at the name {}", index, name),
+            ClassFormatErrorKind::CountOverflow { count, max } =>
+                write!(f, "count or length {} does not fit, at most {} is allowed", count, max),
+            ClassFormatErrorKind::NotPrintable(what) =>
+                write!(f, "{} can't be printed as Jasmin", what),
+        }
+    }
+}
+
+impl Error for ClassFormatError {}
@@ -0,0 +1,811 @@
+use std::collections::HashSet;
+
+use crate::attr::{Attribute, AttributeInfo};
+use crate::attr::annotation::{Annotation, ElementValue};
+use crate::attr::{StackMapFrameKind, VerificationTypeInfo};
+use crate::bytecode;
+use crate::bytecode::{Instruction, WideInstruction};
+use crate::class::ClassFile;
+use crate::class::error::{ClassFormatError, ClassFormatErrorKind};
+use crate::constant_pool::ConstantPoolInfo;
+use crate::descriptor::{FieldType, MethodDescriptor};
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.8
+// Format checking runs after loading and before linking. The loader stops at the first thing it can't
+// parse, but once the class file is parsed we can carry on past a violation, so we report all of them.
+pub fn check_format(class_file: &ClassFile) -> Result<(), Vec<ClassFormatError>> {
+    let mut checker = FormatChecker {
+        class_file,
+        context: Vec::new(),
+        errors: Vec::new(),
+    };
+    checker.check_constant_pool();
+    checker.check_class();
+    checker.check_fields();
+    checker.check_methods();
+    checker.context.push("class".to_string());
+    checker.check_attributes(&class_file.attributes, None);
+    checker.context.pop();
+
+    match checker.errors.is_empty() {
+        true => Ok(()),
+        false => Err(checker.errors),
+    }
+}
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.2
+// At least one character, and none of . ; [ /
+pub fn is_unqualified_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['.', ';', '[', '/'])
+}
+
+// Method names additionally can't contain < or >, except for <init> and <clinit>
+pub fn is_method_name(name: &str) -> bool {
+    name == "<init>" || name == "<clinit>" || (is_unqualified_name(name) && !name.contains(['<', '>']))
+}
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.1
+// Unqualified names separated by /, e.g. java/lang/Object
+pub fn is_binary_name(name: &str) -> bool {
+    name.split('/').all(is_unqualified_name)
+}
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.3
+pub fn is_module_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    if name.is_empty() {
+        return false;
+    }
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{0000}'..='\u{001f}' => return false,
+            // A backslash escapes one of \ : @ and nothing else
+            '\\' if !matches!(chars.next(), Some('\\' | ':' | '@')) => return false,
+            ':' | '@' => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+struct FormatChecker<'a> {
+    class_file: &'a ClassFile,
+    // The structures currently being checked, outermost first, for error reporting
+    context: Vec<String>,
+    errors: Vec<ClassFormatError>,
+}
+
+impl<'a> FormatChecker<'a> {
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4
+    fn check_constant_pool(&mut self) {
+        let is_module = self.class_file.access_flags.is_module();
+        for (index, info) in self.class_file.constant_pool.iter() {
+            self.context.push(format!("constant_pool[{}]", index));
+            match info {
+                ConstantPoolInfo::Class { name_index } => {
+                    if let Some(name) = self.utf8(*name_index) {
+                        // Array classes are named by their descriptor
+                        let valid = match name.starts_with('[') {
+                            true => FieldType::parse(name).is_ok(),
+                            false => is_binary_name(name),
+                        };
+                        if !valid {
+                            self.invalid_name(name, "class name");
+                        }
+                    }
+                }
+                ConstantPoolInfo::String { string_index } => {
+                    self.utf8(*string_index);
+                }
+                ConstantPoolInfo::FieldRef { class_index, name_and_type_index } => {
+                    self.class_name(*class_index);
+                    if let Some((name, descriptor)) = self.name_and_type(*name_and_type_index) {
+                        if !is_unqualified_name(name) {
+                            self.invalid_name(name, "field name");
+                        }
+                        self.field_descriptor(descriptor);
+                    }
+                }
+                ConstantPoolInfo::MethodRef { class_index, name_and_type_index } => {
+                    self.class_name(*class_index);
+                    if let Some((name, descriptor)) = self.name_and_type(*name_and_type_index) {
+                        // Spec: the only special name a Methodref may have is <init>, and it must return void
+                        if name == "<clinit>" || !is_method_name(name) {
+                            self.invalid_name(name, "method name");
+                        }
+                        let descriptor = self.method_descriptor(descriptor);
+                        if name == "<init>" && descriptor.is_some_and(|descriptor| descriptor.return_type.is_some()) {
+                            self.report(ClassFormatErrorKind::BadSpecialMethod("<init> must return void"));
+                        }
+                    }
+                }
+                ConstantPoolInfo::InterfaceMethodRef { class_index, name_and_type_index } => {
+                    self.class_name(*class_index);
+                    if let Some((name, descriptor)) = self.name_and_type(*name_and_type_index) {
+                        if name.starts_with('<') || !is_method_name(name) {
+                            self.invalid_name(name, "interface method name");
+                        }
+                        self.method_descriptor(descriptor);
+                    }
+                }
+                ConstantPoolInfo::NameAndType { name_index, descriptor_index } => {
+                    // Whether these have to be field or method names and descriptors depends on the referrer
+                    self.utf8(*name_index);
+                    self.utf8(*descriptor_index);
+                }
+                ConstantPoolInfo::MethodHandle { reference_kind, reference_index } => {
+                    self.check_method_handle(*reference_kind, *reference_index);
+                }
+                ConstantPoolInfo::MethodType { descriptor_index } => {
+                    if let Some(descriptor) = self.utf8(*descriptor_index) {
+                        self.method_descriptor(descriptor);
+                    }
+                }
+                ConstantPoolInfo::Dynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                    self.check_bootstrap_method_index(*bootstrap_method_attr_index);
+                    if let Some((name, descriptor)) = self.name_and_type(*name_and_type_index) {
+                        if !is_unqualified_name(name) {
+                            self.invalid_name(name, "dynamic constant name");
+                        }
+                        self.field_descriptor(descriptor);
+                    }
+                }
+                ConstantPoolInfo::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                    self.check_bootstrap_method_index(*bootstrap_method_attr_index);
+                    if let Some((name, descriptor)) = self.name_and_type(*name_and_type_index) {
+                        if name.starts_with('<') || !is_method_name(name) {
+                            self.invalid_name(name, "dynamic call site name");
+                        }
+                        self.method_descriptor(descriptor);
+                    }
+                }
+                ConstantPoolInfo::Module { name_index } => {
+                    if !is_module {
+                        self.report(ClassFormatErrorKind::MisplacedModuleConstant(index));
+                    }
+                    if let Some(name) = self.utf8(*name_index) {
+                        if !is_module_name(name) {
+                            self.invalid_name(name, "module name");
+                        }
+                    }
+                }
+                ConstantPoolInfo::Package { name_index } => {
+                    if !is_module {
+                        self.report(ClassFormatErrorKind::MisplacedModuleConstant(index));
+                    }
+                    if let Some(name) = self.utf8(*name_index) {
+                        if !is_binary_name(name) {
+                            self.invalid_name(name, "package name");
+                        }
+                    }
+                }
+                ConstantPoolInfo::Integer { .. } | ConstantPoolInfo::Float { .. } |
+                ConstantPoolInfo::Long { .. } | ConstantPoolInfo::Double { .. } |
+                ConstantPoolInfo::Utf8 { .. } | ConstantPoolInfo::Unusable => {}
+            }
+            self.context.pop();
+        }
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4.8
+    fn check_method_handle(&mut self, reference_kind: u8, reference_index: u16) {
+        let reference = match self.constant(reference_index) {
+            Some(reference) => reference,
+            None => return,
+        };
+        let name_and_type_index = match (reference_kind, reference) {
+            // REF_getField, REF_getStatic, REF_putField, REF_putStatic
+            (1..=4, ConstantPoolInfo::FieldRef { name_and_type_index, .. }) => *name_and_type_index,
+            // REF_invokeVirtual, REF_newInvokeSpecial
+            (5 | 8, ConstantPoolInfo::MethodRef { name_and_type_index, .. }) => *name_and_type_index,
+            // REF_invokeStatic, REF_invokeSpecial, which may only point at interface methods from 52.0 onwards
+            (6 | 7, ConstantPoolInfo::MethodRef { name_and_type_index, .. }) => *name_and_type_index,
+            (6 | 7, ConstantPoolInfo::InterfaceMethodRef { name_and_type_index, .. })
+            if self.class_file.major_version >= 52 => *name_and_type_index,
+            // REF_invokeInterface
+            (9, ConstantPoolInfo::InterfaceMethodRef { name_and_type_index, .. }) => *name_and_type_index,
+            (1..=9, _) => {
+                self.report(ClassFormatErrorKind::BadMethodHandle("reference_index has the wrong kind for reference_kind"));
+                return;
+            }
+            _ => {
+                self.report(ClassFormatErrorKind::BadMethodHandle("reference_kind must be between 1 and 9"));
+                return;
+            }
+        };
+        if let Some((name, _)) = self.name_and_type(name_and_type_index) {
+            match reference_kind {
+                5 | 6 | 7 | 9 if name == "<init>" || name == "<clinit>" =>
+                    self.report(ClassFormatErrorKind::BadMethodHandle("only REF_newInvokeSpecial may refer to <init>")),
+                8 if name != "<init>" =>
+                    self.report(ClassFormatErrorKind::BadMethodHandle("REF_newInvokeSpecial must refer to <init>")),
+                _ => {}
+            }
+        }
+    }
+
+    fn check_bootstrap_method_index(&mut self, index: u16) {
+        let count = self.class_file.attributes.iter().find_map(|attribute| match &attribute.info {
+            AttributeInfo::BootstrapMethods { bootstrap_methods } => Some(bootstrap_methods.len()),
+            _ => None
+        });
+        if count.is_none_or(|count| index as usize >= count) {
+            self.report(ClassFormatErrorKind::BadBootstrapMethodIndex(index));
+        }
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1
+    fn check_class(&mut self) {
+        let class_file = self.class_file;
+        self.context.push("this_class".to_string());
+        let this_class = self.class_name(class_file.this_class);
+        self.context.pop();
+
+        self.context.push("access_flags".to_string());
+        if let Err(kind) = class_file.access_flags.validate(class_file.major_version) {
+            self.report(kind);
+        }
+        self.context.pop();
+
+        // Module rules are enforced by the loader, which knows where things are in the file
+        if class_file.access_flags.is_module() {
+            return;
+        }
+
+        self.context.push("super_class".to_string());
+        match class_file.super_class {
+            0 if this_class != Some("java/lang/Object") =>
+                self.report(ClassFormatErrorKind::BadSuperclass("only java/lang/Object has no superclass")),
+            0 => {}
+            _ if this_class == Some("java/lang/Object") =>
+                self.report(ClassFormatErrorKind::BadSuperclass("java/lang/Object has no superclass")),
+            index => {
+                let super_class = self.class_name(index);
+                if class_file.access_flags.is_interface() && super_class.is_some() &&
+                    super_class != Some("java/lang/Object") {
+                    self.report(ClassFormatErrorKind::BadSuperclass("the superclass of an interface must be java/lang/Object"));
+                }
+            }
+        }
+        self.context.pop();
+
+        for (i, &interface) in class_file.interfaces.iter().enumerate() {
+            self.context.push(format!("interfaces[{}]", i));
+            self.class_name(interface);
+            self.context.pop();
+        }
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.5
+    fn check_fields(&mut self) {
+        let mut seen = HashSet::new();
+        for (i, field) in self.class_file.fields.iter().enumerate() {
+            self.context.push(format!("field #{}", i));
+            let name = self.utf8(field.name_index);
+            if let Some(name) = name {
+                if !is_unqualified_name(name) {
+                    self.invalid_name(name, "field name");
+                }
+            }
+            let descriptor = self.utf8(field.descriptor_index);
+            if let Some(descriptor) = descriptor {
+                self.field_descriptor(descriptor);
+            }
+            if let (Some(name), Some(descriptor)) = (name, descriptor) {
+                if !seen.insert((name, descriptor)) {
+                    self.report(ClassFormatErrorKind::DuplicateMember {
+                        name: name.to_string(),
+                        descriptor: descriptor.to_string(),
+                    });
+                }
+            }
+
+            if let Err(kind) = field.access_flags.validate(self.class_file.access_flags.is_interface()) {
+                self.report(kind);
+            }
+
+            // Spec: the ConstantValue of a non-static field is silently ignored
+            let is_static = field.access_flags.is_static();
+            for attribute in &field.attributes {
+                if let (AttributeInfo::ConstantValue { constantvalue_index }, Some(descriptor), true) =
+                    (&attribute.info, descriptor, is_static) {
+                    self.context.push("attribute ConstantValue".to_string());
+                    self.check_constant_value(*constantvalue_index, descriptor);
+                    self.context.pop();
+                }
+            }
+            self.check_attributes(&field.attributes, None);
+            self.context.pop();
+        }
+    }
+
+    fn check_constant_value(&mut self, index: u16, descriptor: &str) {
+        let constant = match self.constant(index) {
+            Some(constant) => constant,
+            None => return,
+        };
+        let matches = match descriptor {
+            "J" => matches!(constant, ConstantPoolInfo::Long { .. }),
+            "F" => matches!(constant, ConstantPoolInfo::Float { .. }),
+            "D" => matches!(constant, ConstantPoolInfo::Double { .. }),
+            "I" | "S" | "C" | "B" | "Z" => matches!(constant, ConstantPoolInfo::Integer { .. }),
+            "Ljava/lang/String;" => matches!(constant, ConstantPoolInfo::String { .. }),
+            _ => false,
+        };
+        if !matches {
+            self.report(ClassFormatErrorKind::ConstantValueMismatch { descriptor: descriptor.to_string() });
+        }
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.6
+    fn check_methods(&mut self) {
+        let mut seen = HashSet::new();
+        for (i, method) in self.class_file.methods.iter().enumerate() {
+            self.context.push(format!("method #{}", i));
+            let name = self.utf8(method.name_index);
+            if let Some(name) = name {
+                if !is_method_name(name) {
+                    self.invalid_name(name, "method name");
+                }
+            }
+            let descriptor = self.utf8(method.descriptor_index);
+            let parsed = descriptor.and_then(|descriptor| self.method_descriptor(descriptor));
+            match (name, &parsed) {
+                (Some("<init>"), Some(parsed)) if parsed.return_type.is_some() =>
+                    self.report(ClassFormatErrorKind::BadSpecialMethod("<init> must return void")),
+                (Some("<clinit>"), Some(parsed)) if parsed.return_type.is_some() =>
+                    self.report(ClassFormatErrorKind::BadSpecialMethod("<clinit> must return void")),
+                (Some("<clinit>"), Some(parsed))
+                if self.class_file.major_version >= 51 && !parsed.parameters.is_empty() =>
+                    self.report(ClassFormatErrorKind::BadSpecialMethod("<clinit> must take no arguments")),
+                _ => {}
+            }
+            // Spec: from version 51 on, a <clinit> that isn't static isn't the class initializer, which
+            // HotSpot refuses rather than ignore. Interfaces have no instance initializers at all.
+            match name {
+                Some("<clinit>") if self.class_file.major_version >= 51 && !method.access_flags.is_static() =>
+                    self.report(ClassFormatErrorKind::BadSpecialMethod("<clinit> must be static")),
+                Some("<init>") if self.class_file.access_flags.is_interface() =>
+                    self.report(ClassFormatErrorKind::BadSpecialMethod("an interface must not have an <init> method")),
+                _ => {}
+            }
+            if let Some(name) = name {
+                let in_interface = self.class_file.access_flags.is_interface();
+                if let Err(kind) = method.access_flags.validate(name, in_interface, self.class_file.major_version) {
+                    self.report(kind);
+                }
+            }
+            // Spec: the parameters, plus `this` for instance methods, may take up at most 255 slots
+            if let Some(parsed) = &parsed {
+                let slots = parsed.parameter_slots() + !method.access_flags.is_static() as u32;
+                if slots > 255 {
+                    self.report(ClassFormatErrorKind::TooManyParameterSlots(slots));
+                }
+            }
+            if let (Some(name), Some(descriptor)) = (name, descriptor) {
+                if !seen.insert((name, descriptor)) {
+                    self.report(ClassFormatErrorKind::DuplicateMember {
+                        name: name.to_string(),
+                        descriptor: descriptor.to_string(),
+                    });
+                }
+            }
+
+            // Spec: abstract and native methods have no Code attribute, every other method has exactly one
+            let code_count = method.attributes.iter()
+                .filter(|attribute| matches!(attribute.info, AttributeInfo::Code { .. }))
+                .count();
+            let has_body = !method.access_flags.is_abstract() && !method.access_flags.is_native();
+            if has_body && code_count == 0 {
+                self.report(ClassFormatErrorKind::BadCode("method must have a Code attribute"));
+            }
+            if !has_body && code_count != 0 {
+                self.report(ClassFormatErrorKind::BadCode("abstract and native methods must not have a Code attribute"));
+            }
+
+            self.check_attributes(&method.attributes, None);
+            self.context.pop();
+        }
+    }
+
+    // code_length is set for the attributes of a Code attribute
+    fn check_attributes(&mut self, attributes: &'a [Attribute], code_length: Option<u32>) {
+        let mut seen = HashSet::new();
+        for attribute in attributes {
+            let name = attribute.name();
+            // Spec: these may appear at most once in any one attributes table
+            let at_most_once = !matches!(attribute.info, AttributeInfo::LineNumberTable { .. } |
+                AttributeInfo::LocalVariableTable { .. } | AttributeInfo::LocalVariableTypeTable { .. } |
+                AttributeInfo::Synthetic | AttributeInfo::Deprecated | AttributeInfo::Unknown { .. });
+            if at_most_once && !seen.insert(name) {
+                self.report(ClassFormatErrorKind::DuplicateAttribute(name.to_string()));
+            }
+
+            self.context.push(format!("attribute {}", name));
+            self.check_attribute(&attribute.info, code_length);
+            self.context.pop();
+        }
+    }
+
+    fn check_attribute(&mut self, attribute: &'a AttributeInfo, code_length: Option<u32>) {
+        match attribute {
+            AttributeInfo::ConstantValue { constantvalue_index } => {
+                self.constant(*constantvalue_index);
+            }
+            AttributeInfo::Code { code, exception_tables, attributes, .. } => {
+                let code_length = code.len() as u32;
+                // Spec: the code array must not be empty and code_length must be less than 65536
+                if code.is_empty() || code.len() > 65535 {
+                    self.report(ClassFormatErrorKind::BadCode("code_length must be between 1 and 65535"));
+                }
+                for (i, exception_table) in exception_tables.iter().enumerate() {
+                    self.context.push(format!("exception_table[{}]", i));
+                    if exception_table.start_pc >= exception_table.end_pc {
+                        self.report(ClassFormatErrorKind::BadCode("start_pc must be less than end_pc"));
+                    }
+                    // end_pc is exclusive, so it may point just past the end of the code
+                    if exception_table.end_pc as u32 > code_length {
+                        self.report(ClassFormatErrorKind::PcOutOfRange { pc: exception_table.end_pc as u32, code_length });
+                    }
+                    self.check_pc(exception_table.start_pc as u32, code_length);
+                    self.check_pc(exception_table.handler_pc as u32, code_length);
+                    if exception_table.catch_type != 0 {
+                        self.class_name(exception_table.catch_type);
+                    }
+                    self.context.pop();
+                }
+                self.check_instructions(code, !exception_tables.is_empty(), attributes);
+                self.check_attributes(attributes, Some(code_length));
+            }
+            AttributeInfo::LineNumberTable { entries } => {
+                if let Some(code_length) = code_length {
+                    for entry in entries {
+                        self.check_pc(entry.start_pc as u32, code_length);
+                    }
+                }
+            }
+            AttributeInfo::LocalVariableTable { entries } => {
+                for (i, entry) in entries.iter().enumerate() {
+                    self.context.push(format!("local_variable_table[{}]", i));
+                    self.check_pc_range(entry.start_pc, entry.length, code_length);
+                    if let Some(name) = self.utf8(entry.name_index) {
+                        if !is_unqualified_name(name) {
+                            self.invalid_name(name, "local variable name");
+                        }
+                    }
+                    if let Some(descriptor) = self.utf8(entry.descriptor_index) {
+                        self.field_descriptor(descriptor);
+                    }
+                    self.context.pop();
+                }
+            }
+            AttributeInfo::LocalVariableTypeTable { entries } => {
+                for (i, entry) in entries.iter().enumerate() {
+                    self.context.push(format!("local_variable_type_table[{}]", i));
+                    self.check_pc_range(entry.start_pc, entry.length, code_length);
+                    if let Some(name) = self.utf8(entry.name_index) {
+                        if !is_unqualified_name(name) {
+                            self.invalid_name(name, "local variable name");
+                        }
+                    }
+                    self.utf8(entry.signature_index);
+                    self.context.pop();
+                }
+            }
+            AttributeInfo::StackMapTable { entries } => {
+                for (i, entry) in entries.iter().enumerate() {
+                    self.context.push(format!("entries[{}]", i));
+                    if let Some(code_length) = code_length {
+                        self.check_pc(entry.offset, code_length);
+                    }
+                    let types: Vec<&VerificationTypeInfo> = match &entry.kind {
+                        StackMapFrameKind::SameLocals1StackItem { stack } |
+                        StackMapFrameKind::SameLocals1StackItemExtended { stack } => vec![stack],
+                        StackMapFrameKind::Append { locals } => locals.iter().collect(),
+                        StackMapFrameKind::Full { locals, stack } => locals.iter().chain(stack.iter()).collect(),
+                        _ => Vec::new(),
+                    };
+                    for verification_type in types {
+                        match verification_type {
+                            VerificationTypeInfo::Object { cpool_index } => {
+                                self.class_name(*cpool_index);
+                            }
+                            VerificationTypeInfo::Uninitialized { offset } => {
+                                if let Some(code_length) = code_length {
+                                    self.check_pc(*offset as u32, code_length);
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    self.context.pop();
+                }
+            }
+            AttributeInfo::SourceFile { sourcefile_index } => {
+                self.utf8(*sourcefile_index);
+            }
+            AttributeInfo::BootstrapMethods { bootstrap_methods } => {
+                for (i, bootstrap_method) in bootstrap_methods.iter().enumerate() {
+                    self.context.push(format!("bootstrap_methods[{}]", i));
+                    self.expect(bootstrap_method.bootstrap_method_ref, "MethodHandle",
+                                |info| matches!(info, ConstantPoolInfo::MethodHandle { .. }));
+                    for &argument in &bootstrap_method.bootstrap_arguments {
+                        self.expect(argument, "loadable constant", |info| matches!(info,
+                            ConstantPoolInfo::Integer { .. } | ConstantPoolInfo::Float { .. } |
+                            ConstantPoolInfo::Long { .. } | ConstantPoolInfo::Double { .. } |
+                            ConstantPoolInfo::Class { .. } | ConstantPoolInfo::String { .. } |
+                            ConstantPoolInfo::MethodHandle { .. } | ConstantPoolInfo::MethodType { .. } |
+                            ConstantPoolInfo::Dynamic { .. }));
+                    }
+                    self.context.pop();
+                }
+            }
+            AttributeInfo::NestHost { host_class_index } => {
+                self.class_name(*host_class_index);
+            }
+            AttributeInfo::NestMembers { classes } | AttributeInfo::PermittedSubclasses { classes } => {
+                for &class in classes {
+                    self.class_name(class);
+                }
+            }
+            AttributeInfo::Record { components } => {
+                for (i, component) in components.iter().enumerate() {
+                    self.context.push(format!("component #{}", i));
+                    if let Some(name) = self.utf8(component.name_index) {
+                        if !is_unqualified_name(name) {
+                            self.invalid_name(name, "record component name");
+                        }
+                    }
+                    if let Some(descriptor) = self.utf8(component.descriptor_index) {
+                        self.field_descriptor(descriptor);
+                    }
+                    self.check_attributes(&component.attributes, None);
+                    self.context.pop();
+                }
+            }
+            AttributeInfo::InnerClasses { classes } => {
+                for (i, inner_class) in classes.iter().enumerate() {
+                    self.context.push(format!("classes[{}]", i));
+                    self.class_name(inner_class.inner_class_info_index);
+                    if inner_class.outer_class_info_index != 0 {
+                        self.class_name(inner_class.outer_class_info_index);
+                    }
+                    if inner_class.inner_name_index != 0 {
+                        self.utf8(inner_class.inner_name_index);
+                    }
+                    self.context.pop();
+                }
+            }
+            AttributeInfo::EnclosingMethod { class_index, method_index } => {
+                self.class_name(*class_index);
+                if *method_index != 0 {
+                    self.name_and_type(*method_index);
+                }
+            }
+            AttributeInfo::Signature { signature_index } => {
+                self.utf8(*signature_index);
+            }
+            AttributeInfo::Exceptions { exception_index_table } => {
+                for &exception in exception_index_table {
+                    self.class_name(exception);
+                }
+            }
+            AttributeInfo::MethodParameters { parameters } => {
+                for (i, parameter) in parameters.iter().enumerate() {
+                    if parameter.name_index != 0 {
+                        self.context.push(format!("parameters[{}]", i));
+                        if let Some(name) = self.utf8(parameter.name_index) {
+                            if !is_unqualified_name(name) {
+                                self.invalid_name(name, "parameter name");
+                            }
+                        }
+                        self.context.pop();
+                    }
+                }
+            }
+            AttributeInfo::RuntimeVisibleAnnotations { annotations } |
+            AttributeInfo::RuntimeInvisibleAnnotations { annotations } => {
+                for annotation in annotations {
+                    self.check_annotation(annotation);
+                }
+            }
+            AttributeInfo::RuntimeVisibleParameterAnnotations { parameter_annotations } |
+            AttributeInfo::RuntimeInvisibleParameterAnnotations { parameter_annotations } => {
+                for annotation in parameter_annotations.iter().flatten() {
+                    self.check_annotation(annotation);
+                }
+            }
+            AttributeInfo::RuntimeVisibleTypeAnnotations { annotations } |
+            AttributeInfo::RuntimeInvisibleTypeAnnotations { annotations } => {
+                for type_annotation in annotations {
+                    self.check_annotation(&type_annotation.annotation);
+                }
+            }
+            AttributeInfo::AnnotationDefault { default_value } => {
+                self.check_element_value(default_value);
+            }
+            AttributeInfo::Module { .. } => {
+                if let Err(error) = self.class_file.module_descriptor() {
+                    self.report(error.kind);
+                }
+            }
+            AttributeInfo::ModulePackages { package_index } => {
+                for &index in package_index {
+                    self.expect(index, "Package", |info| matches!(info, ConstantPoolInfo::Package { .. }));
+                }
+            }
+            AttributeInfo::ModuleMainClass { main_class_index } => {
+                self.class_name(*main_class_index);
+            }
+            AttributeInfo::Synthetic | AttributeInfo::Deprecated |
+            AttributeInfo::SourceDebugExtension { .. } | AttributeInfo::Unknown { .. } => {}
+        }
+    }
+
+    fn check_annotation(&mut self, annotation: &Annotation) {
+        if let Some(descriptor) = self.utf8(annotation.type_index) {
+            self.field_descriptor(descriptor);
+        }
+        for pair in &annotation.element_value_pairs {
+            self.utf8(pair.element_name_index);
+            self.check_element_value(&pair.value);
+        }
+    }
+
+    fn check_element_value(&mut self, value: &ElementValue) {
+        match value {
+            ElementValue::Const { tag, const_value_index } => {
+                let (expected, matches): (&'static str, fn(&ConstantPoolInfo) -> bool) = match tag {
+                    b'D' => ("Double", |info| matches!(info, ConstantPoolInfo::Double { .. })),
+                    b'F' => ("Float", |info| matches!(info, ConstantPoolInfo::Float { .. })),
+                    b'J' => ("Long", |info| matches!(info, ConstantPoolInfo::Long { .. })),
+                    b's' => ("Utf8", |info| matches!(info, ConstantPoolInfo::Utf8 { .. })),
+                    _ => ("Integer", |info| matches!(info, ConstantPoolInfo::Integer { .. })),
+                };
+                self.expect(*const_value_index, expected, matches);
+            }
+            ElementValue::Enum { type_name_index, const_name_index } => {
+                if let Some(descriptor) = self.utf8(*type_name_index) {
+                    self.field_descriptor(descriptor);
+                }
+                self.utf8(*const_name_index);
+            }
+            ElementValue::Class { class_info_index } => {
+                if let Some(descriptor) = self.utf8(*class_info_index) {
+                    if descriptor != "V" {
+                        self.field_descriptor(descriptor);
+                    }
+                }
+            }
+            ElementValue::Annotation(annotation) => self.check_annotation(annotation),
+            ElementValue::Array(values) => {
+                for value in values {
+                    self.check_element_value(value);
+                }
+            }
+        }
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.9.1
+    // Only that the code decodes and the rules that depend on the class file version, the verifier does the rest
+    fn check_instructions(&mut self, code: &[u8], has_handlers: bool, attributes: &[Attribute]) {
+        let instructions = match bytecode::decode(code) {
+            Ok(instructions) => instructions,
+            Err(error) => {
+                self.report(ClassFormatErrorKind::BadInstruction(error));
+                return;
+            }
+        };
+        let mut has_branches = false;
+        for (pc, instruction) in &instructions {
+            let is_jsr_ret = matches!(instruction, Instruction::Jsr(_) | Instruction::JsrW(_) | Instruction::Ret(_) |
+                Instruction::Wide(WideInstruction::Ret(_)));
+            if is_jsr_ret && !self.class_file.allows_jsr_ret() {
+                self.context.push(format!("pc {}", pc));
+                self.report(ClassFormatErrorKind::BadCode("jsr and ret are not allowed from version 51 on"));
+                self.context.pop();
+            }
+            has_branches |= !instruction.branch_targets().is_empty();
+        }
+
+        let has_stack_map_table = attributes.iter()
+            .any(|attribute| matches!(attribute.info, AttributeInfo::StackMapTable { .. }));
+        if self.class_file.requires_stack_map_table() && (has_branches || has_handlers) && !has_stack_map_table {
+            self.report(ClassFormatErrorKind::BadCode("code with branches or exception handlers needs a StackMapTable from version 50 on"));
+        }
+    }
+
+    fn check_pc(&mut self, pc: u32, code_length: u32) {
+        if pc >= code_length {
+            self.report(ClassFormatErrorKind::PcOutOfRange { pc, code_length });
+        }
+    }
+
+    // Spec: start_pc + length may point just past the end of the code
+    fn check_pc_range(&mut self, start_pc: u16, length: u16, code_length: Option<u32>) {
+        if let Some(code_length) = code_length {
+            self.check_pc(start_pc as u32, code_length);
+            let end = start_pc as u32 + length as u32;
+            if end > code_length {
+                self.report(ClassFormatErrorKind::PcOutOfRange { pc: end, code_length });
+            }
+        }
+    }
+
+    fn field_descriptor(&mut self, descriptor: &str) -> Option<FieldType> {
+        match FieldType::parse(descriptor) {
+            Ok(field_type) => Some(field_type),
+            Err(error) => {
+                self.report(ClassFormatErrorKind::InvalidDescriptor(error));
+                None
+            }
+        }
+    }
+
+    fn method_descriptor(&mut self, descriptor: &str) -> Option<MethodDescriptor> {
+        match MethodDescriptor::parse(descriptor) {
+            Ok(method_descriptor) => Some(method_descriptor),
+            Err(error) => {
+                self.report(ClassFormatErrorKind::InvalidDescriptor(error));
+                None
+            }
+        }
+    }
+
+    fn invalid_name(&mut self, name: &str, expected: &'static str) {
+        self.report(ClassFormatErrorKind::InvalidName { name: name.to_string(), expected });
+    }
+
+    fn constant(&mut self, index: u16) -> Option<&'a ConstantPoolInfo> {
+        match self.class_file.constant_pool.get(index) {
+            Ok(info) => Some(info),
+            Err(kind) => {
+                self.report(kind);
+                None
+            }
+        }
+    }
+
+    fn expect(&mut self, index: u16, expected: &'static str, matches: fn(&ConstantPoolInfo) -> bool)
+              -> Option<&'a ConstantPoolInfo> {
+        let info = self.constant(index)?;
+        if !matches(info) {
+            self.report(ClassFormatErrorKind::WrongConstantKind { index, expected });
+            return None;
+        }
+        Some(info)
+    }
+
+    fn utf8(&mut self, index: u16) -> Option<&'a str> {
+        match self.class_file.constant_pool.utf8(index) {
+            Ok(string) => Some(string),
+            Err(kind) => {
+                self.report(kind);
+                None
+            }
+        }
+    }
+
+    // The name of the Class entry at index
+    fn class_name(&mut self, index: u16) -> Option<&'a str> {
+        match self.class_file.constant_pool.class_name(index) {
+            Ok(name) => Some(name),
+            Err(kind) => {
+                self.report(kind);
+                None
+            }
+        }
+    }
+
+    fn name_and_type(&mut self, index: u16) -> Option<(&'a str, &'a str)> {
+        match self.expect(index, "NameAndType", |info| matches!(info, ConstantPoolInfo::NameAndType { .. }))? {
+            ConstantPoolInfo::NameAndType { name_index, descriptor_index } =>
+                Some((self.utf8(*name_index)?, self.utf8(*descriptor_index)?)),
+            _ => None,
+        }
+    }
+
+    fn report(&mut self, kind: ClassFormatErrorKind) {
+        self.errors.push(ClassFormatError::new(kind, None, self.context.join(" ")));
+    }
+}
@@ -1,36 +1,64 @@
-use crate::attr::{AttributeInfo, ExceptionTable, LineNumberTableEntry};
+use std::io::{self, Read};
+
+use crate::attr::annotation::{Annotation, ElementValue, ElementValuePair, LocalvarTarget, TargetInfo,
+                              TypeAnnotation, TypePathEntry};
+use crate::attr::module::{Exports, Module, Opens, Provides, Requires};
+use crate::attr::{Attribute, AttributeInfo, BootstrapMethod, ExceptionTable, InnerClass, LineNumberTableEntry,
+                  LocalVariableTableEntry, LocalVariableTypeTableEntry, MethodParameter, RecordComponentInfo,
+                  StackMapFrame, StackMapFrameKind, VerificationTypeInfo};
 use crate::attr::AttributeInfo::SourceFile;
 use crate::class::ClassFile;
-use crate::class::ClassFlags;
-use crate::constant_pool::ConstantPoolInfo;
-use crate::field::FieldInfo;
-use crate::method::MethodInfo;
+use crate::class::ClassAccessFlags;
+use crate::class::version::check_version;
+use crate::class::error::{ClassFormatError, ClassFormatErrorKind};
+use crate::constant_pool::{ConstantPool, ConstantPoolInfo};
+use crate::field::{FieldAccessFlags, FieldInfo};
+use crate::method::{MethodAccessFlags, MethodInfo};
 use crate::util::ByteReader;
+use crate::util::mutf8::ModifiedUtf8;
+
+// Attributes only nest inside Code and Record, so real class files are at most two deep. The limits
+// keep malformed ones from recursing until the stack runs out.
+const MAX_ATTRIBUTE_DEPTH: usize = 8;
+const MAX_ELEMENT_VALUE_DEPTH: usize = 64;
+// Spec: code_length must be less than 65536
+const MAX_CODE_LENGTH: u32 = 65535;
 
-pub struct ClassFileLoader {
-    reader: ByteReader,
+pub struct ClassFileLoader<'a> {
+    reader: ByteReader<'a>,
+    // The structures currently being parsed, outermost first, for error reporting
+    context: Vec<String>,
+    enable_preview: bool,
+    // How many read_attributes and read_element_value calls are in progress
+    attribute_depth: usize,
+    element_value_depth: usize,
     minor_version: u16,
     major_version: u16,
-    constant_pool: Vec<ConstantPoolInfo>,
-    // TODO: Should be of ClassFlags type?
-    access_flags: u16,
+    constant_pool: ConstantPool,
+    access_flags: ClassAccessFlags,
     this_class: u16,
     super_class: u16,
     // These values are pointers into the constant pool
     interfaces: Vec<u16>,
     fields: Vec<FieldInfo>,
     methods: Vec<MethodInfo>,
-    attributes: Vec<AttributeInfo>,
+    attributes: Vec<Attribute>,
 }
 
-impl ClassFileLoader {
-    pub fn new(class_file_contents: Vec<u8>) -> ClassFileLoader {
+impl<'a> ClassFileLoader<'a> {
+    // Loads from bytes in memory, e.g. a file that was read or memory-mapped (see util::map_file). Only
+    // what ends up in the ClassFile, like code arrays and strings, is copied out of them.
+    pub fn new(class_file_contents: &'a [u8]) -> ClassFileLoader<'a> {
         ClassFileLoader {
             reader: ByteReader::new(class_file_contents),
+            context: Vec::new(),
+            enable_preview: false,
+            attribute_depth: 0,
+            element_value_depth: 0,
             minor_version: 0,
             major_version: 0,
-            constant_pool: Vec::new(),
-            access_flags: 0,
+            constant_pool: ConstantPool::new(),
+            access_flags: ClassAccessFlags::default(),
             this_class: 0,
             super_class: 0,
             interfaces: Vec::new(),
@@ -40,152 +68,251 @@ impl ClassFileLoader {
         }
     }
 
-    pub fn load(mut self) -> ClassFile {
-        let magic: u32 = self.reader.read_u32();
-        assert_eq!(magic, 0xcafebabe);
-        self.minor_version = self.reader.read_u16();
-        print!("minor_version: {}\n", self.minor_version);
-        self.major_version = self.reader.read_u16();
-        print!("major_version: {}\n", self.major_version);
-        let constant_pool_count: u16 = self.reader.read_u16();
-        print!("constant_pool_count: {}\n", constant_pool_count);
-
-        for _ in 0..constant_pool_count - 1 { // Constant pool index starts at 1
-            let tag = self.reader.read_u8();
-            match tag {
-                9 => {
-                    let info = ConstantPoolInfo::FieldRef {
-                        class_index: self.reader.read_u16(),
-                        name_and_type_index: self.reader.read_u16(),
-                    };
-                    println!("{:?}", info);
-                    self.constant_pool.push(info);
-                }
-                10 => {
-                    let info = ConstantPoolInfo::MethodRef {
-                        class_index: self.reader.read_u16(),
-                        name_and_type_index: self.reader.read_u16(),
-                    };
-                    println!("{:?}", info);
-                    self.constant_pool.push(info);
-                }
-                8 => {
-                    let info = ConstantPoolInfo::String {
-                        string_index: self.reader.read_u16(),
-                    };
-                    println!("{:?}", info);
-                    self.constant_pool.push(info);
-                }
-                7 => {
-                    let info = ConstantPoolInfo::Class {
-                        name_index: self.reader.read_u16(),
-                    };
-                    println!("{:?}", info);
-                    self.constant_pool.push(info);
-                }
+    // Reads the whole class file from source, e.g. a stream from an archive, into buffer and loads it from
+    // there. The buffer is cleared first, so one can be reused for many class files.
+    pub fn from_reader(mut source: impl Read, buffer: &'a mut Vec<u8>) -> io::Result<ClassFileLoader<'a>> {
+        buffer.clear();
+        source.read_to_end(buffer)?;
+        Ok(ClassFileLoader::new(buffer))
+    }
+
+    // Accept classes that use the preview features of MAX_MAJOR_VERSION, like java --enable-preview
+    pub fn enable_preview(mut self, enable_preview: bool) -> ClassFileLoader<'a> {
+        self.enable_preview = enable_preview;
+        self
+    }
+
+    pub fn load(mut self) -> Result<ClassFile, ClassFormatError> {
+        self.context.push("magic".to_string());
+        let magic: u32 = self.read_u32()?;
+        if magic != 0xcafebabe {
+            return Err(self.error_at(0, ClassFormatErrorKind::BadMagic(magic)));
+        }
+        self.context.pop();
+
+        self.context.push("version".to_string());
+        self.minor_version = self.read_u16()?;
+        self.major_version = self.read_u16()?;
+        check_version(self.major_version, self.minor_version, self.enable_preview)
+            .map_err(|kind| self.error_at(4, kind))?;
+        self.context.pop();
+
+        let constant_pool_count: u16 = self.read_u16()?;
+
+        // Constant pool index starts at 1, so the next index is always the count so far
+        while self.constant_pool.count() < constant_pool_count as usize {
+            self.context.push(format!("constant_pool[{}]", self.constant_pool.count()));
+            let tag_offset = self.reader.index;
+            let tag = self.read_u8()?;
+            let info = match tag {
                 1 => {
-                    let mut length: usize = self.reader.read_u16().into();
-                    let string = self.reader.read_str(length);
-                    let info = ConstantPoolInfo::Utf8 {
-                        string: string.to_string(),
-                    };
-                    println!("{:?}", info);
-                    self.constant_pool.push(info);
-                }
-                12 => {
-                    let info = ConstantPoolInfo::NameAndType {
-                        name_index: self.reader.read_u16(),
-                        descriptor_index: self.reader.read_u16(),
-                    };
-                    println!("{:?}", info);
-                    self.constant_pool.push(info);
-                }
-                _ => panic!("unknown constant pool tag: {}\n", tag)
+                    let length: usize = self.read_u16()?.into();
+                    let string = self.read_str(length)?;
+                    ConstantPoolInfo::Utf8 {
+                        string,
+                    }
+                }
+                3 => ConstantPoolInfo::Integer {
+                    bytes: self.read_u32()?,
+                },
+                4 => ConstantPoolInfo::Float {
+                    bytes: self.read_u32()?,
+                },
+                5 => ConstantPoolInfo::Long {
+                    high_bytes: self.read_u32()?,
+                    low_bytes: self.read_u32()?,
+                },
+                6 => ConstantPoolInfo::Double {
+                    high_bytes: self.read_u32()?,
+                    low_bytes: self.read_u32()?,
+                },
+                7 => ConstantPoolInfo::Class {
+                    name_index: self.read_u16()?,
+                },
+                8 => ConstantPoolInfo::String {
+                    string_index: self.read_u16()?,
+                },
+                9 => ConstantPoolInfo::FieldRef {
+                    class_index: self.read_u16()?,
+                    name_and_type_index: self.read_u16()?,
+                },
+                10 => ConstantPoolInfo::MethodRef {
+                    class_index: self.read_u16()?,
+                    name_and_type_index: self.read_u16()?,
+                },
+                11 => ConstantPoolInfo::InterfaceMethodRef {
+                    class_index: self.read_u16()?,
+                    name_and_type_index: self.read_u16()?,
+                },
+                12 => ConstantPoolInfo::NameAndType {
+                    name_index: self.read_u16()?,
+                    descriptor_index: self.read_u16()?,
+                },
+                15 => ConstantPoolInfo::MethodHandle {
+                    reference_kind: self.read_u8()?,
+                    reference_index: self.read_u16()?,
+                },
+                16 => ConstantPoolInfo::MethodType {
+                    descriptor_index: self.read_u16()?,
+                },
+                17 => ConstantPoolInfo::Dynamic {
+                    bootstrap_method_attr_index: self.read_u16()?,
+                    name_and_type_index: self.read_u16()?,
+                },
+                18 => ConstantPoolInfo::InvokeDynamic {
+                    bootstrap_method_attr_index: self.read_u16()?,
+                    name_and_type_index: self.read_u16()?,
+                },
+                19 => ConstantPoolInfo::Module {
+                    name_index: self.read_u16()?,
+                },
+                20 => ConstantPoolInfo::Package {
+                    name_index: self.read_u16()?,
+                },
+                _ => return Err(self.error_at(tag_offset, ClassFormatErrorKind::UnknownConstantPoolTag(tag)))
+            };
+
+            // Spec: Long and Double entries take up two slots; push() takes care of that. Both slots have to
+            // be below constant_pool_count, so one can't be the last entry.
+            if self.constant_pool.count() + info.slots() > constant_pool_count as usize {
+                let index = self.constant_pool.count() as u16;
+                return Err(self.error_at(tag_offset, ClassFormatErrorKind::ConstantPoolOverrun { index, constant_pool_count }));
+            }
+            self.constant_pool.push(info).map_err(|kind| self.error_at(tag_offset, kind))?;
+            self.context.pop();
+        }
+
+        // Which combinations are legal is up to format_check
+        self.access_flags = ClassAccessFlags(self.read_u16()?);
+
+        // Spec: module-info classes follow their own rules, see
+        // https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1
+        let is_module = self.access_flags.is_module();
+        if is_module {
+            self.context.push("module-info".to_string());
+            if self.access_flags != ClassAccessFlags::MODULE {
+                return Err(self.error_at(self.reader.index - 2,
+                                         ClassFormatErrorKind::BadModuleInfo("no flag but ACC_MODULE may be set")));
             }
+            if self.major_version < 53 {
+                return Err(self.error_at(6, ClassFormatErrorKind::BadModuleInfo("class file version must be at least 53.0")));
+            }
+            self.context.pop();
         }
 
-        // TODO: Should be of type ClassFlags?
-        self.access_flags = self.reader.read_u16();
-        println!("is_public {}", ClassFlags::is_public(&self.access_flags));
-        println!("is_final {}", ClassFlags::is_final(&self.access_flags));
-        println!("is_super_special {}", ClassFlags::is_super_special(&self.access_flags));
-        println!("is_interface {}", ClassFlags::is_interface(&self.access_flags));
-        println!("is_abstract {}", ClassFlags::is_abstract(&self.access_flags));
-        println!("is_synthetic {}", ClassFlags::is_synthetic(&self.access_flags));
-        println!("is_annotation {}", ClassFlags::is_annotation(&self.access_flags));
-        println!("is_enum {}", ClassFlags::is_synthetic(&self.access_flags));
-        println!("is_module {}", ClassFlags::is_enum(&self.access_flags));
-
-        // TODO: Spec says:
-        // if access_flags.is_module() {
-        //     return ClassFile {
-        //         minor_version,
-        //         major_version,
-        //         this_class: "module-info",
-        //         super_class: 0,
-        //         interfaces: Vec::new(),
-        //         fields: Vec::new(),
-        //         methods: Vec::new()
-        //     }
-        // }
-
-        self.this_class = self.reader.read_u16();
-
-        let this_class_info = &self.constant_pool[(self.this_class as usize) - 1]; // Constant pool index starts at 1
-        match this_class_info {
-            ConstantPoolInfo::Class { .. } => println!("Yup, it's a class, alright"),
-            _ => panic!("Value of this_class should point to a class in the constant pool")
+        self.context.push("this_class".to_string());
+        let offset = self.reader.index;
+        self.this_class = self.read_class_index()?;
+        if is_module {
+            let name = self.constant_pool.class_name(self.this_class)
+                .map_err(|kind| self.error_at(offset, kind))?;
+            if name != "module-info" {
+                return Err(self.error_at(offset, ClassFormatErrorKind::BadModuleInfo("this_class must be module-info")));
+            }
         }
+        self.context.pop();
 
         // Spec: either zero, or, if nonzero, a valid index into the constant pool
-        self.super_class = self.reader.read_u16();
+        self.context.push("super_class".to_string());
+        let offset = self.reader.index;
+        self.super_class = self.read_u16()?;
+        if is_module && self.super_class != 0 {
+            return Err(self.error_at(offset, ClassFormatErrorKind::BadModuleInfo("super_class must be zero")));
+        }
+        if self.super_class != 0 {
+            self.constant_pool.class(self.super_class)
+                .map_err(|kind| self.error_at(offset, kind))?;
+        }
+        self.context.pop();
 
-        let interfaces_count: u16 = self.reader.read_u16();
-        for _ in 0..interfaces_count {
-            self.interfaces.push(self.reader.read_u16());
+        let interfaces_count: u16 = self.read_u16()?;
+        if is_module && interfaces_count != 0 {
+            return Err(self.error_at(self.reader.index - 2,
+                                     ClassFormatErrorKind::BadModuleInfo("interfaces_count must be zero")));
+        }
+        for i in 0..interfaces_count {
+            self.context.push(format!("interfaces[{}]", i));
+            let interface = self.read_class_index()?;
+            self.interfaces.push(interface);
+            self.context.pop();
         }
 
-        let fields_count: u16 = self.reader.read_u16();
-        for _ in 0..fields_count {
-            let access_flags = self.reader.read_u16();
-            let name_index = self.reader.read_u16(); // TODO: Check constant pool for corresponding utf8
-            let descriptor_index = self.reader.read_u16();
-            let attributes_count = self.reader.read_u16();
-            let attributes: Vec<AttributeInfo> = self.read_attributes(attributes_count);
+        let fields_count: u16 = self.read_u16()?;
+        if is_module && fields_count != 0 {
+            return Err(self.error_at(self.reader.index - 2,
+                                     ClassFormatErrorKind::BadModuleInfo("fields_count must be zero")));
+        }
+        for i in 0..fields_count {
+            self.context.push(format!("field #{}", i));
+            let access_flags = FieldAccessFlags(self.read_u16()?);
+            // Names and descriptors are checked by format_check
+            let name_index = self.read_u16()?;
+            let descriptor_index = self.read_u16()?;
+            let attributes_count = self.read_u16()?;
+            let attributes: Vec<Attribute> = self.read_attributes(attributes_count)?;
             self.fields.push(FieldInfo {
                 access_flags,
                 name_index,
                 descriptor_index,
                 attributes,
             });
+            self.context.pop();
         }
 
-        let methods_count = self.reader.read_u16();
-        for _ in 0..methods_count {
-            let access_flags = self.reader.read_u16();
-            let name_index = self.reader.read_u16(); // TODO: Check constant pool for corresponding utf8
-            let descriptor_index = self.reader.read_u16();
-            // TODO: For descriptor_index, check:
-            // - If method is in class, and name is <init>, then descriptor must denote a void method
-            // - If name of method is <clinit>, then descriptor must denote a void method, and in class
-            //   file >=51.0, method should have zero args
-            let attributes_count = self.reader.read_u16();
-            let attributes: Vec<AttributeInfo> = self.read_attributes(attributes_count);
+        let methods_count = self.read_u16()?;
+        if is_module && methods_count != 0 {
+            return Err(self.error_at(self.reader.index - 2,
+                                     ClassFormatErrorKind::BadModuleInfo("methods_count must be zero")));
+        }
+        for i in 0..methods_count {
+            self.context.push(format!("method #{}", i));
+            let access_flags = MethodAccessFlags(self.read_u16()?);
+            // Names and descriptors are checked by format_check
+            let name_index = self.read_u16()?;
+            let descriptor_index = self.read_u16()?;
+            let attributes_count = self.read_u16()?;
+            let attributes: Vec<Attribute> = self.read_attributes(attributes_count)?;
             self.methods.push(MethodInfo {
                 access_flags,
                 name_index,
                 descriptor_index,
                 attributes,
             });
+            self.context.pop();
         }
 
-        let attributes_count = self.reader.read_u16();
-        self.attributes = self.read_attributes(attributes_count);
+        let attributes_offset = self.reader.index;
+        let attributes_count = self.read_u16()?;
+        self.attributes = self.read_attributes(attributes_count)?;
+        if is_module {
+            self.context.push("module-info".to_string());
+            let module_count = self.attributes.iter()
+                .filter(|attribute| matches!(attribute.info, AttributeInfo::Module { .. }))
+                .count();
+            if module_count != 1 {
+                return Err(self.error_at(attributes_offset,
+                                         ClassFormatErrorKind::BadModuleInfo("there must be exactly one Module attribute")));
+            }
+            // Spec: of the predefined attributes only these may appear, anything unknown is fine
+            let only_allowed_attributes = self.attributes.iter().all(|attribute| matches!(attribute.info,
+                AttributeInfo::Module { .. } | AttributeInfo::ModulePackages { .. } |
+                AttributeInfo::ModuleMainClass { .. } | AttributeInfo::InnerClasses { .. } |
+                AttributeInfo::SourceFile { .. } | AttributeInfo::SourceDebugExtension { .. } |
+                AttributeInfo::RuntimeVisibleAnnotations { .. } | AttributeInfo::RuntimeInvisibleAnnotations { .. } |
+                AttributeInfo::Unknown { .. }));
+            if !only_allowed_attributes {
+                return Err(self.error_at(attributes_offset,
+                                         ClassFormatErrorKind::BadModuleInfo("attribute not allowed in module-info")));
+            }
+            self.context.pop();
+        }
 
-        println!("Class file loaded; read {}/{} bytes",
-                 self.reader.index, self.reader.contents.len());
+        // Spec: the class file must not have extra bytes at the end
+        if self.reader.remaining() > 0 {
+            return Err(self.error(ClassFormatErrorKind::ExtraBytes(self.reader.remaining())));
+        }
 
-        ClassFile {
+        Ok(ClassFile {
             minor_version: self.minor_version,
             major_version: self.major_version,
             constant_pool: self.constant_pool,
@@ -196,42 +323,67 @@ impl ClassFileLoader {
             fields: self.fields,
             methods: self.methods,
             attributes: self.attributes,
-        }
+        })
     }
 
-    fn read_attributes(&mut self, attributes_count: u16) -> Vec<AttributeInfo> {
-        let mut attributes: Vec<AttributeInfo> = Vec::new();
+    fn read_attributes(&mut self, attributes_count: u16) -> Result<Vec<Attribute>, ClassFormatError> {
+        if self.attribute_depth == MAX_ATTRIBUTE_DEPTH {
+            return Err(self.error(ClassFormatErrorKind::NestedTooDeeply { what: "attributes", max: MAX_ATTRIBUTE_DEPTH }));
+        }
+        self.attribute_depth += 1;
+        let mut attributes: Vec<Attribute> = Vec::new();
         for _ in 0..attributes_count {
-            let attribute_name_index = self.reader.read_u16();
-            let attribute_name = match &self.constant_pool[(attribute_name_index as usize) - 1] {
-                ConstantPoolInfo::Utf8 { string } => string,
-                _ => panic!("Expected to find attribute name")
-            };
-            println!("Attribute name: {}", attribute_name);
+            let name_offset = self.reader.index;
+            let attribute_name_index = self.read_u16()?;
+            let attribute_name = self.constant_pool.utf8(attribute_name_index)
+                .map_err(|kind| self.error_at(name_offset, kind))?
+                .to_string();
+            self.context.push(format!("attribute {}", attribute_name));
 
-            self.reader.read_u32(); // attribute_length
+            let attribute_length = self.read_u32()?;
+            let attribute_start = self.reader.index;
+            // Nothing inside the attribute can be larger than the attribute, so no count or length in it
+            // can make us allocate more than is left of the file
+            if attribute_length as usize > self.reader.remaining() {
+                return Err(self.error(ClassFormatErrorKind::Truncated {
+                    wanted: attribute_length as usize,
+                    available: self.reader.remaining(),
+                }));
+            }
 
             let attribute = match attribute_name.as_str() {
+                "ConstantValue" => {
+                    AttributeInfo::ConstantValue {
+                        constantvalue_index: self.read_u16()?,
+                    }
+                }
                 "Code" => {
-                    let max_stack = self.reader.read_u16();
-                    let max_locals = self.reader.read_u16();
+                    let max_stack = self.read_u16()?;
+                    let max_locals = self.read_u16()?;
 
-                    let code_length = self.reader.read_u32();
-                    let code: Vec<u8> = self.reader.read_vec_u8(code_length as usize);
+                    let code_length_offset = self.reader.index;
+                    let code_length = self.read_u32()?;
+                    if code_length > MAX_CODE_LENGTH {
+                        return Err(self.error_at(code_length_offset,
+                                                 ClassFormatErrorKind::BadCode("code_length must be less than 65536")));
+                    }
+                    let code: Vec<u8> = self.read_vec_u8(code_length as usize)?;
 
-                    let exception_table_length = self.reader.read_u16();
+                    let exception_table_length = self.read_u16()?;
                     let mut exception_tables: Vec<ExceptionTable> = Vec::new();
-                    for _ in 0..exception_table_length {
+                    for i in 0..exception_table_length {
+                        self.context.push(format!("exception_table[{}]", i));
                         exception_tables.push(ExceptionTable {
-                            start_pc: self.reader.read_u16(),
-                            end_pc: self.reader.read_u16(),
-                            handler_pc: self.reader.read_u16(),
-                            catch_type: self.reader.read_u16(),
+                            start_pc: self.read_u16()?,
+                            end_pc: self.read_u16()?,
+                            handler_pc: self.read_u16()?,
+                            catch_type: self.read_u16()?,
                         });
+                        self.context.pop();
                     }
 
-                    let code_attributes_count = self.reader.read_u16();
-                    let code_attributes = self.read_attributes(code_attributes_count);
+                    let code_attributes_count = self.read_u16()?;
+                    let code_attributes = self.read_attributes(code_attributes_count)?;
 
                     AttributeInfo::Code {
                         max_stack,
@@ -242,30 +394,575 @@ impl ClassFileLoader {
                     }
                 }
                 "LineNumberTable" => {
-                    let line_number_table_length = self.reader.read_u16();
+                    let line_number_table_length = self.read_u16()?;
                     let mut entries: Vec<LineNumberTableEntry> = Vec::new();
-                    for _ in 0..line_number_table_length {
+                    for i in 0..line_number_table_length {
+                        self.context.push(format!("line_number_table[{}]", i));
                         entries.push(LineNumberTableEntry {
-                            start_pc: self.reader.read_u16(),
-                            line_number: self.reader.read_u16(),
-                        })
+                            start_pc: self.read_u16()?,
+                            line_number: self.read_u16()?,
+                        });
+                        self.context.pop();
                     }
                     AttributeInfo::LineNumberTable {
                         entries
                     }
                 }
+                "LocalVariableTable" => {
+                    let local_variable_table_length = self.read_u16()?;
+                    let mut entries: Vec<LocalVariableTableEntry> = Vec::new();
+                    for i in 0..local_variable_table_length {
+                        self.context.push(format!("local_variable_table[{}]", i));
+                        entries.push(LocalVariableTableEntry {
+                            start_pc: self.read_u16()?,
+                            length: self.read_u16()?,
+                            name_index: self.read_u16()?,
+                            descriptor_index: self.read_u16()?,
+                            index: self.read_u16()?,
+                        });
+                        self.context.pop();
+                    }
+                    AttributeInfo::LocalVariableTable {
+                        entries
+                    }
+                }
+                "LocalVariableTypeTable" => {
+                    let local_variable_type_table_length = self.read_u16()?;
+                    let mut entries: Vec<LocalVariableTypeTableEntry> = Vec::new();
+                    for i in 0..local_variable_type_table_length {
+                        self.context.push(format!("local_variable_type_table[{}]", i));
+                        entries.push(LocalVariableTypeTableEntry {
+                            start_pc: self.read_u16()?,
+                            length: self.read_u16()?,
+                            name_index: self.read_u16()?,
+                            signature_index: self.read_u16()?,
+                            index: self.read_u16()?,
+                        });
+                        self.context.pop();
+                    }
+                    AttributeInfo::LocalVariableTypeTable {
+                        entries
+                    }
+                }
+                "StackMapTable" => {
+                    let number_of_entries = self.read_u16()?;
+                    let mut entries: Vec<StackMapFrame> = Vec::new();
+                    for i in 0..number_of_entries {
+                        self.context.push(format!("entries[{}]", i));
+                        let frame_offset = self.reader.index;
+                        let frame_type = self.read_u8()?;
+                        let (offset_delta, kind) = match frame_type {
+                            0..=63 => (frame_type as u16, StackMapFrameKind::Same),
+                            64..=127 => (frame_type as u16 - 64, StackMapFrameKind::SameLocals1StackItem {
+                                stack: self.read_verification_type_info()?,
+                            }),
+                            247 => (self.read_u16()?, StackMapFrameKind::SameLocals1StackItemExtended {
+                                stack: self.read_verification_type_info()?,
+                            }),
+                            248..=250 => (self.read_u16()?, StackMapFrameKind::Chop {
+                                absent_locals: 251 - frame_type,
+                            }),
+                            251 => (self.read_u16()?, StackMapFrameKind::SameExtended),
+                            252..=254 => {
+                                let offset_delta = self.read_u16()?;
+                                let mut locals = Vec::new();
+                                for _ in 0..frame_type - 251 {
+                                    locals.push(self.read_verification_type_info()?);
+                                }
+                                (offset_delta, StackMapFrameKind::Append { locals })
+                            }
+                            255 => {
+                                let offset_delta = self.read_u16()?;
+                                let number_of_locals = self.read_u16()?;
+                                let mut locals = Vec::new();
+                                for _ in 0..number_of_locals {
+                                    locals.push(self.read_verification_type_info()?);
+                                }
+                                let number_of_stack_items = self.read_u16()?;
+                                let mut stack = Vec::new();
+                                for _ in 0..number_of_stack_items {
+                                    stack.push(self.read_verification_type_info()?);
+                                }
+                                (offset_delta, StackMapFrameKind::Full { locals, stack })
+                            }
+                            _ => return Err(self.error_at(frame_offset,
+                                                          ClassFormatErrorKind::BadStackMapFrameType(frame_type)))
+                        };
+                        let offset = match entries.last() {
+                            None => offset_delta as u32,
+                            Some(previous) => previous.offset + offset_delta as u32 + 1,
+                        };
+                        entries.push(StackMapFrame {
+                            offset_delta,
+                            offset,
+                            kind,
+                        });
+                        self.context.pop();
+                    }
+                    AttributeInfo::StackMapTable {
+                        entries
+                    }
+                }
                 "SourceFile" => {
                     SourceFile {
-                        sourcefile_index: self.reader.read_u16(),
+                        sourcefile_index: self.read_u16()?,
+                    }
+                }
+                "BootstrapMethods" => {
+                    let num_bootstrap_methods = self.read_u16()?;
+                    let mut bootstrap_methods: Vec<BootstrapMethod> = Vec::new();
+                    for i in 0..num_bootstrap_methods {
+                        self.context.push(format!("bootstrap_methods[{}]", i));
+                        bootstrap_methods.push(BootstrapMethod {
+                            bootstrap_method_ref: self.read_u16()?,
+                            bootstrap_arguments: self.read_u16_table()?,
+                        });
+                        self.context.pop();
+                    }
+                    AttributeInfo::BootstrapMethods {
+                        bootstrap_methods
+                    }
+                }
+                "NestHost" => {
+                    AttributeInfo::NestHost {
+                        host_class_index: self.read_u16()?,
+                    }
+                }
+                "NestMembers" => {
+                    AttributeInfo::NestMembers {
+                        classes: self.read_u16_table()?,
+                    }
+                }
+                "PermittedSubclasses" => {
+                    AttributeInfo::PermittedSubclasses {
+                        classes: self.read_u16_table()?,
+                    }
+                }
+                "Record" => {
+                    let components_count = self.read_u16()?;
+                    let mut components: Vec<RecordComponentInfo> = Vec::new();
+                    for i in 0..components_count {
+                        self.context.push(format!("component #{}", i));
+                        let name_index = self.read_u16()?;
+                        let descriptor_index = self.read_u16()?;
+                        let attributes_count = self.read_u16()?;
+                        let attributes = self.read_attributes(attributes_count)?;
+                        components.push(RecordComponentInfo {
+                            name_index,
+                            descriptor_index,
+                            attributes,
+                        });
+                        self.context.pop();
+                    }
+                    AttributeInfo::Record {
+                        components
+                    }
+                }
+                "InnerClasses" => {
+                    let number_of_classes = self.read_u16()?;
+                    let mut classes: Vec<InnerClass> = Vec::new();
+                    for i in 0..number_of_classes {
+                        self.context.push(format!("classes[{}]", i));
+                        classes.push(InnerClass {
+                            inner_class_info_index: self.read_u16()?,
+                            outer_class_info_index: self.read_u16()?,
+                            inner_name_index: self.read_u16()?,
+                            inner_class_access_flags: self.read_u16()?,
+                        });
+                        self.context.pop();
+                    }
+                    AttributeInfo::InnerClasses {
+                        classes
+                    }
+                }
+                "EnclosingMethod" => {
+                    AttributeInfo::EnclosingMethod {
+                        class_index: self.read_u16()?,
+                        method_index: self.read_u16()?,
+                    }
+                }
+                "Signature" => {
+                    AttributeInfo::Signature {
+                        signature_index: self.read_u16()?,
+                    }
+                }
+                "Exceptions" => {
+                    AttributeInfo::Exceptions {
+                        exception_index_table: self.read_u16_table()?,
+                    }
+                }
+                "MethodParameters" => {
+                    let parameters_count = self.read_u8()?;
+                    let mut parameters: Vec<MethodParameter> = Vec::new();
+                    for i in 0..parameters_count {
+                        self.context.push(format!("parameters[{}]", i));
+                        parameters.push(MethodParameter {
+                            name_index: self.read_u16()?,
+                            access_flags: self.read_u16()?,
+                        });
+                        self.context.pop();
+                    }
+                    AttributeInfo::MethodParameters {
+                        parameters
+                    }
+                }
+                "SourceDebugExtension" => {
+                    AttributeInfo::SourceDebugExtension {
+                        debug_extension: self.read_vec_u8(attribute_length as usize)?,
                     }
                 }
-                _ => panic!("Unknown attribute {}", attribute_name)
+                "RuntimeVisibleAnnotations" => {
+                    AttributeInfo::RuntimeVisibleAnnotations {
+                        annotations: self.read_annotations()?,
+                    }
+                }
+                "RuntimeInvisibleAnnotations" => {
+                    AttributeInfo::RuntimeInvisibleAnnotations {
+                        annotations: self.read_annotations()?,
+                    }
+                }
+                "RuntimeVisibleParameterAnnotations" => {
+                    AttributeInfo::RuntimeVisibleParameterAnnotations {
+                        parameter_annotations: self.read_parameter_annotations()?,
+                    }
+                }
+                "RuntimeInvisibleParameterAnnotations" => {
+                    AttributeInfo::RuntimeInvisibleParameterAnnotations {
+                        parameter_annotations: self.read_parameter_annotations()?,
+                    }
+                }
+                "RuntimeVisibleTypeAnnotations" => {
+                    AttributeInfo::RuntimeVisibleTypeAnnotations {
+                        annotations: self.read_type_annotations()?,
+                    }
+                }
+                "RuntimeInvisibleTypeAnnotations" => {
+                    AttributeInfo::RuntimeInvisibleTypeAnnotations {
+                        annotations: self.read_type_annotations()?,
+                    }
+                }
+                "AnnotationDefault" => {
+                    AttributeInfo::AnnotationDefault {
+                        default_value: self.read_element_value()?,
+                    }
+                }
+                "Module" => {
+                    AttributeInfo::Module {
+                        module: self.read_module()?,
+                    }
+                }
+                "ModulePackages" => {
+                    AttributeInfo::ModulePackages {
+                        package_index: self.read_u16_table()?,
+                    }
+                }
+                "ModuleMainClass" => {
+                    AttributeInfo::ModuleMainClass {
+                        main_class_index: self.read_u16()?,
+                    }
+                }
+                "Synthetic" => AttributeInfo::Synthetic,
+                "Deprecated" => AttributeInfo::Deprecated,
+                // Spec: unknown attributes must be silently ignored, we keep them around as they are
+                _ => AttributeInfo::Unknown {
+                    bytes: self.read_vec_u8(attribute_length as usize)?,
+                    name: attribute_name,
+                }
             };
 
-            // println!("{:?}", attribute);
+            let consumed = self.reader.index - attribute_start;
+            if consumed != attribute_length as usize {
+                return Err(self.error_at(attribute_start, ClassFormatErrorKind::AttributeLengthMismatch {
+                    attribute_length,
+                    consumed,
+                }));
+            }
+
+            attributes.push(Attribute { name_index: attribute_name_index, info: attribute });
+            self.context.pop();
+        }
+        self.attribute_depth -= 1;
+        Ok(attributes)
+    }
+
+    fn read_module(&mut self) -> Result<Module, ClassFormatError> {
+        let module_name_index = self.read_u16()?;
+        let module_flags = self.read_u16()?;
+        let module_version_index = self.read_u16()?;
 
-            attributes.push(attribute);
+        let requires_count = self.read_u16()?;
+        let mut requires: Vec<Requires> = Vec::new();
+        for i in 0..requires_count {
+            self.context.push(format!("requires[{}]", i));
+            requires.push(Requires {
+                requires_index: self.read_u16()?,
+                requires_flags: self.read_u16()?,
+                requires_version_index: self.read_u16()?,
+            });
+            self.context.pop();
         }
-        attributes
+
+        let exports_count = self.read_u16()?;
+        let mut exports: Vec<Exports> = Vec::new();
+        for i in 0..exports_count {
+            self.context.push(format!("exports[{}]", i));
+            exports.push(Exports {
+                exports_index: self.read_u16()?,
+                exports_flags: self.read_u16()?,
+                exports_to_index: self.read_u16_table()?,
+            });
+            self.context.pop();
+        }
+
+        let opens_count = self.read_u16()?;
+        let mut opens: Vec<Opens> = Vec::new();
+        for i in 0..opens_count {
+            self.context.push(format!("opens[{}]", i));
+            opens.push(Opens {
+                opens_index: self.read_u16()?,
+                opens_flags: self.read_u16()?,
+                opens_to_index: self.read_u16_table()?,
+            });
+            self.context.pop();
+        }
+
+        let uses_index = self.read_u16_table()?;
+
+        let provides_count = self.read_u16()?;
+        let mut provides: Vec<Provides> = Vec::new();
+        for i in 0..provides_count {
+            self.context.push(format!("provides[{}]", i));
+            provides.push(Provides {
+                provides_index: self.read_u16()?,
+                provides_with_index: self.read_u16_table()?,
+            });
+            self.context.pop();
+        }
+
+        Ok(Module {
+            module_name_index,
+            module_flags,
+            module_version_index,
+            requires,
+            exports,
+            opens,
+            uses_index,
+            provides,
+        })
+    }
+
+    fn read_annotations(&mut self) -> Result<Vec<Annotation>, ClassFormatError> {
+        let num_annotations = self.read_u16()?;
+        let mut annotations: Vec<Annotation> = Vec::new();
+        for i in 0..num_annotations {
+            self.context.push(format!("annotations[{}]", i));
+            annotations.push(self.read_annotation()?);
+            self.context.pop();
+        }
+        Ok(annotations)
+    }
+
+    fn read_parameter_annotations(&mut self) -> Result<Vec<Vec<Annotation>>, ClassFormatError> {
+        let num_parameters = self.read_u8()?;
+        let mut parameter_annotations: Vec<Vec<Annotation>> = Vec::new();
+        for i in 0..num_parameters {
+            self.context.push(format!("parameter_annotations[{}]", i));
+            parameter_annotations.push(self.read_annotations()?);
+            self.context.pop();
+        }
+        Ok(parameter_annotations)
+    }
+
+    fn read_annotation(&mut self) -> Result<Annotation, ClassFormatError> {
+        let type_index = self.read_u16()?;
+        let num_element_value_pairs = self.read_u16()?;
+        let mut element_value_pairs: Vec<ElementValuePair> = Vec::new();
+        for i in 0..num_element_value_pairs {
+            self.context.push(format!("element_value_pairs[{}]", i));
+            element_value_pairs.push(ElementValuePair {
+                element_name_index: self.read_u16()?,
+                value: self.read_element_value()?,
+            });
+            self.context.pop();
+        }
+        Ok(Annotation {
+            type_index,
+            element_value_pairs,
+        })
+    }
+
+    fn read_element_value(&mut self) -> Result<ElementValue, ClassFormatError> {
+        if self.element_value_depth == MAX_ELEMENT_VALUE_DEPTH {
+            return Err(self.error(ClassFormatErrorKind::NestedTooDeeply {
+                what: "element values",
+                max: MAX_ELEMENT_VALUE_DEPTH,
+            }));
+        }
+        self.element_value_depth += 1;
+        let tag_offset = self.reader.index;
+        let tag = self.read_u8()?;
+        let value = match tag {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => ElementValue::Const {
+                tag,
+                const_value_index: self.read_u16()?,
+            },
+            b'e' => ElementValue::Enum {
+                type_name_index: self.read_u16()?,
+                const_name_index: self.read_u16()?,
+            },
+            b'c' => ElementValue::Class {
+                class_info_index: self.read_u16()?,
+            },
+            b'@' => ElementValue::Annotation(self.read_annotation()?),
+            b'[' => {
+                let num_values = self.read_u16()?;
+                let mut values: Vec<ElementValue> = Vec::new();
+                for i in 0..num_values {
+                    self.context.push(format!("values[{}]", i));
+                    values.push(self.read_element_value()?);
+                    self.context.pop();
+                }
+                ElementValue::Array(values)
+            }
+            _ => return Err(self.error_at(tag_offset, ClassFormatErrorKind::BadElementValueTag(tag)))
+        };
+        self.element_value_depth -= 1;
+        Ok(value)
+    }
+
+    fn read_type_annotations(&mut self) -> Result<Vec<TypeAnnotation>, ClassFormatError> {
+        let num_annotations = self.read_u16()?;
+        let mut annotations: Vec<TypeAnnotation> = Vec::new();
+        for i in 0..num_annotations {
+            self.context.push(format!("annotations[{}]", i));
+            let target_type_offset = self.reader.index;
+            let target_type = self.read_u8()?;
+            let target_info = match target_type {
+                0x00 | 0x01 => TargetInfo::TypeParameter {
+                    type_parameter_index: self.read_u8()?,
+                },
+                0x10 => TargetInfo::Supertype {
+                    supertype_index: self.read_u16()?,
+                },
+                0x11 | 0x12 => TargetInfo::TypeParameterBound {
+                    type_parameter_index: self.read_u8()?,
+                    bound_index: self.read_u8()?,
+                },
+                0x13..=0x15 => TargetInfo::Empty,
+                0x16 => TargetInfo::FormalParameter {
+                    formal_parameter_index: self.read_u8()?,
+                },
+                0x17 => TargetInfo::Throws {
+                    throws_type_index: self.read_u16()?,
+                },
+                0x40 | 0x41 => {
+                    let table_length = self.read_u16()?;
+                    let mut table: Vec<LocalvarTarget> = Vec::new();
+                    for _ in 0..table_length {
+                        table.push(LocalvarTarget {
+                            start_pc: self.read_u16()?,
+                            length: self.read_u16()?,
+                            index: self.read_u16()?,
+                        });
+                    }
+                    TargetInfo::Localvar { table }
+                }
+                0x42 => TargetInfo::Catch {
+                    exception_table_index: self.read_u16()?,
+                },
+                0x43..=0x46 => TargetInfo::Offset {
+                    offset: self.read_u16()?,
+                },
+                0x47..=0x4b => TargetInfo::TypeArgument {
+                    offset: self.read_u16()?,
+                    type_argument_index: self.read_u8()?,
+                },
+                _ => return Err(self.error_at(target_type_offset, ClassFormatErrorKind::BadTargetType(target_type)))
+            };
+            let path_length = self.read_u8()?;
+            let mut target_path: Vec<TypePathEntry> = Vec::new();
+            for _ in 0..path_length {
+                target_path.push(TypePathEntry {
+                    type_path_kind: self.read_u8()?,
+                    type_argument_index: self.read_u8()?,
+                });
+            }
+            annotations.push(TypeAnnotation {
+                target_type,
+                target_info,
+                target_path,
+                annotation: self.read_annotation()?,
+            });
+            self.context.pop();
+        }
+        Ok(annotations)
+    }
+
+    fn read_verification_type_info(&mut self) -> Result<VerificationTypeInfo, ClassFormatError> {
+        let tag_offset = self.reader.index;
+        let tag = self.read_u8()?;
+        Ok(match tag {
+            0 => VerificationTypeInfo::Top,
+            1 => VerificationTypeInfo::Integer,
+            2 => VerificationTypeInfo::Float,
+            3 => VerificationTypeInfo::Double,
+            4 => VerificationTypeInfo::Long,
+            5 => VerificationTypeInfo::Null,
+            6 => VerificationTypeInfo::UninitializedThis,
+            7 => VerificationTypeInfo::Object {
+                cpool_index: self.read_u16()?,
+            },
+            8 => VerificationTypeInfo::Uninitialized {
+                offset: self.read_u16()?,
+            },
+            _ => return Err(self.error_at(tag_offset, ClassFormatErrorKind::BadVerificationTypeTag(tag)))
+        })
+    }
+
+    // Reads a constant pool index that must point at a Class entry
+    fn read_class_index(&mut self) -> Result<u16, ClassFormatError> {
+        let offset = self.reader.index;
+        let index = self.read_u16()?;
+        self.constant_pool.class(index)
+            .map_err(|kind| self.error_at(offset, kind))?;
+        Ok(index)
+    }
+
+    // Reads a u2 count followed by that many u2 values, usually constant pool indexes
+    fn read_u16_table(&mut self) -> Result<Vec<u16>, ClassFormatError> {
+        let count = self.read_u16()?;
+        let mut table = Vec::new();
+        for _ in 0..count {
+            table.push(self.read_u16()?);
+        }
+        Ok(table)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ClassFormatError> {
+        self.reader.read_u8().map_err(|e| self.error(e.into()))
+    }
+
+    fn read_u16(&mut self) -> Result<u16, ClassFormatError> {
+        self.reader.read_u16().map_err(|e| self.error(e.into()))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ClassFormatError> {
+        self.reader.read_u32().map_err(|e| self.error(e.into()))
+    }
+
+    fn read_str(&mut self, length: usize) -> Result<ModifiedUtf8, ClassFormatError> {
+        self.reader.read_str(length).map_err(|e| self.error(e.into()))
+    }
+
+    fn read_vec_u8(&mut self, length: usize) -> Result<Vec<u8>, ClassFormatError> {
+        self.reader.read_vec_u8(length).map_err(|e| self.error(e.into()))
+    }
+
+    fn error(&self, kind: ClassFormatErrorKind) -> ClassFormatError {
+        self.error_at(self.reader.index, kind)
+    }
+
+    fn error_at(&self, offset: usize, kind: ClassFormatErrorKind) -> ClassFormatError {
+        ClassFormatError::new(kind, Some(offset), self.context.join(" "))
     }
 }
@@ -1,71 +1,240 @@
+pub mod error;
+pub mod format_check;
 pub mod loading;
+pub mod module;
+pub mod version;
+pub mod view;
+pub mod writing;
 
 use crate::constant_pool;
 use crate::field;
 use crate::attr;
 use crate::method;
 
-use constant_pool::ConstantPoolInfo;
+use std::fmt;
+use std::ops::BitOr;
+
+use constant_pool::ConstantPool;
+use error::{ClassFormatError, ClassFormatErrorKind};
 use field::FieldInfo;
-use attr::AttributeInfo;
+use attr::{Attribute, AttributeInfo, InnerClass};
 use method::MethodInfo;
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct ClassFile {
     pub minor_version: u16,
     pub major_version: u16,
-    pub constant_pool: Vec<ConstantPoolInfo>,
-    // TODO: Should be of ClassFlags type?
-    pub access_flags: u16,
+    pub constant_pool: ConstantPool,
+    pub access_flags: ClassAccessFlags,
     pub this_class: u16,
     pub super_class: u16,
     pub interfaces: Vec<u16>,
     pub fields: Vec<FieldInfo>,
     pub methods: Vec<MethodInfo>,
-    pub attributes: Vec<AttributeInfo>,
+    pub attributes: Vec<Attribute>,
 }
 
 impl ClassFile {
-    pub fn find_name(&self, index: u16) -> String {
-        match &self.constant_pool[(index as usize) - 1] {
-            ConstantPoolInfo::Utf8 { string } => string.to_string(),
-            _ => panic!("Expected to find a utf8 string at constant_pool[{}]", index)
+    pub fn find_name(&self, index: u16) -> Result<String, ClassFormatError> {
+        self.constant_pool.utf8(index)
+            .map(str::to_string)
+            .map_err(|kind| ClassFormatError::new(kind, None, format!("constant_pool[{}]", index)))
+    }
+
+    // Resolves a Class entry to the binary name it refers to
+    pub fn find_class_name(&self, index: u16) -> Result<String, ClassFormatError> {
+        self.constant_pool.class_name(index)
+            .map(str::to_string)
+            .map_err(|kind| ClassFormatError::new(kind, None, format!("constant_pool[{}]", index)))
+    }
+
+    // Generic type of the class, if it has one
+    pub fn signature_index(&self) -> Option<u16> {
+        attr::signature_index(&self.attributes)
+    }
+
+    pub fn is_deprecated(&self) -> bool {
+        attr::is_deprecated(&self.attributes)
+    }
+
+    // Whether invokespecial treats superclass methods specially, which newer versions always do
+    pub fn is_super(&self) -> bool {
+        self.implies_super() || self.access_flags.is_super()
+    }
+
+    pub fn inner_classes(&self) -> &[InnerClass] {
+        self.attributes.iter().find_map(|attribute| match &attribute.info {
+            AttributeInfo::InnerClasses { classes } => Some(classes.as_slice()),
+            _ => None
+        }).unwrap_or(&[])
+    }
+
+    // Maps a binary name like java/util/Map$Entry to its source name java.util.Map.Entry, using the
+    // InnerClasses attribute rather than guessing from the '$', which is legal in plain class names.
+    // Anonymous classes have no source name.
+    pub fn source_name(&self, binary_name: &str) -> Result<Option<String>, ClassFormatError> {
+        let mut name = binary_name.to_string();
+        let mut suffix = String::new();
+        // Every step moves one class outwards, so a well-formed table can't take more steps than it has entries
+        for _ in 0..=self.inner_classes().len() {
+            let mut entry = None;
+            for inner_class in self.inner_classes() {
+                if self.find_class_name(inner_class.inner_class_info_index)? == name {
+                    entry = Some(inner_class);
+                    break;
+                }
+            }
+            let inner_class = match entry {
+                Some(inner_class) => inner_class,
+                None => return Ok(Some(name.replace('/', ".") + &suffix)),
+            };
+            if inner_class.inner_name_index == 0 {
+                return Ok(None);
+            }
+            suffix = format!(".{}{}", self.find_name(inner_class.inner_name_index)?, suffix);
+            // Local classes are only known by their simple name
+            if inner_class.outer_class_info_index == 0 {
+                return Ok(Some(suffix[1..].to_string()));
+            }
+            name = self.find_class_name(inner_class.outer_class_info_index)?;
+        }
+        // The outer classes form a cycle
+        Ok(None)
+    }
+}
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1-200-E.1
+// Any combination of bits can be loaded, validate() says whether it is a legal one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ClassAccessFlags(pub u16);
+
+impl ClassAccessFlags {
+    pub const PUBLIC: ClassAccessFlags = ClassAccessFlags(0x0001);
+    pub const FINAL: ClassAccessFlags = ClassAccessFlags(0x0010);
+    // Treat superclass methods specially when invoked by the invokespecial instruction
+    pub const SUPER: ClassAccessFlags = ClassAccessFlags(0x0020);
+    pub const INTERFACE: ClassAccessFlags = ClassAccessFlags(0x0200);
+    pub const ABSTRACT: ClassAccessFlags = ClassAccessFlags(0x0400);
+    pub const SYNTHETIC: ClassAccessFlags = ClassAccessFlags(0x1000);
+    pub const ANNOTATION: ClassAccessFlags = ClassAccessFlags(0x2000);
+    pub const ENUM: ClassAccessFlags = ClassAccessFlags(0x4000);
+    pub const MODULE: ClassAccessFlags = ClassAccessFlags(0x8000);
+
+    pub fn contains(self, flags: ClassAccessFlags) -> bool { self.0 & flags.0 == flags.0 }
+    pub fn is_public(self) -> bool { self.contains(Self::PUBLIC) }
+    pub fn is_final(self) -> bool { self.contains(Self::FINAL) }
+    // Only the flag itself, see ClassFile::is_super for whether it applies
+    pub fn is_super(self) -> bool { self.contains(Self::SUPER) }
+    pub fn is_interface(self) -> bool { self.contains(Self::INTERFACE) }
+    pub fn is_abstract(self) -> bool { self.contains(Self::ABSTRACT) }
+    pub fn is_synthetic(self) -> bool { self.contains(Self::SYNTHETIC) }
+    pub fn is_annotation(self) -> bool { self.contains(Self::ANNOTATION) }
+    pub fn is_enum(self) -> bool { self.contains(Self::ENUM) }
+    pub fn is_module(self) -> bool { self.contains(Self::MODULE) }
+
+    pub fn validate(self, major_version: u16) -> Result<(), ClassFormatErrorKind> {
+        let illegal = |rule| Err(ClassFormatErrorKind::IllegalAccessFlags { flags: self.0, rule });
+        if self.is_module() {
+            return match self == Self::MODULE {
+                true => Ok(()),
+                false => illegal("no flag but ACC_MODULE may be set"),
+            };
+        }
+        if self.is_interface() {
+            // Older compilers left ACC_ABSTRACT off interfaces, HotSpot lets that go before version 50
+            if !self.is_abstract() && major_version >= 50 {
+                return illegal("an interface must be abstract");
+            }
+            if self.is_final() || self.is_super() || self.is_enum() {
+                return illegal("an interface must not be final, super or enum");
+            }
+        } else {
+            if self.is_annotation() {
+                return illegal("an annotation must be an interface");
+            }
+            if self.is_final() && self.is_abstract() {
+                return illegal("a class must not be both final and abstract");
+            }
         }
+        Ok(())
     }
 }
 
-pub trait ClassFlags {
-    fn is_public(&self) -> bool;
-    fn is_final(&self) -> bool;
-    // Spec(ACC_SUPER): Treat superclass methods specially when invoked by the invokespecial instruction
-    fn is_super_special(&self) -> bool;
-    fn is_interface(&self) -> bool;
-    fn is_abstract(&self) -> bool;
-    fn is_synthetic(&self) -> bool;
-    fn is_annotation(&self) -> bool;
-    fn is_enum(&self) -> bool;
-    fn is_module(&self) -> bool;
+impl BitOr for ClassAccessFlags {
+    type Output = ClassAccessFlags;
+
+    fn bitor(self, flags: ClassAccessFlags) -> ClassAccessFlags {
+        ClassAccessFlags(self.0 | flags.0)
+    }
 }
 
-impl ClassFlags for u16 {
-    fn is_public(&self) -> bool { self & 0x0001 == 0x0001 }
-    fn is_final(&self) -> bool { self & 0x0010 == 0x0010 }
-    fn is_super_special(&self) -> bool { self & 0x0020 == 0x0020 }
-    fn is_interface(&self) -> bool { self & 0x0200 == 0x0200 }
-    fn is_abstract(&self) -> bool { self & 0x0400 == 0x0400 }
-    fn is_synthetic(&self) -> bool { self & 0x1000 == 0x1000 }
-    fn is_annotation(&self) -> bool { self & 0x2000 == 0x2000 }
-    fn is_enum(&self) -> bool { self & 0x4000 == 0x4000 }
-    fn is_module(&self) -> bool { self & 0x8000 == 0x8000 }
+// The Java modifiers, in the order the JLS recommends. Flags that are not modifiers, like ACC_SUPER or
+// ACC_INTERFACE, are left out.
+impl fmt::Display for ClassAccessFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [(self.is_public(), "public"), (self.is_abstract(), "abstract"), (self.is_final(), "final")];
+        let modifiers: Vec<&str> = modifiers.iter().filter(|(set, _)| *set).map(|(_, modifier)| *modifier).collect();
+        write!(f, "{}", modifiers.join(" "))
+    }
 }
 
-impl ClassFlags for ClassFile {
-    fn is_public(&self) -> bool { ClassFlags::is_public(&self.access_flags) }
-    fn is_final(&self) -> bool { ClassFlags::is_final(&self.access_flags) }
-    fn is_super_special(&self) -> bool { ClassFlags::is_super_special(&self.access_flags) }
-    fn is_interface(&self) -> bool { ClassFlags::is_interface(&self.access_flags) }
-    fn is_abstract(&self) -> bool { ClassFlags::is_abstract(&self.access_flags) }
-    fn is_synthetic(&self) -> bool { ClassFlags::is_synthetic(&self.access_flags) }
-    fn is_annotation(&self) -> bool { ClassFlags::is_annotation(&self.access_flags) }
-    fn is_enum(&self) -> bool { ClassFlags::is_enum(&self.access_flags) }
-    fn is_module(&self) -> bool { ClassFlags::is_module(&self.access_flags) }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The rule the flags break, if any
+    fn broken_rule(flags: ClassAccessFlags, major_version: u16) -> Option<&'static str> {
+        match flags.validate(major_version) {
+            Ok(()) => None,
+            Err(ClassFormatErrorKind::IllegalAccessFlags { flags: reported, rule }) => {
+                assert_eq!(reported, flags.0);
+                Some(rule)
+            }
+            Err(kind) => panic!("{}", kind),
+        }
+    }
+
+    #[test]
+    fn legal_flags() {
+        let interface = ClassAccessFlags::INTERFACE | ClassAccessFlags::ABSTRACT;
+        for flags in [
+            ClassAccessFlags::PUBLIC | ClassAccessFlags::SUPER,
+            ClassAccessFlags::PUBLIC | ClassAccessFlags::FINAL | ClassAccessFlags::SUPER | ClassAccessFlags::ENUM,
+            ClassAccessFlags::ABSTRACT | ClassAccessFlags::SYNTHETIC,
+            ClassAccessFlags::PUBLIC | interface,
+            interface | ClassAccessFlags::ANNOTATION,
+            ClassAccessFlags::MODULE,
+        ] {
+            assert_eq!(broken_rule(flags, 61), None, "{:#06x}", flags.0);
+        }
+    }
+
+    #[test]
+    fn interfaces_must_be_abstract() {
+        let flags = ClassAccessFlags::PUBLIC | ClassAccessFlags::INTERFACE;
+        assert_eq!(broken_rule(flags, 50), Some("an interface must be abstract"));
+        // Older compilers left it off
+        assert_eq!(broken_rule(flags, 49), None);
+        for flag in [ClassAccessFlags::FINAL, ClassAccessFlags::SUPER, ClassAccessFlags::ENUM] {
+            let flags = flags | ClassAccessFlags::ABSTRACT | flag;
+            assert_eq!(broken_rule(flags, 61), Some("an interface must not be final, super or enum"));
+        }
+    }
+
+    #[test]
+    fn classes() {
+        let flags = ClassAccessFlags::PUBLIC | ClassAccessFlags::FINAL | ClassAccessFlags::ABSTRACT;
+        assert_eq!(broken_rule(flags, 61), Some("a class must not be both final and abstract"));
+        let flags = ClassAccessFlags::ABSTRACT | ClassAccessFlags::ANNOTATION;
+        assert_eq!(broken_rule(flags, 61), Some("an annotation must be an interface"));
+    }
+
+    #[test]
+    fn modules() {
+        let interface = ClassAccessFlags::INTERFACE | ClassAccessFlags::ABSTRACT;
+        for flag in [ClassAccessFlags::PUBLIC, ClassAccessFlags::SYNTHETIC, interface] {
+            let flags = ClassAccessFlags::MODULE | flag;
+            assert_eq!(broken_rule(flags, 61), Some("no flag but ACC_MODULE may be set"));
+        }
+    }
 }
@@ -0,0 +1,160 @@
+use crate::attr::AttributeInfo;
+use crate::class::ClassFile;
+use crate::class::error::ClassFormatError;
+
+// A module declaration with every constant pool reference resolved, as read from module-info.class.
+// Names are kept in their internal form, so packages use '/' rather than '.'.
+#[derive(Debug)]
+pub struct ModuleDescriptor {
+    pub name: String,
+    pub flags: u16,
+    pub version: Option<String>,
+    pub requires: Vec<ModuleRequires>,
+    pub exports: Vec<ModuleExports>,
+    pub opens: Vec<ModuleOpens>,
+    pub uses: Vec<String>,
+    pub provides: Vec<ModuleProvides>,
+    // From the ModulePackages attribute, empty if there is none
+    pub packages: Vec<String>,
+    // From the ModuleMainClass attribute
+    pub main_class: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ModuleRequires {
+    pub module: String,
+    pub flags: u16,
+    pub version: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ModuleExports {
+    pub package: String,
+    pub flags: u16,
+    // Empty for an unqualified export
+    pub to: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ModuleOpens {
+    pub package: String,
+    pub flags: u16,
+    // Empty for an unqualified open
+    pub to: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ModuleProvides {
+    pub service: String,
+    pub with: Vec<String>,
+}
+
+impl ClassFile {
+    // None unless this is a module-info class
+    pub fn module_descriptor(&self) -> Result<Option<ModuleDescriptor>, ClassFormatError> {
+        let module = match self.attributes.iter().find_map(|attribute| match &attribute.info {
+            AttributeInfo::Module { module } => Some(module),
+            _ => None
+        }) {
+            Some(module) => module,
+            None => return Ok(None),
+        };
+
+        let mut requires = Vec::new();
+        for entry in &module.requires {
+            requires.push(ModuleRequires {
+                module: self.find_module_name(entry.requires_index)?,
+                flags: entry.requires_flags,
+                version: self.find_optional_name(entry.requires_version_index)?,
+            });
+        }
+
+        let mut exports = Vec::new();
+        for entry in &module.exports {
+            exports.push(ModuleExports {
+                package: self.find_package_name(entry.exports_index)?,
+                flags: entry.exports_flags,
+                to: self.find_module_names(&entry.exports_to_index)?,
+            });
+        }
+
+        let mut opens = Vec::new();
+        for entry in &module.opens {
+            opens.push(ModuleOpens {
+                package: self.find_package_name(entry.opens_index)?,
+                flags: entry.opens_flags,
+                to: self.find_module_names(&entry.opens_to_index)?,
+            });
+        }
+
+        let mut uses = Vec::new();
+        for &index in &module.uses_index {
+            uses.push(self.find_class_name(index)?);
+        }
+
+        let mut provides = Vec::new();
+        for entry in &module.provides {
+            let mut with = Vec::new();
+            for &index in &entry.provides_with_index {
+                with.push(self.find_class_name(index)?);
+            }
+            provides.push(ModuleProvides {
+                service: self.find_class_name(entry.provides_index)?,
+                with,
+            });
+        }
+
+        let mut packages = Vec::new();
+        let mut main_class = None;
+        for attribute in &self.attributes {
+            match &attribute.info {
+                AttributeInfo::ModulePackages { package_index } => {
+                    for &index in package_index {
+                        packages.push(self.find_package_name(index)?);
+                    }
+                }
+                AttributeInfo::ModuleMainClass { main_class_index } => {
+                    main_class = Some(self.find_class_name(*main_class_index)?);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Some(ModuleDescriptor {
+            name: self.find_module_name(module.module_name_index)?,
+            flags: module.module_flags,
+            version: self.find_optional_name(module.module_version_index)?,
+            requires,
+            exports,
+            opens,
+            uses,
+            provides,
+            packages,
+            main_class,
+        }))
+    }
+
+    fn find_module_name(&self, index: u16) -> Result<String, ClassFormatError> {
+        self.constant_pool.module_name(index)
+            .map(str::to_string)
+            .map_err(|kind| ClassFormatError::new(kind, None, format!("constant_pool[{}]", index)))
+    }
+
+    fn find_module_names(&self, indexes: &[u16]) -> Result<Vec<String>, ClassFormatError> {
+        indexes.iter().map(|&index| self.find_module_name(index)).collect()
+    }
+
+    fn find_package_name(&self, index: u16) -> Result<String, ClassFormatError> {
+        self.constant_pool.package_name(index)
+            .map(str::to_string)
+            .map_err(|kind| ClassFormatError::new(kind, None, format!("constant_pool[{}]", index)))
+    }
+
+    // Versions are optional, zero means there is none
+    fn find_optional_name(&self, index: u16) -> Result<Option<String>, ClassFormatError> {
+        match index {
+            0 => Ok(None),
+            _ => self.find_name(index).map(Some),
+        }
+    }
+}
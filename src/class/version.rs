@@ -0,0 +1,121 @@
+use crate::class::ClassFile;
+use crate::class::error::ClassFormatErrorKind;
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1
+// JDK 1.0.2 produced version 45, nothing older exists
+pub const MIN_MAJOR_VERSION: u16 = 45;
+// Java SE 17, the version of the spec we implement
+pub const MAX_MAJOR_VERSION: u16 = 61;
+// Spec: from Java SE 12 (56) on, a class file using preview features has this minor version
+pub const PREVIEW_MINOR_VERSION: u16 = 0xffff;
+
+// Checks that we can load a class file of this version. Preview classes are only accepted with
+// enable_preview, and only for MAX_MAJOR_VERSION: preview features of other releases may have changed.
+pub fn check_version(major_version: u16, minor_version: u16, enable_preview: bool) -> Result<(), ClassFormatErrorKind> {
+    let unsupported = ClassFormatErrorKind::UnsupportedVersion { major_version, minor_version };
+    if !(MIN_MAJOR_VERSION..=MAX_MAJOR_VERSION).contains(&major_version) {
+        return Err(unsupported);
+    }
+    // Spec: before 56 any minor version is fine, from 56 on it is either 0 or the preview marker
+    if major_version < 56 {
+        return Ok(());
+    }
+    match minor_version {
+        0 => Ok(()),
+        PREVIEW_MINOR_VERSION if major_version != MAX_MAJOR_VERSION => Err(unsupported),
+        PREVIEW_MINOR_VERSION if !enable_preview => Err(ClassFormatErrorKind::PreviewNotEnabled { major_version }),
+        PREVIEW_MINOR_VERSION => Ok(()),
+        _ => Err(unsupported),
+    }
+}
+
+// Rules that depend on the class file version
+impl ClassFile {
+    pub fn is_preview(&self) -> bool {
+        self.major_version >= 56 && self.minor_version == PREVIEW_MINOR_VERSION
+    }
+
+    // Spec: jsr, jsr_w and ret must not appear in class files of version 51 or above
+    pub fn allows_jsr_ret(&self) -> bool {
+        self.major_version < 51
+    }
+
+    // Spec: from version 50 on code is verified by type checking, which needs a StackMapTable for any
+    // code with branches or exception handlers
+    pub fn requires_stack_map_table(&self) -> bool {
+        self.major_version >= 50
+    }
+
+    // ACC_SUPER only changes how invokespecial picks a method, and from version 53 on it is assumed
+    // to be set whether it is or not
+    pub fn implies_super(&self) -> bool {
+        self.major_version >= 53
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::class::loading::ClassFileLoader;
+
+    // The class file, with its version changed
+    fn with_version(path: &str, major_version: u16, minor_version: u16) -> Vec<u8> {
+        let mut bytes = fs::read(path).unwrap();
+        bytes[4..6].copy_from_slice(&minor_version.to_be_bytes());
+        bytes[6..8].copy_from_slice(&major_version.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn oldest_versions() {
+        assert!(check_version(45, 0, false).is_ok());
+        // Before 56 the minor version can be anything, the preview marker included
+        assert!(check_version(45, 3, false).is_ok());
+        assert!(check_version(55, PREVIEW_MINOR_VERSION, false).is_ok());
+        assert!(matches!(check_version(44, 0, false),
+                         Err(ClassFormatErrorKind::UnsupportedVersion { major_version: 44, minor_version: 0 })));
+        assert!(matches!(check_version(0, 0, true), Err(ClassFormatErrorKind::UnsupportedVersion { .. })));
+    }
+
+    #[test]
+    fn newest_versions() {
+        assert!(check_version(MAX_MAJOR_VERSION, 0, false).is_ok());
+        assert!(matches!(check_version(MAX_MAJOR_VERSION + 1, 0, true),
+                         Err(ClassFormatErrorKind::UnsupportedVersion { major_version: 62, minor_version: 0 })));
+        // From 56 on the minor version is 0 or the preview marker
+        assert!(matches!(check_version(56, 1, true), Err(ClassFormatErrorKind::UnsupportedVersion { .. })));
+    }
+
+    #[test]
+    fn preview_versions() {
+        assert!(check_version(MAX_MAJOR_VERSION, PREVIEW_MINOR_VERSION, true).is_ok());
+        assert!(matches!(check_version(MAX_MAJOR_VERSION, PREVIEW_MINOR_VERSION, false),
+                         Err(ClassFormatErrorKind::PreviewNotEnabled { major_version: MAX_MAJOR_VERSION })));
+        // Preview features of an earlier release aren't those of this one
+        assert!(matches!(check_version(60, PREVIEW_MINOR_VERSION, true),
+                         Err(ClassFormatErrorKind::UnsupportedVersion { major_version: 60, minor_version: 0xffff })));
+
+        let bytes = with_version("HelloWorld.class", MAX_MAJOR_VERSION, PREVIEW_MINOR_VERSION);
+        let error = ClassFileLoader::new(&bytes).load().unwrap_err();
+        assert!(matches!(error.kind, ClassFormatErrorKind::PreviewNotEnabled { .. }), "{}", error);
+        assert_eq!(error.offset, Some(4));
+        let class_file = ClassFileLoader::new(&bytes).enable_preview(true).load().unwrap();
+        assert!(class_file.is_preview());
+        let bytes = with_version("HelloWorld.class", 55, PREVIEW_MINOR_VERSION);
+        let class_file = ClassFileLoader::new(&bytes).load().unwrap();
+        assert!(!class_file.is_preview());
+    }
+
+    #[test]
+    fn module_before_53() {
+        let bytes = with_version("tests/data/classes/module-info.class", 53, 0);
+        assert!(ClassFileLoader::new(&bytes).load().is_ok());
+        let bytes = with_version("tests/data/classes/module-info.class", 52, 0);
+        let error = ClassFileLoader::new(&bytes).load().unwrap_err();
+        assert!(matches!(error.kind, ClassFormatErrorKind::BadModuleInfo("class file version must be at least 53.0")),
+                "{}", error);
+        assert_eq!(error.offset, Some(6));
+    }
+}
@@ -0,0 +1,442 @@
+use std::borrow::Cow;
+
+use crate::attr::ExceptionTable;
+use crate::class::ClassAccessFlags;
+use crate::class::error::{ClassFormatError, ClassFormatErrorKind};
+use crate::class::version::check_version;
+use crate::constant_pool::ConstantPoolInfo;
+use crate::util::mutf8::ModifiedUtf8;
+use crate::util::{ByteReader, ReadError};
+
+// A class file read in place, for when most of it is never looked at, e.g. scanning a large classpath.
+// Only the structure is checked up front: the constant pool is indexed and every member and attribute
+// is bounds checked. Constants, names and attribute contents are decoded when asked for, and borrow
+// from the bytes wherever they can.
+// Use ClassFileLoader to get a fully parsed and checked ClassFile instead.
+pub struct ClassFileView<'a> {
+    bytes: &'a [u8],
+    pub minor_version: u16,
+    pub major_version: u16,
+    // Offset of the tag of each constant pool entry, by constant pool index.
+    // Zero for index 0 and the slot after a Long or Double, no entry can start there.
+    constant_pool_offsets: Vec<usize>,
+    pub access_flags: ClassAccessFlags,
+    pub this_class: u16,
+    pub super_class: u16,
+    // interfaces_count u2 values
+    interfaces: &'a [u8],
+    pub fields: Vec<MemberView<'a>>,
+    pub methods: Vec<MemberView<'a>>,
+    pub attributes: AttributesView<'a>,
+}
+
+// A field_info or method_info, their layout is the same
+#[derive(Debug, Clone, Copy)]
+pub struct MemberView<'a> {
+    // A FieldAccessFlags or MethodAccessFlags, depending on which it is
+    pub access_flags: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: AttributesView<'a>,
+}
+
+// A bounds checked attributes table, iterating it yields each attribute without parsing it
+#[derive(Debug, Clone, Copy)]
+pub struct AttributesView<'a> {
+    count: u16,
+    bytes: &'a [u8],
+    // Offset of bytes in the class file
+    offset: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AttributeView<'a> {
+    pub attribute_name_index: u16,
+    // The attribute_length bytes after the header
+    pub info: &'a [u8],
+    // Offset of info in the class file
+    pub offset: usize,
+}
+
+pub struct AttributesIter<'a> {
+    remaining: u16,
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.3
+#[derive(Debug, Clone, Copy)]
+pub struct CodeView<'a> {
+    pub max_stack: u16,
+    pub max_locals: u16,
+    pub code: &'a [u8],
+    // exception_table_length entries of four u2 values
+    exception_table: &'a [u8],
+    pub attributes: AttributesView<'a>,
+}
+
+impl<'a> ClassFileView<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<ClassFileView<'a>, ClassFormatError> {
+        let mut parser = ViewParser::new(bytes, 0, Vec::new());
+
+        parser.context.push("magic".to_string());
+        let magic = parser.read_u32()?;
+        if magic != 0xcafebabe {
+            return Err(parser.error_at(0, ClassFormatErrorKind::BadMagic(magic)));
+        }
+        parser.context.pop();
+
+        parser.context.push("version".to_string());
+        let minor_version = parser.read_u16()?;
+        let major_version = parser.read_u16()?;
+        // Nothing is run from a view, so there is no need to hold back preview classes
+        check_version(major_version, minor_version, true).map_err(|kind| parser.error_at(4, kind))?;
+        parser.context.pop();
+
+        let constant_pool_count = parser.read_u16()?;
+        let mut constant_pool_offsets = vec![0; constant_pool_count.max(1) as usize];
+        let mut index: usize = 1; // Constant pool index starts at 1
+        while index < constant_pool_count as usize {
+            parser.context.push(format!("constant_pool[{}]", index));
+            let tag_offset = parser.reader.index;
+            let tag = parser.read_u8()?;
+            // Only skip over the entry, it is decoded in constant()
+            let length = match tag {
+                1 => parser.read_u16()? as usize,
+                3 | 4 => 4,
+                5 | 6 => 8,
+                7 | 8 | 16 | 19 | 20 => 2,
+                15 => 3,
+                9 | 10 | 11 | 12 | 17 | 18 => 4,
+                _ => return Err(parser.error_at(tag_offset, ClassFormatErrorKind::UnknownConstantPoolTag(tag))),
+            };
+            parser.read_slice(length)?;
+            constant_pool_offsets[index] = tag_offset;
+            // Spec: Long and Double entries take up two slots; the slot after them is valid but unusable. Both
+            // slots have to be below constant_pool_count, so one can't be the last entry.
+            index += match tag {
+                5 | 6 => 2,
+                _ => 1,
+            };
+            if index > constant_pool_count as usize {
+                let kind = ClassFormatErrorKind::ConstantPoolOverrun { index: (index - 2) as u16, constant_pool_count };
+                return Err(parser.error_at(tag_offset, kind));
+            }
+            parser.context.pop();
+        }
+
+        let access_flags = ClassAccessFlags(parser.read_u16()?);
+        let this_class = parser.read_u16()?;
+        let super_class = parser.read_u16()?;
+        let interfaces_count = parser.read_u16()?;
+        parser.context.push("interfaces".to_string());
+        let interfaces = parser.read_slice(interfaces_count as usize * 2)?;
+        parser.context.pop();
+
+        let fields_count = parser.read_u16()?;
+        let mut fields = Vec::with_capacity(fields_count as usize);
+        for i in 0..fields_count {
+            parser.context.push(format!("field #{}", i));
+            fields.push(parser.read_member()?);
+            parser.context.pop();
+        }
+
+        let methods_count = parser.read_u16()?;
+        let mut methods = Vec::with_capacity(methods_count as usize);
+        for i in 0..methods_count {
+            parser.context.push(format!("method #{}", i));
+            methods.push(parser.read_member()?);
+            parser.context.pop();
+        }
+
+        let attributes = parser.read_attributes()?;
+
+        // Spec: the class file must not have extra bytes at the end
+        let remaining = bytes.len() - parser.reader.index;
+        if remaining > 0 {
+            return Err(parser.error_at(parser.reader.index, ClassFormatErrorKind::ExtraBytes(remaining)));
+        }
+
+        Ok(ClassFileView {
+            bytes,
+            minor_version,
+            major_version,
+            constant_pool_offsets,
+            access_flags,
+            this_class,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+            attributes,
+        })
+    }
+
+    // Number of constant pool slots, plus one for the unused index 0, like constant_pool_count
+    pub fn constant_pool_count(&self) -> u16 {
+        self.constant_pool_offsets.len() as u16
+    }
+
+    // Decodes a single constant pool entry
+    pub fn constant(&self, index: u16) -> Result<ConstantPoolInfo, ClassFormatError> {
+        let mut parser = self.constant_parser(index)?;
+        let tag = parser.read_u8()?;
+        Ok(match tag {
+            1 => {
+                let length = parser.read_u16()? as usize;
+                let offset = parser.reader.index;
+                let bytes = parser.read_slice(length)?;
+                let string = ModifiedUtf8::from_bytes(bytes.to_vec())
+                    .map_err(|e| parser.error_at(offset, ReadError::InvalidModifiedUtf8 { offset: e.offset }.into()))?;
+                ConstantPoolInfo::Utf8 { string }
+            }
+            3 => ConstantPoolInfo::Integer { bytes: parser.read_u32()? },
+            4 => ConstantPoolInfo::Float { bytes: parser.read_u32()? },
+            5 => ConstantPoolInfo::Long { high_bytes: parser.read_u32()?, low_bytes: parser.read_u32()? },
+            6 => ConstantPoolInfo::Double { high_bytes: parser.read_u32()?, low_bytes: parser.read_u32()? },
+            7 => ConstantPoolInfo::Class { name_index: parser.read_u16()? },
+            8 => ConstantPoolInfo::String { string_index: parser.read_u16()? },
+            9 => ConstantPoolInfo::FieldRef {
+                class_index: parser.read_u16()?,
+                name_and_type_index: parser.read_u16()?,
+            },
+            10 => ConstantPoolInfo::MethodRef {
+                class_index: parser.read_u16()?,
+                name_and_type_index: parser.read_u16()?,
+            },
+            11 => ConstantPoolInfo::InterfaceMethodRef {
+                class_index: parser.read_u16()?,
+                name_and_type_index: parser.read_u16()?,
+            },
+            12 => ConstantPoolInfo::NameAndType {
+                name_index: parser.read_u16()?,
+                descriptor_index: parser.read_u16()?,
+            },
+            15 => ConstantPoolInfo::MethodHandle {
+                reference_kind: parser.read_u8()?,
+                reference_index: parser.read_u16()?,
+            },
+            16 => ConstantPoolInfo::MethodType { descriptor_index: parser.read_u16()? },
+            17 => ConstantPoolInfo::Dynamic {
+                bootstrap_method_attr_index: parser.read_u16()?,
+                name_and_type_index: parser.read_u16()?,
+            },
+            18 => ConstantPoolInfo::InvokeDynamic {
+                bootstrap_method_attr_index: parser.read_u16()?,
+                name_and_type_index: parser.read_u16()?,
+            },
+            19 => ConstantPoolInfo::Module { name_index: parser.read_u16()? },
+            20 => ConstantPoolInfo::Package { name_index: parser.read_u16()? },
+            // Every tag was checked when indexing
+            _ => unreachable!("constant pool tag {} was not checked", tag),
+        })
+    }
+
+    // The raw modified UTF-8 bytes of the Utf8 entry at index
+    pub fn utf8_bytes(&self, index: u16) -> Result<&'a [u8], ClassFormatError> {
+        let mut parser = self.constant_parser(index)?;
+        let tag_offset = parser.reader.index;
+        if parser.read_u8()? != 1 {
+            return Err(parser.error_at(tag_offset, ClassFormatErrorKind::WrongConstantKind { index, expected: "Utf8" }));
+        }
+        let length = parser.read_u16()? as usize;
+        parser.read_slice(length)
+    }
+
+    // Borrows the string when it is also valid UTF-8, which it is unless it has a NUL or a supplementary
+    // character in it. Those are encoded differently in modified UTF-8 and are decoded into a new String.
+    // A raw 0x00 byte or a 4 byte sequence is valid UTF-8 but not modified UTF-8, so bytes with either of
+    // those in them go to the decoder too, which turns them down.
+    pub fn find_name(&self, index: u16) -> Result<Cow<'a, str>, ClassFormatError> {
+        let bytes = self.utf8_bytes(index)?;
+        let utf8 = match bytes.iter().all(|&byte| byte != 0 && byte < 0xf0) {
+            true => std::str::from_utf8(bytes).ok(),
+            false => None,
+        };
+        match utf8 {
+            Some(string) => Ok(Cow::Borrowed(string)),
+            None => match ModifiedUtf8::from_bytes(bytes.to_vec()) {
+                Ok(string) => Ok(Cow::Owned(string.as_str().to_string())),
+                Err(e) => Err(ClassFormatError::new(ReadError::InvalidModifiedUtf8 { offset: e.offset }.into(),
+                                                    Some(self.constant_pool_offsets[index as usize]),
+                                                    format!("constant_pool[{}]", index))),
+            },
+        }
+    }
+
+    // Resolves a Class entry to the binary name it refers to
+    pub fn find_class_name(&self, index: u16) -> Result<Cow<'a, str>, ClassFormatError> {
+        let mut parser = self.constant_parser(index)?;
+        let tag_offset = parser.reader.index;
+        if parser.read_u8()? != 7 {
+            return Err(parser.error_at(tag_offset, ClassFormatErrorKind::WrongConstantKind { index, expected: "Class" }));
+        }
+        let name_index = parser.read_u16()?;
+        self.find_name(name_index)
+    }
+
+    pub fn this_class_name(&self) -> Result<Cow<'a, str>, ClassFormatError> {
+        self.find_class_name(self.this_class)
+    }
+
+    // None for java/lang/Object and module-info, which have no superclass
+    pub fn super_class_name(&self) -> Result<Option<Cow<'a, str>>, ClassFormatError> {
+        match self.super_class {
+            0 => Ok(None),
+            super_class => self.find_class_name(super_class).map(Some),
+        }
+    }
+
+    // The constant pool indexes of the direct superinterfaces
+    pub fn interfaces(&self) -> impl Iterator<Item = u16> + 'a {
+        self.interfaces.chunks_exact(2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    // Finds an attribute by name without decoding any names, attribute names are plain ASCII
+    pub fn find_attribute(&self, attributes: AttributesView<'a>, name: &str)
+                          -> Result<Option<AttributeView<'a>>, ClassFormatError> {
+        for attribute in attributes {
+            if self.utf8_bytes(attribute.attribute_name_index)? == name.as_bytes() {
+                return Ok(Some(attribute));
+            }
+        }
+        Ok(None)
+    }
+
+    // Parses the Code attribute of a method, None for abstract and native methods
+    pub fn code(&self, method: &MemberView<'a>) -> Result<Option<CodeView<'a>>, ClassFormatError> {
+        let attribute = match self.find_attribute(method.attributes, "Code")? {
+            Some(attribute) => attribute,
+            None => return Ok(None),
+        };
+        let mut parser = ViewParser::new(self.bytes, attribute.offset, vec!["attribute Code".to_string()]);
+        let end = attribute.offset + attribute.info.len();
+        let max_stack = parser.read_u16()?;
+        let max_locals = parser.read_u16()?;
+        let code_length = parser.read_u32()?;
+        let code = parser.read_slice(code_length as usize)?;
+        let exception_table_length = parser.read_u16()?;
+        let exception_table = parser.read_slice(exception_table_length as usize * 8)?;
+        let attributes = parser.read_attributes()?;
+        // Reading past the attribute is only caught here, the bytes after it are still in the class file
+        if parser.reader.index != end {
+            return Err(parser.error_at(attribute.offset, ClassFormatErrorKind::AttributeLengthMismatch {
+                attribute_length: attribute.info.len() as u32,
+                consumed: parser.reader.index - attribute.offset,
+            }));
+        }
+        Ok(Some(CodeView { max_stack, max_locals, code, exception_table, attributes }))
+    }
+
+    // Positions a parser at the tag of the entry at index
+    fn constant_parser(&self, index: u16) -> Result<ViewParser<'a>, ClassFormatError> {
+        match self.constant_pool_offsets.get(index as usize) {
+            Some(&offset) if offset != 0 => Ok(ViewParser::new(self.bytes, offset, vec![format!("constant_pool[{}]", index)])),
+            _ => Err(ClassFormatError::new(ClassFormatErrorKind::BadConstantPoolIndex(index), None,
+                                           format!("constant_pool[{}]", index))),
+        }
+    }
+}
+
+impl<'a> AttributesView<'a> {
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+impl<'a> IntoIterator for AttributesView<'a> {
+    type Item = AttributeView<'a>;
+    type IntoIter = AttributesIter<'a>;
+
+    fn into_iter(self) -> AttributesIter<'a> {
+        AttributesIter { remaining: self.count, bytes: self.bytes, offset: self.offset }
+    }
+}
+
+impl<'a> Iterator for AttributesIter<'a> {
+    type Item = AttributeView<'a>;
+
+    // The table was bounds checked when it was read, so this cannot run out of bytes
+    fn next(&mut self) -> Option<AttributeView<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let attribute_name_index = u16::from_be_bytes([self.bytes[0], self.bytes[1]]);
+        let attribute_length = u32::from_be_bytes([self.bytes[2], self.bytes[3], self.bytes[4], self.bytes[5]]);
+        let (info, rest) = self.bytes[6..].split_at(attribute_length as usize);
+        let attribute = AttributeView { attribute_name_index, info, offset: self.offset + 6 };
+        self.bytes = rest;
+        self.offset += 6 + info.len();
+        Some(attribute)
+    }
+}
+
+impl<'a> CodeView<'a> {
+    pub fn exception_table(&self) -> impl Iterator<Item = ExceptionTable> + 'a {
+        self.exception_table.chunks_exact(8).map(|bytes| ExceptionTable {
+            start_pc: u16::from_be_bytes([bytes[0], bytes[1]]),
+            end_pc: u16::from_be_bytes([bytes[2], bytes[3]]),
+            handler_pc: u16::from_be_bytes([bytes[4], bytes[5]]),
+            catch_type: u16::from_be_bytes([bytes[6], bytes[7]]),
+        })
+    }
+}
+
+// A ByteReader with the loader's error reporting
+struct ViewParser<'a> {
+    reader: ByteReader<'a>,
+    // The structures currently being parsed, outermost first, for error reporting
+    context: Vec<String>,
+}
+
+impl<'a> ViewParser<'a> {
+    fn new(bytes: &'a [u8], index: usize, context: Vec<String>) -> ViewParser<'a> {
+        ViewParser { reader: ByteReader { index, contents: bytes }, context }
+    }
+
+    fn read_member(&mut self) -> Result<MemberView<'a>, ClassFormatError> {
+        Ok(MemberView {
+            access_flags: self.read_u16()?,
+            name_index: self.read_u16()?,
+            descriptor_index: self.read_u16()?,
+            attributes: self.read_attributes()?,
+        })
+    }
+
+    // Skips over an attributes table, checking only that every attribute fits
+    fn read_attributes(&mut self) -> Result<AttributesView<'a>, ClassFormatError> {
+        let count = self.read_u16()?;
+        let offset = self.reader.index;
+        for _ in 0..count {
+            self.read_u16()?;
+            let attribute_length = self.read_u32()?;
+            self.read_slice(attribute_length as usize)?;
+        }
+        Ok(AttributesView { count, bytes: &self.reader.contents[offset..self.reader.index], offset })
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ClassFormatError> {
+        self.reader.read_u8().map_err(|e| self.error_at(self.reader.index, e.into()))
+    }
+
+    fn read_u16(&mut self) -> Result<u16, ClassFormatError> {
+        self.reader.read_u16().map_err(|e| self.error_at(self.reader.index, e.into()))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ClassFormatError> {
+        self.reader.read_u32().map_err(|e| self.error_at(self.reader.index, e.into()))
+    }
+
+    fn read_slice(&mut self, length: usize) -> Result<&'a [u8], ClassFormatError> {
+        self.reader.read_slice(length).map_err(|e| self.error_at(self.reader.index, e.into()))
+    }
+
+    fn error_at(&self, offset: usize, kind: ClassFormatErrorKind) -> ClassFormatError {
+        ClassFormatError::new(kind, Some(offset), self.context.join(" "))
+    }
+}
@@ -0,0 +1,582 @@
+use std::collections::HashMap;
+
+use crate::attr::annotation::{Annotation, ElementValue, TargetInfo, TypeAnnotation};
+use crate::attr::module::Module;
+use crate::attr::{Attribute, AttributeInfo, StackMapFrameKind, VerificationTypeInfo};
+use crate::class::ClassFile;
+use crate::class::error::{ClassFormatError, ClassFormatErrorKind};
+use crate::constant_pool::ConstantPoolInfo;
+use crate::util::ByteWriter;
+
+impl ClassFile {
+    // Serializes the class file in the format ClassFileLoader reads. A class file that was loaded and not
+    // modified is written back byte for byte.
+    // The names of attributes made in code are looked up in the constant pool, so they have to be in there
+    // already.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ClassFormatError> {
+        let mut utf8_indexes = HashMap::new();
+        for (index, info) in self.constant_pool.iter() {
+            if let ConstantPoolInfo::Utf8 { string } = info {
+                // Keep the first one, in case the pool has duplicates
+                utf8_indexes.entry(string.as_str()).or_insert(index);
+            }
+        }
+        let mut writer = ClassFileWriter {
+            class_file: self,
+            writer: ByteWriter::new(),
+            context: Vec::new(),
+            utf8_indexes,
+        };
+        writer.write()?;
+        Ok(writer.writer.contents)
+    }
+}
+
+struct ClassFileWriter<'a> {
+    class_file: &'a ClassFile,
+    writer: ByteWriter,
+    // The structures currently being written, outermost first, for error reporting
+    context: Vec<String>,
+    utf8_indexes: HashMap<&'a str, u16>,
+}
+
+impl<'a> ClassFileWriter<'a> {
+    fn write(&mut self) -> Result<(), ClassFormatError> {
+        let class_file = self.class_file;
+        self.writer.write_u32(0xcafebabe);
+        self.writer.write_u16(class_file.minor_version);
+        self.writer.write_u16(class_file.major_version);
+
+        self.context.push("constant_pool".to_string());
+        // Spec: constant_pool_count is one more than the number of entries, the Unusable slots included
+        self.write_count(class_file.constant_pool.count())?;
+        self.context.pop();
+        for (index, info) in class_file.constant_pool.iter() {
+            self.context.push(format!("constant_pool[{}]", index));
+            self.write_constant(info)?;
+            self.context.pop();
+        }
+
+        self.writer.write_u16(class_file.access_flags.0);
+        self.writer.write_u16(class_file.this_class);
+        self.writer.write_u16(class_file.super_class);
+        self.context.push("interfaces".to_string());
+        self.write_u16_table(&class_file.interfaces)?;
+        self.context.pop();
+
+        self.context.push("fields".to_string());
+        self.write_count(class_file.fields.len())?;
+        self.context.pop();
+        for (i, field) in class_file.fields.iter().enumerate() {
+            self.context.push(format!("field #{}", i));
+            self.writer.write_u16(field.access_flags.0);
+            self.writer.write_u16(field.name_index);
+            self.writer.write_u16(field.descriptor_index);
+            self.write_attributes(&field.attributes)?;
+            self.context.pop();
+        }
+
+        self.context.push("methods".to_string());
+        self.write_count(class_file.methods.len())?;
+        self.context.pop();
+        for (i, method) in class_file.methods.iter().enumerate() {
+            self.context.push(format!("method #{}", i));
+            self.writer.write_u16(method.access_flags.0);
+            self.writer.write_u16(method.name_index);
+            self.writer.write_u16(method.descriptor_index);
+            self.write_attributes(&method.attributes)?;
+            self.context.pop();
+        }
+
+        self.write_attributes(&class_file.attributes)
+    }
+
+    fn write_constant(&mut self, info: &ConstantPoolInfo) -> Result<(), ClassFormatError> {
+        match info {
+            ConstantPoolInfo::Utf8 { string } => {
+                self.writer.write_u8(1);
+                // A string made in code can be longer than the u2 length allows
+                self.write_count(string.as_bytes().len())?;
+                self.writer.write_slice(string.as_bytes());
+            }
+            ConstantPoolInfo::Integer { bytes } => {
+                self.writer.write_u8(3);
+                self.writer.write_u32(*bytes);
+            }
+            ConstantPoolInfo::Float { bytes } => {
+                self.writer.write_u8(4);
+                self.writer.write_u32(*bytes);
+            }
+            ConstantPoolInfo::Long { high_bytes, low_bytes } => {
+                self.writer.write_u8(5);
+                self.writer.write_u32(*high_bytes);
+                self.writer.write_u32(*low_bytes);
+            }
+            ConstantPoolInfo::Double { high_bytes, low_bytes } => {
+                self.writer.write_u8(6);
+                self.writer.write_u32(*high_bytes);
+                self.writer.write_u32(*low_bytes);
+            }
+            ConstantPoolInfo::Class { name_index } => {
+                self.writer.write_u8(7);
+                self.writer.write_u16(*name_index);
+            }
+            ConstantPoolInfo::String { string_index } => {
+                self.writer.write_u8(8);
+                self.writer.write_u16(*string_index);
+            }
+            ConstantPoolInfo::FieldRef { class_index, name_and_type_index } => {
+                self.writer.write_u8(9);
+                self.writer.write_u16(*class_index);
+                self.writer.write_u16(*name_and_type_index);
+            }
+            ConstantPoolInfo::MethodRef { class_index, name_and_type_index } => {
+                self.writer.write_u8(10);
+                self.writer.write_u16(*class_index);
+                self.writer.write_u16(*name_and_type_index);
+            }
+            ConstantPoolInfo::InterfaceMethodRef { class_index, name_and_type_index } => {
+                self.writer.write_u8(11);
+                self.writer.write_u16(*class_index);
+                self.writer.write_u16(*name_and_type_index);
+            }
+            ConstantPoolInfo::NameAndType { name_index, descriptor_index } => {
+                self.writer.write_u8(12);
+                self.writer.write_u16(*name_index);
+                self.writer.write_u16(*descriptor_index);
+            }
+            ConstantPoolInfo::MethodHandle { reference_kind, reference_index } => {
+                self.writer.write_u8(15);
+                self.writer.write_u8(*reference_kind);
+                self.writer.write_u16(*reference_index);
+            }
+            ConstantPoolInfo::MethodType { descriptor_index } => {
+                self.writer.write_u8(16);
+                self.writer.write_u16(*descriptor_index);
+            }
+            ConstantPoolInfo::Dynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                self.writer.write_u8(17);
+                self.writer.write_u16(*bootstrap_method_attr_index);
+                self.writer.write_u16(*name_and_type_index);
+            }
+            ConstantPoolInfo::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                self.writer.write_u8(18);
+                self.writer.write_u16(*bootstrap_method_attr_index);
+                self.writer.write_u16(*name_and_type_index);
+            }
+            ConstantPoolInfo::Module { name_index } => {
+                self.writer.write_u8(19);
+                self.writer.write_u16(*name_index);
+            }
+            ConstantPoolInfo::Package { name_index } => {
+                self.writer.write_u8(20);
+                self.writer.write_u16(*name_index);
+            }
+            // Never yielded by ConstantPool::iter, it takes up a slot but nothing in the file
+            ConstantPoolInfo::Unusable => {}
+        }
+        Ok(())
+    }
+
+    fn write_attributes(&mut self, attributes: &[Attribute]) -> Result<(), ClassFormatError> {
+        self.write_count(attributes.len())?;
+        for attribute in attributes {
+            self.context.push(format!("attribute {}", attribute.name()));
+            self.write_attribute(attribute)?;
+            self.context.pop();
+        }
+        Ok(())
+    }
+
+    fn write_attribute(&mut self, attribute: &Attribute) -> Result<(), ClassFormatError> {
+        let name = attribute.name();
+        // Loaded attributes keep the name index they had, only those made in code need one looked up
+        let name_index = match (attribute.name_index, self.utf8_indexes.get(name)) {
+            (0, Some(&name_index)) => name_index,
+            (0, None) => return Err(self.error(ClassFormatErrorKind::MissingAttributeName(name.to_string()))),
+            (name_index, _) if self.class_file.constant_pool.utf8(name_index).is_ok_and(|utf8| utf8 == name) => name_index,
+            (name_index, _) => return Err(self.error(ClassFormatErrorKind::AttributeNameMismatch {
+                index: name_index,
+                name: name.to_string(),
+            })),
+        };
+        self.writer.write_u16(name_index);
+        // attribute_length is patched in once we know it
+        let length_index = self.writer.contents.len();
+        self.writer.write_u32(0);
+
+        match &attribute.info {
+            AttributeInfo::ConstantValue { constantvalue_index } => {
+                self.writer.write_u16(*constantvalue_index);
+            }
+            AttributeInfo::Code { max_stack, max_locals, code, exception_tables, attributes } => {
+                self.writer.write_u16(*max_stack);
+                self.writer.write_u16(*max_locals);
+                let code_length = u32::try_from(code.len())
+                    .map_err(|_| self.error(ClassFormatErrorKind::CountOverflow { count: code.len(), max: u32::MAX as usize }))?;
+                self.writer.write_u32(code_length);
+                self.writer.write_slice(code);
+                self.write_count(exception_tables.len())?;
+                for exception_table in exception_tables {
+                    self.writer.write_u16(exception_table.start_pc);
+                    self.writer.write_u16(exception_table.end_pc);
+                    self.writer.write_u16(exception_table.handler_pc);
+                    self.writer.write_u16(exception_table.catch_type);
+                }
+                self.write_attributes(attributes)?;
+            }
+            AttributeInfo::LineNumberTable { entries } => {
+                self.write_count(entries.len())?;
+                for entry in entries {
+                    self.writer.write_u16(entry.start_pc);
+                    self.writer.write_u16(entry.line_number);
+                }
+            }
+            AttributeInfo::LocalVariableTable { entries } => {
+                self.write_count(entries.len())?;
+                for entry in entries {
+                    self.writer.write_u16(entry.start_pc);
+                    self.writer.write_u16(entry.length);
+                    self.writer.write_u16(entry.name_index);
+                    self.writer.write_u16(entry.descriptor_index);
+                    self.writer.write_u16(entry.index);
+                }
+            }
+            AttributeInfo::LocalVariableTypeTable { entries } => {
+                self.write_count(entries.len())?;
+                for entry in entries {
+                    self.writer.write_u16(entry.start_pc);
+                    self.writer.write_u16(entry.length);
+                    self.writer.write_u16(entry.name_index);
+                    self.writer.write_u16(entry.signature_index);
+                    self.writer.write_u16(entry.index);
+                }
+            }
+            AttributeInfo::StackMapTable { entries } => {
+                self.write_count(entries.len())?;
+                // Only offset_delta is written, the absolute offset is derived from it when loading
+                for entry in entries {
+                    let offset_delta = entry.offset_delta;
+                    match &entry.kind {
+                        StackMapFrameKind::Same if offset_delta <= 63 => {
+                            self.writer.write_u8(offset_delta as u8);
+                        }
+                        StackMapFrameKind::Same | StackMapFrameKind::SameExtended => {
+                            self.writer.write_u8(251);
+                            self.writer.write_u16(offset_delta);
+                        }
+                        StackMapFrameKind::SameLocals1StackItem { stack } if offset_delta <= 63 => {
+                            self.writer.write_u8(64 + offset_delta as u8);
+                            self.write_verification_type_info(stack);
+                        }
+                        StackMapFrameKind::SameLocals1StackItem { stack } |
+                        StackMapFrameKind::SameLocals1StackItemExtended { stack } => {
+                            self.writer.write_u8(247);
+                            self.writer.write_u16(offset_delta);
+                            self.write_verification_type_info(stack);
+                        }
+                        // Spec: chop frames remove and append frames add 1 to 3 locals, a frame_type each
+                        StackMapFrameKind::Chop { absent_locals } => {
+                            if !(1..=3).contains(absent_locals) {
+                                return Err(self.error(ClassFormatErrorKind::BadStackMapFrame("a chop frame must remove 1 to 3 locals")));
+                            }
+                            self.writer.write_u8(251 - absent_locals);
+                            self.writer.write_u16(offset_delta);
+                        }
+                        StackMapFrameKind::Append { locals } => {
+                            if !(1..=3).contains(&locals.len()) {
+                                return Err(self.error(ClassFormatErrorKind::BadStackMapFrame("an append frame must add 1 to 3 locals")));
+                            }
+                            self.writer.write_u8(251 + locals.len() as u8);
+                            self.writer.write_u16(offset_delta);
+                            for local in locals {
+                                self.write_verification_type_info(local);
+                            }
+                        }
+                        StackMapFrameKind::Full { locals, stack } => {
+                            self.writer.write_u8(255);
+                            self.writer.write_u16(offset_delta);
+                            self.write_count(locals.len())?;
+                            for local in locals {
+                                self.write_verification_type_info(local);
+                            }
+                            self.write_count(stack.len())?;
+                            for item in stack {
+                                self.write_verification_type_info(item);
+                            }
+                        }
+                    }
+                }
+            }
+            AttributeInfo::SourceFile { sourcefile_index } => {
+                self.writer.write_u16(*sourcefile_index);
+            }
+            AttributeInfo::BootstrapMethods { bootstrap_methods } => {
+                self.write_count(bootstrap_methods.len())?;
+                for bootstrap_method in bootstrap_methods {
+                    self.writer.write_u16(bootstrap_method.bootstrap_method_ref);
+                    self.write_u16_table(&bootstrap_method.bootstrap_arguments)?;
+                }
+            }
+            AttributeInfo::NestHost { host_class_index } => {
+                self.writer.write_u16(*host_class_index);
+            }
+            AttributeInfo::NestMembers { classes } | AttributeInfo::PermittedSubclasses { classes } => {
+                self.write_u16_table(classes)?;
+            }
+            AttributeInfo::Record { components } => {
+                self.write_count(components.len())?;
+                for (i, component) in components.iter().enumerate() {
+                    self.context.push(format!("component #{}", i));
+                    self.writer.write_u16(component.name_index);
+                    self.writer.write_u16(component.descriptor_index);
+                    self.write_attributes(&component.attributes)?;
+                    self.context.pop();
+                }
+            }
+            AttributeInfo::InnerClasses { classes } => {
+                self.write_count(classes.len())?;
+                for inner_class in classes {
+                    self.writer.write_u16(inner_class.inner_class_info_index);
+                    self.writer.write_u16(inner_class.outer_class_info_index);
+                    self.writer.write_u16(inner_class.inner_name_index);
+                    self.writer.write_u16(inner_class.inner_class_access_flags);
+                }
+            }
+            AttributeInfo::EnclosingMethod { class_index, method_index } => {
+                self.writer.write_u16(*class_index);
+                self.writer.write_u16(*method_index);
+            }
+            AttributeInfo::Signature { signature_index } => {
+                self.writer.write_u16(*signature_index);
+            }
+            AttributeInfo::Exceptions { exception_index_table } => {
+                self.write_u16_table(exception_index_table)?;
+            }
+            AttributeInfo::Synthetic | AttributeInfo::Deprecated => {}
+            AttributeInfo::MethodParameters { parameters } => {
+                self.write_u8_count(parameters.len())?;
+                for parameter in parameters {
+                    self.writer.write_u16(parameter.name_index);
+                    self.writer.write_u16(parameter.access_flags);
+                }
+            }
+            AttributeInfo::SourceDebugExtension { debug_extension } => {
+                self.writer.write_slice(debug_extension);
+            }
+            AttributeInfo::RuntimeVisibleAnnotations { annotations } |
+            AttributeInfo::RuntimeInvisibleAnnotations { annotations } => {
+                self.write_annotations(annotations)?;
+            }
+            AttributeInfo::RuntimeVisibleParameterAnnotations { parameter_annotations } |
+            AttributeInfo::RuntimeInvisibleParameterAnnotations { parameter_annotations } => {
+                self.write_u8_count(parameter_annotations.len())?;
+                for annotations in parameter_annotations {
+                    self.write_annotations(annotations)?;
+                }
+            }
+            AttributeInfo::RuntimeVisibleTypeAnnotations { annotations } |
+            AttributeInfo::RuntimeInvisibleTypeAnnotations { annotations } => {
+                self.write_count(annotations.len())?;
+                for annotation in annotations {
+                    self.write_type_annotation(annotation)?;
+                }
+            }
+            AttributeInfo::AnnotationDefault { default_value } => {
+                self.write_element_value(default_value)?;
+            }
+            AttributeInfo::Module { module } => {
+                self.write_module(module)?;
+            }
+            AttributeInfo::ModulePackages { package_index } => {
+                self.write_u16_table(package_index)?;
+            }
+            AttributeInfo::ModuleMainClass { main_class_index } => {
+                self.writer.write_u16(*main_class_index);
+            }
+            AttributeInfo::Unknown { bytes, .. } => {
+                self.writer.write_slice(bytes);
+            }
+        }
+
+        let length = self.writer.contents.len() - length_index - 4;
+        let attribute_length = u32::try_from(length)
+            .map_err(|_| self.error(ClassFormatErrorKind::CountOverflow { count: length, max: u32::MAX as usize }))?;
+        self.writer.patch_u32(length_index, attribute_length);
+        Ok(())
+    }
+
+    fn write_verification_type_info(&mut self, verification_type: &VerificationTypeInfo) {
+        match verification_type {
+            VerificationTypeInfo::Top => self.writer.write_u8(0),
+            VerificationTypeInfo::Integer => self.writer.write_u8(1),
+            VerificationTypeInfo::Float => self.writer.write_u8(2),
+            VerificationTypeInfo::Double => self.writer.write_u8(3),
+            VerificationTypeInfo::Long => self.writer.write_u8(4),
+            VerificationTypeInfo::Null => self.writer.write_u8(5),
+            VerificationTypeInfo::UninitializedThis => self.writer.write_u8(6),
+            VerificationTypeInfo::Object { cpool_index } => {
+                self.writer.write_u8(7);
+                self.writer.write_u16(*cpool_index);
+            }
+            VerificationTypeInfo::Uninitialized { offset } => {
+                self.writer.write_u8(8);
+                self.writer.write_u16(*offset);
+            }
+        }
+    }
+
+    fn write_module(&mut self, module: &Module) -> Result<(), ClassFormatError> {
+        self.writer.write_u16(module.module_name_index);
+        self.writer.write_u16(module.module_flags);
+        self.writer.write_u16(module.module_version_index);
+        self.write_count(module.requires.len())?;
+        for requires in &module.requires {
+            self.writer.write_u16(requires.requires_index);
+            self.writer.write_u16(requires.requires_flags);
+            self.writer.write_u16(requires.requires_version_index);
+        }
+        self.write_count(module.exports.len())?;
+        for exports in &module.exports {
+            self.writer.write_u16(exports.exports_index);
+            self.writer.write_u16(exports.exports_flags);
+            self.write_u16_table(&exports.exports_to_index)?;
+        }
+        self.write_count(module.opens.len())?;
+        for opens in &module.opens {
+            self.writer.write_u16(opens.opens_index);
+            self.writer.write_u16(opens.opens_flags);
+            self.write_u16_table(&opens.opens_to_index)?;
+        }
+        self.write_u16_table(&module.uses_index)?;
+        self.write_count(module.provides.len())?;
+        for provides in &module.provides {
+            self.writer.write_u16(provides.provides_index);
+            self.write_u16_table(&provides.provides_with_index)?;
+        }
+        Ok(())
+    }
+
+    fn write_annotations(&mut self, annotations: &[Annotation]) -> Result<(), ClassFormatError> {
+        self.write_count(annotations.len())?;
+        for annotation in annotations {
+            self.write_annotation(annotation)?;
+        }
+        Ok(())
+    }
+
+    fn write_annotation(&mut self, annotation: &Annotation) -> Result<(), ClassFormatError> {
+        self.writer.write_u16(annotation.type_index);
+        self.write_count(annotation.element_value_pairs.len())?;
+        for pair in &annotation.element_value_pairs {
+            self.writer.write_u16(pair.element_name_index);
+            self.write_element_value(&pair.value)?;
+        }
+        Ok(())
+    }
+
+    fn write_element_value(&mut self, value: &ElementValue) -> Result<(), ClassFormatError> {
+        match value {
+            ElementValue::Const { tag, const_value_index } => {
+                self.writer.write_u8(*tag);
+                self.writer.write_u16(*const_value_index);
+            }
+            ElementValue::Enum { type_name_index, const_name_index } => {
+                self.writer.write_u8(b'e');
+                self.writer.write_u16(*type_name_index);
+                self.writer.write_u16(*const_name_index);
+            }
+            ElementValue::Class { class_info_index } => {
+                self.writer.write_u8(b'c');
+                self.writer.write_u16(*class_info_index);
+            }
+            ElementValue::Annotation(annotation) => {
+                self.writer.write_u8(b'@');
+                self.write_annotation(annotation)?;
+            }
+            ElementValue::Array(values) => {
+                self.writer.write_u8(b'[');
+                self.write_count(values.len())?;
+                for value in values {
+                    self.write_element_value(value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_type_annotation(&mut self, type_annotation: &TypeAnnotation) -> Result<(), ClassFormatError> {
+        self.writer.write_u8(type_annotation.target_type);
+        match &type_annotation.target_info {
+            TargetInfo::TypeParameter { type_parameter_index } => {
+                self.writer.write_u8(*type_parameter_index);
+            }
+            TargetInfo::Supertype { supertype_index } => {
+                self.writer.write_u16(*supertype_index);
+            }
+            TargetInfo::TypeParameterBound { type_parameter_index, bound_index } => {
+                self.writer.write_u8(*type_parameter_index);
+                self.writer.write_u8(*bound_index);
+            }
+            TargetInfo::Empty => {}
+            TargetInfo::FormalParameter { formal_parameter_index } => {
+                self.writer.write_u8(*formal_parameter_index);
+            }
+            TargetInfo::Throws { throws_type_index } => {
+                self.writer.write_u16(*throws_type_index);
+            }
+            TargetInfo::Localvar { table } => {
+                self.write_count(table.len())?;
+                for entry in table {
+                    self.writer.write_u16(entry.start_pc);
+                    self.writer.write_u16(entry.length);
+                    self.writer.write_u16(entry.index);
+                }
+            }
+            TargetInfo::Catch { exception_table_index } => {
+                self.writer.write_u16(*exception_table_index);
+            }
+            TargetInfo::Offset { offset } => {
+                self.writer.write_u16(*offset);
+            }
+            TargetInfo::TypeArgument { offset, type_argument_index } => {
+                self.writer.write_u16(*offset);
+                self.writer.write_u8(*type_argument_index);
+            }
+        }
+        self.write_u8_count(type_annotation.target_path.len())?;
+        for entry in &type_annotation.target_path {
+            self.writer.write_u8(entry.type_path_kind);
+            self.writer.write_u8(entry.type_argument_index);
+        }
+        self.write_annotation(&type_annotation.annotation)
+    }
+
+    fn write_u16_table(&mut self, table: &[u16]) -> Result<(), ClassFormatError> {
+        self.write_count(table.len())?;
+        for &value in table {
+            self.writer.write_u16(value);
+        }
+        Ok(())
+    }
+
+    // Most counts and lengths in a class file are a u2
+    fn write_count(&mut self, count: usize) -> Result<(), ClassFormatError> {
+        let count = u16::try_from(count)
+            .map_err(|_| self.error(ClassFormatErrorKind::CountOverflow { count, max: u16::MAX as usize }))?;
+        self.writer.write_u16(count);
+        Ok(())
+    }
+
+    // A few, like parameter counts, are a u1
+    fn write_u8_count(&mut self, count: usize) -> Result<(), ClassFormatError> {
+        let count = u8::try_from(count)
+            .map_err(|_| self.error(ClassFormatErrorKind::CountOverflow { count, max: u8::MAX as usize }))?;
+        self.writer.write_u8(count);
+        Ok(())
+    }
+
+    fn error(&self, kind: ClassFormatErrorKind) -> ClassFormatError {
+        ClassFormatError::new(kind, Some(self.writer.contents.len()), self.context.join(" "))
+    }
+}
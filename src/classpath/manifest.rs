@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+
+use super::ClassPathEntry;
+
+// Where a JAR keeps its manifest
+pub const PATH: &str = "META-INF/MANIFEST.MF";
+
+// Spec: https://docs.oracle.com/en/java/javase/17/docs/specs/jar/jar.html#jar-manifest
+// The main section of a JAR manifest, which is all the launcher looks at. The per-entry sections after it
+// are left out.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Manifest {
+    // Name and value of each attribute, in the order they appear
+    pub main_attributes: Vec<(String, String)>,
+}
+
+impl Manifest {
+    // Lines end in CR LF, LF or CR, and a line starting with a space continues the one before it. Lines
+    // that aren't "name: value" are skipped rather than failing, so a slightly off manifest still works.
+    // Lines are split at a byte limit, which can be in the middle of a character, so they are only decoded
+    // once they are joined.
+    pub fn parse(bytes: &[u8]) -> Manifest {
+        let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+        let mut lines: Vec<Vec<u8>> = Vec::new();
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let end = rest.iter().position(|&byte| byte == b'\r' || byte == b'\n').unwrap_or(rest.len());
+            let line = &rest[..end];
+            rest = match rest[end..].starts_with(b"\r\n") {
+                true => &rest[end + 2..],
+                false => &rest[(end + 1).min(rest.len())..],
+            };
+            // The main section ends at the first empty line
+            if line.is_empty() {
+                break;
+            }
+            match (line.strip_prefix(b" "), lines.last_mut()) {
+                (Some(continuation), Some(last)) => last.extend_from_slice(continuation),
+                _ => lines.push(line.to_vec()),
+            }
+        }
+        let main_attributes = lines.iter()
+            .map(|line| String::from_utf8_lossy(line))
+            .filter_map(|line| line.split_once(": ").map(|(name, value)| (name.to_string(), value.to_string())))
+            .collect();
+        Manifest { main_attributes }
+    }
+
+    // Attribute names aren't case sensitive
+    pub fn main_attribute(&self, name: &str) -> Option<&str> {
+        self.main_attributes.iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    // The entries of the Class-Path attribute, for the JAR at jar_path. They are URLs separated by spaces
+    // and relative to the directory the JAR is in, which end in / for directories. Only file URLs mean
+    // anything here, so others are left out.
+    pub fn class_path(&self, jar_path: &Path) -> Vec<ClassPathEntry> {
+        let directory = jar_path.parent().unwrap_or(Path::new(""));
+        let Some(class_path) = self.main_attribute("Class-Path") else {
+            return Vec::new();
+        };
+        class_path.split(' ')
+            .filter(|url| !url.is_empty())
+            .filter_map(|url| {
+                let path = match url.strip_prefix("file:") {
+                    Some(path) => path,
+                    // A scheme is letters, digits, + - and . before a colon, and at least two characters so a
+                    // Windows drive letter isn't taken for one
+                    None if url.split_once(':').is_some_and(|(scheme, _)| scheme.len() > 1
+                        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))) => return None,
+                    None => url,
+                };
+                let decoded = percent_decode(path)?;
+                let joined = directory.join(PathBuf::from(decoded.trim_end_matches('/')));
+                Some(match decoded.ends_with('/') {
+                    true => ClassPathEntry::Directory(joined),
+                    false => ClassPathEntry::Archive(joined),
+                })
+            })
+            .collect()
+    }
+}
+
+// Turns the %xx escapes of a URL path back into the bytes they stand for. None if an escape is malformed
+// or the result isn't UTF-8.
+fn percent_decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' => {
+                let hex = bytes.get(index + 1..index + 3).filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+                decoded.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                index += 3;
+            }
+            byte => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
@@ -0,0 +1,183 @@
+pub mod manifest;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use memmap2::Mmap;
+
+use crate::util::map_file;
+use crate::zip::ZipArchive;
+
+// What separates class path entries, like the JVM uses on this platform
+pub const SEPARATOR: char = if cfg!(windows) { ';' } else { ':' };
+
+// The places classes are looked for, in order, as given with -cp or the CLASSPATH environment variable
+#[derive(Debug, Clone, Default)]
+pub struct ClassPath {
+    pub entries: Vec<ClassPathEntry>,
+    // Archives are opened the first time a class is looked for in them, and stay open. None for those
+    // that couldn't be opened, which are skipped from then on.
+    archives: RefCell<HashMap<PathBuf, Option<Rc<ZipArchive<Mmap>>>>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClassPathEntry {
+    // Holds package directories, with a class a/b/C in a/b/C.class
+    Directory(PathBuf),
+    // A JAR or zip file, with a class a/b/C in its entry a/b/C.class
+    Archive(PathBuf),
+}
+
+// The bytes of a class file found on the class path
+pub enum ClassBytes {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl ClassPath {
+    pub fn new(entries: Vec<ClassPathEntry>) -> ClassPath {
+        ClassPath { entries, archives: RefCell::default() }
+    }
+
+    // Splits a class path like lib/*:classes. Empty entries are the current directory, and an entry
+    // ending in * stands for the JAR files in that directory.
+    pub fn parse(class_path: &str) -> ClassPath {
+        let mut entries = Vec::new();
+        for entry in class_path.split(SEPARATOR) {
+            let entry = match entry {
+                "" => ".",
+                entry => entry,
+            };
+            match entry.strip_suffix('*') {
+                Some(directory) if directory.is_empty() || directory.ends_with(['/', std::path::MAIN_SEPARATOR]) =>
+                    entries.extend(jar_files(Path::new(match directory {
+                        "" => ".",
+                        directory => directory,
+                    })).into_iter().map(ClassPathEntry::Archive)),
+                _ => entries.push(ClassPathEntry::new(PathBuf::from(entry))),
+            }
+        }
+        ClassPath::new(entries)
+    }
+
+    // Finds the class file for a binary name like java/lang/Object in the first entry that has it.
+    // Entries that don't exist, and archives that aren't zip files, are skipped like the JVM does.
+    pub fn find(&self, binary_name: &str) -> io::Result<Option<ClassBytes>> {
+        // Nothing but plain package and class names, so a name can't point outside of an entry
+        if binary_name.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+            return Ok(None);
+        }
+        for entry in &self.entries {
+            let bytes = match entry {
+                ClassPathEntry::Directory(directory) => find_in_directory(directory, binary_name)?,
+                ClassPathEntry::Archive(path) => match self.archive(path) {
+                    Some(archive) => find_in_archive(path, &archive, binary_name)?,
+                    None => None,
+                },
+            };
+            if bytes.is_some() {
+                return Ok(bytes);
+            }
+        }
+        Ok(None)
+    }
+
+    fn archive(&self, path: &Path) -> Option<Rc<ZipArchive<Mmap>>> {
+        self.archives.borrow_mut()
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                let mmap = map_file(path).ok()?;
+                ZipArchive::new(mmap).ok().map(Rc::new)
+            })
+            .clone()
+    }
+}
+
+// A ClassPath equals another with the same entries, whichever archives either has opened so far
+impl PartialEq for ClassPath {
+    fn eq(&self, other: &ClassPath) -> bool {
+        self.entries == other.entries
+    }
+}
+
+// Joins the entries back together, with wildcards expanded, like the java.class.path property shows them
+impl fmt::Display for ClassPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", SEPARATOR)?;
+            }
+            write!(f, "{}", entry.path().display())?;
+        }
+        Ok(())
+    }
+}
+
+impl ClassPathEntry {
+    // Files are taken to be archives, anything else as a directory, which may not exist (yet)
+    pub fn new(path: PathBuf) -> ClassPathEntry {
+        match path.is_file() {
+            true => ClassPathEntry::Archive(path),
+            false => ClassPathEntry::Directory(path),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            ClassPathEntry::Directory(path) | ClassPathEntry::Archive(path) => path,
+        }
+    }
+}
+
+impl Deref for ClassBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            ClassBytes::Mapped(mmap) => mmap,
+            ClassBytes::Owned(bytes) => bytes,
+        }
+    }
+}
+
+fn find_in_directory(directory: &Path, binary_name: &str) -> io::Result<Option<ClassBytes>> {
+    let mut path = directory.to_path_buf();
+    path.extend(binary_name.split('/'));
+    path.set_extension("class");
+    match map_file(&path) {
+        Ok(mmap) => Ok(Some(ClassBytes::Mapped(mmap))),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        // A directory named like the class file isn't it either
+        Err(_) if path.is_dir() => Ok(None),
+        Err(error) => Err(io::Error::new(error.kind(), format!("{}: {}", path.display(), error))),
+    }
+}
+
+// Entries are decompressed into memory, unlike class files in directories which are mapped
+fn find_in_archive(path: &Path, archive: &ZipArchive<Mmap>, binary_name: &str) -> io::Result<Option<ClassBytes>> {
+    match archive.read(&format!("{}.class", binary_name)) {
+        Ok(bytes) => Ok(bytes.map(ClassBytes::Owned)),
+        Err(error) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), error))),
+    }
+}
+
+// The .jar and .JAR files in a directory, which a wildcard entry expands to. Sorted, so the order at least
+// doesn't change from one run to the next.
+fn jar_files(directory: &Path) -> Vec<PathBuf> {
+    let Ok(read_dir) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut jars: Vec<PathBuf> = read_dir
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && matches!(path.extension().and_then(|e| e.to_str()), Some("jar" | "JAR")))
+        .collect();
+    jars.sort();
+    jars
+}
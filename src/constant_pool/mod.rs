@@ -1,4 +1,7 @@
-#[derive(Debug)]
+use crate::class::error::ClassFormatErrorKind;
+use crate::util::mutf8::ModifiedUtf8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ConstantPoolInfo {
     // tag=9
     FieldRef {
@@ -50,13 +53,12 @@ pub enum ConstantPoolInfo {
     Utf8 {
         // length: u16,
         // bytes: [u8; length],
-        string: String,
+        string: ModifiedUtf8,
     },
     // tag=15
     MethodHandle {
-        length: u16,
         reference_kind: u8,
-        reference_index: u8,
+        reference_index: u16,
     },
     // tag=16
     MethodType {
@@ -80,4 +82,230 @@ pub enum ConstantPoolInfo {
     Package {
         name_index: u16,
     },
+    // Occupies the slot following a Long or Double, which the spec considers unusable
+    Unusable,
+}
+
+impl ConstantPoolInfo {
+    // How many constant pool indexes the entry takes up: 2 for Long and Double, 1 for the rest
+    pub fn slots(&self) -> usize {
+        match self {
+            ConstantPoolInfo::Long { .. } | ConstantPoolInfo::Double { .. } => 2,
+            _ => 1,
+        }
+    }
+}
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4
+// Entries are looked up by constant pool index, which starts at 1. Long and Double entries take up two
+// indexes, the second of which can't be used.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConstantPool {
+    // Entry i lives at entries[i - 1]
+    entries: Vec<ConstantPoolInfo>,
+}
+
+impl ConstantPool {
+    pub fn new() -> ConstantPool {
+        ConstantPool { entries: Vec::new() }
+    }
+
+    // Adds an entry and returns its index. Long and Double take the index after it as well. An entry that
+    // would take constant_pool_count, a u2, past 65535 isn't added.
+    pub fn push(&mut self, info: ConstantPoolInfo) -> Result<u16, ClassFormatErrorKind> {
+        let count = self.count() + info.slots();
+        if count > u16::MAX as usize {
+            return Err(ClassFormatErrorKind::CountOverflow { count, max: u16::MAX as usize });
+        }
+        let slots = info.slots();
+        self.entries.push(info);
+        let index = self.entries.len() as u16;
+        if slots == 2 {
+            self.entries.push(ConstantPoolInfo::Unusable);
+        }
+        Ok(index)
+    }
+
+    // As in the class file: one more than the highest index, unusable ones included
+    pub fn count(&self) -> usize {
+        self.entries.len() + 1
+    }
+
+    // Every usable entry with its index
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &ConstantPoolInfo)> {
+        self.entries.iter().enumerate()
+            .filter(|(_, info)| !matches!(info, ConstantPoolInfo::Unusable))
+            .map(|(i, info)| ((i + 1) as u16, info))
+    }
+
+    pub fn get(&self, index: u16) -> Result<&ConstantPoolInfo, ClassFormatErrorKind> {
+        match (index as usize).checked_sub(1).and_then(|i| self.entries.get(i)) {
+            Some(ConstantPoolInfo::Unusable) | None => Err(ClassFormatErrorKind::BadConstantPoolIndex(index)),
+            Some(info) => Ok(info),
+        }
+    }
+
+    pub fn utf8(&self, index: u16) -> Result<&str, ClassFormatErrorKind> {
+        match self.get(index)? {
+            ConstantPoolInfo::Utf8 { string } => Ok(string.as_str()),
+            _ => Err(ClassFormatErrorKind::WrongConstantKind { index, expected: "Utf8" }),
+        }
+    }
+
+    // Returns the name_index of the Class entry at index
+    pub fn class(&self, index: u16) -> Result<u16, ClassFormatErrorKind> {
+        match self.get(index)? {
+            ConstantPoolInfo::Class { name_index } => Ok(*name_index),
+            _ => Err(ClassFormatErrorKind::WrongConstantKind { index, expected: "Class" }),
+        }
+    }
+
+    // The binary name the Class entry at index refers to
+    pub fn class_name(&self, index: u16) -> Result<&str, ClassFormatErrorKind> {
+        self.utf8(self.class(index)?)
+    }
+
+    // Returns the name and descriptor of the NameAndType entry at index
+    pub fn name_and_type(&self, index: u16) -> Result<(&str, &str), ClassFormatErrorKind> {
+        match self.get(index)? {
+            ConstantPoolInfo::NameAndType { name_index, descriptor_index } =>
+                Ok((self.utf8(*name_index)?, self.utf8(*descriptor_index)?)),
+            _ => Err(ClassFormatErrorKind::WrongConstantKind { index, expected: "NameAndType" }),
+        }
+    }
+
+    // Resolves a Fieldref, Methodref or InterfaceMethodref to its class, name and descriptor
+    pub fn member_ref(&self, index: u16) -> Result<(&str, &str, &str), ClassFormatErrorKind> {
+        match self.get(index)? {
+            ConstantPoolInfo::FieldRef { class_index, name_and_type_index } |
+            ConstantPoolInfo::MethodRef { class_index, name_and_type_index } |
+            ConstantPoolInfo::InterfaceMethodRef { class_index, name_and_type_index } => {
+                let (name, descriptor) = self.name_and_type(*name_and_type_index)?;
+                Ok((self.class_name(*class_index)?, name, descriptor))
+            }
+            _ => Err(ClassFormatErrorKind::WrongConstantKind {
+                index,
+                expected: "Fieldref, Methodref or InterfaceMethodref",
+            }),
+        }
+    }
+
+    // The name the Module entry at index refers to
+    pub fn module_name(&self, index: u16) -> Result<&str, ClassFormatErrorKind> {
+        match self.get(index)? {
+            ConstantPoolInfo::Module { name_index } => self.utf8(*name_index),
+            _ => Err(ClassFormatErrorKind::WrongConstantKind { index, expected: "Module" }),
+        }
+    }
+
+    // The name the Package entry at index refers to
+    pub fn package_name(&self, index: u16) -> Result<&str, ClassFormatErrorKind> {
+        match self.get(index)? {
+            ConstantPoolInfo::Package { name_index } => self.utf8(*name_index),
+            _ => Err(ClassFormatErrorKind::WrongConstantKind { index, expected: "Package" }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf8(string: &str) -> ConstantPoolInfo {
+        ConstantPoolInfo::Utf8 { string: ModifiedUtf8::from(string) }
+    }
+
+    // #1 "java/lang/Object", #2 Class #1, #3 "<init>", #4 "()V", #5 NameAndType #3:#4, #6 Methodref #2.#5,
+    // #7 Long and #9 Double with #8 and #10 unusable, #11 Integer
+    fn pool() -> ConstantPool {
+        let mut pool = ConstantPool::new();
+        for info in [
+            utf8("java/lang/Object"),
+            ConstantPoolInfo::Class { name_index: 1 },
+            utf8("<init>"),
+            utf8("()V"),
+            ConstantPoolInfo::NameAndType { name_index: 3, descriptor_index: 4 },
+            ConstantPoolInfo::MethodRef { class_index: 2, name_and_type_index: 5 },
+            ConstantPoolInfo::Long { high_bytes: 0, low_bytes: 1 },
+            ConstantPoolInfo::Double { high_bytes: 0, low_bytes: 1 },
+            ConstantPoolInfo::Integer { bytes: 1 },
+        ] {
+            pool.push(info).unwrap();
+        }
+        pool
+    }
+
+    #[test]
+    fn long_and_double_take_two_indexes() {
+        let pool = pool();
+        assert_eq!(pool.count(), 12);
+        assert!(matches!(pool.get(7), Ok(ConstantPoolInfo::Long { .. })));
+        assert!(matches!(pool.get(9), Ok(ConstantPoolInfo::Double { .. })));
+        assert!(matches!(pool.get(11), Ok(ConstantPoolInfo::Integer { .. })));
+        let indexes: Vec<u16> = pool.iter().map(|(index, _)| index).collect();
+        assert_eq!(indexes, [1, 2, 3, 4, 5, 6, 7, 9, 11]);
+    }
+
+    #[test]
+    fn bad_indexes() {
+        let pool = pool();
+        // 0 is never an index, the slots after the Long and Double can't be used, and 12 is past the end
+        for index in [0, 8, 10, 12, u16::MAX] {
+            assert!(matches!(pool.get(index), Err(ClassFormatErrorKind::BadConstantPoolIndex(i)) if i == index));
+            assert!(matches!(pool.utf8(index), Err(ClassFormatErrorKind::BadConstantPoolIndex(i)) if i == index));
+        }
+    }
+
+    // The index and kind of a WrongConstantKind error
+    fn wrong_kind<T>(result: Result<T, ClassFormatErrorKind>) -> (u16, &'static str) {
+        match result {
+            Err(ClassFormatErrorKind::WrongConstantKind { index, expected }) => (index, expected),
+            Err(kind) => panic!("{}", kind),
+            Ok(_) => panic!("no error"),
+        }
+    }
+
+    #[test]
+    fn wrong_kinds() {
+        let pool = pool();
+        assert_eq!(wrong_kind(pool.utf8(2)), (2, "Utf8"));
+        assert_eq!(wrong_kind(pool.class(1)), (1, "Class"));
+        assert_eq!(wrong_kind(pool.name_and_type(6)), (6, "NameAndType"));
+        assert_eq!(wrong_kind(pool.member_ref(5)), (5, "Fieldref, Methodref or InterfaceMethodref"));
+        assert_eq!(wrong_kind(pool.module_name(1)), (1, "Module"));
+        assert_eq!(wrong_kind(pool.package_name(11)), (11, "Package"));
+    }
+
+    #[test]
+    fn member_refs() {
+        let mut pool = pool();
+        assert_eq!(pool.member_ref(6).unwrap(), ("java/lang/Object", "<init>", "()V"));
+        assert_eq!(pool.class_name(2).unwrap(), "java/lang/Object");
+        assert_eq!(pool.name_and_type(5).unwrap(), ("<init>", "()V"));
+
+        // What the ref points at is checked all the way down
+        let field_ref = pool.push(ConstantPoolInfo::FieldRef { class_index: 1, name_and_type_index: 5 }).unwrap();
+        assert_eq!(wrong_kind(pool.member_ref(field_ref)), (1, "Class"));
+        let class = pool.push(ConstantPoolInfo::Class { name_index: 7 }).unwrap();
+        let interface_method_ref = ConstantPoolInfo::InterfaceMethodRef { class_index: class, name_and_type_index: 5 };
+        let interface_method_ref = pool.push(interface_method_ref).unwrap();
+        assert_eq!(wrong_kind(pool.member_ref(interface_method_ref)), (7, "Utf8"));
+        let method_ref = pool.push(ConstantPoolInfo::MethodRef { class_index: 2, name_and_type_index: 8 }).unwrap();
+        assert!(matches!(pool.member_ref(method_ref), Err(ClassFormatErrorKind::BadConstantPoolIndex(8))));
+    }
+
+    #[test]
+    fn full_pool() {
+        let mut pool = ConstantPool::new();
+        for i in 1..u16::MAX - 1 {
+            assert_eq!(pool.push(ConstantPoolInfo::Integer { bytes: i as u32 }).unwrap(), i);
+        }
+        // 65534 is the last index, so a Long doesn't fit there but an Integer does, and after that nothing
+        let error = pool.push(ConstantPoolInfo::Long { high_bytes: 0, low_bytes: 0 }).unwrap_err();
+        assert!(matches!(error, ClassFormatErrorKind::CountOverflow { count: 65536, max: 65535 }), "{}", error);
+        assert_eq!(pool.push(ConstantPoolInfo::Integer { bytes: 0 }).unwrap(), 65534);
+        let error = pool.push(ConstantPoolInfo::Integer { bytes: 0 }).unwrap_err();
+        assert!(matches!(error, ClassFormatErrorKind::CountOverflow { count: 65536, max: 65535 }), "{}", error);
+        assert_eq!(pool.count(), 65535);
+    }
 }
@@ -0,0 +1,280 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::class::format_check::is_binary_name;
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.2
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    // B
+    Byte,
+    // C
+    Char,
+    // D
+    Double,
+    // F
+    Float,
+    // I
+    Int,
+    // J
+    Long,
+    // S
+    Short,
+    // Z
+    Boolean,
+    // L ClassName ;
+    // Holds the binary name of the class, e.g. java/lang/Object
+    Object(String),
+    // [ ComponentType
+    Array(Box<FieldType>),
+}
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.3
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodDescriptor {
+    pub parameters: Vec<FieldType>,
+    // None for void
+    pub return_type: Option<FieldType>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DescriptorError {
+    pub descriptor: String,
+    // Byte offset into the descriptor where parsing went wrong
+    pub position: usize,
+    pub kind: DescriptorErrorKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DescriptorErrorKind {
+    UnexpectedEnd,
+    UnexpectedCharacter(char),
+    // Missing the ; that ends a class name
+    UnterminatedClassName,
+    InvalidClassName(String),
+    // Spec: an array type may have at most 255 dimensions
+    TooManyDimensions,
+    // Anything left after a complete descriptor
+    TrailingCharacters,
+}
+
+impl FieldType {
+    pub fn parse(descriptor: &str) -> Result<FieldType, DescriptorError> {
+        let mut parser = Parser { descriptor, position: 0 };
+        let field_type = parser.field_type()?;
+        parser.end()?;
+        Ok(field_type)
+    }
+
+    // Number of local variable or operand stack slots a value of this type takes up.
+    // Longs and doubles take two, everything else one.
+    pub fn slot_size(&self) -> u16 {
+        match self {
+            FieldType::Long | FieldType::Double => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(self, FieldType::Object(_) | FieldType::Array(_))
+    }
+
+    // Number of array dimensions, zero if this is not an array
+    pub fn dimensions(&self) -> usize {
+        match self {
+            FieldType::Array(component_type) => 1 + component_type.dimensions(),
+            _ => 0,
+        }
+    }
+}
+
+impl MethodDescriptor {
+    pub fn parse(descriptor: &str) -> Result<MethodDescriptor, DescriptorError> {
+        let mut parser = Parser { descriptor, position: 0 };
+        parser.expect('(')?;
+        let mut parameters = Vec::new();
+        while parser.peek() != Some(b')') {
+            parameters.push(parser.field_type()?);
+        }
+        parser.expect(')')?;
+        let return_type = match parser.peek() {
+            Some(b'V') => {
+                parser.position += 1;
+                None
+            }
+            _ => Some(parser.field_type()?),
+        };
+        parser.end()?;
+        Ok(MethodDescriptor { parameters, return_type })
+    }
+
+    // Number of local variable slots the parameters take up, not counting `this`.
+    // Spec: this, plus `this` for instance methods, may be at most 255. A descriptor can hold far more
+    // parameters than that, so the sum is a u32 that doesn't overflow.
+    pub fn parameter_slots(&self) -> u32 {
+        self.parameters.iter().map(|parameter| parameter.slot_size() as u32).sum()
+    }
+
+    // Number of operand stack slots the return value takes up, zero for void
+    pub fn return_slots(&self) -> u16 {
+        self.return_type.as_ref().map_or(0, FieldType::slot_size)
+    }
+}
+
+struct Parser<'a> {
+    descriptor: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn field_type(&mut self) -> Result<FieldType, DescriptorError> {
+        let start = self.position;
+        let mut dimensions = 0;
+        while self.peek() == Some(b'[') {
+            self.position += 1;
+            dimensions += 1;
+        }
+        if dimensions > 255 {
+            return Err(self.error_at(start, DescriptorErrorKind::TooManyDimensions));
+        }
+
+        let mut field_type = match self.peek() {
+            Some(b'B') => FieldType::Byte,
+            Some(b'C') => FieldType::Char,
+            Some(b'D') => FieldType::Double,
+            Some(b'F') => FieldType::Float,
+            Some(b'I') => FieldType::Int,
+            Some(b'J') => FieldType::Long,
+            Some(b'S') => FieldType::Short,
+            Some(b'Z') => FieldType::Boolean,
+            Some(b'L') => {
+                let name_start = self.position + 1;
+                let name_end = match self.descriptor[name_start..].find(';') {
+                    Some(length) => name_start + length,
+                    None => return Err(self.error_at(self.position, DescriptorErrorKind::UnterminatedClassName)),
+                };
+                let name = &self.descriptor[name_start..name_end];
+                if !is_binary_name(name) {
+                    return Err(self.error_at(name_start, DescriptorErrorKind::InvalidClassName(name.to_string())));
+                }
+                // Step onto the ; so the shared increment below moves past it
+                self.position = name_end;
+                FieldType::Object(name.to_string())
+            }
+            Some(_) => return Err(self.unexpected_character()),
+            None => return Err(self.error_at(self.position, DescriptorErrorKind::UnexpectedEnd)),
+        };
+        self.position += 1;
+
+        for _ in 0..dimensions {
+            field_type = FieldType::Array(Box::new(field_type));
+        }
+        Ok(field_type)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), DescriptorError> {
+        match self.peek() {
+            Some(b) if b as char == expected => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(_) => Err(self.unexpected_character()),
+            None => Err(self.error_at(self.position, DescriptorErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    fn end(&self) -> Result<(), DescriptorError> {
+        match self.position == self.descriptor.len() {
+            true => Ok(()),
+            false => Err(self.error_at(self.position, DescriptorErrorKind::TrailingCharacters)),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.descriptor.as_bytes().get(self.position).copied()
+    }
+
+    fn unexpected_character(&self) -> DescriptorError {
+        let c = self.descriptor[self.position..].chars().next().unwrap_or(char::REPLACEMENT_CHARACTER);
+        self.error_at(self.position, DescriptorErrorKind::UnexpectedCharacter(c))
+    }
+
+    fn error_at(&self, position: usize, kind: DescriptorErrorKind) -> DescriptorError {
+        DescriptorError { descriptor: self.descriptor.to_string(), position, kind }
+    }
+}
+
+// Both print in descriptor syntax, so parsing and printing round-trips
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::Byte => write!(f, "B"),
+            FieldType::Char => write!(f, "C"),
+            FieldType::Double => write!(f, "D"),
+            FieldType::Float => write!(f, "F"),
+            FieldType::Int => write!(f, "I"),
+            FieldType::Long => write!(f, "J"),
+            FieldType::Short => write!(f, "S"),
+            FieldType::Boolean => write!(f, "Z"),
+            FieldType::Object(class_name) => write!(f, "L{};", class_name),
+            FieldType::Array(component_type) => write!(f, "[{}", component_type),
+        }
+    }
+}
+
+impl fmt::Display for MethodDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        for parameter in &self.parameters {
+            write!(f, "{}", parameter)?;
+        }
+        write!(f, ")")?;
+        match &self.return_type {
+            Some(return_type) => write!(f, "{}", return_type),
+            None => write!(f, "V"),
+        }
+    }
+}
+
+impl fmt::Display for DescriptorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid descriptor {:?} at position {}: ", self.descriptor, self.position)?;
+        match &self.kind {
+            DescriptorErrorKind::UnexpectedEnd => write!(f, "unexpected end"),
+            DescriptorErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character {:?}", c),
+            DescriptorErrorKind::UnterminatedClassName => write!(f, "class name is missing its ';'"),
+            DescriptorErrorKind::InvalidClassName(name) => write!(f, "{:?} is not a valid class name", name),
+            DescriptorErrorKind::TooManyDimensions => write!(f, "more than 255 array dimensions"),
+            DescriptorErrorKind::TrailingCharacters => write!(f, "unexpected characters after the descriptor"),
+        }
+    }
+}
+
+impl Error for DescriptorError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn descriptor(parameters: &str, count: usize) -> MethodDescriptor {
+        MethodDescriptor::parse(&format!("({})V", parameters.repeat(count))).unwrap()
+    }
+
+    #[test]
+    fn parameter_slots() {
+        assert_eq!(descriptor("", 0).parameter_slots(), 0);
+        assert_eq!(descriptor("IJLjava/lang/String;D[J", 1).parameter_slots(), 7);
+    }
+
+    #[test]
+    fn parameter_slots_past_255() {
+        assert_eq!(descriptor("J", 128).parameter_slots(), 256);
+        assert_eq!(descriptor("I", 300).parameter_slots(), 300);
+    }
+
+    #[test]
+    fn parameter_slots_past_65535() {
+        assert_eq!(descriptor("J", 32768).parameter_slots(), 65536);
+        assert_eq!(descriptor("D", 40000).parameter_slots(), 80000);
+    }
+}
@@ -1,35 +1,148 @@
+use std::fmt;
+use std::ops::BitOr;
+
 use crate::attr;
-use attr::AttributeInfo;
+use crate::class::error::ClassFormatErrorKind;
+use attr::Attribute;
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct FieldInfo {
-    // TODO: Should be of type FieldFlags?
-    pub access_flags: u16,
+    pub access_flags: FieldAccessFlags,
     pub name_index: u16,
     pub descriptor_index: u16,
     // attributes_count: u16,
-    pub attributes: Vec<AttributeInfo>
+    pub attributes: Vec<Attribute>
+}
+
+impl FieldInfo {
+    // Generic type of the field, if it has one
+    pub fn signature_index(&self) -> Option<u16> {
+        attr::signature_index(&self.attributes)
+    }
+
+    pub fn is_deprecated(&self) -> bool {
+        attr::is_deprecated(&self.attributes)
+    }
+}
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.5-200-A.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FieldAccessFlags(pub u16);
+
+impl FieldAccessFlags {
+    pub const PUBLIC: FieldAccessFlags = FieldAccessFlags(0x0001);
+    pub const PRIVATE: FieldAccessFlags = FieldAccessFlags(0x0002);
+    pub const PROTECTED: FieldAccessFlags = FieldAccessFlags(0x0004);
+    pub const STATIC: FieldAccessFlags = FieldAccessFlags(0x0008);
+    pub const FINAL: FieldAccessFlags = FieldAccessFlags(0x0010);
+    pub const VOLATILE: FieldAccessFlags = FieldAccessFlags(0x0040);
+    pub const TRANSIENT: FieldAccessFlags = FieldAccessFlags(0x0080);
+    pub const SYNTHETIC: FieldAccessFlags = FieldAccessFlags(0x1000);
+    pub const ENUM: FieldAccessFlags = FieldAccessFlags(0x4000);
+
+    pub fn contains(self, flags: FieldAccessFlags) -> bool { self.0 & flags.0 == flags.0 }
+    pub fn is_public(self) -> bool { self.contains(Self::PUBLIC) }
+    pub fn is_private(self) -> bool { self.contains(Self::PRIVATE) }
+    pub fn is_protected(self) -> bool { self.contains(Self::PROTECTED) }
+    pub fn is_static(self) -> bool { self.contains(Self::STATIC) }
+    pub fn is_final(self) -> bool { self.contains(Self::FINAL) }
+    pub fn is_volatile(self) -> bool { self.contains(Self::VOLATILE) }
+    pub fn is_transient(self) -> bool { self.contains(Self::TRANSIENT) }
+    pub fn is_synthetic(self) -> bool { self.contains(Self::SYNTHETIC) }
+    pub fn is_enum(self) -> bool { self.contains(Self::ENUM) }
+
+    pub fn validate(self, in_interface: bool) -> Result<(), ClassFormatErrorKind> {
+        let illegal = |rule| Err(ClassFormatErrorKind::IllegalAccessFlags { flags: self.0, rule });
+        if in_interface {
+            // Spec: exactly public static final, and optionally synthetic
+            let required = Self::PUBLIC | Self::STATIC | Self::FINAL;
+            return match self.contains(required) && (self.0 & !(required | Self::SYNTHETIC).0) == 0 {
+                true => Ok(()),
+                false => illegal("an interface field must be public, static and final, and nothing else"),
+            };
+        }
+        let visibilities = [self.is_public(), self.is_private(), self.is_protected()];
+        if visibilities.iter().filter(|&&set| set).count() > 1 {
+            return illegal("at most one of public, private and protected may be set");
+        }
+        if self.is_final() && self.is_volatile() {
+            return illegal("a field must not be both final and volatile");
+        }
+        Ok(())
+    }
+}
+
+impl BitOr for FieldAccessFlags {
+    type Output = FieldAccessFlags;
+
+    fn bitor(self, flags: FieldAccessFlags) -> FieldAccessFlags {
+        FieldAccessFlags(self.0 | flags.0)
+    }
 }
 
-pub trait FieldFlags {
-    fn is_public(&self) -> bool;
-    fn is_private(&self) -> bool;
-    fn is_protected(&self) -> bool;
-    fn is_static(&self) -> bool;
-    fn is_final(&self) -> bool;
-    fn is_volatile(&self) -> bool;
-    fn is_transient(&self) -> bool;
-    fn is_synthetic(&self) -> bool;
-    fn is_enum(&self) -> bool;
+// The Java modifiers, in the order the JLS recommends
+impl fmt::Display for FieldAccessFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.is_public(), "public"),
+            (self.is_protected(), "protected"),
+            (self.is_private(), "private"),
+            (self.is_static(), "static"),
+            (self.is_final(), "final"),
+            (self.is_transient(), "transient"),
+            (self.is_volatile(), "volatile"),
+        ];
+        let modifiers: Vec<&str> = modifiers.iter().filter(|(set, _)| *set).map(|(_, modifier)| *modifier).collect();
+        write!(f, "{}", modifiers.join(" "))
+    }
 }
 
-impl FieldFlags for u16 {
-    fn is_public(&self) -> bool { self & 0x0001 == 0x0001 }
-    fn is_private(&self) -> bool { self & 0x0002 == 0x0002 }
-    fn is_protected(&self) -> bool { self & 0x0004 == 0x0004 }
-    fn is_static(&self) -> bool { self & 0x0008 == 0x0008 }
-    fn is_final(&self) -> bool { self & 0x0010 == 0x0010 }
-    fn is_volatile(&self) -> bool { self & 0x0040 == 0x0040 }
-    fn is_transient(&self) -> bool { self & 0x0080 == 0x0080 }
-    fn is_synthetic(&self) -> bool { self & 0x1000 == 0x1000 }
-    fn is_enum(&self) -> bool { self & 0x4000 == 0x4000 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The rule the flags break, if any
+    fn broken_rule(flags: FieldAccessFlags, in_interface: bool) -> Option<&'static str> {
+        match flags.validate(in_interface) {
+            Ok(()) => None,
+            Err(ClassFormatErrorKind::IllegalAccessFlags { rule, .. }) => Some(rule),
+            Err(kind) => panic!("{}", kind),
+        }
+    }
+
+    #[test]
+    fn visibility() {
+        let public = FieldAccessFlags::PUBLIC;
+        let private = FieldAccessFlags::PRIVATE;
+        let protected = FieldAccessFlags::PROTECTED;
+        for flags in [FieldAccessFlags(0), public, private, protected | FieldAccessFlags::STATIC] {
+            assert_eq!(broken_rule(flags, false), None, "{:#06x}", flags.0);
+        }
+        for flags in [public | private, public | protected, private | protected, public | private | protected] {
+            assert_eq!(broken_rule(flags, false), Some("at most one of public, private and protected may be set"));
+        }
+    }
+
+    #[test]
+    fn final_and_volatile() {
+        let flags = FieldAccessFlags::PRIVATE | FieldAccessFlags::FINAL | FieldAccessFlags::VOLATILE;
+        assert_eq!(broken_rule(flags, false), Some("a field must not be both final and volatile"));
+        assert_eq!(broken_rule(FieldAccessFlags::FINAL | FieldAccessFlags::TRANSIENT, false), None);
+    }
+
+    #[test]
+    fn interface_fields() {
+        let constant = FieldAccessFlags::PUBLIC | FieldAccessFlags::STATIC | FieldAccessFlags::FINAL;
+        assert_eq!(broken_rule(constant, true), None);
+        assert_eq!(broken_rule(constant | FieldAccessFlags::SYNTHETIC, true), None);
+        for flags in [
+            FieldAccessFlags::PUBLIC | FieldAccessFlags::STATIC,
+            FieldAccessFlags::PRIVATE | FieldAccessFlags::STATIC | FieldAccessFlags::FINAL,
+            constant | FieldAccessFlags::TRANSIENT,
+            constant | FieldAccessFlags::ENUM,
+        ] {
+            assert_eq!(broken_rule(flags, true),
+                       Some("an interface field must be public, static and final, and nothing else"));
+        }
+    }
 }
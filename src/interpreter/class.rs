@@ -0,0 +1,180 @@
+use std::rc::Rc;
+
+use crate::attr::{Attribute, AttributeInfo, BootstrapMethod, ExceptionTable, LineNumberTableEntry};
+use crate::bytecode::{decode, Instruction};
+use crate::class::{ClassAccessFlags, ClassFile};
+use crate::class::error::{ClassFormatError, ClassFormatErrorKind};
+use crate::constant_pool::ConstantPool;
+use crate::descriptor::{FieldType, MethodDescriptor};
+use crate::field::FieldAccessFlags;
+use crate::method::MethodAccessFlags;
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.3
+// A loaded class, with the parts of the class file the interpreter needs worked out ahead of time.
+// Superclasses and interfaces are kept by name and looked up in the VM when needed.
+pub struct Class {
+    pub name: String,
+    // None only for java/lang/Object
+    pub super_class: Option<String>,
+    pub interfaces: Vec<String>,
+    pub access_flags: ClassAccessFlags,
+    pub fields: Vec<Field>,
+    pub methods: Vec<Rc<Method>>,
+    pub constant_pool: ConstantPool,
+    pub bootstrap_methods: Vec<BootstrapMethod>,
+    pub source_file: Option<String>,
+    // Stands in for a class of the Java class library, whose methods are implemented in Rust
+    pub is_builtin: bool,
+}
+
+pub struct Field {
+    pub name: String,
+    pub descriptor: FieldType,
+    pub access_flags: FieldAccessFlags,
+    // Index of the ConstantValue a static field starts out with
+    pub constant_value: Option<u16>,
+}
+
+pub struct Method {
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: MethodAccessFlags,
+    // None for abstract and native methods
+    pub code: Option<Code>,
+}
+
+pub struct Code {
+    pub max_locals: u16,
+    pub instructions: Vec<Instruction>,
+    // pcs[i] is the pc of instructions[i], in increasing order
+    pub pcs: Vec<u32>,
+    pub exception_table: Vec<ExceptionTable>,
+    pub line_numbers: Vec<LineNumberTableEntry>,
+}
+
+impl Class {
+    // Expects a class file that has passed check_format
+    pub fn from_class_file(class_file: ClassFile) -> Result<Class, ClassFormatError> {
+        let name = class_file.find_class_name(class_file.this_class)?;
+        let super_class = match class_file.super_class {
+            0 => None,
+            index => Some(class_file.find_class_name(index)?),
+        };
+        let interfaces = class_file.interfaces.iter()
+            .map(|&index| class_file.find_class_name(index))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut fields = Vec::new();
+        for field_info in &class_file.fields {
+            let field_name = class_file.find_name(field_info.name_index)?;
+            let descriptor = FieldType::parse(&class_file.find_name(field_info.descriptor_index)?)
+                .map_err(|error| ClassFormatError::new(ClassFormatErrorKind::InvalidDescriptor(error), None,
+                                                       format!("field {}", field_name)))?;
+            let constant_value = field_info.attributes.iter().find_map(|attribute| match &attribute.info {
+                AttributeInfo::ConstantValue { constantvalue_index } => Some(*constantvalue_index),
+                _ => None
+            });
+            fields.push(Field { name: field_name, descriptor, access_flags: field_info.access_flags, constant_value });
+        }
+
+        let mut methods = Vec::new();
+        for method_info in &class_file.methods {
+            let method_name = class_file.find_name(method_info.name_index)?;
+            let descriptor = class_file.find_name(method_info.descriptor_index)?;
+            let context = format!("method {}{}", method_name, descriptor);
+            MethodDescriptor::parse(&descriptor)
+                .map_err(|error| ClassFormatError::new(ClassFormatErrorKind::InvalidDescriptor(error), None,
+                                                       context.as_str()))?;
+            let code = method_info.attributes.iter().find_map(|attribute| match &attribute.info {
+                AttributeInfo::Code { max_locals, code, exception_tables, attributes, .. } =>
+                    Some(Code::new(*max_locals, code, exception_tables, attributes)),
+                _ => None
+            }).transpose().map_err(|kind| ClassFormatError::new(kind, None, context.as_str()))?;
+            methods.push(Rc::new(Method {
+                name: method_name,
+                descriptor,
+                access_flags: method_info.access_flags,
+                code,
+            }));
+        }
+
+        let mut bootstrap_methods = Vec::new();
+        let mut source_file = None;
+        for attribute in &class_file.attributes {
+            match &attribute.info {
+                AttributeInfo::BootstrapMethods { bootstrap_methods: methods } => bootstrap_methods = methods.clone(),
+                AttributeInfo::SourceFile { sourcefile_index } => source_file = Some(class_file.find_name(*sourcefile_index)?),
+                _ => {}
+            }
+        }
+
+        Ok(Class {
+            name,
+            super_class,
+            interfaces,
+            access_flags: class_file.access_flags,
+            fields,
+            methods,
+            constant_pool: class_file.constant_pool,
+            bootstrap_methods,
+            source_file,
+            is_builtin: false,
+        })
+    }
+
+    // A class of the Java class library: only its place in the class hierarchy is known here
+    pub fn builtin(name: &str, super_class: Option<&str>, interfaces: &[&str], access_flags: ClassAccessFlags) -> Class {
+        Class {
+            name: name.to_string(),
+            super_class: super_class.map(str::to_string),
+            interfaces: interfaces.iter().map(|name| name.to_string()).collect(),
+            access_flags,
+            fields: Vec::new(),
+            methods: Vec::new(),
+            constant_pool: ConstantPool::new(),
+            bootstrap_methods: Vec::new(),
+            source_file: None,
+            is_builtin: true,
+        }
+    }
+
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<&Rc<Method>> {
+        self.methods.iter().find(|method| method.name == name && method.descriptor == descriptor)
+    }
+
+    pub fn find_field(&self, name: &str, descriptor: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.name == name && field.descriptor.to_string() == descriptor)
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.access_flags.is_interface()
+    }
+}
+
+impl Code {
+    fn new(max_locals: u16, code: &[u8], exception_table: &[ExceptionTable], attributes: &[Attribute])
+           -> Result<Code, ClassFormatErrorKind> {
+        let (pcs, instructions) = decode(code).map_err(ClassFormatErrorKind::BadInstruction)?.into_iter().unzip();
+        let mut line_numbers: Vec<LineNumberTableEntry> = attributes.iter()
+            .filter_map(|attribute| match &attribute.info {
+                AttributeInfo::LineNumberTable { entries } => Some(entries.iter().cloned()),
+                _ => None
+            })
+            .flatten()
+            .collect();
+        line_numbers.sort_by_key(|entry| entry.start_pc);
+        Ok(Code { max_locals, instructions, pcs, exception_table: exception_table.to_vec(), line_numbers })
+    }
+
+    // Which instruction starts at pc, if any
+    pub fn index_of(&self, pc: u32) -> Option<usize> {
+        self.pcs.binary_search(&pc).ok()
+    }
+
+    // The source line the instruction at pc came from: that of the last entry starting at or before it
+    pub fn line_number(&self, pc: u32) -> Option<u16> {
+        self.line_numbers.iter().rev()
+            .find(|entry| entry.start_pc as u32 <= pc)
+            .map(|entry| entry.line_number)
+    }
+}
@@ -0,0 +1,758 @@
+use std::rc::Rc;
+
+use crate::bytecode::{Instruction, WideInstruction};
+use crate::descriptor::{FieldType, MethodDescriptor};
+
+use super::{bad_constant, class_cast, field_type, java_name, Unwind, Vm};
+use super::class::{Class, Code};
+use super::frame::{bad_code, Frame};
+use super::heap::{Object, Reference, Value};
+
+// What comes after an instruction
+enum Step {
+    Next,
+    Jump(u32),
+    Return(Option<Value>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InvokeKind {
+    Virtual,
+    Special,
+    Static,
+    Interface,
+}
+
+impl Vm {
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-2.html#jvms-2.10
+    // Runs the code of a method until it returns, or throws an exception it doesn't catch itself
+    pub(super) fn execute(&mut self, class: &Rc<Class>, code: &Code, mut frame: Frame) -> Result<Option<Value>, Unwind> {
+        let mut index = 0;
+        loop {
+            let pc = *code.pcs.get(index).ok_or_else(|| bad_code("Falling off the end of the code"))?;
+            if let Some(entry) = self.stack.last_mut() {
+                entry.pc = pc;
+            }
+            let target = match self.step(class, &code.instructions[index], pc, &mut frame) {
+                Ok(Step::Next) => {
+                    index += 1;
+                    continue;
+                }
+                Ok(Step::Jump(target)) => target,
+                Ok(Step::Return(value)) => return Ok(value),
+                Err(Unwind::Exit(status)) => return Err(Unwind::Exit(status)),
+                Err(unwind) => {
+                    let exception = self.throwable(unwind)?;
+                    match self.find_handler(class, code, pc, exception)? {
+                        Some(handler) => {
+                            frame.op_stack.clear();
+                            frame.push(Value::Reference(Some(exception)));
+                            handler
+                        }
+                        None => return Err(Unwind::Throw(exception)),
+                    }
+                }
+            };
+            index = code.index_of(target).ok_or_else(|| bad_code("Jump to the middle of an instruction"))?;
+        }
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-2.html#jvms-2.10
+    // The first entry of the exception table that covers pc and catches the exception
+    fn find_handler(&mut self, class: &Rc<Class>, code: &Code, pc: u32, exception: Reference) -> Result<Option<u32>, Unwind> {
+        for entry in &code.exception_table {
+            if pc < entry.start_pc as u32 || pc >= entry.end_pc as u32 {
+                continue;
+            }
+            let catches = match entry.catch_type {
+                0 => true,
+                index => {
+                    let catch_type = class.constant_pool.class_name(index).map_err(bad_constant)?.to_string();
+                    self.is_instance_of(exception, &catch_type)?
+                }
+            };
+            if catches {
+                return Ok(Some(entry.handler_pc as u32));
+            }
+        }
+        Ok(None)
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5
+    fn step(&mut self, class: &Rc<Class>, instruction: &Instruction, pc: u32, frame: &mut Frame) -> Result<Step, Unwind> {
+        match instruction {
+            Instruction::Nop => {}
+            Instruction::AconstNull => frame.push(Value::Reference(None)),
+            Instruction::IconstM1 => frame.push(Value::Int(-1)),
+            Instruction::Iconst0 => frame.push(Value::Int(0)),
+            Instruction::Iconst1 => frame.push(Value::Int(1)),
+            Instruction::Iconst2 => frame.push(Value::Int(2)),
+            Instruction::Iconst3 => frame.push(Value::Int(3)),
+            Instruction::Iconst4 => frame.push(Value::Int(4)),
+            Instruction::Iconst5 => frame.push(Value::Int(5)),
+            Instruction::Lconst0 => frame.push(Value::Long(0)),
+            Instruction::Lconst1 => frame.push(Value::Long(1)),
+            Instruction::Fconst0 => frame.push(Value::Float(0.0)),
+            Instruction::Fconst1 => frame.push(Value::Float(1.0)),
+            Instruction::Fconst2 => frame.push(Value::Float(2.0)),
+            Instruction::Dconst0 => frame.push(Value::Double(0.0)),
+            Instruction::Dconst1 => frame.push(Value::Double(1.0)),
+            Instruction::Bipush(value) => frame.push(Value::Int(*value as i32)),
+            Instruction::Sipush(value) => frame.push(Value::Int(*value as i32)),
+            Instruction::Ldc(index) => frame.push(self.load_constant(class, *index as u16)?),
+            Instruction::LdcW(index) | Instruction::Ldc2W(index) => frame.push(self.load_constant(class, *index)?),
+
+            Instruction::Iload(index) | Instruction::Lload(index) | Instruction::Fload(index) |
+            Instruction::Dload(index) | Instruction::Aload(index) => frame.push(frame.load(*index as u16)?),
+            Instruction::Iload0 | Instruction::Lload0 | Instruction::Fload0 | Instruction::Dload0 |
+            Instruction::Aload0 => frame.push(frame.load(0)?),
+            Instruction::Iload1 | Instruction::Lload1 | Instruction::Fload1 | Instruction::Dload1 |
+            Instruction::Aload1 => frame.push(frame.load(1)?),
+            Instruction::Iload2 | Instruction::Lload2 | Instruction::Fload2 | Instruction::Dload2 |
+            Instruction::Aload2 => frame.push(frame.load(2)?),
+            Instruction::Iload3 | Instruction::Lload3 | Instruction::Fload3 | Instruction::Dload3 |
+            Instruction::Aload3 => frame.push(frame.load(3)?),
+            Instruction::Iaload | Instruction::Laload | Instruction::Faload | Instruction::Daload |
+            Instruction::Aaload | Instruction::Baload | Instruction::Caload | Instruction::Saload =>
+                self.array_load(instruction, frame)?,
+
+            Instruction::Istore(index) | Instruction::Lstore(index) | Instruction::Fstore(index) |
+            Instruction::Dstore(index) | Instruction::Astore(index) => {
+                let value = frame.pop()?;
+                frame.store(*index as u16, value)?;
+            }
+            Instruction::Istore0 | Instruction::Lstore0 | Instruction::Fstore0 | Instruction::Dstore0 |
+            Instruction::Astore0 => {
+                let value = frame.pop()?;
+                frame.store(0, value)?;
+            }
+            Instruction::Istore1 | Instruction::Lstore1 | Instruction::Fstore1 | Instruction::Dstore1 |
+            Instruction::Astore1 => {
+                let value = frame.pop()?;
+                frame.store(1, value)?;
+            }
+            Instruction::Istore2 | Instruction::Lstore2 | Instruction::Fstore2 | Instruction::Dstore2 |
+            Instruction::Astore2 => {
+                let value = frame.pop()?;
+                frame.store(2, value)?;
+            }
+            Instruction::Istore3 | Instruction::Lstore3 | Instruction::Fstore3 | Instruction::Dstore3 |
+            Instruction::Astore3 => {
+                let value = frame.pop()?;
+                frame.store(3, value)?;
+            }
+            Instruction::Iastore | Instruction::Lastore | Instruction::Fastore | Instruction::Dastore |
+            Instruction::Aastore | Instruction::Bastore | Instruction::Castore | Instruction::Sastore =>
+                self.array_store(instruction, frame)?,
+
+            Instruction::Pop => frame.pop_slots(1)?,
+            Instruction::Pop2 => frame.pop_slots(2)?,
+            Instruction::Dup => frame.dup(1, 0)?,
+            Instruction::DupX1 => frame.dup(1, 1)?,
+            Instruction::DupX2 => frame.dup(1, 2)?,
+            Instruction::Dup2 => frame.dup(2, 0)?,
+            Instruction::Dup2X1 => frame.dup(2, 1)?,
+            Instruction::Dup2X2 => frame.dup(2, 2)?,
+            Instruction::Swap => {
+                let value1 = frame.pop()?;
+                let value2 = frame.pop()?;
+                if value1.is_category_2() || value2.is_category_2() {
+                    return Err(bad_code("Category 2 value split by a stack instruction"));
+                }
+                frame.push(value1);
+                frame.push(value2);
+            }
+
+            Instruction::Iadd => int_operation(frame, |a, b| Ok(a.wrapping_add(b)))?,
+            Instruction::Isub => int_operation(frame, |a, b| Ok(a.wrapping_sub(b)))?,
+            Instruction::Imul => int_operation(frame, |a, b| Ok(a.wrapping_mul(b)))?,
+            Instruction::Idiv => int_operation(frame, |a, b| match b {
+                0 => Err(divide_by_zero()),
+                b => Ok(a.wrapping_div(b)),
+            })?,
+            Instruction::Irem => int_operation(frame, |a, b| match b {
+                0 => Err(divide_by_zero()),
+                b => Ok(a.wrapping_rem(b)),
+            })?,
+            Instruction::Iand => int_operation(frame, |a, b| Ok(a & b))?,
+            Instruction::Ior => int_operation(frame, |a, b| Ok(a | b))?,
+            Instruction::Ixor => int_operation(frame, |a, b| Ok(a ^ b))?,
+            // Only the low five bits of the shift distance count, which wrapping_shl and friends do too
+            Instruction::Ishl => int_operation(frame, |a, b| Ok(a.wrapping_shl(b as u32)))?,
+            Instruction::Ishr => int_operation(frame, |a, b| Ok(a.wrapping_shr(b as u32)))?,
+            Instruction::Iushr => int_operation(frame, |a, b| Ok((a as u32).wrapping_shr(b as u32) as i32))?,
+            Instruction::Ladd => long_operation(frame, |a, b| Ok(a.wrapping_add(b)))?,
+            Instruction::Lsub => long_operation(frame, |a, b| Ok(a.wrapping_sub(b)))?,
+            Instruction::Lmul => long_operation(frame, |a, b| Ok(a.wrapping_mul(b)))?,
+            Instruction::Ldiv => long_operation(frame, |a, b| match b {
+                0 => Err(divide_by_zero()),
+                b => Ok(a.wrapping_div(b)),
+            })?,
+            Instruction::Lrem => long_operation(frame, |a, b| match b {
+                0 => Err(divide_by_zero()),
+                b => Ok(a.wrapping_rem(b)),
+            })?,
+            Instruction::Land => long_operation(frame, |a, b| Ok(a & b))?,
+            Instruction::Lor => long_operation(frame, |a, b| Ok(a | b))?,
+            Instruction::Lxor => long_operation(frame, |a, b| Ok(a ^ b))?,
+            // The shift distance of the long shifts is an int
+            Instruction::Lshl | Instruction::Lshr | Instruction::Lushr => {
+                let distance = frame.pop_int()? as u32;
+                let value = frame.pop_long()?;
+                frame.push(Value::Long(match instruction {
+                    Instruction::Lshl => value.wrapping_shl(distance),
+                    Instruction::Lshr => value.wrapping_shr(distance),
+                    _ => (value as u64).wrapping_shr(distance) as i64,
+                }));
+            }
+            // Rust's % on floating point numbers is fmod, like Java's
+            Instruction::Fadd => float_operation(frame, |a, b| a + b)?,
+            Instruction::Fsub => float_operation(frame, |a, b| a - b)?,
+            Instruction::Fmul => float_operation(frame, |a, b| a * b)?,
+            Instruction::Fdiv => float_operation(frame, |a, b| a / b)?,
+            Instruction::Frem => float_operation(frame, |a, b| a % b)?,
+            Instruction::Dadd => double_operation(frame, |a, b| a + b)?,
+            Instruction::Dsub => double_operation(frame, |a, b| a - b)?,
+            Instruction::Dmul => double_operation(frame, |a, b| a * b)?,
+            Instruction::Ddiv => double_operation(frame, |a, b| a / b)?,
+            Instruction::Drem => double_operation(frame, |a, b| a % b)?,
+            Instruction::Ineg => {
+                let value = frame.pop_int()?;
+                frame.push(Value::Int(value.wrapping_neg()));
+            }
+            Instruction::Lneg => {
+                let value = frame.pop_long()?;
+                frame.push(Value::Long(value.wrapping_neg()));
+            }
+            Instruction::Fneg => {
+                let value = frame.pop_float()?;
+                frame.push(Value::Float(-value));
+            }
+            Instruction::Dneg => {
+                let value = frame.pop_double()?;
+                frame.push(Value::Double(-value));
+            }
+            Instruction::Iinc { index, constant } => self.increment(frame, *index as u16, *constant as i32)?,
+
+            // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-2.html#jvms-2.8.3
+            // Rust's `as` rounds towards zero, saturates and turns NaN into zero, just like Java
+            Instruction::I2l => {
+                let value = frame.pop_int()?;
+                frame.push(Value::Long(value as i64));
+            }
+            Instruction::I2f => {
+                let value = frame.pop_int()?;
+                frame.push(Value::Float(value as f32));
+            }
+            Instruction::I2d => {
+                let value = frame.pop_int()?;
+                frame.push(Value::Double(value as f64));
+            }
+            Instruction::L2i => {
+                let value = frame.pop_long()?;
+                frame.push(Value::Int(value as i32));
+            }
+            Instruction::L2f => {
+                let value = frame.pop_long()?;
+                frame.push(Value::Float(value as f32));
+            }
+            Instruction::L2d => {
+                let value = frame.pop_long()?;
+                frame.push(Value::Double(value as f64));
+            }
+            Instruction::F2i => {
+                let value = frame.pop_float()?;
+                frame.push(Value::Int(value as i32));
+            }
+            Instruction::F2l => {
+                let value = frame.pop_float()?;
+                frame.push(Value::Long(value as i64));
+            }
+            Instruction::F2d => {
+                let value = frame.pop_float()?;
+                frame.push(Value::Double(value as f64));
+            }
+            Instruction::D2i => {
+                let value = frame.pop_double()?;
+                frame.push(Value::Int(value as i32));
+            }
+            Instruction::D2l => {
+                let value = frame.pop_double()?;
+                frame.push(Value::Long(value as i64));
+            }
+            Instruction::D2f => {
+                let value = frame.pop_double()?;
+                frame.push(Value::Float(value as f32));
+            }
+            Instruction::I2b => {
+                let value = frame.pop_int()?;
+                frame.push(Value::Int(value as i8 as i32));
+            }
+            Instruction::I2c => {
+                let value = frame.pop_int()?;
+                frame.push(Value::Int(value as u16 as i32));
+            }
+            Instruction::I2s => {
+                let value = frame.pop_int()?;
+                frame.push(Value::Int(value as i16 as i32));
+            }
+
+            Instruction::Lcmp => {
+                let value2 = frame.pop_long()?;
+                let value1 = frame.pop_long()?;
+                frame.push(Value::Int(value1.cmp(&value2) as i32));
+            }
+            // The l and g variants differ in what NaN compares as
+            Instruction::Fcmpl | Instruction::Fcmpg => {
+                let value2 = frame.pop_float()?;
+                let value1 = frame.pop_float()?;
+                let nan = if matches!(instruction, Instruction::Fcmpl) { -1 } else { 1 };
+                frame.push(Value::Int(value1.partial_cmp(&value2).map_or(nan, |ordering| ordering as i32)));
+            }
+            Instruction::Dcmpl | Instruction::Dcmpg => {
+                let value2 = frame.pop_double()?;
+                let value1 = frame.pop_double()?;
+                let nan = if matches!(instruction, Instruction::Dcmpl) { -1 } else { 1 };
+                frame.push(Value::Int(value1.partial_cmp(&value2).map_or(nan, |ordering| ordering as i32)));
+            }
+
+            Instruction::Ifeq(target) => return branch(frame.pop_int()? == 0, *target),
+            Instruction::Ifne(target) => return branch(frame.pop_int()? != 0, *target),
+            Instruction::Iflt(target) => return branch(frame.pop_int()? < 0, *target),
+            Instruction::Ifge(target) => return branch(frame.pop_int()? >= 0, *target),
+            Instruction::Ifgt(target) => return branch(frame.pop_int()? > 0, *target),
+            Instruction::Ifle(target) => return branch(frame.pop_int()? <= 0, *target),
+            Instruction::IfIcmpeq(target) | Instruction::IfIcmpne(target) | Instruction::IfIcmplt(target) |
+            Instruction::IfIcmpge(target) | Instruction::IfIcmpgt(target) | Instruction::IfIcmple(target) => {
+                let value2 = frame.pop_int()?;
+                let value1 = frame.pop_int()?;
+                let taken = match instruction {
+                    Instruction::IfIcmpeq(_) => value1 == value2,
+                    Instruction::IfIcmpne(_) => value1 != value2,
+                    Instruction::IfIcmplt(_) => value1 < value2,
+                    Instruction::IfIcmpge(_) => value1 >= value2,
+                    Instruction::IfIcmpgt(_) => value1 > value2,
+                    _ => value1 <= value2,
+                };
+                return branch(taken, *target);
+            }
+            Instruction::IfAcmpeq(target) | Instruction::IfAcmpne(target) => {
+                let value2 = frame.pop_reference()?;
+                let value1 = frame.pop_reference()?;
+                return branch((value1 == value2) == matches!(instruction, Instruction::IfAcmpeq(_)), *target);
+            }
+            Instruction::Ifnull(target) => return branch(frame.pop_reference()?.is_none(), *target),
+            Instruction::Ifnonnull(target) => return branch(frame.pop_reference()?.is_some(), *target),
+            Instruction::Goto(target) | Instruction::GotoW(target) => return Ok(Step::Jump(*target)),
+            Instruction::Jsr(target) | Instruction::JsrW(target) => {
+                frame.push(Value::ReturnAddress(pc + instruction.length(pc)));
+                return Ok(Step::Jump(*target));
+            }
+            Instruction::Ret(index) => return ret(frame, *index as u16),
+            Instruction::Tableswitch { default, low, high, targets } => {
+                let key = frame.pop_int()?;
+                return Ok(Step::Jump(match key >= *low && key <= *high {
+                    true => targets[(key as i64 - *low as i64) as usize],
+                    false => *default,
+                }));
+            }
+            Instruction::Lookupswitch { default, pairs } => {
+                let key = frame.pop_int()?;
+                let target = pairs.iter().find(|(match_, _)| *match_ == key).map_or(*default, |(_, target)| *target);
+                return Ok(Step::Jump(target));
+            }
+
+            Instruction::Ireturn | Instruction::Lreturn | Instruction::Freturn | Instruction::Dreturn |
+            Instruction::Areturn => return Ok(Step::Return(Some(frame.pop()?))),
+            Instruction::Return => return Ok(Step::Return(None)),
+
+            Instruction::Getstatic(index) => {
+                let (class_name, name, is_builtin) = self.static_field(class, *index)?;
+                let value = match is_builtin {
+                    true => self.builtin_static(&class_name, &name)?,
+                    false => self.statics.get(&(class_name, name)).copied().unwrap_or(Value::Reference(None)),
+                };
+                frame.push(value);
+            }
+            Instruction::Putstatic(index) => {
+                let value = frame.pop()?;
+                match self.static_field(class, *index)? {
+                    (_, name, true) => return Err(Unwind::Raise("java/lang/IllegalAccessError", Some(name))),
+                    (class_name, name, false) => {
+                        self.statics.insert((class_name, name), value);
+                    }
+                }
+            }
+            Instruction::Getfield(index) => {
+                let (declaring_class, name, field_type) = self.instance_field(class, *index)?;
+                let Some(object) = frame.pop_reference()? else {
+                    return Err(null_pointer(format!("Cannot read field \"{}\"", name)));
+                };
+                let value = self.get_field(object, &declaring_class, &name).unwrap_or_else(|| Value::default_for(&field_type));
+                frame.push(value);
+            }
+            Instruction::Putfield(index) => {
+                let (declaring_class, name, _) = self.instance_field(class, *index)?;
+                let value = frame.pop()?;
+                let Some(object) = frame.pop_reference()? else {
+                    return Err(null_pointer(format!("Cannot assign field \"{}\"", name)));
+                };
+                self.set_field(object, &declaring_class, &name, value);
+            }
+            Instruction::Invokevirtual(index) => self.invoke(class, InvokeKind::Virtual, *index, frame)?,
+            Instruction::Invokespecial(index) => self.invoke(class, InvokeKind::Special, *index, frame)?,
+            Instruction::Invokestatic(index) => self.invoke(class, InvokeKind::Static, *index, frame)?,
+            Instruction::Invokeinterface { index, .. } => self.invoke(class, InvokeKind::Interface, *index, frame)?,
+            Instruction::Invokedynamic(index) => self.invoke_dynamic(class, *index, frame)?,
+
+            Instruction::New(index) => {
+                let class_name = class.constant_pool.class_name(*index).map_err(bad_constant)?.to_string();
+                let new_class = self.load_class(&class_name)?;
+                if new_class.is_interface() || new_class.access_flags.is_abstract() {
+                    return Err(Unwind::Raise("java/lang/InstantiationError", Some(java_name(&class_name))));
+                }
+                self.initialize(&new_class)?;
+                let object = self.new_instance(&new_class)?;
+                frame.push(Value::Reference(Some(object)));
+            }
+            Instruction::Newarray(array_type) => {
+                let component = match array_type {
+                    4 => "Z",
+                    5 => "C",
+                    6 => "F",
+                    7 => "D",
+                    8 => "B",
+                    9 => "S",
+                    10 => "I",
+                    11 => "J",
+                    _ => return Err(bad_code("Bad newarray type")),
+                };
+                let count = frame.pop_int()?;
+                let array = self.new_array(component, &[count])?;
+                frame.push(Value::Reference(Some(array)));
+            }
+            Instruction::Anewarray(index) => {
+                let class_name = class.constant_pool.class_name(*index).map_err(bad_constant)?;
+                let component = match class_name.starts_with('[') {
+                    true => class_name.to_string(),
+                    false => format!("L{};", class_name),
+                };
+                let count = frame.pop_int()?;
+                let array = self.new_array(&component, &[count])?;
+                frame.push(Value::Reference(Some(array)));
+            }
+            Instruction::Multianewarray { index, dimensions } => {
+                let class_name = class.constant_pool.class_name(*index).map_err(bad_constant)?.to_string();
+                let counts: Vec<i32> = frame.pop_arguments(*dimensions as usize)?.into_iter()
+                    .map(|count| match count {
+                        Value::Int(count) => Ok(count),
+                        _ => Err(bad_code("Expecting an int on the operand stack")),
+                    })
+                    .collect::<Result<_, _>>()?;
+                let component = class_name.strip_prefix('[').ok_or_else(|| bad_code("multianewarray of a class that isn't an array"))?;
+                let array = self.new_array(component, &counts)?;
+                frame.push(Value::Reference(Some(array)));
+            }
+            Instruction::Arraylength => {
+                let Some(array) = frame.pop_reference()? else {
+                    return Err(null_pointer("Cannot read the array length".to_string()));
+                };
+                match self.heap.get(array) {
+                    Object::Array { values, .. } => frame.push(Value::Int(values.len() as i32)),
+                    _ => return Err(bad_code("Expecting an array on the operand stack")),
+                }
+            }
+            Instruction::Athrow => {
+                let Some(exception) = frame.pop_reference()? else {
+                    return Err(null_pointer("Cannot throw exception".to_string()));
+                };
+                return Err(Unwind::Throw(exception));
+            }
+            Instruction::Checkcast(index) => {
+                let class_name = class.constant_pool.class_name(*index).map_err(bad_constant)?.to_string();
+                if let Some(Value::Reference(Some(object))) = frame.op_stack.last().copied() {
+                    if !self.is_instance_of(object, &class_name)? {
+                        let object_class = self.heap.get(object).class_name();
+                        return Err(class_cast(&object_class, &class_name));
+                    }
+                }
+            }
+            Instruction::Instanceof(index) => {
+                let class_name = class.constant_pool.class_name(*index).map_err(bad_constant)?.to_string();
+                let result = match frame.pop_reference()? {
+                    Some(object) => self.is_instance_of(object, &class_name)?,
+                    None => false,
+                };
+                frame.push(Value::Int(result as i32));
+            }
+            // With a single thread, there is nobody to hold a monitor against
+            Instruction::Monitorenter => if frame.pop_reference()?.is_none() {
+                return Err(null_pointer("Cannot enter synchronized block".to_string()));
+            },
+            Instruction::Monitorexit => if frame.pop_reference()?.is_none() {
+                return Err(null_pointer("Cannot exit synchronized block".to_string()));
+            },
+
+            Instruction::Wide(wide) => match wide {
+                WideInstruction::Iload(index) | WideInstruction::Lload(index) | WideInstruction::Fload(index) |
+                WideInstruction::Dload(index) | WideInstruction::Aload(index) => frame.push(frame.load(*index)?),
+                WideInstruction::Istore(index) | WideInstruction::Lstore(index) | WideInstruction::Fstore(index) |
+                WideInstruction::Dstore(index) | WideInstruction::Astore(index) => {
+                    let value = frame.pop()?;
+                    frame.store(*index, value)?;
+                }
+                WideInstruction::Ret(index) => return ret(frame, *index),
+                WideInstruction::Iinc { index, constant } => self.increment(frame, *index, *constant as i32)?,
+            },
+        }
+        Ok(Step::Next)
+    }
+
+    fn increment(&mut self, frame: &mut Frame, index: u16, constant: i32) -> Result<(), Unwind> {
+        match frame.load(index)? {
+            Value::Int(value) => frame.store(index, Value::Int(value.wrapping_add(constant))),
+            _ => Err(bad_code("Expecting an int in the local variable")),
+        }
+    }
+
+    fn array_load(&mut self, instruction: &Instruction, frame: &mut Frame) -> Result<(), Unwind> {
+        let index = frame.pop_int()?;
+        let Some(array) = frame.pop_reference()? else {
+            return Err(null_pointer(format!("Cannot load from {} array", array_kind(instruction))));
+        };
+        let Object::Array { values, .. } = self.heap.get(array) else {
+            return Err(bad_code("Expecting an array on the operand stack"));
+        };
+        let value = *values.get(index as usize).filter(|_| index >= 0).ok_or_else(|| out_of_bounds(index, values.len()))?;
+        frame.push(value);
+        Ok(())
+    }
+
+    fn array_store(&mut self, instruction: &Instruction, frame: &mut Frame) -> Result<(), Unwind> {
+        let value = frame.pop()?;
+        let index = frame.pop_int()?;
+        let Some(array) = frame.pop_reference()? else {
+            return Err(null_pointer(format!("Cannot store to {} array", array_kind(instruction))));
+        };
+        let Object::Array { component, values } = self.heap.get(array) else {
+            return Err(bad_code("Expecting an array on the operand stack"));
+        };
+        if index < 0 || index as usize >= values.len() {
+            return Err(out_of_bounds(index, values.len()));
+        }
+        // bastore, castore and sastore narrow the int to the element type
+        let value = match (instruction, value, component.as_str()) {
+            (Instruction::Bastore, Value::Int(value), "Z") => Value::Int(value & 1),
+            (Instruction::Bastore, Value::Int(value), _) => Value::Int(value as i8 as i32),
+            (Instruction::Castore, Value::Int(value), _) => Value::Int(value as u16 as i32),
+            (Instruction::Sastore, Value::Int(value), _) => Value::Int(value as i16 as i32),
+            (Instruction::Aastore, Value::Reference(Some(object)), component) => {
+                let component = component.strip_prefix('L').and_then(|name| name.strip_suffix(';')).unwrap_or(component);
+                let component = component.to_string();
+                if !self.is_instance_of(object, &component)? {
+                    let object_class = self.heap.get(object).class_name();
+                    return Err(Unwind::Raise("java/lang/ArrayStoreException", Some(java_name(&object_class))));
+                }
+                value
+            }
+            (_, value, _) => value,
+        };
+        if let Object::Array { values, .. } = self.heap.get_mut(array) {
+            values[index as usize] = value;
+        }
+        Ok(())
+    }
+
+    // An array of arrays for each count after the first, like multianewarray makes
+    fn new_array(&mut self, component: &str, counts: &[i32]) -> Result<Reference, Unwind> {
+        if let Some(count) = counts.iter().find(|&&count| count < 0) {
+            return Err(Unwind::Raise("java/lang/NegativeArraySizeException", Some(count.to_string())));
+        }
+        let count = counts[0] as usize;
+        let values = match &counts[1..] {
+            [] => vec![Value::default_for(&field_type(component)?); count],
+            rest => {
+                let inner = component.strip_prefix('[').ok_or_else(|| bad_code("Too many dimensions for the array class"))?;
+                (0..count).map(|_| self.new_array(inner, rest).map(|array| Value::Reference(Some(array))))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+        Ok(self.heap.allocate(Object::Array { component: component.to_string(), values }))
+    }
+
+    // The class declaring the static field a Fieldref points to, initialized, and the field's name. Library
+    // classes have none but the few builtin_static knows, so for them it's the class named and true.
+    fn static_field(&mut self, class: &Rc<Class>, index: u16) -> Result<(String, String, bool), Unwind> {
+        let (class_name, name, descriptor) = class.constant_pool.member_ref(index).map_err(bad_constant)?;
+        let (name, descriptor) = (name.to_string(), descriptor.to_string());
+        let field_class = self.load_class(class_name)?;
+        if field_class.is_builtin {
+            return Ok((field_class.name.clone(), name, true));
+        }
+        let declaring_class = self.resolve_field(&field_class, &name, &descriptor)?
+            .ok_or_else(|| Unwind::Raise("java/lang/NoSuchFieldError", Some(name.clone())))?;
+        self.initialize(&declaring_class)?;
+        Ok((declaring_class.name.clone(), name, false))
+    }
+
+    fn instance_field(&mut self, class: &Rc<Class>, index: u16) -> Result<(String, String, FieldType), Unwind> {
+        let (class_name, name, descriptor) = class.constant_pool.member_ref(index).map_err(bad_constant)?;
+        let (name, descriptor) = (name.to_string(), descriptor.to_string());
+        let field_class = self.load_class(class_name)?;
+        let declaring_class = self.resolve_field(&field_class, &name, &descriptor)?
+            .ok_or_else(|| Unwind::Raise("java/lang/NoSuchFieldError", Some(name.clone())))?;
+        Ok((declaring_class.name.clone(), name, field_type(&descriptor)?))
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5.invokespecial
+    fn invoke(&mut self, class: &Rc<Class>, kind: InvokeKind, index: u16, frame: &mut Frame) -> Result<(), Unwind> {
+        let (class_name, name, descriptor) = class.constant_pool.member_ref(index).map_err(bad_constant)?;
+        let (class_name, name, descriptor) = (class_name.to_string(), name.to_string(), descriptor.to_string());
+        let parsed_descriptor = MethodDescriptor::parse(&descriptor)
+            .map_err(|error| Unwind::Raise("java/lang/ClassFormatError", Some(error.to_string())))?;
+        let count = parsed_descriptor.parameters.len() + (kind != InvokeKind::Static) as usize;
+        let arguments = frame.pop_arguments(count)?;
+        let referenced = self.load_class(&class_name)?;
+
+        let result = match kind {
+            InvokeKind::Static => {
+                self.initialize(&referenced)?;
+                self.invoke_from(&referenced, &name, &descriptor, arguments)?
+            }
+            _ => {
+                let receiver = match arguments[0] {
+                    Value::Reference(Some(receiver)) => receiver,
+                    Value::Reference(None) => return Err(null_pointer(format!(
+                        "Cannot invoke \"{}.{}({})\"", npe_name(&class_name), name, java_parameters(&parsed_descriptor)))),
+                    _ => return Err(bad_code("Expecting a reference on the operand stack")),
+                };
+                let start = match kind {
+                    // Methods of the superclass, called with super.name(), are looked up from the superclass
+                    // of the calling class rather than the one named, in case there is one in between
+                    InvokeKind::Special if name != "<init>" && class.name != class_name && !referenced.is_interface() => {
+                        match self.super_class(class)? {
+                            Some(super_class) if self.is_assignable(&super_class.name, &class_name)? => super_class,
+                            _ => referenced,
+                        }
+                    }
+                    InvokeKind::Special => referenced,
+                    _ => match referenced.find_method(&name, &descriptor) {
+                        // Private methods are never overridden
+                        Some(method) if method.access_flags.is_private() => {
+                            let method = method.clone();
+                            let result = self.invoke_method(&referenced, &method, arguments)?;
+                            if let Some(value) = result {
+                                frame.push(value);
+                            }
+                            return Ok(());
+                        }
+                        _ => self.load_class(&self.heap.get(receiver).class_name())?,
+                    },
+                };
+                self.invoke_from(&start, &name, &descriptor, arguments)?
+            }
+        };
+        if let Some(value) = result {
+            frame.push(value);
+        }
+        Ok(())
+    }
+}
+
+fn int_operation(frame: &mut Frame, operation: impl Fn(i32, i32) -> Result<i32, Unwind>) -> Result<(), Unwind> {
+    let value2 = frame.pop_int()?;
+    let value1 = frame.pop_int()?;
+    frame.push(Value::Int(operation(value1, value2)?));
+    Ok(())
+}
+
+fn long_operation(frame: &mut Frame, operation: impl Fn(i64, i64) -> Result<i64, Unwind>) -> Result<(), Unwind> {
+    let value2 = frame.pop_long()?;
+    let value1 = frame.pop_long()?;
+    frame.push(Value::Long(operation(value1, value2)?));
+    Ok(())
+}
+
+fn float_operation(frame: &mut Frame, operation: impl Fn(f32, f32) -> f32) -> Result<(), Unwind> {
+    let value2 = frame.pop_float()?;
+    let value1 = frame.pop_float()?;
+    frame.push(Value::Float(operation(value1, value2)));
+    Ok(())
+}
+
+fn double_operation(frame: &mut Frame, operation: impl Fn(f64, f64) -> f64) -> Result<(), Unwind> {
+    let value2 = frame.pop_double()?;
+    let value1 = frame.pop_double()?;
+    frame.push(Value::Double(operation(value1, value2)));
+    Ok(())
+}
+
+fn branch(taken: bool, target: u32) -> Result<Step, Unwind> {
+    match taken {
+        true => Ok(Step::Jump(target)),
+        false => Ok(Step::Next),
+    }
+}
+
+fn ret(frame: &Frame, index: u16) -> Result<Step, Unwind> {
+    match frame.load(index)? {
+        Value::ReturnAddress(target) => Ok(Step::Jump(target)),
+        _ => Err(bad_code("Expecting a returnAddress in the local variable")),
+    }
+}
+
+// The element type the helpful NullPointerException messages name for an array instruction
+fn array_kind(instruction: &Instruction) -> &'static str {
+    match instruction {
+        Instruction::Iaload | Instruction::Iastore => "int",
+        Instruction::Laload | Instruction::Lastore => "long",
+        Instruction::Faload | Instruction::Fastore => "float",
+        Instruction::Daload | Instruction::Dastore => "double",
+        Instruction::Baload | Instruction::Bastore => "byte/boolean",
+        Instruction::Caload | Instruction::Castore => "char",
+        Instruction::Saload | Instruction::Sastore => "short",
+        _ => "object",
+    }
+}
+
+// The parameter types of a method the way Java source writes them, like int, java.lang.String[]
+fn java_parameters(descriptor: &MethodDescriptor) -> String {
+    descriptor.parameters.iter().map(java_type).collect::<Vec<_>>().join(", ")
+}
+
+fn java_type(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::Byte => "byte".to_string(),
+        FieldType::Char => "char".to_string(),
+        FieldType::Double => "double".to_string(),
+        FieldType::Float => "float".to_string(),
+        FieldType::Int => "int".to_string(),
+        FieldType::Long => "long".to_string(),
+        FieldType::Short => "short".to_string(),
+        FieldType::Boolean => "boolean".to_string(),
+        FieldType::Object(name) => npe_name(name),
+        FieldType::Array(component) => format!("{}[]", java_type(component)),
+    }
+}
+
+// NullPointerException messages leave the package off String and Object
+fn npe_name(binary_name: &str) -> String {
+    match binary_name {
+        "java/lang/String" => "String".to_string(),
+        "java/lang/Object" => "Object".to_string(),
+        name => java_name(name),
+    }
+}
+
+fn null_pointer(message: String) -> Unwind {
+    Unwind::Raise("java/lang/NullPointerException", Some(message))
+}
+
+fn divide_by_zero() -> Unwind {
+    Unwind::Raise("java/lang/ArithmeticException", Some("/ by zero".to_string()))
+}
+
+fn out_of_bounds(index: i32, length: usize) -> Unwind {
+    Unwind::Raise("java/lang/ArrayIndexOutOfBoundsException",
+                  Some(format!("Index {} out of bounds for length {}", index, length)))
+}
@@ -0,0 +1,140 @@
+use super::Unwind;
+use super::heap::{Reference, Value};
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-2.html#jvms-2.6
+// Class files aren't verified, so code that mixes up types gets a VerifyError when it runs into them.
+pub struct Frame {
+    // Size of local_vars is max_locals from the Code attribute.
+    // Longs and doubles are referenced by their first index, but the whole value occupies index and index+1,
+    // so the second of the two holds whatever was there before.
+    // For instance methods, index 0 is always 'this', followed by the parameters.
+    pub local_vars: Vec<Value>,
+    // Holds values rather than slots, so a long or double is a single entry
+    pub op_stack: Vec<Value>,
+}
+
+impl Frame {
+    pub fn new(max_locals: u16, arguments: Vec<Value>) -> Result<Frame, Unwind> {
+        let mut local_vars = vec![Value::Int(0); max_locals as usize];
+        let mut index = 0;
+        for argument in arguments {
+            *local_vars.get_mut(index).ok_or_else(|| bad_code("Arguments don't fit in max_locals"))? = argument;
+            index += match argument.is_category_2() {
+                true => 2,
+                false => 1,
+            };
+        }
+        if index > local_vars.len() {
+            return Err(bad_code("Arguments don't fit in max_locals"));
+        }
+        Ok(Frame { local_vars, op_stack: Vec::new() })
+    }
+
+    pub fn push(&mut self, value: Value) {
+        self.op_stack.push(value);
+    }
+
+    pub fn pop(&mut self) -> Result<Value, Unwind> {
+        self.op_stack.pop().ok_or_else(|| bad_code("Operand stack underflow"))
+    }
+
+    pub fn pop_int(&mut self) -> Result<i32, Unwind> {
+        match self.pop()? {
+            Value::Int(value) => Ok(value),
+            _ => Err(bad_code("Expecting an int on the operand stack")),
+        }
+    }
+
+    pub fn pop_long(&mut self) -> Result<i64, Unwind> {
+        match self.pop()? {
+            Value::Long(value) => Ok(value),
+            _ => Err(bad_code("Expecting a long on the operand stack")),
+        }
+    }
+
+    pub fn pop_float(&mut self) -> Result<f32, Unwind> {
+        match self.pop()? {
+            Value::Float(value) => Ok(value),
+            _ => Err(bad_code("Expecting a float on the operand stack")),
+        }
+    }
+
+    pub fn pop_double(&mut self) -> Result<f64, Unwind> {
+        match self.pop()? {
+            Value::Double(value) => Ok(value),
+            _ => Err(bad_code("Expecting a double on the operand stack")),
+        }
+    }
+
+    pub fn pop_reference(&mut self) -> Result<Option<Reference>, Unwind> {
+        match self.pop()? {
+            Value::Reference(reference) => Ok(reference),
+            _ => Err(bad_code("Expecting a reference on the operand stack")),
+        }
+    }
+
+    // Pops the arguments of a method taking this many values, which come out in order
+    pub fn pop_arguments(&mut self, count: usize) -> Result<Vec<Value>, Unwind> {
+        match self.op_stack.len().checked_sub(count) {
+            Some(start) => Ok(self.op_stack.split_off(start)),
+            None => Err(bad_code("Operand stack underflow")),
+        }
+    }
+
+    pub fn load(&self, index: u16) -> Result<Value, Unwind> {
+        self.local_vars.get(index as usize).copied().ok_or_else(|| bad_code("Local variable index out of range"))
+    }
+
+    pub fn store(&mut self, index: u16, value: Value) -> Result<(), Unwind> {
+        let slots = match value.is_category_2() {
+            true => 2,
+            false => 1,
+        };
+        if index as usize + slots > self.local_vars.len() {
+            return Err(bad_code("Local variable index out of range"));
+        }
+        self.local_vars[index as usize] = value;
+        Ok(())
+    }
+
+    // Removes values taking up this many slots from the top of the stack, like pop and pop2
+    pub fn pop_slots(&mut self, slots: usize) -> Result<(), Unwind> {
+        let count = self.values_in_slots(self.op_stack.len(), slots)?;
+        self.op_stack.truncate(self.op_stack.len() - count);
+        Ok(())
+    }
+
+    // The dup instructions: copies the values in the top `slots` slots and puts the copy under the
+    // values in the `below` slots after those. dup2_x1 is dup(2, 1), for example.
+    pub fn dup(&mut self, slots: usize, below: usize) -> Result<(), Unwind> {
+        let top = self.values_in_slots(self.op_stack.len(), slots)?;
+        let under = self.values_in_slots(self.op_stack.len() - top, below)?;
+        let copy: Vec<Value> = self.op_stack[self.op_stack.len() - top..].to_vec();
+        let at = self.op_stack.len() - top - under;
+        self.op_stack.splice(at..at, copy);
+        Ok(())
+    }
+
+    // How many of the values below end take up exactly this many slots; a category 2 value can't be split
+    fn values_in_slots(&self, end: usize, slots: usize) -> Result<usize, Unwind> {
+        let mut count = 0;
+        let mut taken = 0;
+        while taken < slots {
+            let value = end.checked_sub(count + 1).map(|i| self.op_stack[i])
+                .ok_or_else(|| bad_code("Operand stack underflow"))?;
+            taken += match value.is_category_2() {
+                true => 2,
+                false => 1,
+            };
+            count += 1;
+        }
+        match taken == slots {
+            true => Ok(count),
+            false => Err(bad_code("Category 2 value split by a stack instruction")),
+        }
+    }
+}
+
+pub fn bad_code(message: &str) -> Unwind {
+    Unwind::Raise("java/lang/VerifyError", Some(message.to_string()))
+}
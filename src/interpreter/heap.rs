@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::descriptor::FieldType;
+
+use super::class::Class;
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-2.html#jvms-2.2
+// boolean, byte, char and short values are held as ints, like on the operand stack.
+// Longs and doubles are single values here, though they take up two local variable slots.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    // None is null
+    Reference(Option<Reference>),
+    // Pushed by jsr, the pc to return to
+    ReturnAddress(u32),
+}
+
+// Index of an object in the heap
+pub type Reference = usize;
+
+impl Value {
+    // The value a field or array element of this type starts out with
+    pub fn default_for(field_type: &FieldType) -> Value {
+        match field_type {
+            FieldType::Long => Value::Long(0),
+            FieldType::Float => Value::Float(0.0),
+            FieldType::Double => Value::Double(0.0),
+            FieldType::Object(_) | FieldType::Array(_) => Value::Reference(None),
+            _ => Value::Int(0),
+        }
+    }
+
+    // Category 2 values take up two slots, which matters to instructions like pop2 and dup2
+    pub fn is_category_2(&self) -> bool {
+        matches!(self, Value::Long(_) | Value::Double(_))
+    }
+}
+
+// Objects are never freed: there is no garbage collector
+pub enum Object {
+    // Instance fields are keyed by the class declaring them and their name, since a subclass can
+    // declare a field with the same name as one it inherits
+    Instance {
+        class: Rc<Class>,
+        fields: HashMap<(String, String), Value>,
+    },
+    Array {
+        // Descriptor of the component type, like I or Ljava/lang/String;
+        component: String,
+        values: Vec<Value>,
+    },
+    // The objects of library classes, which don't have fields like those of loaded classes do
+    String(Vec<u16>),
+    StringBuilder(Vec<u16>),
+    // Integer, Character, Boolean and the others wrapping a primitive value
+    Boxed {
+        class: &'static str,
+        value: Value,
+    },
+    // A java.lang.Class, by binary name, or descriptor for array classes
+    Class(String),
+    PrintStream(Stream),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stream {
+    Out,
+    Err,
+}
+
+#[derive(Default)]
+pub struct Heap {
+    objects: Vec<Object>,
+}
+
+impl Object {
+    // The binary name of the object's class, or its descriptor for arrays
+    pub fn class_name(&self) -> String {
+        match self {
+            Object::Instance { class, .. } => class.name.clone(),
+            Object::Array { component, .. } => format!("[{}", component),
+            Object::String(_) => "java/lang/String".to_string(),
+            Object::StringBuilder(_) => "java/lang/StringBuilder".to_string(),
+            Object::Boxed { class, .. } => class.to_string(),
+            Object::Class(_) => "java/lang/Class".to_string(),
+            Object::PrintStream(_) => "java/io/PrintStream".to_string(),
+        }
+    }
+}
+
+impl Heap {
+    pub fn allocate(&mut self, object: Object) -> Reference {
+        self.objects.push(object);
+        self.objects.len() - 1
+    }
+
+    pub fn get(&self, reference: Reference) -> &Object {
+        &self.objects[reference]
+    }
+
+    pub fn get_mut(&mut self, reference: Reference) -> &mut Object {
+        &mut self.objects[reference]
+    }
+}
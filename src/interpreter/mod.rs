@@ -0,0 +1,745 @@
+mod class;
+mod execute;
+mod frame;
+mod heap;
+mod native;
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use crate::class::error::ClassFormatErrorKind;
+use crate::class::format_check::check_format;
+use crate::class::loading::ClassFileLoader;
+use crate::classpath::ClassPath;
+use crate::constant_pool::ConstantPoolInfo;
+use crate::descriptor::FieldType;
+
+use class::{Class, Method};
+use frame::Frame;
+use heap::{Heap, Object, Reference, Stream, Value};
+
+// How deep Java calls may go before a StackOverflowError. Every Java call is a few nested Rust calls,
+// so the thread running the VM needs a stack of at least STACK_SIZE.
+const MAX_DEPTH: usize = 3000;
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+// Like the JVM's MaxJavaStackTraceDepth
+const MAX_STACK_TRACE_DEPTH: usize = 1024;
+
+// An interpreter for the class files on a class path.
+//
+// Only classes from the class path are interpreted. There is no Java class library to run: the parts of
+// java.lang that simple programs use, like String, StringBuilder, System.out and the exceptions, are
+// implemented in Rust (see native.rs), and anything else of the library is a NoClassDefFoundError or
+// UnsatisfiedLinkError when used. There is one thread, no verifier and no garbage collector.
+pub struct Vm {
+    class_path: ClassPath,
+    enable_preview: bool,
+    properties: HashMap<String, String>,
+    classes: HashMap<String, Rc<Class>>,
+    // The classes whose superclasses and interfaces are being loaded
+    loading: HashSet<String>,
+    init_states: HashMap<String, InitState>,
+    // Static field values, by declaring class and field name
+    statics: HashMap<(String, String), Value>,
+    heap: Heap,
+    // String literals, so equal ones are the same object
+    interned: HashMap<Vec<u16>, Reference>,
+    class_objects: HashMap<String, Reference>,
+    // Integer.valueOf and friends hand out the same object for small values
+    boxes: HashMap<(&'static str, i64), Reference>,
+    streams: HashMap<Stream, Reference>,
+    // The Java methods being run, innermost last
+    stack: Vec<StackEntry>,
+    stack_traces: HashMap<Reference, Vec<StackTraceElement>>,
+    // State of Math.random, 0 until first used
+    random: u64,
+}
+
+// A method and the class it was found in
+type ResolvedMethod = (Rc<Class>, Rc<Method>);
+
+// Why the code being run stopped short of returning normally
+enum Unwind {
+    // A Java exception on its way to a handler
+    Throw(Reference),
+    // An exception the VM throws, like a NullPointerException, yet to be created: its class and message
+    Raise(&'static str, Option<String>),
+    // System.exit was called
+    Exit(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InitState {
+    InProgress,
+    Done,
+    // <clinit> threw, so the class can't be used
+    Failed,
+}
+
+struct StackEntry {
+    class: Rc<Class>,
+    method: Rc<Method>,
+    pc: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct StackTraceElement {
+    class_name: String,
+    method_name: String,
+    source_file: Option<String>,
+    line_number: Option<u16>,
+}
+
+// The ways running a main class can fail before its main method starts, with the messages the java
+// launcher prints for them
+#[derive(Debug)]
+pub struct LaunchError {
+    // As given on the command line
+    pub main_class: String,
+    pub kind: LaunchErrorKind,
+}
+
+#[derive(Debug)]
+pub enum LaunchErrorKind {
+    NotFound,
+    // Found, but couldn't be loaded: the exception that caused it, like java.lang.ClassFormatError: ...
+    LoadFailed(String),
+    MainNotFound,
+    MainNotStatic,
+}
+
+impl fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = &self.main_class;
+        match &self.kind {
+            LaunchErrorKind::NotFound =>
+                write!(f, "Could not find or load main class {}\nCaused by: java.lang.ClassNotFoundException: {}", name, name),
+            LaunchErrorKind::LoadFailed(cause) if cause.starts_with("java.lang.NoClassDefFoundError") =>
+                write!(f, "Could not find or load main class {}\nCaused by: {}", name, cause),
+            LaunchErrorKind::LoadFailed(cause) =>
+                write!(f, "LinkageError occurred while loading main class {}\n\t{}", name, cause),
+            LaunchErrorKind::MainNotFound =>
+                write!(f, "Main method not found in class {}, please define the main method as:\n   \
+                           public static void main(String[] args)\nor a JavaFX application class must extend \
+                           javafx.application.Application", name),
+            LaunchErrorKind::MainNotStatic =>
+                write!(f, "Main method is not static in class {}, please define the main method as:\n   \
+                           public static void main(String[] args)", name),
+        }
+    }
+}
+
+impl Error for LaunchError {}
+
+impl fmt::Display for StackTraceElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}(", java_name(&self.class_name), self.method_name)?;
+        match (&self.source_file, self.line_number) {
+            (Some(source_file), Some(line_number)) => write!(f, "{}:{})", source_file, line_number),
+            (Some(source_file), None) => write!(f, "{})", source_file),
+            (None, _) => write!(f, "Unknown Source)"),
+        }
+    }
+}
+
+impl Vm {
+    pub fn new(class_path: ClassPath) -> Vm {
+        let mut properties = HashMap::new();
+        properties.insert("java.class.path".to_string(), class_path.to_string());
+        properties.insert("java.vm.name".to_string(), "jvmmy".to_string());
+        properties.insert("file.separator".to_string(), std::path::MAIN_SEPARATOR.to_string());
+        properties.insert("path.separator".to_string(), crate::classpath::SEPARATOR.to_string());
+        properties.insert("line.separator".to_string(), "\n".to_string());
+        properties.insert("os.name".to_string(), std::env::consts::OS.to_string());
+        properties.insert("os.arch".to_string(), std::env::consts::ARCH.to_string());
+        if let Ok(directory) = std::env::current_dir() {
+            properties.insert("user.dir".to_string(), directory.display().to_string());
+        }
+        if let Ok(home) = std::env::var("HOME") {
+            properties.insert("user.home".to_string(), home);
+        }
+        Vm {
+            class_path,
+            enable_preview: false,
+            properties,
+            classes: HashMap::new(),
+            loading: HashSet::new(),
+            init_states: HashMap::new(),
+            statics: HashMap::new(),
+            heap: Heap::default(),
+            interned: HashMap::new(),
+            class_objects: HashMap::new(),
+            boxes: HashMap::new(),
+            streams: HashMap::new(),
+            stack: Vec::new(),
+            stack_traces: HashMap::new(),
+            random: 0,
+        }
+    }
+
+    // Allows classes that use preview features of the newest class file version
+    pub fn enable_preview(mut self, enable_preview: bool) -> Vm {
+        self.enable_preview = enable_preview;
+        self
+    }
+
+    // A system property, as set with -Dname=value
+    pub fn property(mut self, name: &str, value: &str) -> Vm {
+        self.properties.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    // Runs public static void main(String[] args) of a class, named like com.example.Main, and returns the
+    // exit status: the one given to System.exit, 1 after an uncaught exception and 0 otherwise
+    pub fn run_main(&mut self, main_class: &str, args: &[String]) -> Result<i32, LaunchError> {
+        let launch_error = |kind| LaunchError { main_class: main_class.to_string(), kind };
+        let binary_name = main_class.replace('.', "/");
+        let found = self.classes.contains_key(&binary_name)
+            || native::builtin_class(&binary_name).is_some()
+            || !matches!(self.class_path.find(&binary_name), Ok(None));
+        if !found {
+            return Err(launch_error(LaunchErrorKind::NotFound));
+        }
+        let class = match self.load_class(&binary_name) {
+            Ok(class) => class,
+            Err(Unwind::Raise(exception, message)) => {
+                let cause = match message {
+                    Some(message) => format!("{}: {}", java_name(exception), message),
+                    None => java_name(exception),
+                };
+                return Err(launch_error(LaunchErrorKind::LoadFailed(cause)));
+            }
+            Err(_) => return Err(launch_error(LaunchErrorKind::LoadFailed(binary_name))),
+        };
+        let main = match self.find_main(&class) {
+            Ok(Some(main)) => main,
+            Ok(None) | Err(_) => return Err(launch_error(LaunchErrorKind::MainNotFound)),
+        };
+        if !main.1.access_flags.is_static() {
+            return Err(launch_error(LaunchErrorKind::MainNotStatic));
+        }
+
+        let values = args.iter()
+            .map(|arg| Value::Reference(Some(self.new_string(arg.encode_utf16().collect()))))
+            .collect();
+        let array = self.heap.allocate(Object::Array { component: "Ljava/lang/String;".to_string(), values });
+        let result = self.initialize(&main.0)
+            .and_then(|_| self.invoke_method(&main.0, &main.1, vec![Value::Reference(Some(array))]));
+        let status = match result {
+            Ok(_) => 0,
+            Err(Unwind::Exit(status)) => status,
+            Err(unwind) => match self.throwable(unwind) {
+                Ok(exception) => {
+                    self.print_uncaught(exception);
+                    1
+                }
+                Err(Unwind::Exit(status)) => status,
+                Err(_) => 1,
+            },
+        };
+        let _ = io::stdout().flush();
+        Ok(status)
+    }
+
+    // main can be inherited from a superclass, but has to be public
+    fn find_main(&mut self, class: &Rc<Class>) -> Result<Option<ResolvedMethod>, Unwind> {
+        let mut current = Some(class.clone());
+        while let Some(class) = current {
+            if let Some(method) = class.find_method("main", "([Ljava/lang/String;)V") {
+                return Ok(match method.access_flags.is_public() {
+                    true => Some((class.clone(), method.clone())),
+                    false => None,
+                });
+            }
+            current = self.super_class(&class)?;
+        }
+        Ok(None)
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.3
+    // Loads a class and its superclasses and interfaces, once. Array classes are made up on the spot.
+    fn load_class(&mut self, name: &str) -> Result<Rc<Class>, Unwind> {
+        if let Some(class) = self.classes.get(name) {
+            return Ok(class.clone());
+        }
+        let class = match (name.starts_with('['), native::builtin_class(name)) {
+            (true, _) => Class::builtin(name, Some("java/lang/Object"), &["java/lang/Cloneable", "java/io/Serializable"],
+                                        Default::default()),
+            (false, Some(class)) => class,
+            (false, None) => self.load_from_class_path(name)?,
+        };
+        // A class being loaded again before it is done must be its own superclass or superinterface
+        if !self.loading.insert(name.to_string()) {
+            return Err(Unwind::Raise("java/lang/ClassCircularityError", Some(name.to_string())));
+        }
+        let supers: Vec<String> = class.super_class.iter().chain(&class.interfaces).cloned().collect();
+        let loaded = supers.iter().try_for_each(|super_name| self.load_class(super_name).map(|_| ()));
+        self.loading.remove(name);
+        loaded?;
+        let class = Rc::new(class);
+        self.classes.insert(name.to_string(), class.clone());
+        Ok(class)
+    }
+
+    fn load_from_class_path(&mut self, name: &str) -> Result<Class, Unwind> {
+        let no_class = || Unwind::Raise("java/lang/NoClassDefFoundError", Some(name.to_string()));
+        // The class library isn't there to load, and user classes can't be in its packages
+        if name.starts_with("java/") {
+            return Err(no_class());
+        }
+        let bytes = match self.class_path.find(name) {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => return Err(no_class()),
+            Err(error) => return Err(Unwind::Raise("java/lang/NoClassDefFoundError", Some(format!("{}: {}", name, error)))),
+        };
+        let format_error = |error: crate::class::error::ClassFormatError| match error.kind {
+            ClassFormatErrorKind::UnsupportedVersion { .. } | ClassFormatErrorKind::PreviewNotEnabled { .. } =>
+                Unwind::Raise("java/lang/UnsupportedClassVersionError", Some(format!("{}: {}", name, error.kind))),
+            _ => Unwind::Raise("java/lang/ClassFormatError", Some(format!("{}: {}", name, error))),
+        };
+        let class_file = ClassFileLoader::new(&bytes).enable_preview(self.enable_preview).load().map_err(format_error)?;
+        if let Err(mut errors) = check_format(&class_file) {
+            return Err(format_error(errors.remove(0)));
+        }
+        let class = Class::from_class_file(class_file).map_err(format_error)?;
+        if class.name != name {
+            return Err(Unwind::Raise("java/lang/NoClassDefFoundError",
+                                     Some(format!("{} (wrong name: {})", name, class.name))));
+        }
+        Ok(class)
+    }
+
+    fn super_class(&mut self, class: &Class) -> Result<Option<Rc<Class>>, Unwind> {
+        class.super_class.as_deref().map(|name| self.load_class(name)).transpose()
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.5
+    // Runs the static initializer of a class and its superclasses, the first time one is used
+    fn initialize(&mut self, class: &Rc<Class>) -> Result<(), Unwind> {
+        match self.init_states.get(&class.name) {
+            Some(InitState::InProgress | InitState::Done) => return Ok(()),
+            Some(InitState::Failed) => return Err(Unwind::Raise(
+                "java/lang/NoClassDefFoundError", Some(format!("Could not initialize class {}", java_name(&class.name))))),
+            None => {}
+        }
+        self.init_states.insert(class.name.clone(), InitState::InProgress);
+        let result = self.run_initializer(class);
+        let state = match result {
+            Ok(()) => InitState::Done,
+            Err(_) => InitState::Failed,
+        };
+        self.init_states.insert(class.name.clone(), state);
+        match result {
+            Err(Unwind::Exit(status)) => Err(Unwind::Exit(status)),
+            Err(unwind) => {
+                let exception = self.throwable(unwind)?;
+                let exception_class = self.heap.get(exception).class_name();
+                match self.is_assignable(&exception_class, "java/lang/Error")? {
+                    true => Err(Unwind::Throw(exception)),
+                    false => {
+                        let error = self.new_throwable("java/lang/ExceptionInInitializerError", None)?;
+                        self.set_field(error, "java/lang/Throwable", "cause", Value::Reference(Some(exception)));
+                        Err(Unwind::Throw(error))
+                    }
+                }
+            }
+            Ok(()) => Ok(()),
+        }
+    }
+
+    fn run_initializer(&mut self, class: &Rc<Class>) -> Result<(), Unwind> {
+        if class.is_builtin {
+            return Ok(());
+        }
+        if !class.is_interface() {
+            if let Some(super_class) = self.super_class(class)? {
+                self.initialize(&super_class)?;
+            }
+        }
+        for field in class.fields.iter().filter(|field| field.access_flags.is_static()) {
+            let value = match field.constant_value {
+                Some(index) => self.load_constant(class, index)?,
+                None => Value::default_for(&field.descriptor),
+            };
+            self.statics.insert((class.name.clone(), field.name.clone()), value);
+        }
+        match class.find_method("<clinit>", "()V") {
+            Some(clinit) => self.invoke_method(class, &clinit.clone(), Vec::new()).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5.ldc
+    fn load_constant(&mut self, class: &Rc<Class>, index: u16) -> Result<Value, Unwind> {
+        let value = match class.constant_pool.get(index).map_err(bad_constant)? {
+            ConstantPoolInfo::Integer { bytes } => Value::Int(*bytes as i32),
+            ConstantPoolInfo::Float { bytes } => Value::Float(f32::from_bits(*bytes)),
+            ConstantPoolInfo::Long { high_bytes, low_bytes } => Value::Long(((*high_bytes as u64) << 32 | *low_bytes as u64) as i64),
+            ConstantPoolInfo::Double { high_bytes, low_bytes } =>
+                Value::Double(f64::from_bits((*high_bytes as u64) << 32 | *low_bytes as u64)),
+            ConstantPoolInfo::String { string_index } => match class.constant_pool.get(*string_index).map_err(bad_constant)? {
+                ConstantPoolInfo::Utf8 { string } => Value::Reference(Some(self.intern(string.to_utf16()))),
+                _ => return Err(bad_constant(ClassFormatErrorKind::WrongConstantKind { index: *string_index, expected: "Utf8" })),
+            },
+            ConstantPoolInfo::Class { .. } => {
+                let name = class.constant_pool.class_name(index).map_err(bad_constant)?.to_string();
+                Value::Reference(Some(self.class_object(&name)))
+            }
+            _ => return Err(Unwind::Raise("java/lang/InternalError",
+                                          Some(format!("jvmmy can't load constant #{} of {}", index, java_name(&class.name))))),
+        };
+        Ok(value)
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5.invokevirtual
+    // Finds the method a call ends up in, starting at a class and going up its superclasses, and calls it.
+    // Library classes are asked whether they implement it, and default methods of interfaces come last.
+    fn invoke_from(&mut self, class: &Rc<Class>, name: &str, descriptor: &str, arguments: Vec<Value>)
+                   -> Result<Option<Value>, Unwind> {
+        let mut current = Some(class.clone());
+        let mut reached_builtin = false;
+        while let Some(class) = current {
+            match class.is_builtin {
+                true => {
+                    if let Some(result) = self.call_builtin(&class.name, name, descriptor, &arguments) {
+                        return result;
+                    }
+                    reached_builtin = true;
+                }
+                false => if let Some(method) = class.find_method(name, descriptor) {
+                    if !method.access_flags.is_abstract() {
+                        return self.invoke_method(&class, &method.clone(), arguments);
+                    }
+                    break;
+                },
+            }
+            current = self.super_class(&class)?;
+        }
+        match self.find_default_method(class, name, descriptor)? {
+            Some((class, method)) => self.invoke_method(&class, &method, arguments),
+            // A library method the VM doesn't have rather than one that doesn't exist
+            None if reached_builtin => Err(Unwind::Raise("java/lang/UnsatisfiedLinkError", Some(format!(
+                "jvmmy doesn't implement {}.{}{}", java_name(&class.name), name, descriptor)))),
+            None => {
+                let exception = match class.find_method(name, descriptor).is_some() || class.is_interface() {
+                    true => "java/lang/AbstractMethodError",
+                    false => "java/lang/NoSuchMethodError",
+                };
+                Err(Unwind::Raise(exception, Some(format!("{}.{}{}", java_name(&class.name), name, descriptor))))
+            }
+        }
+    }
+
+    // A non-abstract instance method of one of the interfaces of a class or its superclasses
+    fn find_default_method(&mut self, class: &Rc<Class>, name: &str, descriptor: &str)
+                           -> Result<Option<ResolvedMethod>, Unwind> {
+        let mut pending = Vec::new();
+        let mut current = Some(class.clone());
+        while let Some(class) = current {
+            pending.extend(class.interfaces.iter().cloned());
+            current = self.super_class(&class)?;
+        }
+        let mut seen = HashSet::new();
+        let mut index = 0;
+        while index < pending.len() {
+            let interface = self.load_class(&pending[index].clone())?;
+            index += 1;
+            if !seen.insert(interface.name.clone()) {
+                continue;
+            }
+            if let Some(method) = interface.find_method(name, descriptor) {
+                if !method.access_flags.is_abstract() && !method.access_flags.is_static() {
+                    return Ok(Some((interface.clone(), method.clone())));
+                }
+            }
+            pending.extend(interface.interfaces.iter().cloned());
+        }
+        Ok(None)
+    }
+
+    // Calls a method on an object, picking the method by the object's class
+    fn invoke_virtual(&mut self, receiver: Reference, name: &str, descriptor: &str, arguments: &[Value])
+                      -> Result<Option<Value>, Unwind> {
+        let class = self.load_class(&self.heap.get(receiver).class_name())?;
+        let mut values = vec![Value::Reference(Some(receiver))];
+        values.extend_from_slice(arguments);
+        self.invoke_from(&class, name, descriptor, values)
+    }
+
+    fn invoke_method(&mut self, class: &Rc<Class>, method: &Rc<Method>, arguments: Vec<Value>) -> Result<Option<Value>, Unwind> {
+        let Some(code) = &method.code else {
+            let exception = match method.access_flags.is_native() {
+                true => "java/lang/UnsatisfiedLinkError",
+                false => "java/lang/AbstractMethodError",
+            };
+            return Err(Unwind::Raise(exception, Some(format!("{}.{}{}", java_name(&class.name), method.name, method.descriptor))));
+        };
+        if self.stack.len() >= MAX_DEPTH {
+            return Err(Unwind::Raise("java/lang/StackOverflowError", None));
+        }
+        let frame = Frame::new(code.max_locals, arguments)?;
+        self.stack.push(StackEntry { class: class.clone(), method: method.clone(), pc: 0 });
+        let result = self.execute(class, code, frame);
+        self.stack.pop();
+        result
+    }
+
+    // Turns a pending exception into the object for it
+    fn throwable(&mut self, unwind: Unwind) -> Result<Reference, Unwind> {
+        match unwind {
+            Unwind::Throw(exception) => Ok(exception),
+            Unwind::Raise(class, message) => self.new_throwable(class, message),
+            Unwind::Exit(status) => Err(Unwind::Exit(status)),
+        }
+    }
+
+    // An exception thrown by the VM itself, from wherever the current method is
+    fn new_throwable(&mut self, class_name: &str, message: Option<String>) -> Result<Reference, Unwind> {
+        let class = self.load_class(class_name)?;
+        let exception = self.new_instance(&class)?;
+        let message = message.map(|message| Value::Reference(Some(self.new_string(message.encode_utf16().collect()))));
+        self.set_field(exception, "java/lang/Throwable", "detailMessage", message.unwrap_or(Value::Reference(None)));
+        let trace = self.stack_trace(0);
+        self.stack_traces.insert(exception, trace);
+        Ok(exception)
+    }
+
+    // The methods being run, innermost first, leaving out the innermost `skip` of them
+    fn stack_trace(&self, skip: usize) -> Vec<StackTraceElement> {
+        self.stack.iter().rev().skip(skip).take(MAX_STACK_TRACE_DEPTH).map(|entry| StackTraceElement {
+            class_name: entry.class.name.clone(),
+            method_name: entry.method.name.clone(),
+            source_file: entry.class.source_file.clone(),
+            line_number: entry.method.code.as_ref().and_then(|code| code.line_number(entry.pc)),
+        }).collect()
+    }
+
+    fn new_instance(&mut self, class: &Rc<Class>) -> Result<Reference, Unwind> {
+        if let Some(object) = native::new_builtin(&class.name) {
+            return Ok(self.heap.allocate(object));
+        }
+        let mut fields = HashMap::new();
+        let mut current = Some(class.clone());
+        while let Some(class) = current {
+            for field in class.fields.iter().filter(|field| !field.access_flags.is_static()) {
+                fields.insert((class.name.clone(), field.name.clone()), Value::default_for(&field.descriptor));
+            }
+            current = self.super_class(&class)?;
+        }
+        Ok(self.heap.allocate(Object::Instance { class: class.clone(), fields }))
+    }
+
+    fn get_field(&self, object: Reference, class_name: &str, name: &str) -> Option<Value> {
+        match self.heap.get(object) {
+            Object::Instance { fields, .. } => fields.get(&(class_name.to_string(), name.to_string())).copied(),
+            _ => None,
+        }
+    }
+
+    fn set_field(&mut self, object: Reference, class_name: &str, name: &str, value: Value) {
+        if let Object::Instance { fields, .. } = self.heap.get_mut(object) {
+            fields.insert((class_name.to_string(), name.to_string()), value);
+        }
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.3.2
+    // The class declaring a field: the class itself, one of its interfaces or one of its superclasses
+    fn resolve_field(&mut self, class: &Rc<Class>, name: &str, descriptor: &str) -> Result<Option<Rc<Class>>, Unwind> {
+        if class.find_field(name, descriptor).is_some() {
+            return Ok(Some(class.clone()));
+        }
+        for interface in class.interfaces.clone() {
+            let interface = self.load_class(&interface)?;
+            if let Some(found) = self.resolve_field(&interface, name, descriptor)? {
+                return Ok(Some(found));
+            }
+        }
+        match self.super_class(class)? {
+            Some(super_class) => self.resolve_field(&super_class, name, descriptor),
+            None => Ok(None),
+        }
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5.checkcast
+    // Whether a value of one class (or array descriptor) can be used as one of the other
+    fn is_assignable(&mut self, from: &str, to: &str) -> Result<bool, Unwind> {
+        if from == to || to == "java/lang/Object" {
+            return Ok(true);
+        }
+        if let Some(from_component) = from.strip_prefix('[') {
+            return match to.strip_prefix('[') {
+                Some(to_component) => match (class_of_component(from_component), class_of_component(to_component)) {
+                    (Some(from), Some(to)) => self.is_assignable(from, to),
+                    _ => Ok(false),
+                },
+                None => Ok(to == "java/lang/Cloneable" || to == "java/io/Serializable"),
+            };
+        }
+        if to.starts_with('[') {
+            return Ok(false);
+        }
+        let class = self.load_class(from)?;
+        let supers: Vec<String> = class.super_class.iter().chain(&class.interfaces).cloned().collect();
+        for super_name in supers {
+            if self.is_assignable(&super_name, to)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn is_instance_of(&mut self, object: Reference, class_name: &str) -> Result<bool, Unwind> {
+        let object_class = self.heap.get(object).class_name();
+        self.is_assignable(&object_class, class_name)
+    }
+
+    fn new_string(&mut self, chars: Vec<u16>) -> Reference {
+        self.heap.allocate(Object::String(chars))
+    }
+
+    fn intern(&mut self, chars: Vec<u16>) -> Reference {
+        if let Some(&string) = self.interned.get(&chars) {
+            return string;
+        }
+        let string = self.new_string(chars.clone());
+        self.interned.insert(chars, string);
+        string
+    }
+
+    // The one Class object for a class
+    fn class_object(&mut self, name: &str) -> Reference {
+        if let Some(&object) = self.class_objects.get(name) {
+            return object;
+        }
+        let object = self.heap.allocate(Object::Class(name.to_string()));
+        self.class_objects.insert(name.to_string(), object);
+        object
+    }
+
+    // The chars of a String object
+    fn chars(&self, string: Reference) -> Result<Vec<u16>, Unwind> {
+        match self.heap.get(string) {
+            Object::String(chars) => Ok(chars.clone()),
+            object => Err(class_cast(&object.class_name(), "java/lang/String")),
+        }
+    }
+
+    // String.valueOf(Object): "null", or what toString returns
+    fn string_value_of(&mut self, object: Option<Reference>) -> Result<Vec<u16>, Unwind> {
+        let Some(object) = object else {
+            return Ok("null".encode_utf16().collect());
+        };
+        if let Object::String(chars) = self.heap.get(object) {
+            return Ok(chars.clone());
+        }
+        match self.invoke_virtual(object, "toString", "()Ljava/lang/String;", &[])? {
+            Some(Value::Reference(string)) => self.string_value_of(string),
+            _ => Err(frame::bad_code("toString didn't return a String")),
+        }
+    }
+
+    // What an uncaught exception prints: the exception, where it was thrown from and what caused it
+    fn print_uncaught(&mut self, exception: Reference) {
+        let _ = io::stdout().flush();
+        eprint!("Exception in thread \"main\" ");
+        self.print_stack_trace(exception, Stream::Err);
+    }
+
+    // Throwable.printStackTrace, with "... n more" for the frames a cause has in common with what it caused
+    fn print_stack_trace(&mut self, exception: Reference, stream: Stream) {
+        let mut output = String::new();
+        let mut enclosing: Vec<StackTraceElement> = Vec::new();
+        let mut seen = HashSet::new();
+        let mut current = Some(exception);
+        while let Some(exception) = current {
+            let description = self.describe(exception);
+            if !seen.insert(exception) {
+                output.push_str(&format!("Caused by: [CIRCULAR REFERENCE: {}]\n", description));
+                break;
+            }
+            if seen.len() > 1 {
+                output.push_str("Caused by: ");
+            }
+            output.push_str(&description);
+            output.push('\n');
+            let trace = self.stack_traces.get(&exception).cloned().unwrap_or_default();
+            let in_common = trace.iter().rev().zip(enclosing.iter().rev()).take_while(|(a, b)| a == b).count();
+            for element in &trace[..trace.len() - in_common] {
+                output.push_str(&format!("\tat {}\n", element));
+            }
+            if in_common != 0 {
+                output.push_str(&format!("\t... {} more\n", in_common));
+            }
+            enclosing = trace;
+            current = match self.get_field(exception, "java/lang/Throwable", "cause") {
+                Some(Value::Reference(Some(cause))) if cause != exception => Some(cause),
+                _ => None,
+            };
+        }
+        self.write(stream, &output);
+    }
+
+    // toString of an exception, falling back to its class name if that throws
+    fn describe(&mut self, exception: Reference) -> String {
+        match self.string_value_of(Some(exception)) {
+            Ok(chars) => to_rust_string(&chars),
+            Err(_) => java_name(&self.heap.get(exception).class_name()),
+        }
+    }
+
+    fn write(&mut self, stream: Stream, text: &str) {
+        let _ = match stream {
+            Stream::Out => io::stdout().write_all(text.as_bytes()),
+            Stream::Err => io::stderr().write_all(text.as_bytes()),
+        };
+    }
+}
+
+// The class an array component descriptor stands for, or None for primitive types
+fn class_of_component(descriptor: &str) -> Option<&str> {
+    match descriptor.as_bytes().first() {
+        Some(b'L') => descriptor.strip_prefix('L').and_then(|name| name.strip_suffix(';')),
+        Some(b'[') => Some(descriptor),
+        _ => None,
+    }
+}
+
+// A binary name the way Java source and the JVM's messages write it, like java.lang.Object
+fn java_name(binary_name: &str) -> String {
+    binary_name.replace('/', ".")
+}
+
+// Java's chars as Rust text, with a ? for unpaired surrogates, which is what Java prints for them
+fn to_rust_string(chars: &[u16]) -> String {
+    char::decode_utf16(chars.iter().copied()).map(|c| c.unwrap_or('?')).collect()
+}
+
+// The ClassCastException for a failed cast, which like the JVM's says where both classes come from: the
+// class library is java.base, everything else the class path
+fn class_cast(from: &str, to: &str) -> Unwind {
+    let location = |name: &str| {
+        let element = name.trim_start_matches('[');
+        match element.len() == 1 || element.trim_start_matches('L').starts_with("java/") {
+            true => "module java.base of loader 'bootstrap'",
+            false => "unnamed module of loader 'app'",
+        }
+    };
+    let (from_name, to_name) = (java_name(from), java_name(to));
+    let locations = match location(from) == location(to) {
+        true => format!("{} and {} are in {}", from_name, to_name, location(from)),
+        false => format!("{} is in {}; {} is in {}", from_name, location(from), to_name, location(to)),
+    };
+    Unwind::Raise("java/lang/ClassCastException", Some(format!(
+        "class {} cannot be cast to class {} ({})", from_name, to_name, locations)))
+}
+
+fn bad_constant(kind: ClassFormatErrorKind) -> Unwind {
+    Unwind::Raise("java/lang/ClassFormatError", Some(kind.to_string()))
+}
+
+fn field_type(descriptor: &str) -> Result<FieldType, Unwind> {
+    FieldType::parse(descriptor).map_err(|error| Unwind::Raise("java/lang/ClassFormatError", Some(error.to_string())))
+}
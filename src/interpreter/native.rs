@@ -0,0 +1,1612 @@
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::class::ClassAccessFlags;
+use crate::constant_pool::ConstantPoolInfo;
+use crate::descriptor::{FieldType, MethodDescriptor};
+use crate::util::number::{java_double, java_float};
+
+use super::{bad_constant, java_name, to_rust_string, Unwind, Vm};
+use super::class::Class;
+use super::frame::{bad_code, Frame};
+use super::heap::{Object, Reference, Stream, Value};
+
+// The classes of the Java class library the VM knows: their superclass, interfaces and access flags.
+// Their methods are the ones native() has, which only go as far as simple programs need.
+const BUILTIN_CLASSES: &[(&str, Option<&str>, &[&str], u16)] = &[
+    ("java/lang/Object", None, &[], PUBLIC),
+    ("java/lang/CharSequence", Some("java/lang/Object"), &[], INTERFACE),
+    ("java/lang/Comparable", Some("java/lang/Object"), &[], INTERFACE),
+    ("java/lang/Cloneable", Some("java/lang/Object"), &[], INTERFACE),
+    ("java/lang/Runnable", Some("java/lang/Object"), &[], INTERFACE),
+    ("java/lang/AutoCloseable", Some("java/lang/Object"), &[], INTERFACE),
+    ("java/lang/Iterable", Some("java/lang/Object"), &[], INTERFACE),
+    ("java/io/Serializable", Some("java/lang/Object"), &[], INTERFACE),
+    ("java/lang/String", Some("java/lang/Object"),
+     &["java/io/Serializable", "java/lang/Comparable", "java/lang/CharSequence"], FINAL),
+    ("java/lang/StringBuilder", Some("java/lang/Object"), &["java/io/Serializable", "java/lang/CharSequence"], FINAL),
+    ("java/lang/System", Some("java/lang/Object"), &[], FINAL),
+    ("java/lang/Math", Some("java/lang/Object"), &[], FINAL),
+    ("java/util/Objects", Some("java/lang/Object"), &[], FINAL),
+    ("java/lang/Thread", Some("java/lang/Object"), &["java/lang/Runnable"], PUBLIC),
+    ("java/lang/Class", Some("java/lang/Object"), &["java/io/Serializable"], FINAL),
+    ("java/io/PrintStream", Some("java/lang/Object"), &["java/lang/AutoCloseable"], PUBLIC),
+    ("java/lang/Enum", Some("java/lang/Object"), &["java/lang/Comparable", "java/io/Serializable"], ABSTRACT),
+    ("java/lang/Record", Some("java/lang/Object"), &[], ABSTRACT),
+    ("java/lang/Number", Some("java/lang/Object"), &["java/io/Serializable"], ABSTRACT),
+    ("java/lang/Integer", Some("java/lang/Number"), &["java/lang/Comparable"], FINAL),
+    ("java/lang/Long", Some("java/lang/Number"), &["java/lang/Comparable"], FINAL),
+    ("java/lang/Short", Some("java/lang/Number"), &["java/lang/Comparable"], FINAL),
+    ("java/lang/Byte", Some("java/lang/Number"), &["java/lang/Comparable"], FINAL),
+    ("java/lang/Double", Some("java/lang/Number"), &["java/lang/Comparable"], FINAL),
+    ("java/lang/Float", Some("java/lang/Number"), &["java/lang/Comparable"], FINAL),
+    ("java/lang/Boolean", Some("java/lang/Object"), &["java/io/Serializable", "java/lang/Comparable"], FINAL),
+    ("java/lang/Character", Some("java/lang/Object"), &["java/io/Serializable", "java/lang/Comparable"], FINAL),
+    ("java/lang/Throwable", Some("java/lang/Object"), &["java/io/Serializable"], PUBLIC),
+    ("java/lang/Exception", Some("java/lang/Throwable"), &[], PUBLIC),
+    ("java/lang/RuntimeException", Some("java/lang/Exception"), &[], PUBLIC),
+    ("java/lang/ArithmeticException", Some("java/lang/RuntimeException"), &[], PUBLIC),
+    ("java/lang/ArrayStoreException", Some("java/lang/RuntimeException"), &[], PUBLIC),
+    ("java/lang/ClassCastException", Some("java/lang/RuntimeException"), &[], PUBLIC),
+    ("java/lang/IllegalArgumentException", Some("java/lang/RuntimeException"), &[], PUBLIC),
+    ("java/lang/NumberFormatException", Some("java/lang/IllegalArgumentException"), &[], PUBLIC),
+    ("java/lang/IllegalStateException", Some("java/lang/RuntimeException"), &[], PUBLIC),
+    ("java/lang/IndexOutOfBoundsException", Some("java/lang/RuntimeException"), &[], PUBLIC),
+    ("java/lang/ArrayIndexOutOfBoundsException", Some("java/lang/IndexOutOfBoundsException"), &[], PUBLIC),
+    ("java/lang/StringIndexOutOfBoundsException", Some("java/lang/IndexOutOfBoundsException"), &[], PUBLIC),
+    ("java/lang/NegativeArraySizeException", Some("java/lang/RuntimeException"), &[], PUBLIC),
+    ("java/lang/NullPointerException", Some("java/lang/RuntimeException"), &[], PUBLIC),
+    ("java/lang/UnsupportedOperationException", Some("java/lang/RuntimeException"), &[], PUBLIC),
+    ("java/lang/CloneNotSupportedException", Some("java/lang/Exception"), &[], PUBLIC),
+    ("java/lang/InterruptedException", Some("java/lang/Exception"), &[], PUBLIC),
+    ("java/lang/ReflectiveOperationException", Some("java/lang/Exception"), &[], PUBLIC),
+    ("java/lang/ClassNotFoundException", Some("java/lang/ReflectiveOperationException"), &[], PUBLIC),
+    ("java/io/IOException", Some("java/lang/Exception"), &[], PUBLIC),
+    ("java/io/UncheckedIOException", Some("java/lang/RuntimeException"), &[], PUBLIC),
+    ("java/lang/Error", Some("java/lang/Throwable"), &[], PUBLIC),
+    ("java/lang/AssertionError", Some("java/lang/Error"), &[], PUBLIC),
+    ("java/lang/VirtualMachineError", Some("java/lang/Error"), &[], ABSTRACT),
+    ("java/lang/StackOverflowError", Some("java/lang/VirtualMachineError"), &[], PUBLIC),
+    ("java/lang/OutOfMemoryError", Some("java/lang/VirtualMachineError"), &[], PUBLIC),
+    ("java/lang/InternalError", Some("java/lang/VirtualMachineError"), &[], PUBLIC),
+    ("java/lang/LinkageError", Some("java/lang/Error"), &[], PUBLIC),
+    ("java/lang/NoClassDefFoundError", Some("java/lang/LinkageError"), &[], PUBLIC),
+    ("java/lang/ClassCircularityError", Some("java/lang/LinkageError"), &[], PUBLIC),
+    ("java/lang/ClassFormatError", Some("java/lang/LinkageError"), &[], PUBLIC),
+    ("java/lang/UnsupportedClassVersionError", Some("java/lang/ClassFormatError"), &[], PUBLIC),
+    ("java/lang/VerifyError", Some("java/lang/LinkageError"), &[], PUBLIC),
+    ("java/lang/UnsatisfiedLinkError", Some("java/lang/LinkageError"), &[], PUBLIC),
+    ("java/lang/BootstrapMethodError", Some("java/lang/LinkageError"), &[], PUBLIC),
+    ("java/lang/ExceptionInInitializerError", Some("java/lang/LinkageError"), &[], PUBLIC),
+    ("java/lang/IncompatibleClassChangeError", Some("java/lang/LinkageError"), &[], PUBLIC),
+    ("java/lang/AbstractMethodError", Some("java/lang/IncompatibleClassChangeError"), &[], PUBLIC),
+    ("java/lang/IllegalAccessError", Some("java/lang/IncompatibleClassChangeError"), &[], PUBLIC),
+    ("java/lang/InstantiationError", Some("java/lang/IncompatibleClassChangeError"), &[], PUBLIC),
+    ("java/lang/NoSuchFieldError", Some("java/lang/IncompatibleClassChangeError"), &[], PUBLIC),
+    ("java/lang/NoSuchMethodError", Some("java/lang/IncompatibleClassChangeError"), &[], PUBLIC),
+];
+
+const PUBLIC: u16 = 0x0001;
+const FINAL: u16 = 0x0011;
+const ABSTRACT: u16 = 0x0401;
+const INTERFACE: u16 = 0x0601;
+
+// A library method, called with the method descriptor and the arguments, `this` first
+type Native = fn(&mut Vm, &str, &[Value]) -> Result<Option<Value>, Unwind>;
+
+pub fn builtin_class(name: &str) -> Option<Class> {
+    BUILTIN_CLASSES.iter().find(|(class_name, ..)| *class_name == name).map(|(name, super_class, interfaces, flags)|
+        Class::builtin(name, *super_class, interfaces, ClassAccessFlags(*flags)))
+}
+
+// The object `new` makes for a library class that isn't made of fields
+pub fn new_builtin(class_name: &str) -> Option<Object> {
+    match class_name {
+        "java/lang/String" => Some(Object::String(Vec::new())),
+        "java/lang/StringBuilder" => Some(Object::StringBuilder(Vec::new())),
+        _ => None,
+    }
+}
+
+impl Vm {
+    // Runs a library method, if it is one the VM has. None sends the lookup on to the superclass.
+    pub(super) fn call_builtin(&mut self, class_name: &str, name: &str, descriptor: &str, arguments: &[Value])
+                               -> Option<Result<Option<Value>, Unwind>> {
+        native(class_name, name, descriptor).map(|native| native(self, descriptor, arguments))
+    }
+
+    // The static fields of library classes that aren't constants javac copies into the class using them
+    pub(super) fn builtin_static(&mut self, class_name: &str, name: &str) -> Result<Value, Unwind> {
+        let object = match (class_name, name) {
+            ("java/lang/System", "out") => self.stream(Stream::Out),
+            ("java/lang/System", "err") => self.stream(Stream::Err),
+            ("java/lang/Boolean", "TRUE") => self.boxed("java/lang/Boolean", Value::Int(1)),
+            ("java/lang/Boolean", "FALSE") => self.boxed("java/lang/Boolean", Value::Int(0)),
+            _ => return Err(Unwind::Raise("java/lang/NoSuchFieldError", Some(name.to_string()))),
+        };
+        Ok(Value::Reference(Some(object)))
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5.invokedynamic
+    // Only the call sites javac makes for string concatenation and for the methods of records are supported
+    pub(super) fn invoke_dynamic(&mut self, class: &Rc<Class>, index: u16, frame: &mut Frame) -> Result<(), Unwind> {
+        let constant_pool = &class.constant_pool;
+        let ConstantPoolInfo::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } =
+            constant_pool.get(index).map_err(bad_constant)? else {
+            return Err(bad_code("invokedynamic of a constant that isn't an InvokeDynamic"));
+        };
+        let (name, descriptor) = constant_pool.name_and_type(*name_and_type_index).map_err(bad_constant)?;
+        let descriptor = MethodDescriptor::parse(descriptor)
+            .map_err(|error| Unwind::Raise("java/lang/ClassFormatError", Some(error.to_string())))?;
+        let bootstrap_method = class.bootstrap_methods.get(*bootstrap_method_attr_index as usize)
+            .ok_or_else(|| Unwind::Raise("java/lang/ClassFormatError", Some("Missing BootstrapMethods attribute".to_string())))?;
+        let ConstantPoolInfo::MethodHandle { reference_index, .. } =
+            constant_pool.get(bootstrap_method.bootstrap_method_ref).map_err(bad_constant)? else {
+            return Err(bad_code("Bootstrap method that isn't a MethodHandle"));
+        };
+        let (bootstrap_class, bootstrap_name, _) = constant_pool.member_ref(*reference_index).map_err(bad_constant)?;
+
+        let arguments = frame.pop_arguments(descriptor.parameters.len())?;
+        let bootstrap_arguments = &bootstrap_method.bootstrap_arguments;
+        let result = match (bootstrap_class, bootstrap_name) {
+            ("java/lang/invoke/StringConcatFactory", "makeConcatWithConstants") => {
+                let (&recipe, constants) = bootstrap_arguments.split_first()
+                    .ok_or_else(|| Unwind::Raise("java/lang/BootstrapMethodError", Some("Missing recipe".to_string())))?;
+                let recipe = match self.load_constant(class, recipe)? {
+                    Value::Reference(Some(recipe)) => self.chars(recipe)?,
+                    _ => return Err(Unwind::Raise("java/lang/BootstrapMethodError", Some("Bad recipe".to_string()))),
+                };
+                self.concat(class, &recipe, constants, arguments, &descriptor.parameters)?
+            }
+            ("java/lang/invoke/StringConcatFactory", "makeConcat") =>
+                self.concat(class, &vec![1; arguments.len()], &[], arguments, &descriptor.parameters)?,
+            ("java/lang/runtime/ObjectMethods", "bootstrap") => self.record_method(class, name, bootstrap_arguments, &arguments)?,
+            _ => return Err(Unwind::Raise("java/lang/BootstrapMethodError", Some(format!(
+                "jvmmy doesn't support the bootstrap method {}.{}", java_name(bootstrap_class), bootstrap_name)))),
+        };
+        frame.push(result);
+        Ok(())
+    }
+
+    // StringConcatFactory: the recipe has \1 where the next argument goes and \2 for the next constant
+    fn concat(&mut self, class: &Rc<Class>, recipe: &[u16], constants: &[u16], arguments: Vec<Value>, parameters: &[FieldType])
+              -> Result<Value, Unwind> {
+        let mut arguments = arguments.into_iter().zip(parameters);
+        let mut constants = constants.iter();
+        let mut result = Vec::new();
+        for &c in recipe {
+            match c {
+                1 => {
+                    let (argument, parameter) = arguments.next().ok_or_else(|| bad_code("Too few arguments for the recipe"))?;
+                    result.extend(self.string_of(argument, parameter)?);
+                }
+                2 => {
+                    let &constant = constants.next().ok_or_else(|| bad_code("Too few constants for the recipe"))?;
+                    let chars = match self.load_constant(class, constant)? {
+                        Value::Reference(reference) => self.string_value_of(reference)?,
+                        value => self.string_of(value, &primitive_type(value))?,
+                    };
+                    result.extend(chars);
+                }
+                c => result.push(c),
+            }
+        }
+        Ok(Value::Reference(Some(self.new_string(result))))
+    }
+
+    // ObjectMethods.bootstrap: toString, hashCode or equals of a record, made from its components. The
+    // bootstrap arguments are the record class, the component names separated by ; and a getter for each.
+    fn record_method(&mut self, class: &Rc<Class>, name: &str, bootstrap_arguments: &[u16], arguments: &[Value])
+                     -> Result<Value, Unwind> {
+        let constant_pool = &class.constant_pool;
+        let mut components = Vec::new();
+        for &getter in bootstrap_arguments.iter().skip(2) {
+            let ConstantPoolInfo::MethodHandle { reference_index, .. } = constant_pool.get(getter).map_err(bad_constant)? else {
+                return Err(Unwind::Raise("java/lang/BootstrapMethodError", Some("Bad record component getter".to_string())));
+            };
+            let (field_class, field_name, descriptor) = constant_pool.member_ref(*reference_index).map_err(bad_constant)?;
+            let field_type = FieldType::parse(descriptor)
+                .map_err(|error| Unwind::Raise("java/lang/ClassFormatError", Some(error.to_string())))?;
+            components.push((field_class.to_string(), field_name.to_string(), field_type));
+        }
+        let record = this(arguments)?;
+        let component_values = |vm: &Vm, object: Reference| -> Vec<Value> {
+            components.iter().map(|(class_name, name, field_type)|
+                vm.get_field(object, class_name, name).unwrap_or(Value::default_for(field_type))).collect()
+        };
+        let values = component_values(self, record);
+        match name {
+            "toString" => {
+                let class_name = self.heap.get(record).class_name();
+                let mut text = utf16(class_name.rsplit(['/', '$']).next().unwrap_or_default());
+                text.push(b'[' as u16);
+                for (index, ((_, name, field_type), value)) in components.iter().zip(values).enumerate() {
+                    if index > 0 {
+                        text.extend(utf16(", "));
+                    }
+                    text.extend(utf16(&format!("{}=", name)));
+                    text.extend(self.string_of(value, field_type)?);
+                }
+                text.push(b']' as u16);
+                Ok(Value::Reference(Some(self.new_string(text))))
+            }
+            "hashCode" => {
+                let mut hash = 0i32;
+                for ((_, _, field_type), value) in components.iter().zip(values) {
+                    let component_hash = match value {
+                        Value::Reference(reference) => self.hash_code(reference)?,
+                        value => primitive_hash(value, field_type),
+                    };
+                    hash = hash.wrapping_mul(31).wrapping_add(component_hash);
+                }
+                Ok(Value::Int(hash))
+            }
+            "equals" => {
+                let other = match reference(arguments, 1)? {
+                    Some(other) if self.heap.get(other).class_name() == self.heap.get(record).class_name() => other,
+                    _ => return Ok(Value::Int(0)),
+                };
+                let other_values = component_values(self, other);
+                for (value, other_value) in values.into_iter().zip(other_values) {
+                    let equal = match (value, other_value) {
+                        (Value::Reference(value), Value::Reference(other_value)) => self.equals(value, other_value)?,
+                        (value, other_value) => java_compare(value, other_value) == 0,
+                    };
+                    if !equal {
+                        return Ok(Value::Int(0));
+                    }
+                }
+                Ok(Value::Int(1))
+            }
+            _ => Err(Unwind::Raise("java/lang/BootstrapMethodError", Some(format!("No record method {}", name)))),
+        }
+    }
+
+    // String.valueOf for a value of the given type
+    fn string_of(&mut self, value: Value, field_type: &FieldType) -> Result<Vec<u16>, Unwind> {
+        let text = match (field_type, value) {
+            (FieldType::Boolean, Value::Int(value)) => (value != 0).to_string(),
+            (FieldType::Char, Value::Int(value)) => return Ok(vec![value as u16]),
+            (_, Value::Int(value)) => value.to_string(),
+            (_, Value::Long(value)) => value.to_string(),
+            (_, Value::Float(value)) => java_float(value),
+            (_, Value::Double(value)) => java_double(value),
+            (FieldType::Array(component), Value::Reference(Some(array))) if **component == FieldType::Char =>
+                return self.char_array(array),
+            (_, Value::Reference(reference)) => return self.string_value_of(reference),
+            (_, Value::ReturnAddress(_)) => return Err(bad_code("A returnAddress can't be turned into a string")),
+        };
+        Ok(utf16(&text))
+    }
+
+    fn char_array(&self, array: Reference) -> Result<Vec<u16>, Unwind> {
+        match self.heap.get(array) {
+            Object::Array { values, .. } => Ok(values.iter().map(|value| match value {
+                Value::Int(c) => *c as u16,
+                _ => 0,
+            }).collect()),
+            _ => Err(bad_code("Expecting a char array")),
+        }
+    }
+
+    fn new_char_array(&mut self, chars: &[u16]) -> Reference {
+        let values = chars.iter().map(|&c| Value::Int(c as i32)).collect();
+        self.heap.allocate(Object::Array { component: "C".to_string(), values })
+    }
+
+    fn stream(&mut self, stream: Stream) -> Reference {
+        if let Some(&object) = self.streams.get(&stream) {
+            return object;
+        }
+        let object = self.heap.allocate(Object::PrintStream(stream));
+        self.streams.insert(stream, object);
+        object
+    }
+
+    // Integer.valueOf and the others, which give out the same object for small values
+    fn boxed(&mut self, class: &'static str, value: Value) -> Reference {
+        let key = match value {
+            Value::Int(value) if class == "java/lang/Character" && (0..=127).contains(&value) => Some(value as i64),
+            Value::Int(value) if class != "java/lang/Character" && (-128..=127).contains(&value) => Some(value as i64),
+            Value::Long(value) if (-128..=127).contains(&value) => Some(value),
+            _ => None,
+        };
+        if let Some(&object) = key.and_then(|key| self.boxes.get(&(class, key))) {
+            return object;
+        }
+        let object = self.heap.allocate(Object::Boxed { class, value });
+        if let Some(key) = key {
+            self.boxes.insert((class, key), object);
+        }
+        object
+    }
+
+    fn unboxed(&self, object: Reference) -> Result<Value, Unwind> {
+        match self.heap.get(object) {
+            Object::Boxed { value, .. } => Ok(*value),
+            _ => Err(bad_code("Expecting a boxed primitive")),
+        }
+    }
+
+    fn string_result(&mut self, chars: Vec<u16>) -> Result<Option<Value>, Unwind> {
+        Ok(Some(Value::Reference(Some(self.new_string(chars)))))
+    }
+
+    fn boxed_result(&mut self, class: &'static str, value: Value) -> Result<Option<Value>, Unwind> {
+        Ok(Some(Value::Reference(Some(self.boxed(class, value)))))
+    }
+
+    // Throwable.fillInStackTrace, which leaves out the constructors of the exception itself
+    fn fill_in_stack_trace(&mut self, exception: Reference) -> Result<(), Unwind> {
+        let class_name = self.heap.get(exception).class_name();
+        let constructors: Vec<String> = self.stack.iter().rev()
+            .take_while(|entry| entry.method.name == "<init>")
+            .map(|entry| entry.class.name.clone())
+            .collect();
+        let mut skip = 0;
+        for constructor_class in constructors {
+            if !self.is_assignable(&class_name, &constructor_class)? {
+                break;
+            }
+            skip += 1;
+        }
+        let trace = self.stack_trace(skip);
+        self.stack_traces.insert(exception, trace);
+        Ok(())
+    }
+
+    fn print(&mut self, descriptor: &str, arguments: &[Value], newline: bool) -> Result<Option<Value>, Unwind> {
+        let stream = match self.heap.get(this(arguments)?) {
+            Object::PrintStream(stream) => *stream,
+            _ => return Err(bad_code("Expecting a PrintStream")),
+        };
+        let mut text = match (parameter_type(descriptor)?, arguments.get(1)) {
+            (Some(parameter), Some(&value)) => to_rust_string(&self.string_of(value, &parameter)?),
+            _ => String::new(),
+        };
+        if newline {
+            text.push('\n');
+        }
+        self.write(stream, &text);
+        Ok(None)
+    }
+
+    // StringBuilder.append and insert, which take the same types String.valueOf does
+    fn append(&mut self, descriptor: &str, arguments: &[Value], at: Option<i32>) -> Result<Option<Value>, Unwind> {
+        let builder = this(arguments)?;
+        let (parameter, value) = match (parameter_type(descriptor)?, at) {
+            (Some(parameter), None) => (parameter, argument(arguments, 1)?),
+            _ => {
+                let parameter = MethodDescriptor::parse(descriptor).ok().and_then(|d| d.parameters.get(1).cloned())
+                    .ok_or_else(|| bad_code("Bad insert descriptor"))?;
+                (parameter, argument(arguments, 2)?)
+            }
+        };
+        let chars = self.string_of(value, &parameter)?;
+        let Object::StringBuilder(contents) = self.heap.get_mut(builder) else {
+            return Err(bad_code("Expecting a StringBuilder"));
+        };
+        match at {
+            None => contents.extend(chars),
+            Some(offset) if offset >= 0 && offset as usize <= contents.len() => {
+                let offset = offset as usize;
+                contents.splice(offset..offset, chars);
+            }
+            Some(offset) => return Err(string_index(format!("offset {}, length {}", offset, contents.len()))),
+        }
+        Ok(Some(Value::Reference(Some(builder))))
+    }
+
+    fn builder(&mut self, arguments: &[Value]) -> Result<&mut Vec<u16>, Unwind> {
+        match self.heap.get_mut(this(arguments)?) {
+            Object::StringBuilder(contents) => Ok(contents),
+            _ => Err(bad_code("Expecting a StringBuilder")),
+        }
+    }
+
+    fn this_chars(&self, arguments: &[Value]) -> Result<Vec<u16>, Unwind> {
+        self.chars(this(arguments)?)
+    }
+
+    // A String argument that may not be null
+    fn string_argument(&self, arguments: &[Value], index: usize) -> Result<Vec<u16>, Unwind> {
+        match reference(arguments, index)? {
+            Some(string) => self.chars(string),
+            None => Err(null_pointer()),
+        }
+    }
+
+    // The chars of a CharSequence argument, a String or StringBuilder
+    fn char_sequence(&mut self, arguments: &[Value], index: usize) -> Result<Vec<u16>, Unwind> {
+        match reference(arguments, index)? {
+            Some(object) => self.string_value_of(Some(object)),
+            None => Err(null_pointer()),
+        }
+    }
+
+    fn equals(&mut self, object: Option<Reference>, other: Option<Reference>) -> Result<bool, Unwind> {
+        match (object, other) {
+            (None, None) => Ok(true),
+            (Some(object), other) if Some(object) != other => {
+                match self.invoke_virtual(object, "equals", "(Ljava/lang/Object;)Z", &[Value::Reference(other)])? {
+                    Some(Value::Int(equal)) => Ok(equal != 0),
+                    _ => Err(bad_code("equals didn't return a boolean")),
+                }
+            }
+            (Some(_), _) => Ok(true),
+            (None, Some(_)) => Ok(false),
+        }
+    }
+
+    fn hash_code(&mut self, object: Option<Reference>) -> Result<i32, Unwind> {
+        let Some(object) = object else {
+            return Ok(0);
+        };
+        match self.invoke_virtual(object, "hashCode", "()I", &[])? {
+            Some(Value::Int(hash)) => Ok(hash),
+            _ => Err(bad_code("hashCode didn't return an int")),
+        }
+    }
+
+    // Enum.valueOf: the constant of an enum class with the name, found through its values() method
+    fn enum_constant(&mut self, class_object: Reference, name: &[u16]) -> Result<Option<Value>, Unwind> {
+        let Object::Class(class_name) = self.heap.get(class_object) else {
+            return Err(bad_code("Expecting a Class"));
+        };
+        let class_name = class_name.clone();
+        let class = self.load_class(&class_name)?;
+        self.initialize(&class)?;
+        let values = self.invoke_from(&class, "values", &format!("()[L{};", class_name), Vec::new())?;
+        if let Some(Value::Reference(Some(array))) = values {
+            let Object::Array { values, .. } = self.heap.get(array) else {
+                return Err(bad_code("values() didn't return an array"));
+            };
+            for value in values.clone() {
+                if let Value::Reference(Some(constant)) = value {
+                    if let Some(Value::Reference(Some(constant_name))) = self.get_field(constant, "java/lang/Enum", "name") {
+                        if self.chars(constant_name)? == name {
+                            return Ok(Some(value));
+                        }
+                    }
+                }
+            }
+        }
+        Err(Unwind::Raise("java/lang/IllegalArgumentException", Some(format!(
+            "No enum constant {}.{}", java_name(&class_name).replace('$', "."), to_rust_string(name)))))
+    }
+
+    fn next_random(&mut self) -> f64 {
+        // xorshift64*, seeded from the clock the first time
+        if self.random == 0 {
+            self.random = SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |time| time.as_nanos() as u64) | 1;
+        }
+        self.random ^= self.random >> 12;
+        self.random ^= self.random << 25;
+        self.random ^= self.random >> 27;
+        (self.random.wrapping_mul(0x2545f4914f6cdd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// Spec: the library methods, by the class that declares them. Methods a class inherits from a library
+// superclass are found there, like Integer.intValue in Number.
+fn native(class_name: &str, name: &str, descriptor: &str) -> Option<Native> {
+    let native: Native = match (class_name, name, descriptor) {
+        ("java/lang/Object", "<init>", "()V") => |_, _, _| Ok(None),
+        ("java/lang/Object", "hashCode", "()I") => |_, _, arguments| int_result(this(arguments)? as i32),
+        ("java/lang/Object", "equals", "(Ljava/lang/Object;)Z") =>
+            |_, _, arguments| bool_result(Some(this(arguments)?) == reference(arguments, 1)?),
+        ("java/lang/Object", "toString", "()Ljava/lang/String;") => |vm, _, arguments| {
+            let object = this(arguments)?;
+            let hash = vm.hash_code(Some(object))?;
+            let text = format!("{}@{:x}", java_name(&vm.heap.get(object).class_name()), hash);
+            vm.string_result(utf16(&text))
+        },
+        ("java/lang/Object", "getClass", "()Ljava/lang/Class;") => |vm, _, arguments| {
+            let class_name = vm.heap.get(this(arguments)?).class_name();
+            reference_result(Some(vm.class_object(&class_name)))
+        },
+        ("java/lang/Object", "clone", "()Ljava/lang/Object;") => |vm, _, arguments| {
+            let object = this(arguments)?;
+            let copy = match vm.heap.get(object) {
+                Object::Array { component, values } => Object::Array { component: component.clone(), values: values.clone() },
+                Object::Instance { class, fields } => {
+                    let (class, fields) = (class.clone(), fields.clone());
+                    if !vm.is_assignable(&class.name, "java/lang/Cloneable")? {
+                        return Err(Unwind::Raise("java/lang/CloneNotSupportedException", Some(java_name(&class.name))));
+                    }
+                    Object::Instance { class, fields }
+                }
+                object => return Err(Unwind::Raise("java/lang/CloneNotSupportedException",
+                                                   Some(java_name(&object.class_name())))),
+            };
+            reference_result(Some(vm.heap.allocate(copy)))
+        },
+
+        ("java/lang/Throwable", "<init>", "()V") => |vm, _, arguments| {
+            vm.fill_in_stack_trace(this(arguments)?)?;
+            Ok(None)
+        },
+        ("java/lang/Throwable", "<init>", "(Ljava/lang/String;)V" | "(Ljava/lang/String;Ljava/lang/Throwable;)V" |
+         "(Ljava/lang/String;Ljava/lang/Throwable;ZZ)V") => |vm, _, arguments| {
+            let exception = this(arguments)?;
+            vm.set_field(exception, "java/lang/Throwable", "detailMessage", Value::Reference(reference(arguments, 1)?));
+            if arguments.len() > 2 {
+                vm.set_field(exception, "java/lang/Throwable", "cause", Value::Reference(reference(arguments, 2)?));
+            }
+            vm.fill_in_stack_trace(exception)?;
+            Ok(None)
+        },
+        ("java/lang/Throwable", "<init>", "(Ljava/lang/Throwable;)V") => |vm, _, arguments| {
+            let exception = this(arguments)?;
+            let cause = reference(arguments, 1)?;
+            let message = match cause {
+                Some(cause) => {
+                    let description = vm.string_value_of(Some(cause))?;
+                    Some(vm.new_string(description))
+                }
+                None => None,
+            };
+            vm.set_field(exception, "java/lang/Throwable", "detailMessage", Value::Reference(message));
+            vm.set_field(exception, "java/lang/Throwable", "cause", Value::Reference(cause));
+            vm.fill_in_stack_trace(exception)?;
+            Ok(None)
+        },
+        // AssertionError(Object), which assert statements with a message use
+        ("java/lang/AssertionError", "<init>", "(Ljava/lang/Object;)V") => |vm, _, arguments| {
+            let exception = this(arguments)?;
+            let detail = reference(arguments, 1)?;
+            let message = vm.string_value_of(detail)?;
+            let message = vm.new_string(message);
+            vm.set_field(exception, "java/lang/Throwable", "detailMessage", Value::Reference(Some(message)));
+            if let Some(detail) = detail.filter(|&detail| vm.is_instance_of(detail, "java/lang/Throwable").unwrap_or(false)) {
+                vm.set_field(exception, "java/lang/Throwable", "cause", Value::Reference(Some(detail)));
+            }
+            vm.fill_in_stack_trace(exception)?;
+            Ok(None)
+        },
+        ("java/lang/Throwable", "getMessage", "()Ljava/lang/String;") => |vm, _, arguments|
+            Ok(Some(vm.get_field(this(arguments)?, "java/lang/Throwable", "detailMessage").unwrap_or(Value::Reference(None)))),
+        ("java/lang/Throwable", "getLocalizedMessage", "()Ljava/lang/String;") =>
+            |vm, _, arguments| vm.invoke_virtual(this(arguments)?, "getMessage", "()Ljava/lang/String;", &[]),
+        ("java/lang/Throwable", "getCause", "()Ljava/lang/Throwable;") => |vm, _, arguments|
+            Ok(Some(vm.get_field(this(arguments)?, "java/lang/Throwable", "cause").unwrap_or(Value::Reference(None)))),
+        ("java/lang/Throwable", "initCause", "(Ljava/lang/Throwable;)Ljava/lang/Throwable;") => |vm, _, arguments| {
+            let exception = this(arguments)?;
+            vm.set_field(exception, "java/lang/Throwable", "cause", Value::Reference(reference(arguments, 1)?));
+            reference_result(Some(exception))
+        },
+        ("java/lang/Throwable", "fillInStackTrace", "()Ljava/lang/Throwable;") => |vm, _, arguments| {
+            vm.fill_in_stack_trace(this(arguments)?)?;
+            reference_result(Some(this(arguments)?))
+        },
+        ("java/lang/Throwable", "toString", "()Ljava/lang/String;") => |vm, _, arguments| {
+            let exception = this(arguments)?;
+            let mut text = utf16(&java_name(&vm.heap.get(exception).class_name()));
+            if let Some(Value::Reference(Some(message))) =
+                vm.invoke_virtual(exception, "getLocalizedMessage", "()Ljava/lang/String;", &[])? {
+                text.extend(utf16(": "));
+                text.extend(vm.chars(message)?);
+            }
+            vm.string_result(text)
+        },
+        ("java/lang/Throwable", "printStackTrace", "()V") => |vm, _, arguments| {
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+            vm.print_stack_trace(this(arguments)?, Stream::Err);
+            Ok(None)
+        },
+        // Suppressed exceptions aren't printed, so there is no need to keep them
+        ("java/lang/Throwable", "addSuppressed", "(Ljava/lang/Throwable;)V") => |_, _, _| Ok(None),
+
+        ("java/lang/String", "<init>", "()V") => |_, _, _| Ok(None),
+        ("java/lang/String", "<init>", "([C)V" | "(Ljava/lang/String;)V" | "(Ljava/lang/StringBuilder;)V") =>
+            |vm, descriptor, arguments| {
+                let value = argument(arguments, 1)?;
+                let chars = match value {
+                    Value::Reference(None) => return Err(null_pointer()),
+                    value => vm.string_of(value, &parameter_type(descriptor)?.unwrap_or(FieldType::Int))?,
+                };
+                *vm.heap.get_mut(this(arguments)?) = Object::String(chars);
+                Ok(None)
+            },
+        ("java/lang/String", "<init>", "([CII)V") => |vm, _, arguments| {
+            let chars = vm.char_array(reference(arguments, 1)?.ok_or_else(null_pointer)?)?;
+            let (offset, count) = (int(arguments, 2)?, int(arguments, 3)?);
+            let end = offset as i64 + count as i64;
+            if offset < 0 || count < 0 || end > chars.len() as i64 {
+                return Err(string_index(format!("offset {}, count {}, length {}", offset, count, chars.len())));
+            }
+            *vm.heap.get_mut(this(arguments)?) = Object::String(chars[offset as usize..end as usize].to_vec());
+            Ok(None)
+        },
+        ("java/lang/String", "<init>", "([B)V") => |vm, _, arguments| {
+            let bytes = match vm.heap.get(reference(arguments, 1)?.ok_or_else(null_pointer)?) {
+                Object::Array { values, .. } => values.iter().map(|value| match value {
+                    Value::Int(byte) => *byte as u8,
+                    _ => 0,
+                }).collect::<Vec<u8>>(),
+                _ => return Err(bad_code("Expecting a byte array")),
+            };
+            *vm.heap.get_mut(this(arguments)?) = Object::String(utf16(&String::from_utf8_lossy(&bytes)));
+            Ok(None)
+        },
+        ("java/lang/String", "length", "()I") => |vm, _, arguments| int_result(vm.this_chars(arguments)?.len() as i32),
+        ("java/lang/String", "isEmpty", "()Z") => |vm, _, arguments| bool_result(vm.this_chars(arguments)?.is_empty()),
+        ("java/lang/String", "isBlank", "()Z") => |vm, _, arguments|
+            bool_result(to_rust_string(&vm.this_chars(arguments)?).chars().all(char::is_whitespace)),
+        ("java/lang/String", "charAt", "(I)C") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            let index = int(arguments, 1)?;
+            match chars.get(index as usize).filter(|_| index >= 0) {
+                Some(&c) => int_result(c as i32),
+                None => Err(string_index(format!("index {}, length {}", index, chars.len()))),
+            }
+        },
+        ("java/lang/String", "equals", "(Ljava/lang/Object;)Z") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            bool_result(match reference(arguments, 1)?.map(|other| vm.heap.get(other)) {
+                Some(Object::String(other)) => chars == *other,
+                _ => false,
+            })
+        },
+        ("java/lang/String", "equalsIgnoreCase", "(Ljava/lang/String;)Z") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            let other = match reference(arguments, 1)? {
+                Some(other) => vm.chars(other)?,
+                None => return bool_result(false),
+            };
+            bool_result(chars.len() == other.len() && chars.iter().zip(&other).all(|(&a, &b)| fold_case(a) == fold_case(b)))
+        },
+        ("java/lang/String", "hashCode", "()I") => |vm, _, arguments|
+            int_result(vm.this_chars(arguments)?.iter().fold(0i32, |hash, &c| hash.wrapping_mul(31).wrapping_add(c as i32))),
+        ("java/lang/String", "compareTo", "(Ljava/lang/String;)I" | "(Ljava/lang/Object;)I") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            let other = vm.string_argument(arguments, 1)?;
+            int_result(match chars.iter().zip(&other).find(|(a, b)| a != b) {
+                Some((&a, &b)) => a as i32 - b as i32,
+                None => chars.len() as i32 - other.len() as i32,
+            })
+        },
+        ("java/lang/String", "concat", "(Ljava/lang/String;)Ljava/lang/String;") => |vm, _, arguments| {
+            let mut chars = vm.this_chars(arguments)?;
+            chars.extend(vm.string_argument(arguments, 1)?);
+            vm.string_result(chars)
+        },
+        ("java/lang/String", "substring", "(I)Ljava/lang/String;" | "(II)Ljava/lang/String;") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            let begin = int(arguments, 1)?;
+            let end = match arguments.len() {
+                3 => int(arguments, 2)?,
+                _ => chars.len() as i32,
+            };
+            if begin < 0 || begin > end || end as usize > chars.len() {
+                return Err(string_index(format!("begin {}, end {}, length {}", begin, end, chars.len())));
+            }
+            vm.string_result(chars[begin as usize..end as usize].to_vec())
+        },
+        ("java/lang/String", "indexOf", "(I)I" | "(II)I") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            let from = match arguments.len() {
+                3 => int(arguments, 2)?.max(0) as usize,
+                _ => 0,
+            };
+            let target = utf16(&char::from_u32(int(arguments, 1)? as u32).map(String::from).unwrap_or_default());
+            int_result(find(&chars, &target, from).map_or(-1, |index| index as i32))
+        },
+        ("java/lang/String", "indexOf", "(Ljava/lang/String;)I" | "(Ljava/lang/String;I)I") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            let target = vm.string_argument(arguments, 1)?;
+            let from = match arguments.len() {
+                3 => int(arguments, 2)?.max(0) as usize,
+                _ => 0,
+            };
+            int_result(find(&chars, &target, from).map_or(-1, |index| index as i32))
+        },
+        ("java/lang/String", "lastIndexOf", "(I)I") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            let target = utf16(&char::from_u32(int(arguments, 1)? as u32).map(String::from).unwrap_or_default());
+            int_result(rfind(&chars, &target).map_or(-1, |index| index as i32))
+        },
+        ("java/lang/String", "lastIndexOf", "(Ljava/lang/String;)I") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            let target = vm.string_argument(arguments, 1)?;
+            int_result(rfind(&chars, &target).map_or(-1, |index| index as i32))
+        },
+        ("java/lang/String", "contains", "(Ljava/lang/CharSequence;)Z") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            let target = vm.char_sequence(arguments, 1)?;
+            bool_result(find(&chars, &target, 0).is_some())
+        },
+        ("java/lang/String", "startsWith", "(Ljava/lang/String;)Z") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            bool_result(chars.starts_with(&vm.string_argument(arguments, 1)?))
+        },
+        ("java/lang/String", "endsWith", "(Ljava/lang/String;)Z") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            bool_result(chars.ends_with(&vm.string_argument(arguments, 1)?))
+        },
+        ("java/lang/String", "toUpperCase", "()Ljava/lang/String;") => |vm, _, arguments| {
+            let text = to_rust_string(&vm.this_chars(arguments)?).to_uppercase();
+            vm.string_result(utf16(&text))
+        },
+        ("java/lang/String", "toLowerCase", "()Ljava/lang/String;") => |vm, _, arguments| {
+            let text = to_rust_string(&vm.this_chars(arguments)?).to_lowercase();
+            vm.string_result(utf16(&text))
+        },
+        // trim takes off everything up to and including the space, strip only whitespace
+        ("java/lang/String", "trim", "()Ljava/lang/String;") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            let start = chars.iter().position(|&c| c > b' ' as u16).unwrap_or(chars.len());
+            let end = chars.iter().rposition(|&c| c > b' ' as u16).map_or(start, |end| end + 1);
+            vm.string_result(chars[start..end].to_vec())
+        },
+        ("java/lang/String", "strip", "()Ljava/lang/String;") => |vm, _, arguments| {
+            let text = to_rust_string(&vm.this_chars(arguments)?).trim().to_string();
+            vm.string_result(utf16(&text))
+        },
+        ("java/lang/String", "repeat", "(I)Ljava/lang/String;") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            let count = int(arguments, 1)?;
+            if count < 0 {
+                return Err(Unwind::Raise("java/lang/IllegalArgumentException", Some(format!("count is negative: {}", count))));
+            }
+            vm.string_result(chars.repeat(count as usize))
+        },
+        ("java/lang/String", "replace", "(CC)Ljava/lang/String;") => |vm, _, arguments| {
+            let (old, new) = (int(arguments, 1)? as u16, int(arguments, 2)? as u16);
+            let chars = vm.this_chars(arguments)?.into_iter().map(|c| if c == old { new } else { c }).collect();
+            vm.string_result(chars)
+        },
+        ("java/lang/String", "replace", "(Ljava/lang/CharSequence;Ljava/lang/CharSequence;)Ljava/lang/String;") =>
+            |vm, _, arguments| {
+                let chars = vm.this_chars(arguments)?;
+                let (target, replacement) = (vm.char_sequence(arguments, 1)?, vm.char_sequence(arguments, 2)?);
+                vm.string_result(replace(&chars, &target, &replacement))
+            },
+        ("java/lang/String", "toCharArray", "()[C") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            reference_result(Some(vm.new_char_array(&chars)))
+        },
+        ("java/lang/String", "getBytes", "()[B") => |vm, _, arguments| {
+            let text = to_rust_string(&vm.this_chars(arguments)?);
+            let values = text.bytes().map(|byte| Value::Int(byte as i8 as i32)).collect();
+            reference_result(Some(vm.heap.allocate(Object::Array { component: "B".to_string(), values })))
+        },
+        ("java/lang/String", "intern", "()Ljava/lang/String;") => |vm, _, arguments| {
+            let chars = vm.this_chars(arguments)?;
+            reference_result(Some(vm.intern(chars)))
+        },
+        ("java/lang/String", "toString", "()Ljava/lang/String;") => |_, _, arguments| reference_result(Some(this(arguments)?)),
+        ("java/lang/String", "valueOf", "(I)Ljava/lang/String;" | "(J)Ljava/lang/String;" | "(C)Ljava/lang/String;" |
+         "(Z)Ljava/lang/String;" | "(F)Ljava/lang/String;" | "(D)Ljava/lang/String;" | "([C)Ljava/lang/String;" |
+         "(Ljava/lang/Object;)Ljava/lang/String;") => |vm, descriptor, arguments| {
+            let parameter = parameter_type(descriptor)?.ok_or_else(|| bad_code("Missing parameter"))?;
+            let chars = vm.string_of(argument(arguments, 0)?, &parameter)?;
+            vm.string_result(chars)
+        },
+
+        ("java/lang/StringBuilder", "<init>", "()V" | "(I)V") => |_, _, _| Ok(None),
+        ("java/lang/StringBuilder", "<init>", "(Ljava/lang/String;)V" | "(Ljava/lang/CharSequence;)V") => |vm, _, arguments| {
+            let chars = vm.char_sequence(arguments, 1)?;
+            *vm.builder(arguments)? = chars;
+            Ok(None)
+        },
+        ("java/lang/StringBuilder", "append", "(Ljava/lang/String;)Ljava/lang/StringBuilder;" |
+         "(Ljava/lang/Object;)Ljava/lang/StringBuilder;" | "(Ljava/lang/CharSequence;)Ljava/lang/StringBuilder;" |
+         "(I)Ljava/lang/StringBuilder;" | "(J)Ljava/lang/StringBuilder;" | "(C)Ljava/lang/StringBuilder;" |
+         "(Z)Ljava/lang/StringBuilder;" | "(F)Ljava/lang/StringBuilder;" | "(D)Ljava/lang/StringBuilder;" |
+         "([C)Ljava/lang/StringBuilder;") => |vm, descriptor, arguments| vm.append(descriptor, arguments, None),
+        ("java/lang/StringBuilder", "insert", "(ILjava/lang/String;)Ljava/lang/StringBuilder;" |
+         "(ILjava/lang/Object;)Ljava/lang/StringBuilder;" | "(II)Ljava/lang/StringBuilder;" |
+         "(IJ)Ljava/lang/StringBuilder;" | "(IC)Ljava/lang/StringBuilder;" | "(IZ)Ljava/lang/StringBuilder;" |
+         "(ID)Ljava/lang/StringBuilder;") => |vm, descriptor, arguments| {
+            let at = int(arguments, 1)?;
+            vm.append(descriptor, arguments, Some(at))
+        },
+        ("java/lang/StringBuilder", "toString", "()Ljava/lang/String;") => |vm, _, arguments| {
+            let chars = vm.builder(arguments)?.clone();
+            vm.string_result(chars)
+        },
+        ("java/lang/StringBuilder", "length", "()I") => |vm, _, arguments| int_result(vm.builder(arguments)?.len() as i32),
+        ("java/lang/StringBuilder", "charAt", "(I)C") => |vm, _, arguments| {
+            let index = int(arguments, 1)?;
+            let contents = vm.builder(arguments)?;
+            match contents.get(index as usize).filter(|_| index >= 0) {
+                Some(&c) => int_result(c as i32),
+                None => Err(string_index(format!("index {},length {}", index, contents.len()))),
+            }
+        },
+        ("java/lang/StringBuilder", "setCharAt", "(IC)V") => |vm, _, arguments| {
+            let (index, c) = (int(arguments, 1)?, int(arguments, 2)? as u16);
+            let contents = vm.builder(arguments)?;
+            match contents.get_mut(index as usize).filter(|_| index >= 0) {
+                Some(slot) => *slot = c,
+                None => return Err(string_index(format!("index {},length {}", index, contents.len()))),
+            }
+            Ok(None)
+        },
+        ("java/lang/StringBuilder", "deleteCharAt", "(I)Ljava/lang/StringBuilder;") => |vm, _, arguments| {
+            let index = int(arguments, 1)?;
+            let contents = vm.builder(arguments)?;
+            if index < 0 || index as usize >= contents.len() {
+                return Err(string_index(format!("index {},length {}", index, contents.len())));
+            }
+            contents.remove(index as usize);
+            reference_result(Some(this(arguments)?))
+        },
+        ("java/lang/StringBuilder", "delete", "(II)Ljava/lang/StringBuilder;") => |vm, _, arguments| {
+            let (start, end) = (int(arguments, 1)?, int(arguments, 2)?);
+            let contents = vm.builder(arguments)?;
+            let end = (end.max(0) as usize).min(contents.len());
+            if start < 0 || start as usize > end {
+                return Err(string_index(format!("start {}, end {}, length {}", start, end, contents.len())));
+            }
+            contents.drain(start as usize..end);
+            reference_result(Some(this(arguments)?))
+        },
+        ("java/lang/StringBuilder", "setLength", "(I)V") => |vm, _, arguments| {
+            let length = int(arguments, 1)?;
+            if length < 0 {
+                return Err(string_index(length.to_string()));
+            }
+            vm.builder(arguments)?.resize(length as usize, 0);
+            Ok(None)
+        },
+        // Surrogate pairs stay in order, like StringBuilder.reverse does
+        ("java/lang/StringBuilder", "reverse", "()Ljava/lang/StringBuilder;") => |vm, _, arguments| {
+            let contents = vm.builder(arguments)?;
+            let reversed: String = to_rust_string(contents).chars().rev().collect();
+            *contents = utf16(&reversed);
+            reference_result(Some(this(arguments)?))
+        },
+        ("java/lang/StringBuilder", "indexOf", "(Ljava/lang/String;)I") => |vm, _, arguments| {
+            let target = vm.string_argument(arguments, 1)?;
+            let contents = vm.builder(arguments)?;
+            int_result(find(contents, &target, 0).map_or(-1, |index| index as i32))
+        },
+        ("java/lang/StringBuilder", "isEmpty", "()Z") => |vm, _, arguments| bool_result(vm.builder(arguments)?.is_empty()),
+
+        ("java/io/PrintStream", "println", "()V" | "(Z)V" | "(C)V" | "(I)V" | "(J)V" | "(F)V" | "(D)V" | "([C)V" |
+         "(Ljava/lang/String;)V" | "(Ljava/lang/Object;)V") =>
+            |vm, descriptor, arguments| vm.print(descriptor, arguments, true),
+        ("java/io/PrintStream", "print", "(Z)V" | "(C)V" | "(I)V" | "(J)V" | "(F)V" | "(D)V" | "([C)V" |
+         "(Ljava/lang/String;)V" | "(Ljava/lang/Object;)V") =>
+            |vm, descriptor, arguments| vm.print(descriptor, arguments, false),
+        ("java/io/PrintStream", "flush", "()V") => |_, _, _| {
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+            Ok(None)
+        },
+
+        ("java/lang/System", "exit", "(I)V") => |_, _, arguments| Err(Unwind::Exit(int(arguments, 0)?)),
+        ("java/lang/System", "currentTimeMillis", "()J") =>
+            |_, _, _| long_result(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_millis() as i64)),
+        ("java/lang/System", "nanoTime", "()J") =>
+            |_, _, _| long_result(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as i64)),
+        ("java/lang/System", "identityHashCode", "(Ljava/lang/Object;)I") =>
+            |_, _, arguments| int_result(reference(arguments, 0)?.map_or(0, |object| object as i32)),
+        ("java/lang/System", "lineSeparator", "()Ljava/lang/String;") => |vm, _, _| vm.string_result(utf16("\n")),
+        ("java/lang/System", "getProperty", "(Ljava/lang/String;)Ljava/lang/String;" |
+         "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;") => |vm, _, arguments| {
+            let key = to_rust_string(&vm.string_argument(arguments, 0)?);
+            match vm.properties.get(&key).cloned() {
+                Some(value) => vm.string_result(utf16(&value)),
+                None => Ok(Some(Value::Reference(reference(arguments, 1).unwrap_or(None)))),
+            }
+        },
+        ("java/lang/System", "setProperty", "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;") => |vm, _, arguments| {
+            let key = to_rust_string(&vm.string_argument(arguments, 0)?);
+            let value = to_rust_string(&vm.string_argument(arguments, 1)?);
+            match vm.properties.insert(key, value) {
+                Some(previous) => vm.string_result(utf16(&previous)),
+                None => reference_result(None),
+            }
+        },
+        ("java/lang/System", "getenv", "(Ljava/lang/String;)Ljava/lang/String;") => |vm, _, arguments| {
+            let name = to_rust_string(&vm.string_argument(arguments, 0)?);
+            match std::env::var(name) {
+                Ok(value) => vm.string_result(utf16(&value)),
+                Err(_) => reference_result(None),
+            }
+        },
+        ("java/lang/System", "arraycopy", "(Ljava/lang/Object;ILjava/lang/Object;II)V") => |vm, _, arguments| {
+            let source = reference(arguments, 0)?.ok_or_else(null_pointer)?;
+            let destination = reference(arguments, 2)?.ok_or_else(null_pointer)?;
+            let (source_position, destination_position, length) = (int(arguments, 1)?, int(arguments, 3)?, int(arguments, 4)?);
+            let (Object::Array { component: source_component, values: source_values },
+                 Object::Array { component: destination_component, values: destination_values }) =
+                (vm.heap.get(source), vm.heap.get(destination)) else {
+                return Err(Unwind::Raise("java/lang/ArrayStoreException", Some("arraycopy: argument type mismatch".to_string())));
+            };
+            let is_primitive = |component: &str| !component.starts_with(['L', '[']);
+            if source_component != destination_component && (is_primitive(source_component) || is_primitive(destination_component)) {
+                return Err(Unwind::Raise("java/lang/ArrayStoreException", Some(format!(
+                    "arraycopy: type mismatch: can not copy {}[] into {}[]", source_component, destination_component))));
+            }
+            let fits = |position: i32, values: &Vec<Value>| position >= 0 && position as i64 + length as i64 <= values.len() as i64;
+            if length < 0 || !fits(source_position, source_values) || !fits(destination_position, destination_values) {
+                return Err(Unwind::Raise("java/lang/ArrayIndexOutOfBoundsException", Some(format!(
+                    "arraycopy: last index {} out of bounds", source_position as i64 + length as i64))));
+            }
+            let copied = source_values[source_position as usize..(source_position + length) as usize].to_vec();
+            if let Object::Array { values, .. } = vm.heap.get_mut(destination) {
+                values.splice(destination_position as usize..(destination_position + length) as usize, copied);
+            }
+            Ok(None)
+        },
+
+        ("java/lang/Thread", "sleep", "(J)V") => |_, _, arguments| {
+            std::thread::sleep(std::time::Duration::from_millis(long(arguments, 0)?.max(0) as u64));
+            Ok(None)
+        },
+
+        ("java/lang/Math", "abs", "(I)I") => |_, _, arguments| int_result(int(arguments, 0)?.wrapping_abs()),
+        ("java/lang/Math", "abs", "(J)J") => |_, _, arguments| long_result(long(arguments, 0)?.wrapping_abs()),
+        ("java/lang/Math", "abs", "(F)F") => |_, _, arguments| float_result(float(arguments, 0)?.abs()),
+        ("java/lang/Math", "abs", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.abs()),
+        ("java/lang/Math", "max", "(II)I") => |_, _, arguments| int_result(int(arguments, 0)?.max(int(arguments, 1)?)),
+        ("java/lang/Math", "min", "(II)I") => |_, _, arguments| int_result(int(arguments, 0)?.min(int(arguments, 1)?)),
+        ("java/lang/Math", "max", "(JJ)J") => |_, _, arguments| long_result(long(arguments, 0)?.max(long(arguments, 1)?)),
+        ("java/lang/Math", "min", "(JJ)J") => |_, _, arguments| long_result(long(arguments, 0)?.min(long(arguments, 1)?)),
+        ("java/lang/Math", "max", "(DD)D") =>
+            |_, _, arguments| double_result(java_max(double(arguments, 0)?, double(arguments, 1)?)),
+        ("java/lang/Math", "min", "(DD)D") =>
+            |_, _, arguments| double_result(-java_max(-double(arguments, 0)?, -double(arguments, 1)?)),
+        ("java/lang/Math", "max", "(FF)F") =>
+            |_, _, arguments| float_result(java_max(float(arguments, 0)? as f64, float(arguments, 1)? as f64) as f32),
+        ("java/lang/Math", "min", "(FF)F") =>
+            |_, _, arguments| float_result(-java_max(-float(arguments, 0)? as f64, -float(arguments, 1)? as f64) as f32),
+        ("java/lang/Math", "sqrt", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.sqrt()),
+        ("java/lang/Math", "cbrt", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.cbrt()),
+        ("java/lang/Math", "pow", "(DD)D") => |_, _, arguments| double_result(double(arguments, 0)?.powf(double(arguments, 1)?)),
+        ("java/lang/Math", "exp", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.exp()),
+        ("java/lang/Math", "log", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.ln()),
+        ("java/lang/Math", "log10", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.log10()),
+        ("java/lang/Math", "sin", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.sin()),
+        ("java/lang/Math", "cos", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.cos()),
+        ("java/lang/Math", "tan", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.tan()),
+        ("java/lang/Math", "atan", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.atan()),
+        ("java/lang/Math", "atan2", "(DD)D") => |_, _, arguments| double_result(double(arguments, 0)?.atan2(double(arguments, 1)?)),
+        ("java/lang/Math", "hypot", "(DD)D") => |_, _, arguments| double_result(double(arguments, 0)?.hypot(double(arguments, 1)?)),
+        ("java/lang/Math", "floor", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.floor()),
+        ("java/lang/Math", "ceil", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.ceil()),
+        ("java/lang/Math", "signum", "(D)D") => |_, _, arguments| {
+            let value = double(arguments, 0)?;
+            double_result(if value == 0.0 || value.is_nan() { value } else { value.signum() })
+        },
+        ("java/lang/Math", "toRadians", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.to_radians()),
+        ("java/lang/Math", "toDegrees", "(D)D") => |_, _, arguments| double_result(double(arguments, 0)?.to_degrees()),
+        // Rounds halves up, towards positive infinity; NaN is zero and `as` saturates, like in Java
+        ("java/lang/Math", "round", "(D)J") => |_, _, arguments| {
+            let value = double(arguments, 0)?;
+            let floor = value.floor();
+            long_result(if value - floor >= 0.5 { floor + 1.0 } else { floor } as i64)
+        },
+        ("java/lang/Math", "round", "(F)I") => |_, _, arguments| {
+            let value = float(arguments, 0)?;
+            let floor = value.floor();
+            int_result(if value - floor >= 0.5 { floor + 1.0 } else { floor } as i32)
+        },
+        ("java/lang/Math", "floorDiv", "(II)I") => |_, _, arguments| match int(arguments, 1)? {
+            0 => Err(Unwind::Raise("java/lang/ArithmeticException", Some("/ by zero".to_string()))),
+            divisor => {
+                let dividend = int(arguments, 0)?;
+                let quotient = dividend.wrapping_div(divisor);
+                int_result(if (dividend % divisor != 0) && ((dividend < 0) != (divisor < 0)) { quotient - 1 } else { quotient })
+            }
+        },
+        ("java/lang/Math", "floorMod", "(II)I") => |_, _, arguments| match int(arguments, 1)? {
+            0 => Err(Unwind::Raise("java/lang/ArithmeticException", Some("/ by zero".to_string()))),
+            divisor => {
+                let remainder = int(arguments, 0)?.wrapping_rem(divisor);
+                int_result(if remainder != 0 && ((remainder < 0) != (divisor < 0)) { remainder + divisor } else { remainder })
+            }
+        },
+        ("java/lang/Math", "random", "()D") => |vm, _, _| double_result(vm.next_random()),
+
+        ("java/lang/Number", "intValue", "()I") => |vm, _, arguments| match vm.unboxed(this(arguments)?)? {
+            Value::Long(value) => int_result(value as i32),
+            Value::Float(value) => int_result(value as i32),
+            Value::Double(value) => int_result(value as i32),
+            value => Ok(Some(value)),
+        },
+        ("java/lang/Number", "longValue", "()J") => |vm, _, arguments| match vm.unboxed(this(arguments)?)? {
+            Value::Int(value) => long_result(value as i64),
+            Value::Float(value) => long_result(value as i64),
+            Value::Double(value) => long_result(value as i64),
+            value => Ok(Some(value)),
+        },
+        ("java/lang/Number", "floatValue", "()F") => |vm, _, arguments| match vm.unboxed(this(arguments)?)? {
+            Value::Int(value) => float_result(value as f32),
+            Value::Long(value) => float_result(value as f32),
+            Value::Double(value) => float_result(value as f32),
+            value => Ok(Some(value)),
+        },
+        ("java/lang/Number", "doubleValue", "()D") => |vm, _, arguments| match vm.unboxed(this(arguments)?)? {
+            Value::Int(value) => double_result(value as f64),
+            Value::Long(value) => double_result(value as f64),
+            Value::Float(value) => double_result(value as f64),
+            value => Ok(Some(value)),
+        },
+        ("java/lang/Number", "shortValue", "()S") => |vm, descriptor, arguments| {
+            let Some(Value::Int(value)) = native("java/lang/Number", "intValue", "()I").map(|int_value| int_value(vm, descriptor, arguments)).transpose()?.flatten() else {
+                return Err(bad_code("Expecting a number"));
+            };
+            int_result(value as i16 as i32)
+        },
+        ("java/lang/Number", "byteValue", "()B") => |vm, descriptor, arguments| {
+            let Some(Value::Int(value)) = native("java/lang/Number", "intValue", "()I").map(|int_value| int_value(vm, descriptor, arguments)).transpose()?.flatten() else {
+                return Err(bad_code("Expecting a number"));
+            };
+            int_result(value as i8 as i32)
+        },
+        ("java/lang/Boolean", "booleanValue", "()Z") | ("java/lang/Character", "charValue", "()C") =>
+            |vm, _, arguments| Ok(Some(vm.unboxed(this(arguments)?)?)),
+        // toString, hashCode, equals and compareTo of all the boxes
+        ("java/lang/Integer" | "java/lang/Long" | "java/lang/Short" | "java/lang/Byte" | "java/lang/Double" |
+         "java/lang/Float" | "java/lang/Boolean" | "java/lang/Character", "toString", "()Ljava/lang/String;") =>
+            |vm, _, arguments| {
+                let object = this(arguments)?;
+                let value = vm.unboxed(object)?;
+                let field_type = box_type(&vm.heap.get(object).class_name());
+                let chars = vm.string_of(value, &field_type)?;
+                vm.string_result(chars)
+            },
+        ("java/lang/Integer" | "java/lang/Long" | "java/lang/Short" | "java/lang/Byte" | "java/lang/Double" |
+         "java/lang/Float" | "java/lang/Boolean" | "java/lang/Character", "hashCode", "()I") =>
+            |vm, _, arguments| {
+                let object = this(arguments)?;
+                let field_type = box_type(&vm.heap.get(object).class_name());
+                int_result(primitive_hash(vm.unboxed(object)?, &field_type))
+            },
+        ("java/lang/Integer" | "java/lang/Long" | "java/lang/Short" | "java/lang/Byte" | "java/lang/Double" |
+         "java/lang/Float" | "java/lang/Boolean" | "java/lang/Character", "equals", "(Ljava/lang/Object;)Z") =>
+            |vm, _, arguments| {
+                let object = this(arguments)?;
+                let Some(other) = reference(arguments, 1)? else {
+                    return bool_result(false);
+                };
+                bool_result(match (vm.heap.get(object), vm.heap.get(other)) {
+                    (Object::Boxed { class, value }, Object::Boxed { class: other_class, value: other_value }) if class == other_class =>
+                        java_compare(*value, *other_value) == 0,
+                    _ => false,
+                })
+            },
+        ("java/lang/Integer" | "java/lang/Long" | "java/lang/Short" | "java/lang/Byte" | "java/lang/Double" |
+         "java/lang/Float" | "java/lang/Boolean" | "java/lang/Character", "compareTo", _)
+            if descriptor.ends_with(";)I") => |vm, _, arguments| {
+                let value = vm.unboxed(this(arguments)?)?;
+                let other = reference(arguments, 1)?.ok_or_else(null_pointer)?;
+                let other = vm.unboxed(other)?;
+                int_result(java_compare(value, other))
+            },
+
+        ("java/lang/Integer", "valueOf", "(I)Ljava/lang/Integer;") =>
+            |vm, _, arguments| vm.boxed_result("java/lang/Integer", Value::Int(int(arguments, 0)?)),
+        ("java/lang/Long", "valueOf", "(J)Ljava/lang/Long;") =>
+            |vm, _, arguments| vm.boxed_result("java/lang/Long", Value::Long(long(arguments, 0)?)),
+        ("java/lang/Short", "valueOf", "(S)Ljava/lang/Short;") =>
+            |vm, _, arguments| vm.boxed_result("java/lang/Short", Value::Int(int(arguments, 0)?)),
+        ("java/lang/Byte", "valueOf", "(B)Ljava/lang/Byte;") =>
+            |vm, _, arguments| vm.boxed_result("java/lang/Byte", Value::Int(int(arguments, 0)?)),
+        ("java/lang/Double", "valueOf", "(D)Ljava/lang/Double;") =>
+            |vm, _, arguments| vm.boxed_result("java/lang/Double", Value::Double(double(arguments, 0)?)),
+        ("java/lang/Float", "valueOf", "(F)Ljava/lang/Float;") =>
+            |vm, _, arguments| vm.boxed_result("java/lang/Float", Value::Float(float(arguments, 0)?)),
+        ("java/lang/Boolean", "valueOf", "(Z)Ljava/lang/Boolean;") =>
+            |vm, _, arguments| vm.boxed_result("java/lang/Boolean", Value::Int((int(arguments, 0)? != 0) as i32)),
+        ("java/lang/Character", "valueOf", "(C)Ljava/lang/Character;") =>
+            |vm, _, arguments| vm.boxed_result("java/lang/Character", Value::Int(int(arguments, 0)?)),
+        ("java/lang/Integer", "parseInt", "(Ljava/lang/String;)I" | "(Ljava/lang/String;I)I") => |vm, _, arguments| {
+            let radix = match arguments.len() {
+                2 => int(arguments, 1)?,
+                _ => 10,
+            };
+            int_result(parse_integer(vm, reference(arguments, 0)?, radix, |text, radix| i32::from_str_radix(text, radix).ok())?)
+        },
+        ("java/lang/Integer", "valueOf", "(Ljava/lang/String;)Ljava/lang/Integer;") => |vm, _, arguments| {
+            let value = parse_integer(vm, reference(arguments, 0)?, 10, |text, radix| i32::from_str_radix(text, radix).ok())?;
+            vm.boxed_result("java/lang/Integer", Value::Int(value))
+        },
+        ("java/lang/Long", "parseLong", "(Ljava/lang/String;)J") => |vm, _, arguments|
+            long_result(parse_integer(vm, reference(arguments, 0)?, 10, |text, radix| i64::from_str_radix(text, radix).ok())?),
+        ("java/lang/Double", "parseDouble", "(Ljava/lang/String;)D") =>
+            |vm, _, arguments| double_result(parse_decimal(vm, reference(arguments, 0)?, |text| text.parse::<f64>().ok())?),
+        ("java/lang/Double", "valueOf", "(Ljava/lang/String;)Ljava/lang/Double;") => |vm, _, arguments| {
+            let value = parse_decimal(vm, reference(arguments, 0)?, |text| text.parse::<f64>().ok())?;
+            vm.boxed_result("java/lang/Double", Value::Double(value))
+        },
+        ("java/lang/Float", "parseFloat", "(Ljava/lang/String;)F") =>
+            |vm, _, arguments| float_result(parse_decimal(vm, reference(arguments, 0)?, |text| text.parse::<f32>().ok())?),
+        ("java/lang/Boolean", "parseBoolean", "(Ljava/lang/String;)Z") => |vm, _, arguments| {
+            let text = match reference(arguments, 0)? {
+                Some(string) => to_rust_string(&vm.chars(string)?),
+                None => String::new(),
+            };
+            bool_result(text.eq_ignore_ascii_case("true"))
+        },
+        ("java/lang/Integer", "toString", "(I)Ljava/lang/String;") | ("java/lang/Long", "toString", "(J)Ljava/lang/String;") |
+        ("java/lang/Double", "toString", "(D)Ljava/lang/String;") | ("java/lang/Float", "toString", "(F)Ljava/lang/String;") |
+        ("java/lang/Boolean", "toString", "(Z)Ljava/lang/String;") | ("java/lang/Character", "toString", "(C)Ljava/lang/String;") =>
+            |vm, descriptor, arguments| {
+                let parameter = parameter_type(descriptor)?.ok_or_else(|| bad_code("Missing parameter"))?;
+                let chars = vm.string_of(argument(arguments, 0)?, &parameter)?;
+                vm.string_result(chars)
+            },
+        ("java/lang/Integer", "toBinaryString", "(I)Ljava/lang/String;") =>
+            |vm, _, arguments| vm.string_result(utf16(&format!("{:b}", int(arguments, 0)?))),
+        ("java/lang/Integer", "toOctalString", "(I)Ljava/lang/String;") =>
+            |vm, _, arguments| vm.string_result(utf16(&format!("{:o}", int(arguments, 0)?))),
+        ("java/lang/Integer", "toHexString", "(I)Ljava/lang/String;") =>
+            |vm, _, arguments| vm.string_result(utf16(&format!("{:x}", int(arguments, 0)?))),
+        ("java/lang/Long", "toBinaryString", "(J)Ljava/lang/String;") =>
+            |vm, _, arguments| vm.string_result(utf16(&format!("{:b}", long(arguments, 0)?))),
+        ("java/lang/Long", "toHexString", "(J)Ljava/lang/String;") =>
+            |vm, _, arguments| vm.string_result(utf16(&format!("{:x}", long(arguments, 0)?))),
+        ("java/lang/Integer", "compare", "(II)I") | ("java/lang/Character", "compare", "(CC)I") |
+        ("java/lang/Boolean", "compare", "(ZZ)I") => |_, _, arguments| int_result(int(arguments, 0)?.cmp(&int(arguments, 1)?) as i32),
+        ("java/lang/Long", "compare", "(JJ)I") => |_, _, arguments| int_result(long(arguments, 0)?.cmp(&long(arguments, 1)?) as i32),
+        ("java/lang/Double", "compare", "(DD)I") =>
+            |_, _, arguments| int_result(java_compare(argument(arguments, 0)?, argument(arguments, 1)?)),
+        ("java/lang/Float", "compare", "(FF)I") =>
+            |_, _, arguments| int_result(java_compare(argument(arguments, 0)?, argument(arguments, 1)?)),
+        ("java/lang/Integer", "sum", "(II)I") => |_, _, arguments| int_result(int(arguments, 0)?.wrapping_add(int(arguments, 1)?)),
+        ("java/lang/Integer", "max", "(II)I") => |_, _, arguments| int_result(int(arguments, 0)?.max(int(arguments, 1)?)),
+        ("java/lang/Integer", "min", "(II)I") => |_, _, arguments| int_result(int(arguments, 0)?.min(int(arguments, 1)?)),
+        ("java/lang/Integer", "signum", "(I)I") => |_, _, arguments| int_result(int(arguments, 0)?.signum()),
+        ("java/lang/Integer", "bitCount", "(I)I") => |_, _, arguments| int_result(int(arguments, 0)?.count_ones() as i32),
+        ("java/lang/Integer", "hashCode", "(I)I") => |_, _, arguments| int_result(int(arguments, 0)?),
+        ("java/lang/Double", "isNaN", "(D)Z") => |_, _, arguments| bool_result(double(arguments, 0)?.is_nan()),
+        ("java/lang/Double", "isInfinite", "(D)Z") => |_, _, arguments| bool_result(double(arguments, 0)?.is_infinite()),
+        ("java/lang/Double", "isFinite", "(D)Z") => |_, _, arguments| bool_result(double(arguments, 0)?.is_finite()),
+        ("java/lang/Double", "isNaN", "()Z") => |vm, _, arguments| match vm.unboxed(this(arguments)?)? {
+            Value::Double(value) => bool_result(value.is_nan()),
+            _ => Err(bad_code("Expecting a Double")),
+        },
+        ("java/lang/Double", "doubleToLongBits", "(D)J") => |_, _, arguments| long_result(double_bits(double(arguments, 0)?)),
+        ("java/lang/Double", "doubleToRawLongBits", "(D)J") => |_, _, arguments| long_result(double(arguments, 0)?.to_bits() as i64),
+        ("java/lang/Double", "longBitsToDouble", "(J)D") => |_, _, arguments| double_result(f64::from_bits(long(arguments, 0)? as u64)),
+        ("java/lang/Float", "isNaN", "(F)Z") => |_, _, arguments| bool_result(float(arguments, 0)?.is_nan()),
+        ("java/lang/Float", "floatToIntBits", "(F)I") => |_, _, arguments| int_result(float_bits(float(arguments, 0)?)),
+        ("java/lang/Float", "intBitsToFloat", "(I)F") => |_, _, arguments| float_result(f32::from_bits(int(arguments, 0)? as u32)),
+
+        ("java/lang/Character", "isDigit", "(C)Z") => |_, _, arguments| bool_result(char_of(arguments)?.is_numeric()),
+        ("java/lang/Character", "isLetter", "(C)Z") => |_, _, arguments| bool_result(char_of(arguments)?.is_alphabetic()),
+        ("java/lang/Character", "isLetterOrDigit", "(C)Z") => |_, _, arguments| bool_result(char_of(arguments)?.is_alphanumeric()),
+        ("java/lang/Character", "isAlphabetic", "(I)Z") => |_, _, arguments| bool_result(char_of(arguments)?.is_alphabetic()),
+        ("java/lang/Character", "isWhitespace", "(C)Z") => |_, _, arguments| {
+            // Java doesn't count the no-break spaces
+            let c = char_of(arguments)?;
+            bool_result(c.is_whitespace() && !matches!(c, '\u{a0}' | '\u{2007}' | '\u{202f}'))
+        },
+        ("java/lang/Character", "isUpperCase", "(C)Z") => |_, _, arguments| bool_result(char_of(arguments)?.is_uppercase()),
+        ("java/lang/Character", "isLowerCase", "(C)Z") => |_, _, arguments| bool_result(char_of(arguments)?.is_lowercase()),
+        ("java/lang/Character", "toUpperCase", "(C)C") => |_, _, arguments| int_result(map_char(arguments, char::to_uppercase)?),
+        ("java/lang/Character", "toLowerCase", "(C)C") => |_, _, arguments| int_result(map_char(arguments, char::to_lowercase)?),
+        ("java/lang/Character", "getNumericValue", "(C)I") =>
+            |_, _, arguments| int_result(char_of(arguments)?.to_digit(36).map_or(-1, |digit| digit as i32)),
+        ("java/lang/Character", "digit", "(CI)I") => |_, _, arguments| {
+            let radix = int(arguments, 1)?;
+            int_result(match (2..=36).contains(&radix) {
+                true => char_of(arguments)?.to_digit(radix as u32).map_or(-1, |digit| digit as i32),
+                false => -1,
+            })
+        },
+        ("java/lang/Character", "forDigit", "(II)C") => |_, _, arguments| {
+            let (digit, radix) = (int(arguments, 0)?, int(arguments, 1)?);
+            int_result(match (2..=36).contains(&radix) && digit >= 0 {
+                true => char::from_digit(digit as u32, radix as u32).map_or(0, |c| c as i32),
+                false => 0,
+            })
+        },
+
+        ("java/util/Objects", "requireNonNull", "(Ljava/lang/Object;)Ljava/lang/Object;") => |_, _, arguments| match reference(arguments, 0)? {
+            Some(object) => reference_result(Some(object)),
+            None => Err(null_pointer()),
+        },
+        ("java/util/Objects", "requireNonNull", "(Ljava/lang/Object;Ljava/lang/String;)Ljava/lang/Object;") => |vm, _, arguments| {
+            match reference(arguments, 0)? {
+                Some(object) => reference_result(Some(object)),
+                None => {
+                    let message = vm.string_value_of(reference(arguments, 1)?)?;
+                    Err(Unwind::Raise("java/lang/NullPointerException", Some(to_rust_string(&message))))
+                }
+            }
+        },
+        ("java/util/Objects", "equals", "(Ljava/lang/Object;Ljava/lang/Object;)Z") =>
+            |vm, _, arguments| bool_result(vm.equals(reference(arguments, 0)?, reference(arguments, 1)?)?),
+        ("java/util/Objects", "hashCode", "(Ljava/lang/Object;)I") => |vm, _, arguments| int_result(vm.hash_code(reference(arguments, 0)?)?),
+        ("java/util/Objects", "hash", "([Ljava/lang/Object;)I") => |vm, _, arguments| {
+            let Some(array) = reference(arguments, 0)? else {
+                return int_result(0);
+            };
+            let Object::Array { values, .. } = vm.heap.get(array) else {
+                return Err(bad_code("Expecting an array"));
+            };
+            let mut hash = 1i32;
+            for value in values.clone() {
+                let Value::Reference(element) = value else {
+                    return Err(bad_code("Expecting a reference array"));
+                };
+                hash = hash.wrapping_mul(31).wrapping_add(vm.hash_code(element)?);
+            }
+            int_result(hash)
+        },
+        ("java/util/Objects", "toString", "(Ljava/lang/Object;)Ljava/lang/String;") => |vm, _, arguments| {
+            let chars = vm.string_value_of(reference(arguments, 0)?)?;
+            vm.string_result(chars)
+        },
+        ("java/util/Objects", "isNull", "(Ljava/lang/Object;)Z") => |_, _, arguments| bool_result(reference(arguments, 0)?.is_none()),
+        ("java/util/Objects", "nonNull", "(Ljava/lang/Object;)Z") => |_, _, arguments| bool_result(reference(arguments, 0)?.is_some()),
+
+        ("java/lang/Class", "getName", "()Ljava/lang/String;") => |vm, _, arguments| {
+            let name = java_name(&class_object_name(vm, arguments)?);
+            vm.string_result(utf16(&name))
+        },
+        ("java/lang/Class", "getSimpleName", "()Ljava/lang/String;") => |vm, _, arguments| {
+            let name = class_object_name(vm, arguments)?;
+            let dimensions = name.chars().take_while(|&c| c == '[').count();
+            let simple_name = match field_type(&name[dimensions..]) {
+                Some(FieldType::Object(class_name)) => class_name.rsplit(['/', '$']).next().unwrap_or_default().to_string(),
+                Some(primitive) => primitive_name(&primitive).to_string(),
+                None => name.rsplit(['/', '$']).next().unwrap_or_default().to_string(),
+            };
+            vm.string_result(utf16(&format!("{}{}", simple_name, "[]".repeat(dimensions))))
+        },
+        ("java/lang/Class", "toString", "()Ljava/lang/String;") => |vm, _, arguments| {
+            let name = class_object_name(vm, arguments)?;
+            let is_interface = vm.load_class(&name).is_ok_and(|class| class.is_interface());
+            let kind = if is_interface { "interface" } else { "class" };
+            vm.string_result(utf16(&format!("{} {}", kind, java_name(&name))))
+        },
+        ("java/lang/Class", "isArray", "()Z") => |vm, _, arguments| bool_result(class_object_name(vm, arguments)?.starts_with('[')),
+        ("java/lang/Class", "isInstance", "(Ljava/lang/Object;)Z") => |vm, _, arguments| {
+            let name = class_object_name(vm, arguments)?;
+            match reference(arguments, 1)? {
+                Some(object) => bool_result(vm.is_instance_of(object, &name)?),
+                None => bool_result(false),
+            }
+        },
+        // Assertions are never enabled: there is no -ea
+        ("java/lang/Class", "desiredAssertionStatus", "()Z") => |_, _, _| bool_result(false),
+
+        ("java/lang/Enum", "<init>", "(Ljava/lang/String;I)V") => |vm, _, arguments| {
+            let constant = this(arguments)?;
+            vm.set_field(constant, "java/lang/Enum", "name", argument(arguments, 1)?);
+            vm.set_field(constant, "java/lang/Enum", "ordinal", argument(arguments, 2)?);
+            Ok(None)
+        },
+        ("java/lang/Enum", "name" | "toString", "()Ljava/lang/String;") =>
+            |vm, _, arguments| Ok(Some(vm.get_field(this(arguments)?, "java/lang/Enum", "name").unwrap_or(Value::Reference(None)))),
+        ("java/lang/Enum", "ordinal", "()I") =>
+            |vm, _, arguments| Ok(Some(vm.get_field(this(arguments)?, "java/lang/Enum", "ordinal").unwrap_or(Value::Int(0)))),
+        ("java/lang/Enum", "compareTo", "(Ljava/lang/Enum;)I" | "(Ljava/lang/Object;)I") => |vm, _, arguments| {
+            let other = reference(arguments, 1)?.ok_or_else(null_pointer)?;
+            match (vm.get_field(this(arguments)?, "java/lang/Enum", "ordinal"), vm.get_field(other, "java/lang/Enum", "ordinal")) {
+                (Some(Value::Int(ordinal)), Some(Value::Int(other))) => int_result(ordinal - other),
+                _ => Err(Unwind::Raise("java/lang/ClassCastException", None)),
+            }
+        },
+        ("java/lang/Enum", "getDeclaringClass", "()Ljava/lang/Class;") => |vm, _, arguments| {
+            let class = vm.load_class(&vm.heap.get(this(arguments)?).class_name())?;
+            // Constants with a body are instances of an anonymous subclass of the enum
+            let name = match class.super_class.as_deref() {
+                Some("java/lang/Enum") | None => class.name.clone(),
+                Some(super_class) => super_class.to_string(),
+            };
+            reference_result(Some(vm.class_object(&name)))
+        },
+        ("java/lang/Enum", "valueOf", "(Ljava/lang/Class;Ljava/lang/String;)Ljava/lang/Enum;") => |vm, _, arguments| {
+            let class_object = reference(arguments, 0)?.ok_or_else(null_pointer)?;
+            let Some(name) = reference(arguments, 1)? else {
+                return Err(Unwind::Raise("java/lang/NullPointerException", Some("Name is null".to_string())));
+            };
+            let name = vm.chars(name)?;
+            vm.enum_constant(class_object, &name)
+        },
+        ("java/lang/Record", "<init>", "()V") => |_, _, _| Ok(None),
+        _ => return None,
+    };
+    Some(native)
+}
+
+fn argument(arguments: &[Value], index: usize) -> Result<Value, Unwind> {
+    arguments.get(index).copied().ok_or_else(|| bad_code("Missing argument"))
+}
+
+fn int(arguments: &[Value], index: usize) -> Result<i32, Unwind> {
+    match argument(arguments, index)? {
+        Value::Int(value) => Ok(value),
+        _ => Err(bad_code("Expecting an int argument")),
+    }
+}
+
+fn long(arguments: &[Value], index: usize) -> Result<i64, Unwind> {
+    match argument(arguments, index)? {
+        Value::Long(value) => Ok(value),
+        _ => Err(bad_code("Expecting a long argument")),
+    }
+}
+
+fn float(arguments: &[Value], index: usize) -> Result<f32, Unwind> {
+    match argument(arguments, index)? {
+        Value::Float(value) => Ok(value),
+        _ => Err(bad_code("Expecting a float argument")),
+    }
+}
+
+fn double(arguments: &[Value], index: usize) -> Result<f64, Unwind> {
+    match argument(arguments, index)? {
+        Value::Double(value) => Ok(value),
+        _ => Err(bad_code("Expecting a double argument")),
+    }
+}
+
+fn reference(arguments: &[Value], index: usize) -> Result<Option<Reference>, Unwind> {
+    match argument(arguments, index)? {
+        Value::Reference(reference) => Ok(reference),
+        _ => Err(bad_code("Expecting a reference argument")),
+    }
+}
+
+// The object an instance method is called on, which the invoke instructions have checked isn't null
+fn this(arguments: &[Value]) -> Result<Reference, Unwind> {
+    reference(arguments, 0)?.ok_or_else(|| bad_code("Missing this"))
+}
+
+fn char_of(arguments: &[Value]) -> Result<char, Unwind> {
+    Ok(char::from_u32(int(arguments, 0)? as u32).unwrap_or('\u{fffd}'))
+}
+
+// Character.toUpperCase and toLowerCase, which leave a char alone if it maps to more than one
+fn map_char<I: Iterator<Item = char>>(arguments: &[Value], map: fn(char) -> I) -> Result<i32, Unwind> {
+    let c = int(arguments, 0)?;
+    let Some(original) = char::from_u32(c as u32) else {
+        return Ok(c);
+    };
+    let mapped: Vec<char> = map(original).collect();
+    Ok(match mapped.as_slice() {
+        [single] if (*single as u32) <= 0xffff => *single as i32,
+        _ => c,
+    })
+}
+
+fn class_object_name(vm: &Vm, arguments: &[Value]) -> Result<String, Unwind> {
+    match vm.heap.get(this(arguments)?) {
+        Object::Class(name) => Ok(name.clone()),
+        _ => Err(bad_code("Expecting a Class")),
+    }
+}
+
+fn int_result(value: i32) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Int(value)))
+}
+
+fn bool_result(value: bool) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Int(value as i32)))
+}
+
+fn long_result(value: i64) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Long(value)))
+}
+
+fn float_result(value: f32) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Float(value)))
+}
+
+fn double_result(value: f64) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Double(value)))
+}
+
+fn reference_result(value: Option<Reference>) -> Result<Option<Value>, Unwind> {
+    Ok(Some(Value::Reference(value)))
+}
+
+fn utf16(text: &str) -> Vec<u16> {
+    text.encode_utf16().collect()
+}
+
+fn null_pointer() -> Unwind {
+    Unwind::Raise("java/lang/NullPointerException", None)
+}
+
+fn string_index(message: String) -> Unwind {
+    Unwind::Raise("java/lang/StringIndexOutOfBoundsException", Some(message))
+}
+
+// The type of the only (or first) parameter of a method, None if it has none
+fn parameter_type(descriptor: &str) -> Result<Option<FieldType>, Unwind> {
+    MethodDescriptor::parse(descriptor)
+        .map(|descriptor| descriptor.parameters.into_iter().next())
+        .map_err(|error| Unwind::Raise("java/lang/ClassFormatError", Some(error.to_string())))
+}
+
+fn field_type(descriptor: &str) -> Option<FieldType> {
+    FieldType::parse(descriptor).ok()
+}
+
+fn primitive_type(value: Value) -> FieldType {
+    match value {
+        Value::Long(_) => FieldType::Long,
+        Value::Float(_) => FieldType::Float,
+        Value::Double(_) => FieldType::Double,
+        _ => FieldType::Int,
+    }
+}
+
+fn primitive_name(field_type: &FieldType) -> &'static str {
+    match field_type {
+        FieldType::Byte => "byte",
+        FieldType::Char => "char",
+        FieldType::Double => "double",
+        FieldType::Float => "float",
+        FieldType::Int => "int",
+        FieldType::Long => "long",
+        FieldType::Short => "short",
+        FieldType::Boolean => "boolean",
+        FieldType::Object(_) | FieldType::Array(_) => "",
+    }
+}
+
+// The primitive type a box class holds
+fn box_type(class_name: &str) -> FieldType {
+    match class_name {
+        "java/lang/Boolean" => FieldType::Boolean,
+        "java/lang/Character" => FieldType::Char,
+        "java/lang/Long" => FieldType::Long,
+        "java/lang/Float" => FieldType::Float,
+        "java/lang/Double" => FieldType::Double,
+        _ => FieldType::Int,
+    }
+}
+
+// hashCode of the box for a primitive value
+fn primitive_hash(value: Value, field_type: &FieldType) -> i32 {
+    match value {
+        Value::Int(value) if *field_type == FieldType::Boolean => if value != 0 { 1231 } else { 1237 },
+        Value::Int(value) => value,
+        Value::Long(value) => (value ^ ((value as u64) >> 32) as i64) as i32,
+        Value::Float(value) => float_bits(value),
+        Value::Double(value) => {
+            let bits = double_bits(value);
+            (bits ^ ((bits as u64) >> 32) as i64) as i32
+        }
+        _ => 0,
+    }
+}
+
+// Double.doubleToLongBits, with one NaN for all of them
+fn double_bits(value: f64) -> i64 {
+    match value.is_nan() {
+        true => 0x7ff8000000000000,
+        false => value.to_bits() as i64,
+    }
+}
+
+fn float_bits(value: f32) -> i32 {
+    match value.is_nan() {
+        true => 0x7fc00000,
+        false => value.to_bits() as i32,
+    }
+}
+
+// compareTo of the boxes: -0.0 is less than 0.0 and NaN is greater than everything, itself excepted
+fn java_compare(value: Value, other: Value) -> i32 {
+    match (value, other) {
+        (Value::Int(a), Value::Int(b)) => a.cmp(&b) as i32,
+        (Value::Long(a), Value::Long(b)) => a.cmp(&b) as i32,
+        (Value::Float(a), Value::Float(b)) => match a.partial_cmp(&b) {
+            Some(std::cmp::Ordering::Equal) | None => float_bits(a).cmp(&float_bits(b)) as i32,
+            Some(ordering) => ordering as i32,
+        },
+        (Value::Double(a), Value::Double(b)) => match a.partial_cmp(&b) {
+            Some(std::cmp::Ordering::Equal) | None => double_bits(a).cmp(&double_bits(b)) as i32,
+            Some(ordering) => ordering as i32,
+        },
+        _ => 1,
+    }
+}
+
+// Math.max: NaN if either is, and 0.0 is greater than -0.0
+fn java_max(a: f64, b: f64) -> f64 {
+    match (a.is_nan(), b.is_nan()) {
+        (true, _) => a,
+        (_, true) => b,
+        _ if a == 0.0 && b == 0.0 => if a.is_sign_negative() { b } else { a },
+        _ => a.max(b),
+    }
+}
+
+// A simple case folding for equalsIgnoreCase, which compares chars upper-cased and then lower-cased
+fn fold_case(c: u16) -> u16 {
+    let mapped = |c: u16, map: fn(char) -> char| char::from_u32(c as u32).map_or(c, |c| {
+        let mapped = map(c) as u32;
+        if mapped <= 0xffff { mapped as u16 } else { c as u32 as u16 }
+    });
+    mapped(mapped(c, |c| c.to_uppercase().next().unwrap_or(c)), |c| c.to_lowercase().next().unwrap_or(c))
+}
+
+fn find(chars: &[u16], target: &[u16], from: usize) -> Option<usize> {
+    if target.is_empty() {
+        return Some(from.min(chars.len()));
+    }
+    (from..chars.len()).find(|&index| chars[index..].starts_with(target))
+}
+
+fn rfind(chars: &[u16], target: &[u16]) -> Option<usize> {
+    (0..=chars.len()).rev().find(|&index| chars[index..].starts_with(target))
+}
+
+fn replace(chars: &[u16], target: &[u16], replacement: &[u16]) -> Vec<u16> {
+    let mut result = Vec::new();
+    let mut index = 0;
+    // An empty target matches before every char and at the end
+    if target.is_empty() {
+        for &c in chars {
+            result.extend_from_slice(replacement);
+            result.push(c);
+        }
+        result.extend_from_slice(replacement);
+        return result;
+    }
+    while index < chars.len() {
+        match chars[index..].starts_with(target) {
+            true => {
+                result.extend_from_slice(replacement);
+                index += target.len();
+            }
+            false => {
+                result.push(chars[index]);
+                index += 1;
+            }
+        }
+    }
+    result
+}
+
+// Integer.parseInt and Long.parseLong: an optional sign and digits, nothing else
+fn parse_integer<T>(vm: &Vm, string: Option<Reference>, radix: i32, parse: fn(&str, u32) -> Option<T>) -> Result<T, Unwind> {
+    let number_format = |message: String| Unwind::Raise("java/lang/NumberFormatException", Some(message));
+    let Some(string) = string else {
+        return Err(number_format("Cannot parse null string: null".to_string()));
+    };
+    if !(2..=36).contains(&radix) {
+        return Err(number_format(format!("radix {} out of range", radix)));
+    }
+    let text = to_rust_string(&vm.chars(string)?);
+    let digits = text.strip_prefix(['+', '-']).unwrap_or(&text);
+    let valid = !digits.is_empty() && digits.chars().all(|c| c.is_digit(radix as u32));
+    match parse(&text, radix as u32).filter(|_| valid) {
+        Some(value) => Ok(value),
+        None => Err(number_format(match radix {
+            10 => format!("For input string: \"{}\"", text),
+            radix => format!("For input string: \"{}\" under radix {}", text, radix),
+        })),
+    }
+}
+
+// Double.parseDouble and Float.parseFloat: Java's decimal notation, with leading and trailing whitespace,
+// an optional f or d suffix, NaN and Infinity
+fn parse_decimal<T>(vm: &Vm, string: Option<Reference>, parse: fn(&str) -> Option<T>) -> Result<T, Unwind> {
+    let Some(string) = string else {
+        return Err(null_pointer());
+    };
+    let text = to_rust_string(&vm.chars(string)?);
+    let trimmed = text.trim_matches(|c: char| c <= ' ');
+    if trimmed.is_empty() {
+        return Err(Unwind::Raise("java/lang/NumberFormatException", Some("empty String".to_string())));
+    }
+    let unsigned = trimmed.strip_prefix(['+', '-']).unwrap_or(trimmed);
+    let number = match unsigned {
+        "NaN" | "Infinity" => Some(trimmed),
+        _ => {
+            let number = trimmed.strip_suffix(['f', 'F', 'd', 'D']).unwrap_or(trimmed);
+            let unsigned = number.strip_prefix(['+', '-']).unwrap_or(number);
+            // Rust also takes inf and nan, Java doesn't
+            let plain = unsigned.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'));
+            Some(number).filter(|_| plain && unsigned.chars().any(|c| c.is_ascii_digit()))
+        }
+    };
+    match number.map(|number| number.replace("Infinity", "inf")).and_then(|number| parse(&number)) {
+        Some(value) => Ok(value),
+        None => Err(Unwind::Raise("java/lang/NumberFormatException", Some(format!("For input string: \"{}\"", trimmed)))),
+    }
+}
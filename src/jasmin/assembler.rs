@@ -0,0 +1,1191 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::attr::{Attribute, AttributeInfo, BootstrapMethod, ExceptionTable, InnerClass, LineNumberTableEntry,
+                  LocalVariableTableEntry, StackMapFrame, StackMapFrameKind, VerificationTypeInfo};
+use crate::bytecode::{self, Instruction, WideInstruction};
+use crate::class::{ClassAccessFlags, ClassFile};
+use crate::constant_pool::{ConstantPool, ConstantPoolInfo};
+use crate::descriptor::MethodDescriptor;
+use crate::field::{FieldAccessFlags, FieldInfo};
+use crate::method::{MethodAccessFlags, MethodInfo};
+use crate::util::mutf8::ModifiedUtf8;
+
+use super::{AssemblyError, AssemblyErrorKind, ARRAY_TYPES, CLASS_FLAGS, FIELD_FLAGS, INNER_CLASS_FLAGS, METHOD_FLAGS,
+            REFERENCE_KINDS};
+
+pub(super) struct Assembler {
+    constant_pool: ConstantPool,
+    // Where each constant in the pool is, so none goes in twice
+    constant_indexes: HashMap<ConstantPoolInfo, u16>,
+    // Set once a constant didn't fit in the pool, which fails the assembly at the end
+    too_many_constants: bool,
+    // The instructions without operands, by mnemonic
+    simple_instructions: HashMap<&'static str, Instruction>,
+    minor_version: u16,
+    major_version: u16,
+    // None until the .class or .interface directive
+    access_flags: Option<ClassAccessFlags>,
+    this_class: u16,
+    super_class: u16,
+    interfaces: Vec<u16>,
+    fields: Vec<FieldInfo>,
+    methods: Vec<MethodInfo>,
+    attributes: Vec<Attribute>,
+    bootstrap_methods: Vec<BootstrapMethod>,
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Word(String),
+    // A "quoted string", as UTF-16 so it can hold any Java string
+    Quoted(Vec<u16>),
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    number: usize,
+    tokens: Vec<Token>,
+    // The next token to be read
+    position: usize,
+}
+
+// The type a number in a constant gets
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NumberType {
+    Int,
+    Float,
+    Long,
+    Double,
+}
+
+// The method between .method and .end method
+struct Method {
+    // Of the .method directive
+    line: usize,
+    access_flags: MethodAccessFlags,
+    // name(parameters)return, for error messages
+    signature: String,
+    name_index: u16,
+    descriptor_index: u16,
+    descriptor: String,
+    max_stack: Option<u16>,
+    max_locals: Option<u16>,
+    // One past the highest local variable the instructions use
+    locals_used: u16,
+    // Whether anything that goes in a Code attribute was written
+    has_code: bool,
+    instructions: Vec<PendingInstruction>,
+    // The index of the instruction each label is in front of
+    labels: HashMap<String, usize>,
+    // catch_type and the labels of start_pc, end_pc and handler_pc
+    exception_tables: Vec<(usize, u16, [String; 3])>,
+    // The index of the instruction each line number starts at
+    line_numbers: Vec<(usize, u16)>,
+    // Slot, name_index, descriptor_index and the labels of the start and end of the range
+    local_variables: Vec<(usize, u16, u16, u16, [String; 2])>,
+    // The index of the instruction each frame is for, and the labels of its uninitialized types in order
+    frames: Vec<(usize, usize, StackMapFrameKind, Vec<String>)>,
+    attributes: Vec<Attribute>,
+}
+
+// An instruction whose branch targets are still labels
+struct PendingInstruction {
+    line: usize,
+    instruction: Instruction,
+    // In the order of Instruction::branch_targets
+    labels: Vec<String>,
+}
+
+impl Assembler {
+    pub(super) fn new() -> Assembler {
+        // Every instruction without operands decodes from its opcode alone
+        let mut simple_instructions = HashMap::new();
+        for opcode in 0..=u8::MAX {
+            if let Ok(instructions) = bytecode::decode(&[opcode]) {
+                for (_, instruction) in instructions {
+                    simple_instructions.insert(instruction.mnemonic(), instruction);
+                }
+            }
+        }
+        Assembler {
+            constant_pool: ConstantPool::new(),
+            constant_indexes: HashMap::new(),
+            too_many_constants: false,
+            simple_instructions,
+            minor_version: 3,
+            major_version: 45,
+            access_flags: None,
+            this_class: 0,
+            super_class: 0,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+            bootstrap_methods: Vec::new(),
+        }
+    }
+
+    pub(super) fn assemble(mut self, source: &str) -> Result<ClassFile, AssemblyError> {
+        let mut lines = Vec::new();
+        for (i, text) in source.lines().enumerate() {
+            let line = tokenize(i + 1, text)?;
+            if !line.tokens.is_empty() {
+                lines.push(line);
+            }
+        }
+        // The constants of ldc instructions go in first, so their indexes fit in its single byte
+        for line in &lines {
+            let mut line = line.clone();
+            if line.peek_word().is_some_and(is_label) {
+                line.position += 1;
+            }
+            if line.peek_word() == Some("ldc") {
+                line.position += 1;
+                self.loadable_constant(&mut line, NumberType::Int, NumberType::Float)?;
+            }
+        }
+
+        let mut method: Option<Method> = None;
+        let mut lines = lines.into_iter();
+        while let Some(mut line) = lines.next() {
+            let mut word = line.text("a directive or instruction")?;
+            if is_label(&word) {
+                let method = method.as_mut().ok_or_else(|| line.error(AssemblyErrorKind::Misplaced(word.clone())))?;
+                let label = word.trim_end_matches(':').to_string();
+                if method.labels.insert(label.clone(), method.instructions.len()).is_some() {
+                    return Err(line.error(AssemblyErrorKind::DuplicateLabel(label)));
+                }
+                method.has_code = true;
+                if line.at_end() {
+                    continue;
+                }
+                word = line.text("an instruction")?;
+            }
+            match (word.as_str(), method.as_mut()) {
+                (".method", None) => method = Some(self.method(&mut line)?),
+                (".end", Some(_)) => {
+                    line.keyword("method")?;
+                    line.end()?;
+                    if let Some(method) = method.take() {
+                        self.end_method(method)?;
+                    }
+                }
+                (directive, None) if directive.starts_with('.') => self.class_directive(directive, &mut line)?,
+                (directive, Some(method)) if directive.starts_with('.') =>
+                    self.method_directive(method, directive, &mut line)?,
+                (mnemonic, Some(method)) => self.instruction(method, mnemonic, &mut line, &mut lines)?,
+                (mnemonic, None) => return Err(line.error(AssemblyErrorKind::Misplaced(mnemonic.to_string()))),
+            }
+        }
+        if let Some(method) = method {
+            return Err(AssemblyError { line: method.line, kind: AssemblyErrorKind::MissingEndMethod });
+        }
+        let access_flags = self.access_flags.ok_or(AssemblyError { line: 0, kind: AssemblyErrorKind::MissingClass })?;
+        if !self.bootstrap_methods.is_empty() {
+            let bootstrap_methods = std::mem::take(&mut self.bootstrap_methods);
+            let attribute = self.attribute(AttributeInfo::BootstrapMethods { bootstrap_methods });
+            self.attributes.push(attribute);
+        }
+        // Spec: constant_pool_count is a u2
+        if self.too_many_constants {
+            return Err(AssemblyError { line: 0, kind: AssemblyErrorKind::TooManyConstants });
+        }
+        Ok(ClassFile {
+            minor_version: self.minor_version,
+            major_version: self.major_version,
+            constant_pool: self.constant_pool,
+            access_flags,
+            this_class: self.this_class,
+            super_class: self.super_class,
+            interfaces: self.interfaces,
+            fields: self.fields,
+            methods: self.methods,
+            attributes: self.attributes,
+        })
+    }
+
+    fn class_directive(&mut self, directive: &str, line: &mut Line) -> Result<(), AssemblyError> {
+        match directive {
+            ".bytecode" => {
+                let version = line.text("a version like 61.0")?;
+                let (major, minor) = version.split_once('.').unwrap_or((&version, "0"));
+                self.major_version = parse_number(line, major)?;
+                self.minor_version = parse_number(line, minor)?;
+            }
+            ".source" => {
+                let source_file = line.text("a file name")?;
+                let sourcefile_index = self.utf8(&source_file);
+                let attribute = self.attribute(AttributeInfo::SourceFile { sourcefile_index });
+                self.attributes.push(attribute);
+            }
+            ".class" | ".interface" => {
+                let mut flags = flags(line, &CLASS_FLAGS)?;
+                if directive == ".interface" {
+                    flags |= ClassAccessFlags::INTERFACE.0;
+                }
+                if line.tokens.len() - line.position > 1 {
+                    let flag = line.text("a flag")?;
+                    return Err(line.error(AssemblyErrorKind::UnknownFlag(flag)));
+                }
+                let name = line.text("a class name")?;
+                self.access_flags = Some(ClassAccessFlags(flags));
+                self.this_class = self.class(&name);
+            }
+            ".super" => {
+                let name = line.text("a class name")?;
+                self.super_class = self.class(&name);
+            }
+            ".implements" => {
+                let name = line.text("an interface name")?;
+                let interface = self.class(&name);
+                self.interfaces.push(interface);
+            }
+            ".signature" => {
+                let signature = self.signature(line)?;
+                self.attributes.push(signature);
+            }
+            ".inner" => {
+                let inner_class_access_flags = flags(line, &INNER_CLASS_FLAGS)?;
+                let name = line.text("a class name")?;
+                let inner_class_info_index = self.class(&name);
+                let outer_class_info_index = match line.skip_word("outer") {
+                    true => {
+                        let name = line.text("a class name")?;
+                        self.class(&name)
+                    }
+                    false => 0,
+                };
+                let inner_name_index = match line.skip_word("name") {
+                    true => {
+                        let name = line.text("a simple name")?;
+                        self.utf8(&name)
+                    }
+                    false => 0,
+                };
+                let inner_class = InnerClass {
+                    inner_class_info_index,
+                    outer_class_info_index,
+                    inner_name_index,
+                    inner_class_access_flags,
+                };
+                let classes = self.attributes.iter_mut().find_map(|attribute| match &mut attribute.info {
+                    AttributeInfo::InnerClasses { classes } => Some(classes),
+                    _ => None
+                });
+                match classes {
+                    Some(classes) => classes.push(inner_class),
+                    None => {
+                        let attribute = self.attribute(AttributeInfo::InnerClasses { classes: vec![inner_class] });
+                        self.attributes.push(attribute);
+                    }
+                }
+            }
+            ".nesthost" => {
+                let name = line.text("a class name")?;
+                let host_class_index = self.class(&name);
+                let attribute = self.attribute(AttributeInfo::NestHost { host_class_index });
+                self.attributes.push(attribute);
+            }
+            ".nestmember" => {
+                let name = line.text("a class name")?;
+                let member = self.class(&name);
+                let classes = self.attributes.iter_mut().find_map(|attribute| match &mut attribute.info {
+                    AttributeInfo::NestMembers { classes } => Some(classes),
+                    _ => None
+                });
+                match classes {
+                    Some(classes) => classes.push(member),
+                    None => {
+                        let attribute = self.attribute(AttributeInfo::NestMembers { classes: vec![member] });
+                        self.attributes.push(attribute);
+                    }
+                }
+            }
+            ".bootstrap" => {
+                let index: u16 = line.number("a bootstrap method index")?;
+                if index as usize != self.bootstrap_methods.len() {
+                    return Err(line.error(AssemblyErrorKind::BootstrapOutOfOrder(index)));
+                }
+                let bootstrap_method_ref = self.method_handle(line)?;
+                let mut bootstrap_arguments = Vec::new();
+                while !line.at_end() {
+                    bootstrap_arguments.push(self.loadable_constant(line, NumberType::Int, NumberType::Float)?);
+                }
+                self.bootstrap_methods.push(BootstrapMethod { bootstrap_method_ref, bootstrap_arguments });
+            }
+            ".field" => {
+                let access_flags = FieldAccessFlags(flags(line, &FIELD_FLAGS)?);
+                let name = line.text("a field name")?;
+                let descriptor = line.text("a field descriptor")?;
+                let name_index = self.utf8(&name);
+                let descriptor_index = self.utf8(&descriptor);
+                let mut attributes = Vec::new();
+                if line.peek_word() == Some("signature") {
+                    line.position += 1;
+                    attributes.push(self.signature(line)?);
+                }
+                if line.peek_word() == Some("=") {
+                    line.position += 1;
+                    // Bare numbers get the type of the field
+                    let (integer_type, decimal_type) = match descriptor.as_str() {
+                        "J" => (NumberType::Long, NumberType::Long),
+                        "F" => (NumberType::Float, NumberType::Float),
+                        "D" => (NumberType::Double, NumberType::Double),
+                        _ => (NumberType::Int, NumberType::Float),
+                    };
+                    let constantvalue_index = self.loadable_constant(line, integer_type, decimal_type)?;
+                    attributes.insert(0, self.attribute(AttributeInfo::ConstantValue { constantvalue_index }));
+                }
+                self.fields.push(FieldInfo { access_flags, name_index, descriptor_index, attributes });
+            }
+            _ => return Err(line.error(AssemblyErrorKind::UnknownDirective(directive.to_string()))),
+        }
+        line.end()
+    }
+
+    fn method(&mut self, line: &mut Line) -> Result<Method, AssemblyError> {
+        let access_flags = MethodAccessFlags(flags(line, &METHOD_FLAGS)?);
+        if line.tokens.len() - line.position > 1 {
+            let flag = line.text("a flag")?;
+            return Err(line.error(AssemblyErrorKind::UnknownFlag(flag)));
+        }
+        let signature = line.text("a method name and descriptor")?;
+        line.end()?;
+        let (name, descriptor) = signature.find('(')
+            .map(|paren| signature.split_at(paren))
+            .ok_or_else(|| line.error(AssemblyErrorKind::BadReference(signature.clone())))?;
+        Ok(Method {
+            line: line.number,
+            access_flags,
+            name_index: self.utf8(name),
+            descriptor_index: self.utf8(descriptor),
+            descriptor: descriptor.to_string(),
+            signature: signature.clone(),
+            max_stack: None,
+            max_locals: None,
+            locals_used: 0,
+            has_code: false,
+            instructions: Vec::new(),
+            labels: HashMap::new(),
+            exception_tables: Vec::new(),
+            line_numbers: Vec::new(),
+            local_variables: Vec::new(),
+            frames: Vec::new(),
+            attributes: Vec::new(),
+        })
+    }
+
+    fn method_directive(&mut self, method: &mut Method, directive: &str, line: &mut Line) -> Result<(), AssemblyError> {
+        match directive {
+            ".limit" => {
+                let limit = line.text("stack or locals")?;
+                let value = line.number("a limit")?;
+                match limit.as_str() {
+                    "stack" => method.max_stack = Some(value),
+                    "locals" => method.max_locals = Some(value),
+                    _ => return Err(line.error(AssemblyErrorKind::UnexpectedToken(limit))),
+                }
+                method.has_code = true;
+            }
+            ".throws" => {
+                let name = line.text("an exception class")?;
+                let exception = self.class(&name);
+                let exceptions = method.attributes.iter_mut().find_map(|attribute| match &mut attribute.info {
+                    AttributeInfo::Exceptions { exception_index_table } => Some(exception_index_table),
+                    _ => None
+                });
+                match exceptions {
+                    Some(exceptions) => exceptions.push(exception),
+                    None => {
+                        let attribute = self.attribute(AttributeInfo::Exceptions { exception_index_table: vec![exception] });
+                        method.attributes.push(attribute);
+                    }
+                }
+            }
+            ".signature" => {
+                let signature = self.signature(line)?;
+                method.attributes.push(signature);
+            }
+            ".line" => {
+                let line_number = line.number("a line number")?;
+                method.line_numbers.push((method.instructions.len(), line_number));
+                method.has_code = true;
+            }
+            ".catch" => {
+                let catch_class = line.text("an exception class or all")?;
+                let catch_type = match catch_class.as_str() {
+                    "all" => 0,
+                    name => self.class(name),
+                };
+                line.keyword("from")?;
+                let start = line.text("a label")?;
+                line.keyword("to")?;
+                let end = line.text("a label")?;
+                line.keyword("using")?;
+                let handler = line.text("a label")?;
+                method.exception_tables.push((line.number, catch_type, [start, end, handler]));
+                method.has_code = true;
+            }
+            ".var" => {
+                let slot = line.number("a local variable index")?;
+                line.keyword("is")?;
+                let name = line.text("a variable name")?;
+                let descriptor = line.text("a field descriptor")?;
+                line.keyword("from")?;
+                let start = line.text("a label")?;
+                line.keyword("to")?;
+                let end = line.text("a label")?;
+                let name_index = self.utf8(&name);
+                let descriptor_index = self.utf8(&descriptor);
+                method.local_variables.push((line.number, slot, name_index, descriptor_index, [start, end]));
+                method.has_code = true;
+            }
+            ".stack" => {
+                let frame_type = line.text("a frame type")?;
+                let mut uninitialized = Vec::new();
+                let kind = match frame_type.as_str() {
+                    "same" => StackMapFrameKind::Same,
+                    "same_extended" => StackMapFrameKind::SameExtended,
+                    "same_locals_1_stack_item" => {
+                        let stack = self.verification_type(line, &mut uninitialized)?;
+                        StackMapFrameKind::SameLocals1StackItem { stack }
+                    }
+                    "same_locals_1_stack_item_extended" => {
+                        let stack = self.verification_type(line, &mut uninitialized)?;
+                        StackMapFrameKind::SameLocals1StackItemExtended { stack }
+                    }
+                    "chop" => StackMapFrameKind::Chop { absent_locals: line.number("the number of locals removed")? },
+                    "append" => {
+                        let mut locals = Vec::new();
+                        while !line.at_end() {
+                            locals.push(self.verification_type(line, &mut uninitialized)?);
+                        }
+                        StackMapFrameKind::Append { locals }
+                    }
+                    "full" => {
+                        line.keyword("locals")?;
+                        let mut locals = Vec::new();
+                        while !line.at_end() && line.peek_word() != Some("stack") {
+                            locals.push(self.verification_type(line, &mut uninitialized)?);
+                        }
+                        line.keyword("stack")?;
+                        let mut stack = Vec::new();
+                        while !line.at_end() {
+                            stack.push(self.verification_type(line, &mut uninitialized)?);
+                        }
+                        StackMapFrameKind::Full { locals, stack }
+                    }
+                    _ => return Err(line.error(AssemblyErrorKind::UnexpectedToken(frame_type))),
+                };
+                method.frames.push((line.number, method.instructions.len(), kind, uninitialized));
+                method.has_code = true;
+            }
+            ".method" | ".bytecode" | ".source" | ".class" | ".interface" | ".super" | ".implements" | ".bootstrap" |
+            ".inner" | ".nesthost" | ".nestmember" | ".field" =>
+                return Err(line.error(AssemblyErrorKind::Misplaced(directive.to_string()))),
+            _ => return Err(line.error(AssemblyErrorKind::UnknownDirective(directive.to_string()))),
+        }
+        line.end()
+    }
+
+    fn end_method(&mut self, method: Method) -> Result<(), AssemblyError> {
+        let mut attributes = Vec::new();
+        if method.has_code {
+            let max_stack = method.max_stack
+                .ok_or(AssemblyError { line: method.line, kind: AssemblyErrorKind::MissingLimitStack(method.signature.clone()) })?;
+            let max_locals = match method.max_locals {
+                Some(max_locals) => max_locals,
+                None => {
+                    let descriptor = MethodDescriptor::parse(&method.descriptor)
+                        .map_err(|error| AssemblyError { line: method.line, kind: AssemblyErrorKind::BadDescriptor(error) })?;
+                    let this = if method.access_flags.is_static() { 0 } else { 1 };
+                    let max_locals = (method.locals_used as u32).max(descriptor.parameter_slots() + this);
+                    u16::try_from(max_locals).map_err(|_| AssemblyError {
+                        line: method.line,
+                        kind: AssemblyErrorKind::OutOfRange(format!("max_locals {}", max_locals)),
+                    })?
+                }
+            };
+
+            let mut pcs = Vec::with_capacity(method.instructions.len() + 1);
+            let mut pc = 0;
+            for pending in &method.instructions {
+                pcs.push(pc);
+                pc += pending.instruction.length(pc);
+            }
+            pcs.push(pc);
+            // Spec: code_length is less than 65536, so every pc fits in a u2
+            if pc > u16::MAX as u32 {
+                return Err(AssemblyError { line: method.line, kind: AssemblyErrorKind::OutOfRange(format!("code length {}", pc)) });
+            }
+            let label_pc = |label: &str, line: usize| match method.labels.get(label) {
+                Some(&index) => Ok(pcs[index]),
+                None => Err(AssemblyError { line, kind: AssemblyErrorKind::UndefinedLabel(label.to_string()) }),
+            };
+
+            let mut instructions = Vec::with_capacity(method.instructions.len());
+            for pending in &method.instructions {
+                let mut instruction = pending.instruction.clone();
+                for (target, label) in instruction.branch_targets_mut().into_iter().zip(&pending.labels) {
+                    *target = label_pc(label, pending.line)?;
+                }
+                instructions.push(instruction);
+            }
+            let code = bytecode::encode(&instructions).map_err(|error| {
+                let line = pcs.iter().position(|&pc| pc == error.pc)
+                    .and_then(|index| method.instructions.get(index))
+                    .map_or(method.line, |pending| pending.line);
+                AssemblyError { line, kind: AssemblyErrorKind::Bytecode(error) }
+            })?;
+
+            let mut exception_tables = Vec::with_capacity(method.exception_tables.len());
+            for (line, catch_type, [start, end, handler]) in &method.exception_tables {
+                exception_tables.push(ExceptionTable {
+                    start_pc: label_pc(start, *line)? as u16,
+                    end_pc: label_pc(end, *line)? as u16,
+                    handler_pc: label_pc(handler, *line)? as u16,
+                    catch_type: *catch_type,
+                });
+            }
+            let mut code_attributes = Vec::new();
+            if !method.line_numbers.is_empty() {
+                let entries = method.line_numbers.iter()
+                    .map(|&(index, line_number)| LineNumberTableEntry { start_pc: pcs[index] as u16, line_number })
+                    .collect();
+                code_attributes.push(self.attribute(AttributeInfo::LineNumberTable { entries }));
+            }
+            if !method.local_variables.is_empty() {
+                let mut entries = Vec::with_capacity(method.local_variables.len());
+                for (line, index, name_index, descriptor_index, [start, end]) in &method.local_variables {
+                    let start_pc = label_pc(start, *line)?;
+                    let end_pc = label_pc(end, *line)?;
+                    let length = end_pc.checked_sub(start_pc)
+                        .ok_or(AssemblyError { line: *line, kind: AssemblyErrorKind::OutOfRange(format!("range {} to {}", start, end)) })?;
+                    entries.push(LocalVariableTableEntry {
+                        start_pc: start_pc as u16,
+                        length: length as u16,
+                        name_index: *name_index,
+                        descriptor_index: *descriptor_index,
+                        index: *index,
+                    });
+                }
+                code_attributes.push(self.attribute(AttributeInfo::LocalVariableTable { entries }));
+            }
+            if !method.frames.is_empty() {
+                let mut entries: Vec<StackMapFrame> = Vec::with_capacity(method.frames.len());
+                for (line, index, kind, uninitialized) in &method.frames {
+                    // Spec: each frame is for the start of an instruction, after the one before it
+                    let previous = entries.last().map(|frame| frame.offset);
+                    if *index == method.instructions.len() || previous == Some(pcs[*index]) {
+                        let kind = AssemblyErrorKind::Misplaced(".stack".to_string());
+                        return Err(AssemblyError { line: *line, kind });
+                    }
+                    let offset = pcs[*index];
+                    let offset_delta = match previous {
+                        Some(previous) => offset - previous - 1,
+                        None => offset,
+                    };
+                    let mut kind = kind.clone();
+                    let types = kind.verification_types_mut().into_iter()
+                        .filter(|info| matches!(info, VerificationTypeInfo::Uninitialized { .. }));
+                    for (info, label) in types.zip(uninitialized) {
+                        *info = VerificationTypeInfo::Uninitialized { offset: label_pc(label, *line)? as u16 };
+                    }
+                    entries.push(StackMapFrame { offset_delta: offset_delta as u16, offset, kind });
+                }
+                code_attributes.push(self.attribute(AttributeInfo::StackMapTable { entries }));
+            }
+            attributes.push(self.attribute(AttributeInfo::Code {
+                max_stack,
+                max_locals,
+                code,
+                exception_tables,
+                attributes: code_attributes,
+            }));
+        }
+        attributes.extend(method.attributes);
+        self.methods.push(MethodInfo {
+            access_flags: method.access_flags,
+            name_index: method.name_index,
+            descriptor_index: method.descriptor_index,
+            attributes,
+        });
+        Ok(())
+    }
+
+    fn instruction(&mut self, method: &mut Method, mnemonic: &str, line: &mut Line,
+                   lines: &mut impl Iterator<Item = Line>) -> Result<(), AssemblyError> {
+        let mut labels = Vec::new();
+        let instruction = match mnemonic {
+            "bipush" => Instruction::Bipush(line.number("a byte")?),
+            "sipush" => Instruction::Sipush(line.number("a short")?),
+            "ldc" => {
+                let index = self.loadable_constant(line, NumberType::Int, NumberType::Float)?;
+                let index = u8::try_from(index)
+                    .map_err(|_| line.error(AssemblyErrorKind::OutOfRange(format!("constant #{} for ldc", index))))?;
+                Instruction::Ldc(index)
+            }
+            "ldc_w" => Instruction::LdcW(self.loadable_constant(line, NumberType::Int, NumberType::Float)?),
+            "ldc2_w" => Instruction::Ldc2W(self.loadable_constant(line, NumberType::Long, NumberType::Double)?),
+            "iinc" => iinc(method, line, false)?,
+            "wide" => {
+                let mnemonic = line.text("an instruction")?;
+                match mnemonic.as_str() {
+                    "iinc" => iinc(method, line, true)?,
+                    mnemonic => local_instruction(method, mnemonic, line, true)?
+                        .ok_or_else(|| line.error(AssemblyErrorKind::UnexpectedToken(mnemonic.to_string())))?,
+                }
+            }
+            "tableswitch" => {
+                let low = line.number("the lowest key")?;
+                let high = line.number("the highest key")?;
+                line.end()?;
+                let mut targets = Vec::new();
+                loop {
+                    let mut entry = lines.next().ok_or_else(|| line.error(AssemblyErrorKind::MissingOperand("default : label")))?;
+                    let label = entry.text("a label")?;
+                    if label == "default" || label.starts_with("default:") {
+                        entry.position -= 1;
+                        labels.push(switch_entry(&mut entry)?.1);
+                        break;
+                    }
+                    entry.end()?;
+                    targets.push(0);
+                    labels.push(label);
+                }
+                labels.rotate_right(1);
+                Instruction::Tableswitch { default: 0, low, high, targets }
+            }
+            "lookupswitch" => {
+                line.end()?;
+                let mut pairs = Vec::new();
+                loop {
+                    let mut entry = lines.next().ok_or_else(|| line.error(AssemblyErrorKind::MissingOperand("default : label")))?;
+                    let (key, label) = switch_entry(&mut entry)?;
+                    labels.push(label);
+                    if key == "default" {
+                        break;
+                    }
+                    pairs.push((parse_number(&entry, &key)?, 0));
+                }
+                labels.rotate_right(1);
+                Instruction::Lookupswitch { default: 0, pairs }
+            }
+            "getstatic" | "putstatic" | "getfield" | "putfield" => {
+                let reference = line.text("a field")?;
+                let descriptor = line.text("a field descriptor")?;
+                let index = self.field_ref(line, &reference, &descriptor)?;
+                match mnemonic {
+                    "getstatic" => Instruction::Getstatic(index),
+                    "putstatic" => Instruction::Putstatic(index),
+                    "getfield" => Instruction::Getfield(index),
+                    _ => Instruction::Putfield(index),
+                }
+            }
+            "invokevirtual" | "invokespecial" | "invokestatic" => {
+                let interface = line.skip_word("interface");
+                let reference = line.text("a method")?;
+                let index = self.method_ref(line, &reference, interface)?;
+                match mnemonic {
+                    "invokevirtual" => Instruction::Invokevirtual(index),
+                    "invokespecial" => Instruction::Invokespecial(index),
+                    _ => Instruction::Invokestatic(index),
+                }
+            }
+            "invokeinterface" => {
+                let reference = line.text("a method")?;
+                let index = self.method_ref(line, &reference, true)?;
+                // Spec: the count is the size of the arguments, the object included
+                let count = match line.at_end() {
+                    true => {
+                        let descriptor = &reference[reference.find('(').unwrap_or(0)..];
+                        let descriptor = MethodDescriptor::parse(descriptor)
+                            .map_err(|error| line.error(AssemblyErrorKind::BadDescriptor(error)))?;
+                        u8::try_from(descriptor.parameter_slots() + 1)
+                            .map_err(|_| line.error(AssemblyErrorKind::OutOfRange(reference.clone())))?
+                    }
+                    false => line.number("a count")?,
+                };
+                Instruction::Invokeinterface { index, count }
+            }
+            "invokedynamic" => {
+                let bootstrap_method_attr_index = line.number("a bootstrap method index")?;
+                let signature = line.text("a name and descriptor")?;
+                let (name, descriptor) = signature.find('(')
+                    .map(|paren| signature.split_at(paren))
+                    .ok_or_else(|| line.error(AssemblyErrorKind::BadReference(signature.clone())))?;
+                let name_and_type_index = self.name_and_type(name, descriptor);
+                Instruction::Invokedynamic(self.add(ConstantPoolInfo::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index }))
+            }
+            "new" | "anewarray" | "checkcast" | "instanceof" => {
+                let name = line.text("a class name")?;
+                let index = self.class(&name);
+                match mnemonic {
+                    "new" => Instruction::New(index),
+                    "anewarray" => Instruction::Anewarray(index),
+                    "checkcast" => Instruction::Checkcast(index),
+                    _ => Instruction::Instanceof(index),
+                }
+            }
+            "newarray" => {
+                let element_type = line.text("an array type")?;
+                match ARRAY_TYPES.iter().find(|(_, name)| *name == element_type) {
+                    Some(&(atype, _)) => Instruction::Newarray(atype),
+                    None => Instruction::Newarray(parse_number(line, &element_type)?),
+                }
+            }
+            "multianewarray" => {
+                let name = line.text("an array class")?;
+                let index = self.class(&name);
+                Instruction::Multianewarray { index, dimensions: line.number("a number of dimensions")? }
+            }
+            mnemonic => match branch(mnemonic) {
+                Some(branch) => {
+                    labels.push(line.text("a label")?);
+                    branch(0)
+                }
+                None => match local_instruction(method, mnemonic, line, false)? {
+                    Some(instruction) => instruction,
+                    None => {
+                        let instruction = self.simple_instructions.get(mnemonic)
+                            .ok_or_else(|| line.error(AssemblyErrorKind::UnknownInstruction(mnemonic.to_string())))?;
+                        // iload_0 and friends
+                        if let Some((base, slot)) = mnemonic.split_once('_') {
+                            if let (true, Ok(slot)) = (base.ends_with("load") || base.ends_with("store"), slot.parse::<u16>()) {
+                                method.use_local(slot, base);
+                            }
+                        }
+                        instruction.clone()
+                    }
+                },
+            },
+        };
+        line.end()?;
+        method.instructions.push(PendingInstruction { line: line.number, instruction, labels });
+        method.has_code = true;
+        Ok(())
+    }
+
+    // A constant for ldc, a field value or a bootstrap argument. Bare numbers get integer_type if they are
+    // integers and decimal_type otherwise.
+    fn loadable_constant(&mut self, line: &mut Line, integer_type: NumberType, decimal_type: NumberType)
+                         -> Result<u16, AssemblyError> {
+        let word = match line.next() {
+            Some(Token::Quoted(units)) => {
+                let string_index = self.add(ConstantPoolInfo::Utf8 { string: ModifiedUtf8::from_utf16(&units) });
+                return Ok(self.add(ConstantPoolInfo::String { string_index }));
+            }
+            Some(Token::Word(word)) => word,
+            None => return Err(line.error(AssemblyErrorKind::MissingOperand("a constant"))),
+        };
+        let number_type = match word.as_str() {
+            "class" => {
+                let name = line.text("a class name")?;
+                return Ok(self.class(&name));
+            }
+            "methodtype" => {
+                let descriptor = line.text("a method descriptor")?;
+                let descriptor_index = self.utf8(&descriptor);
+                return Ok(self.add(ConstantPoolInfo::MethodType { descriptor_index }));
+            }
+            "methodhandle" => return self.method_handle(line),
+            "dynamic" => {
+                let bootstrap_method_attr_index = line.number("a bootstrap method index")?;
+                let name = line.text("a name")?;
+                let descriptor = line.text("a field descriptor")?;
+                let name_and_type_index = self.name_and_type(&name, &descriptor);
+                return Ok(self.add(ConstantPoolInfo::Dynamic { bootstrap_method_attr_index, name_and_type_index }));
+            }
+            "int" => NumberType::Int,
+            "float" => NumberType::Float,
+            "long" => NumberType::Long,
+            "double" => NumberType::Double,
+            number => {
+                let number_type = match is_integer(number) {
+                    true => integer_type,
+                    false => decimal_type,
+                };
+                return self.number(line, number, number_type);
+            }
+        };
+        let number = line.text("a number")?;
+        self.number(line, &number, number_type)
+    }
+
+    fn number(&mut self, line: &Line, number: &str, number_type: NumberType) -> Result<u16, AssemblyError> {
+        let info = match number.strip_prefix("0x") {
+            // The bits of the number
+            Some(hex) => {
+                let bits = u64::from_str_radix(hex, 16)
+                    .map_err(|_| line.error(AssemblyErrorKind::BadNumber(number.to_string())))?;
+                let small_bits = || u32::try_from(bits).map_err(|_| line.error(AssemblyErrorKind::OutOfRange(number.to_string())));
+                match number_type {
+                    NumberType::Int => ConstantPoolInfo::Integer { bytes: small_bits()? },
+                    NumberType::Float => ConstantPoolInfo::Float { bytes: small_bits()? },
+                    NumberType::Long => ConstantPoolInfo::Long { high_bytes: (bits >> 32) as u32, low_bytes: bits as u32 },
+                    NumberType::Double => ConstantPoolInfo::Double { high_bytes: (bits >> 32) as u32, low_bytes: bits as u32 },
+                }
+            }
+            None => match number_type {
+                NumberType::Int => ConstantPoolInfo::Integer { bytes: parse_number::<i32>(line, number)? as u32 },
+                NumberType::Float => ConstantPoolInfo::Float { bytes: parse_number::<f32>(line, number)?.to_bits() },
+                NumberType::Long => {
+                    let bits = parse_number::<i64>(line, number)? as u64;
+                    ConstantPoolInfo::Long { high_bytes: (bits >> 32) as u32, low_bytes: bits as u32 }
+                }
+                NumberType::Double => {
+                    let bits = parse_number::<f64>(line, number)?.to_bits();
+                    ConstantPoolInfo::Double { high_bytes: (bits >> 32) as u32, low_bytes: bits as u32 }
+                }
+            },
+        };
+        Ok(self.add(info))
+    }
+
+    // kind [interface] member, e.g. invokestatic java/lang/Integer/valueOf(I)Ljava/lang/Integer;
+    fn method_handle(&mut self, line: &mut Line) -> Result<u16, AssemblyError> {
+        let kind = line.text("a method handle kind")?;
+        let reference_kind = REFERENCE_KINDS.iter().find(|(_, name)| *name == kind).map(|&(reference_kind, _)| reference_kind)
+            .ok_or_else(|| line.error(AssemblyErrorKind::UnexpectedToken(kind.clone())))?;
+        let interface = line.skip_word("interface");
+        let reference_index = match reference_kind {
+            // Spec: kinds 1 to 4 refer to fields, the others to methods
+            1..=4 => {
+                let reference = line.text("a field")?;
+                let descriptor = line.text("a field descriptor")?;
+                self.field_ref(line, &reference, &descriptor)?
+            }
+            _ => {
+                let reference = line.text("a method")?;
+                // REF_invokeInterface can only refer to an interface method
+                self.method_ref(line, &reference, interface || reference_kind == 9)?
+            }
+        };
+        Ok(self.add(ConstantPoolInfo::MethodHandle { reference_kind, reference_index }))
+    }
+
+    // "signature" in a quoted string or as a word
+    fn signature(&mut self, line: &mut Line) -> Result<Attribute, AssemblyError> {
+        let signature_index = match line.next() {
+            Some(Token::Quoted(units)) => self.add(ConstantPoolInfo::Utf8 { string: ModifiedUtf8::from_utf16(&units) }),
+            Some(Token::Word(word)) => self.utf8(&word),
+            None => return Err(line.error(AssemblyErrorKind::MissingOperand("a signature"))),
+        };
+        Ok(self.attribute(AttributeInfo::Signature { signature_index }))
+    }
+
+    // A type in a .stack frame. The label of an uninitialized type is added to labels, to be resolved once
+    // the code is laid out.
+    fn verification_type(&mut self, line: &mut Line, labels: &mut Vec<String>)
+                         -> Result<VerificationTypeInfo, AssemblyError> {
+        let word = line.text("a verification type")?;
+        Ok(match word.as_str() {
+            "top" => VerificationTypeInfo::Top,
+            "int" => VerificationTypeInfo::Integer,
+            "float" => VerificationTypeInfo::Float,
+            "double" => VerificationTypeInfo::Double,
+            "long" => VerificationTypeInfo::Long,
+            "null" => VerificationTypeInfo::Null,
+            "this" => VerificationTypeInfo::UninitializedThis,
+            "class" => {
+                let name = line.text("a class name")?;
+                VerificationTypeInfo::Object { cpool_index: self.class(&name) }
+            }
+            "uninitialized" => {
+                labels.push(line.text("a label")?);
+                VerificationTypeInfo::Uninitialized { offset: 0 }
+            }
+            _ => return Err(line.error(AssemblyErrorKind::UnexpectedToken(word))),
+        })
+    }
+
+    // java/lang/System/out and Ljava/io/PrintStream;
+    fn field_ref(&mut self, line: &Line, reference: &str, descriptor: &str) -> Result<u16, AssemblyError> {
+        let (class_name, name) = reference.rsplit_once('/')
+            .ok_or_else(|| line.error(AssemblyErrorKind::BadReference(reference.to_string())))?;
+        let class_index = self.class(class_name);
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        Ok(self.add(ConstantPoolInfo::FieldRef { class_index, name_and_type_index }))
+    }
+
+    // java/io/PrintStream/println(Ljava/lang/String;)V
+    fn method_ref(&mut self, line: &Line, reference: &str, interface: bool) -> Result<u16, AssemblyError> {
+        let bad_reference = || line.error(AssemblyErrorKind::BadReference(reference.to_string()));
+        let paren = reference.find('(').ok_or_else(bad_reference)?;
+        let (class_name, name) = reference[..paren].rsplit_once('/').ok_or_else(bad_reference)?;
+        let class_index = self.class(class_name);
+        let name_and_type_index = self.name_and_type(name, &reference[paren..]);
+        Ok(self.add(match interface {
+            true => ConstantPoolInfo::InterfaceMethodRef { class_index, name_and_type_index },
+            false => ConstantPoolInfo::MethodRef { class_index, name_and_type_index },
+        }))
+    }
+
+    fn name_and_type(&mut self, name: &str, descriptor: &str) -> u16 {
+        let name_index = self.utf8(name);
+        let descriptor_index = self.utf8(descriptor);
+        self.add(ConstantPoolInfo::NameAndType { name_index, descriptor_index })
+    }
+
+    fn class(&mut self, name: &str) -> u16 {
+        let name_index = self.utf8(name);
+        self.add(ConstantPoolInfo::Class { name_index })
+    }
+
+    fn utf8(&mut self, string: &str) -> u16 {
+        self.add(ConstantPoolInfo::Utf8 { string: ModifiedUtf8::from(string) })
+    }
+
+    // The index of the constant, which is added to the pool unless it's in there already
+    fn add(&mut self, info: ConstantPoolInfo) -> u16 {
+        if let Some(&index) = self.constant_indexes.get(&info) {
+            return index;
+        }
+        // Constants that don't fit get index 0, for as long as it takes to get to the end
+        let Ok(index) = self.constant_pool.push(info.clone()) else {
+            self.too_many_constants = true;
+            return 0;
+        };
+        self.constant_indexes.insert(info, index);
+        index
+    }
+
+    // Puts the name of the attribute in the constant pool
+    fn attribute(&mut self, info: AttributeInfo) -> Attribute {
+        Attribute { name_index: self.utf8(info.name()), info }
+    }
+}
+
+impl Method {
+    fn use_local(&mut self, slot: u16, mnemonic: &str) {
+        let size = match mnemonic.starts_with('l') || mnemonic.starts_with('d') {
+            true => 2,
+            false => 1,
+        };
+        self.locals_used = self.locals_used.max(slot.saturating_add(size));
+    }
+}
+
+impl Line {
+    fn at_end(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_word(&self) -> Option<&str> {
+        match self.tokens.get(self.position) {
+            Some(Token::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    // A word or quoted string, described by `what` in the error if there is none
+    fn text(&mut self, what: &'static str) -> Result<String, AssemblyError> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            Some(Token::Quoted(units)) => Ok(String::from_utf16_lossy(&units)),
+            None => Err(self.error(AssemblyErrorKind::MissingOperand(what))),
+        }
+    }
+
+    fn number<T: FromStr>(&mut self, what: &'static str) -> Result<T, AssemblyError> {
+        let number = self.text(what)?;
+        parse_number(self, &number)
+    }
+
+    fn keyword(&mut self, keyword: &'static str) -> Result<(), AssemblyError> {
+        match self.text(keyword)? {
+            word if word == keyword => Ok(()),
+            word => Err(self.error(AssemblyErrorKind::UnexpectedToken(word))),
+        }
+    }
+
+    // Skips the word if it's next, and says whether it was
+    fn skip_word(&mut self, word: &str) -> bool {
+        let found = self.peek_word() == Some(word);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn end(&mut self) -> Result<(), AssemblyError> {
+        match self.at_end() {
+            true => Ok(()),
+            false => {
+                let token = self.text("")?;
+                Err(self.error(AssemblyErrorKind::UnexpectedToken(token)))
+            }
+        }
+    }
+
+    fn error(&self, kind: AssemblyErrorKind) -> AssemblyError {
+        AssemblyError { line: self.number, kind }
+    }
+}
+
+// Splits a line into words and quoted strings, leaving out the comment
+fn tokenize(number: usize, text: &str) -> Result<Line, AssemblyError> {
+    let error = |reason| AssemblyError { line: number, kind: AssemblyErrorKind::BadString(reason) };
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.next() {
+            None | Some(';') => break,
+            Some('"') => {
+                let mut units = Vec::new();
+                loop {
+                    let c = match chars.next().ok_or_else(|| error("missing closing quote"))? {
+                        '"' => break,
+                        '\\' => match chars.next().ok_or_else(|| error("missing closing quote"))? {
+                            'n' => '\n',
+                            't' => '\t',
+                            'r' => '\r',
+                            'b' => '\u{8}',
+                            'f' => '\u{c}',
+                            '"' => '"',
+                            '\'' => '\'',
+                            '\\' => '\\',
+                            // A UTF-16 code unit, which may be half of a surrogate pair
+                            'u' => {
+                                let hex: String = chars.by_ref().take(4).collect();
+                                let unit = u16::from_str_radix(&hex, 16).map_err(|_| error("\\u needs four hex digits"))?;
+                                units.push(unit);
+                                continue;
+                            }
+                            _ => return Err(error("unknown escape sequence")),
+                        },
+                        c => c,
+                    };
+                    let mut buffer = [0; 2];
+                    units.extend_from_slice(c.encode_utf16(&mut buffer));
+                }
+                tokens.push(Token::Quoted(units));
+            }
+            Some(c) => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(Line { number, tokens, position: 0 })
+}
+
+// key : label or default : label, with or without spaces around the colon
+fn switch_entry(line: &mut Line) -> Result<(String, String), AssemblyError> {
+    let mut entry = String::new();
+    while !line.at_end() {
+        entry.push_str(&line.text("")?);
+    }
+    match entry.split_once(':') {
+        Some((key, label)) if !label.is_empty() => Ok((key.to_string(), label.to_string())),
+        _ => Err(line.error(AssemblyErrorKind::MissingOperand("key : label"))),
+    }
+}
+
+// Access flag keywords and hexadecimal numbers, up to the first word that is neither
+fn flags(line: &mut Line, table: &[(u16, &str)]) -> Result<u16, AssemblyError> {
+    let mut flags = 0;
+    while let Some(word) = line.peek_word() {
+        match (table.iter().find(|(_, name)| *name == word), word.strip_prefix("0x")) {
+            (Some((flag, _)), _) => flags |= flag,
+            (None, Some(hex)) => {
+                flags |= u16::from_str_radix(hex, 16).map_err(|_| line.error(AssemblyErrorKind::BadNumber(word.to_string())))?
+            }
+            (None, None) => break,
+        }
+        line.position += 1;
+    }
+    Ok(flags)
+}
+
+// iinc index constant, made wide if either doesn't fit in a byte
+fn iinc(method: &mut Method, line: &mut Line, wide: bool) -> Result<Instruction, AssemblyError> {
+    let index: u16 = line.number("a local variable index")?;
+    let constant: i16 = line.number("a constant")?;
+    method.use_local(index, "iinc");
+    Ok(match (wide, u8::try_from(index), i8::try_from(constant)) {
+        (false, Ok(index), Ok(constant)) => Instruction::Iinc { index, constant },
+        _ => Instruction::Wide(WideInstruction::Iinc { index, constant }),
+    })
+}
+
+// The narrow and wide forms of a load, store or ret
+type LocalInstructions = (fn(u8) -> Instruction, fn(u16) -> WideInstruction);
+
+// A load, store or ret with its local variable index, made wide if the index doesn't fit in a byte.
+// None if the mnemonic isn't one of them.
+fn local_instruction(method: &mut Method, mnemonic: &str, line: &mut Line, wide: bool)
+                     -> Result<Option<Instruction>, AssemblyError> {
+    let (narrow, widened): LocalInstructions = match mnemonic {
+        "iload" => (Instruction::Iload, WideInstruction::Iload),
+        "lload" => (Instruction::Lload, WideInstruction::Lload),
+        "fload" => (Instruction::Fload, WideInstruction::Fload),
+        "dload" => (Instruction::Dload, WideInstruction::Dload),
+        "aload" => (Instruction::Aload, WideInstruction::Aload),
+        "istore" => (Instruction::Istore, WideInstruction::Istore),
+        "lstore" => (Instruction::Lstore, WideInstruction::Lstore),
+        "fstore" => (Instruction::Fstore, WideInstruction::Fstore),
+        "dstore" => (Instruction::Dstore, WideInstruction::Dstore),
+        "astore" => (Instruction::Astore, WideInstruction::Astore),
+        "ret" => (Instruction::Ret, WideInstruction::Ret),
+        _ => return Ok(None),
+    };
+    let index: u16 = line.number("a local variable index")?;
+    method.use_local(index, mnemonic);
+    Ok(Some(match (wide, u8::try_from(index)) {
+        (false, Ok(index)) => narrow(index),
+        _ => Instruction::Wide(widened(index)),
+    }))
+}
+
+// The instruction a branch mnemonic stands for, given its target
+fn branch(mnemonic: &str) -> Option<fn(u32) -> Instruction> {
+    Some(match mnemonic {
+        "ifeq" => Instruction::Ifeq,
+        "ifne" => Instruction::Ifne,
+        "iflt" => Instruction::Iflt,
+        "ifge" => Instruction::Ifge,
+        "ifgt" => Instruction::Ifgt,
+        "ifle" => Instruction::Ifle,
+        "if_icmpeq" => Instruction::IfIcmpeq,
+        "if_icmpne" => Instruction::IfIcmpne,
+        "if_icmplt" => Instruction::IfIcmplt,
+        "if_icmpge" => Instruction::IfIcmpge,
+        "if_icmpgt" => Instruction::IfIcmpgt,
+        "if_icmple" => Instruction::IfIcmple,
+        "if_acmpeq" => Instruction::IfAcmpeq,
+        "if_acmpne" => Instruction::IfAcmpne,
+        "goto" => Instruction::Goto,
+        "jsr" => Instruction::Jsr,
+        "ifnull" => Instruction::Ifnull,
+        "ifnonnull" => Instruction::Ifnonnull,
+        "goto_w" => Instruction::GotoW,
+        "jsr_w" => Instruction::JsrW,
+        _ => return None,
+    })
+}
+
+fn is_label(word: &str) -> bool {
+    word.len() > 1 && word.ends_with(':') && !word.starts_with('.')
+}
+
+// Whether a bare number is an integer, rather than a decimal like 1.5, 1e10 or NaN
+fn is_integer(number: &str) -> bool {
+    let digits = number.strip_prefix('-').unwrap_or(number);
+    digits.starts_with("0x") || (!digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn parse_number<T: FromStr>(line: &Line, number: &str) -> Result<T, AssemblyError> {
+    number.parse().map_err(|_| line.error(AssemblyErrorKind::BadNumber(number.to_string())))
+}
@@ -0,0 +1,229 @@
+mod assembler;
+mod printer;
+
+use std::error::Error;
+use std::fmt;
+
+use crate::bytecode::BytecodeError;
+use crate::class::ClassFile;
+use crate::class::error::ClassFormatError;
+use crate::descriptor::DescriptorError;
+
+// A Jasmin-style text format for class files, for writing test classes by hand. A class looks like:
+//
+//     .bytecode 45.3
+//     .source Count.java
+//     .class public super Count
+//     .super java/lang/Object
+//
+//     .field private static total J = 0
+//
+//     .method public static count(I)V
+//         .limit stack 4
+//         .limit locals 1
+//         .line 5
+//       Loop:
+//         iload_0
+//         ifle Done
+//         getstatic Count/total J
+//         ldc2_w 1
+//         ladd
+//         putstatic Count/total J
+//         iinc 0 -1
+//         goto Loop
+//       Done:
+//         return
+//     .end method
+//
+// One directive or instruction goes on a line, a ';' at the start of a word comments out the rest of the
+// line, and words with spaces or quotes in them can be written as "quoted strings".
+//
+// Class directives: .bytecode major.minor (45.3 if left out, which needs no StackMapTable), .source,
+// .class or .interface with flags and the name, .super (left out for a class without one, which
+// only java/lang/Object may be), .implements, .signature, .bootstrap index method_handle arguments... and .field.
+// Flags are exactly the ones written, so a class needs `super` to get ACC_SUPER; numbers like 0x0800 add
+// flags that have no keyword. Nested classes use .inner flags class [outer class] [name simple_name], one
+// per entry of InnerClasses, .nesthost class and .nestmember class.
+//
+// Inside .method and .end method: .limit stack n, .limit locals n (worked out from the descriptor and the
+// local variable instructions if left out), .throws class, .signature, .line n for the next instruction,
+// .catch class|all from label to label using label, .var slot is name descriptor from label to label,
+// .stack frame for the next instruction, labels ending in ':', and instructions.
+//
+// A .stack frame is one of the StackMapTable frame types: same, same_extended,
+// same_locals_1_stack_item type, same_locals_1_stack_item_extended type, chop n, append types... or
+// full locals types... stack types... A type is top, int, float, double, long, null, this (for
+// uninitializedThis), class name or uninitialized label, the label being the one of the new instruction.
+//
+// Instruction operands:
+// - fields are class/name descriptor, methods class/name(parameters)return, optionally after the word
+//   `interface` for methods of interfaces; invokeinterface can end with its count, which is otherwise
+//   worked out from the descriptor
+// - invokedynamic bootstrap_index name(parameters)return
+// - branches take a label; tableswitch low high is followed by one label per line and lookupswitch by
+//   key : label lines, both ending with default : label
+// - a local variable index that doesn't fit in a byte makes the instruction wide, writing `wide` in
+//   front makes it wide anyway
+// - newarray takes the element type, e.g. int
+// - ldc, ldc_w and bootstrap arguments take a constant: 5 is an int, 1.5 a float, "text" a String, and
+//   there are class name, methodtype descriptor, methodhandle kind member and dynamic bootstrap_index
+//   name descriptor. ldc2_w takes 5 as a long and 1.5 as a double. A number written after int, float,
+//   long or double has that type wherever it is, and a hexadecimal one gives its bits.
+// - a field's value after '=' has the type of the field
+pub fn assemble(source: &str) -> Result<ClassFile, AssemblyError> {
+    assembler::Assembler::new().assemble(source)
+}
+
+// Prints a class file in the format assemble reads. Attributes the format has no directive for are left
+// out, with a comment saying so; none of them are needed to load the class. module-info can't be
+// printed, since it can't load without its Module attribute.
+pub fn print(class_file: &ClassFile) -> Result<String, ClassFormatError> {
+    printer::Printer::new(class_file).print()
+}
+
+#[derive(Debug)]
+pub struct AssemblyError {
+    // Starting at 1, zero for errors about the source as a whole
+    pub line: usize,
+    pub kind: AssemblyErrorKind,
+}
+
+#[derive(Debug)]
+pub enum AssemblyErrorKind {
+    UnknownDirective(String),
+    UnknownInstruction(String),
+    UnknownFlag(String),
+    // A directive that only goes in a method outside of one, or the other way around
+    Misplaced(String),
+    // What was missing, e.g. "a label"
+    MissingOperand(&'static str),
+    UnexpectedToken(String),
+    BadNumber(String),
+    // A number that doesn't fit the operand it's for
+    OutOfRange(String),
+    BadString(&'static str),
+    BadReference(String),
+    BadDescriptor(DescriptorError),
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+    // The directives a class can't do without
+    MissingClass,
+    MissingLimitStack(String),
+    MissingEndMethod,
+    // Bootstrap methods are numbered 0, 1, 2... in the order they are written
+    BootstrapOutOfOrder(u16),
+    TooManyConstants,
+    Bytecode(BytecodeError),
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line != 0 {
+            write!(f, "line {}: ", self.line)?;
+        }
+        match &self.kind {
+            AssemblyErrorKind::UnknownDirective(directive) => write!(f, "unknown directive {}", directive),
+            AssemblyErrorKind::UnknownInstruction(mnemonic) => write!(f, "unknown instruction {}", mnemonic),
+            AssemblyErrorKind::UnknownFlag(flag) => write!(f, "unknown flag {}", flag),
+            AssemblyErrorKind::Misplaced(directive) => write!(f, "{} is not allowed here", directive),
+            AssemblyErrorKind::MissingOperand(operand) => write!(f, "expected {}", operand),
+            AssemblyErrorKind::UnexpectedToken(token) => write!(f, "unexpected {}", token),
+            AssemblyErrorKind::BadNumber(number) => write!(f, "bad number {}", number),
+            AssemblyErrorKind::OutOfRange(number) => write!(f, "{} is out of range", number),
+            AssemblyErrorKind::BadString(reason) => write!(f, "bad string: {}", reason),
+            AssemblyErrorKind::BadReference(reference) => write!(f, "bad member reference {}", reference),
+            AssemblyErrorKind::BadDescriptor(error) => write!(f, "{}", error),
+            AssemblyErrorKind::UndefinedLabel(label) => write!(f, "undefined label {}", label),
+            AssemblyErrorKind::DuplicateLabel(label) => write!(f, "label {} is defined twice", label),
+            AssemblyErrorKind::MissingClass => write!(f, "no .class or .interface directive"),
+            AssemblyErrorKind::MissingLimitStack(method) => write!(f, "method {} has code but no .limit stack", method),
+            AssemblyErrorKind::MissingEndMethod => write!(f, ".method without .end method"),
+            AssemblyErrorKind::BootstrapOutOfOrder(index) => write!(f, "bootstrap method {} is out of order", index),
+            AssemblyErrorKind::TooManyConstants => write!(f, "too many constants for the constant pool"),
+            AssemblyErrorKind::Bytecode(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for AssemblyError {}
+
+// The keywords for access flags, in the order the printer writes them
+const CLASS_FLAGS: [(u16, &str); 9] = [
+    (0x0001, "public"),
+    (0x0010, "final"),
+    (0x0020, "super"),
+    (0x0200, "interface"),
+    (0x0400, "abstract"),
+    (0x1000, "synthetic"),
+    (0x2000, "annotation"),
+    (0x4000, "enum"),
+    (0x8000, "module"),
+];
+const INNER_CLASS_FLAGS: [(u16, &str); 10] = [
+    (0x0001, "public"),
+    (0x0002, "private"),
+    (0x0004, "protected"),
+    (0x0008, "static"),
+    (0x0010, "final"),
+    (0x0200, "interface"),
+    (0x0400, "abstract"),
+    (0x1000, "synthetic"),
+    (0x2000, "annotation"),
+    (0x4000, "enum"),
+];
+const FIELD_FLAGS: [(u16, &str); 9] = [
+    (0x0001, "public"),
+    (0x0002, "private"),
+    (0x0004, "protected"),
+    (0x0008, "static"),
+    (0x0010, "final"),
+    (0x0040, "volatile"),
+    (0x0080, "transient"),
+    (0x1000, "synthetic"),
+    (0x4000, "enum"),
+];
+const METHOD_FLAGS: [(u16, &str); 12] = [
+    (0x0001, "public"),
+    (0x0002, "private"),
+    (0x0004, "protected"),
+    (0x0008, "static"),
+    (0x0010, "final"),
+    (0x0020, "synchronized"),
+    (0x0040, "bridge"),
+    (0x0080, "varargs"),
+    (0x0100, "native"),
+    (0x0400, "abstract"),
+    (0x0800, "strict"),
+    (0x1000, "synthetic"),
+];
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5.newarray
+const ARRAY_TYPES: [(u8, &str); 8] = [
+    (4, "boolean"),
+    (5, "char"),
+    (6, "float"),
+    (7, "double"),
+    (8, "byte"),
+    (9, "short"),
+    (10, "int"),
+    (11, "long"),
+];
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.3.5
+const REFERENCE_KINDS: [(u8, &str); 9] = [
+    (1, "getfield"),
+    (2, "getstatic"),
+    (3, "putfield"),
+    (4, "putstatic"),
+    (5, "invokevirtual"),
+    (6, "invokestatic"),
+    (7, "invokespecial"),
+    (8, "newinvokespecial"),
+    (9, "invokeinterface"),
+];
+
+// Whether a word can be written as is, rather than as a quoted string
+fn is_plain_word(word: &str) -> bool {
+    !word.is_empty() && !word.starts_with(';') && !word.starts_with('"')
+        && !word.chars().any(|c| c.is_whitespace() || c.is_control())
+}
@@ -0,0 +1,617 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::attr::{AttributeInfo, StackMapFrameKind, VerificationTypeInfo};
+use crate::bytecode::{self, Instruction, WideInstruction};
+use crate::class::ClassFile;
+use crate::class::error::{ClassFormatError, ClassFormatErrorKind};
+use crate::constant_pool::ConstantPoolInfo;
+use crate::field::FieldInfo;
+use crate::method::MethodInfo;
+use crate::util::mutf8::ModifiedUtf8;
+
+use super::{is_plain_word, ARRAY_TYPES, CLASS_FLAGS, FIELD_FLAGS, INNER_CLASS_FLAGS, METHOD_FLAGS, REFERENCE_KINDS};
+
+pub(super) struct Printer<'a> {
+    class_file: &'a ClassFile,
+    out: String,
+    // Spaces in front of every line
+    indent: usize,
+    // The structures currently being printed, outermost first, for error reporting
+    context: Vec<String>,
+}
+
+// The type bare numbers in a constant are read as, see the assembler
+#[derive(Clone, Copy, PartialEq)]
+enum NumberType {
+    Int,
+    Float,
+    Long,
+    Double,
+}
+
+impl<'a> Printer<'a> {
+    pub(super) fn new(class_file: &'a ClassFile) -> Printer<'a> {
+        Printer { class_file, out: String::new(), indent: 0, context: Vec::new() }
+    }
+
+    pub(super) fn print(mut self) -> Result<String, ClassFormatError> {
+        let class_file = self.class_file;
+        // Spec: a module-info class has to have a Module attribute, which the format has no directive for
+        if class_file.access_flags.is_module() {
+            return Err(self.error(ClassFormatErrorKind::NotPrintable("module-info")));
+        }
+        self.line(&format!(".bytecode {}.{}", class_file.major_version, class_file.minor_version));
+        for attribute in &class_file.attributes {
+            if let AttributeInfo::SourceFile { sourcefile_index } = &attribute.info {
+                let source_file = self.utf8_text(*sourcefile_index)?;
+                self.line(&format!(".source {}", source_file));
+            }
+        }
+        let mut flags = class_file.access_flags.0;
+        let directive = match class_file.access_flags.is_interface() {
+            true => {
+                flags &= !0x0200;
+                ".interface"
+            }
+            false => ".class",
+        };
+        let this_class = name_word(self.class_name(class_file.this_class)?, &CLASS_FLAGS);
+        self.line(&format!("{} {}{}", directive, flag_words(flags, &CLASS_FLAGS), this_class));
+        if class_file.super_class != 0 {
+            let super_class = word(self.class_name(class_file.super_class)?);
+            self.line(&format!(".super {}", super_class));
+        }
+        for &interface in &class_file.interfaces {
+            let interface = word(self.class_name(interface)?);
+            self.line(&format!(".implements {}", interface));
+        }
+        for attribute in &class_file.attributes {
+            match &attribute.info {
+                AttributeInfo::SourceFile { .. } => {}
+                AttributeInfo::Signature { signature_index } => {
+                    let signature = self.utf8_text(*signature_index)?;
+                    self.line(&format!(".signature {}", signature));
+                }
+                AttributeInfo::InnerClasses { classes } => {
+                    for inner_class in classes {
+                        let flags = flag_words(inner_class.inner_class_access_flags, &INNER_CLASS_FLAGS);
+                        let name = name_word(self.class_name(inner_class.inner_class_info_index)?, &INNER_CLASS_FLAGS);
+                        let mut line = format!(".inner {}{}", flags, name);
+                        if inner_class.outer_class_info_index != 0 {
+                            line.push_str(" outer ");
+                            line.push_str(&word(self.class_name(inner_class.outer_class_info_index)?));
+                        }
+                        if inner_class.inner_name_index != 0 {
+                            line.push_str(" name ");
+                            line.push_str(&self.utf8_text(inner_class.inner_name_index)?);
+                        }
+                        self.line(&line);
+                    }
+                }
+                AttributeInfo::NestHost { host_class_index } => {
+                    let host_class = word(self.class_name(*host_class_index)?);
+                    self.line(&format!(".nesthost {}", host_class));
+                }
+                AttributeInfo::NestMembers { classes } => {
+                    for &member in classes {
+                        let member = word(self.class_name(member)?);
+                        self.line(&format!(".nestmember {}", member));
+                    }
+                }
+                AttributeInfo::BootstrapMethods { bootstrap_methods } => {
+                    for (i, bootstrap_method) in bootstrap_methods.iter().enumerate() {
+                        self.context.push(format!("bootstrap method {}", i));
+                        let mut line = format!(".bootstrap {} {}", i, self.method_handle(bootstrap_method.bootstrap_method_ref)?);
+                        for &argument in &bootstrap_method.bootstrap_arguments {
+                            line.push(' ');
+                            line.push_str(&self.constant(argument, NumberType::Int, NumberType::Float)?);
+                        }
+                        self.line(&line);
+                        self.context.pop();
+                    }
+                }
+                attribute => self.left_out(attribute),
+            }
+        }
+
+        for (i, field) in class_file.fields.iter().enumerate() {
+            self.context.push(format!("field #{}", i));
+            self.field(field)?;
+            self.context.pop();
+        }
+        for (i, method) in class_file.methods.iter().enumerate() {
+            self.context.push(format!("method #{}", i));
+            self.method(method)?;
+            self.context.pop();
+        }
+        Ok(self.out)
+    }
+
+    fn field(&mut self, field: &FieldInfo) -> Result<(), ClassFormatError> {
+        let descriptor = self.utf8(field.descriptor_index)?;
+        let mut line = format!(".field {}{} {}", flag_words(field.access_flags.0, &FIELD_FLAGS),
+                               name_word(self.utf8(field.name_index)?, &FIELD_FLAGS), word(descriptor));
+        let mut left_out = Vec::new();
+        let mut value = None;
+        for attribute in &field.attributes {
+            match &attribute.info {
+                AttributeInfo::Signature { signature_index } => {
+                    line.push_str(" signature ");
+                    line.push_str(&self.utf8_text(*signature_index)?);
+                }
+                // Spec: only the first ConstantValue counts, see 4.7.2
+                AttributeInfo::ConstantValue { constantvalue_index } if value.is_none() => {
+                    let (integer_type, decimal_type) = match descriptor {
+                        "J" => (NumberType::Long, NumberType::Long),
+                        "F" => (NumberType::Float, NumberType::Float),
+                        "D" => (NumberType::Double, NumberType::Double),
+                        _ => (NumberType::Int, NumberType::Float),
+                    };
+                    value = Some(self.constant(*constantvalue_index, integer_type, decimal_type)?);
+                }
+                attribute => left_out.push(attribute),
+            }
+        }
+        if let Some(value) = value {
+            line.push_str(" = ");
+            line.push_str(&value);
+        }
+        self.blank_line();
+        self.line(&line);
+        self.indent += 4;
+        for attribute in left_out {
+            self.left_out(attribute);
+        }
+        self.indent -= 4;
+        Ok(())
+    }
+
+    fn method(&mut self, method: &MethodInfo) -> Result<(), ClassFormatError> {
+        let signature = format!("{}{}", self.utf8(method.name_index)?, self.utf8(method.descriptor_index)?);
+        self.blank_line();
+        self.line(&format!(".method {}{}", flag_words(method.access_flags.0, &METHOD_FLAGS), word(&signature)));
+        self.indent += 4;
+        let mut code = None;
+        for attribute in &method.attributes {
+            match &attribute.info {
+                AttributeInfo::Code { .. } if code.is_none() => code = Some(&attribute.info),
+                AttributeInfo::Exceptions { exception_index_table } => {
+                    for &exception in exception_index_table {
+                        let exception = word(self.class_name(exception)?);
+                        self.line(&format!(".throws {}", exception));
+                    }
+                }
+                AttributeInfo::Signature { signature_index } => {
+                    let signature = self.utf8_text(*signature_index)?;
+                    self.line(&format!(".signature {}", signature));
+                }
+                attribute => self.left_out(attribute),
+            }
+        }
+        if let Some(AttributeInfo::Code { max_stack, max_locals, code, exception_tables, attributes }) = code {
+            self.context.push("attribute Code".to_string());
+            self.line(&format!(".limit stack {}", max_stack));
+            self.line(&format!(".limit locals {}", max_locals));
+            let instructions = bytecode::decode(code).map_err(|error| self.error(ClassFormatErrorKind::BadInstruction(error)))?;
+            let code_length = code.len() as u32;
+            // Labels can only go in front of an instruction or at the end of the code
+            let boundaries: BTreeSet<u32> = instructions.iter().map(|&(pc, _)| pc).chain([code_length]).collect();
+            let mut labels = BTreeSet::new();
+            for (_, instruction) in &instructions {
+                labels.extend(instruction.branch_targets());
+            }
+
+            for exception_table in exception_tables {
+                let pcs = [exception_table.start_pc, exception_table.end_pc, exception_table.handler_pc].map(u32::from);
+                if !pcs.iter().all(|pc| boundaries.contains(pc)) {
+                    self.line(&format!("; exception handler at pc {} left out", exception_table.handler_pc));
+                    continue;
+                }
+                labels.extend(pcs);
+                let catch_type = match exception_table.catch_type {
+                    0 => "all".to_string(),
+                    index => word(self.class_name(index)?),
+                };
+                self.line(&format!(".catch {} from L{} to L{} using L{}", catch_type, pcs[0], pcs[1], pcs[2]));
+            }
+
+            // The line numbers of each pc, in the order they start
+            let mut line_numbers: HashMap<u32, Vec<u16>> = HashMap::new();
+            let mut frames = HashMap::new();
+            for attribute in attributes {
+                match &attribute.info {
+                    AttributeInfo::LineNumberTable { entries } => {
+                        for entry in entries {
+                            match boundaries.contains(&(entry.start_pc as u32)) && (entry.start_pc as u32) < code_length {
+                                true => line_numbers.entry(entry.start_pc as u32).or_default().push(entry.line_number),
+                                false => self.line(&format!("; line {} at pc {} left out", entry.line_number, entry.start_pc)),
+                            }
+                        }
+                    }
+                    AttributeInfo::LocalVariableTable { entries } => {
+                        for entry in entries {
+                            let start_pc = entry.start_pc as u32;
+                            let end_pc = start_pc + entry.length as u32;
+                            let name = self.utf8(entry.name_index)?;
+                            if !boundaries.contains(&start_pc) || !boundaries.contains(&end_pc) {
+                                self.line(&format!("; local variable {} left out", word(name)));
+                                continue;
+                            }
+                            labels.extend([start_pc, end_pc]);
+                            let descriptor = word(self.utf8(entry.descriptor_index)?);
+                            self.line(&format!(".var {} is {} {} from L{} to L{}", entry.index, word(name), descriptor,
+                                               start_pc, end_pc));
+                        }
+                    }
+                    // Leaving frames out would keep the class from loading, so every one has to be printed
+                    AttributeInfo::StackMapTable { entries } => {
+                        for entry in entries {
+                            // The pcs of new instructions that uninitialized types refer to need labels
+                            let uninitialized = entry.kind.verification_types().into_iter().filter_map(|info| match info {
+                                VerificationTypeInfo::Uninitialized { offset } => Some(*offset as u32),
+                                _ => None,
+                            });
+                            let pcs: Vec<u32> = [entry.offset].into_iter().chain(uninitialized).collect();
+                            if !pcs.iter().all(|pc| boundaries.contains(pc) && *pc < code_length) {
+                                let kind = ClassFormatErrorKind::BadStackMapFrame("frame is not at an instruction");
+                                return Err(self.error(kind));
+                            }
+                            labels.extend(&pcs[1..]);
+                            frames.insert(entry.offset, &entry.kind);
+                        }
+                    }
+                    attribute => self.left_out(attribute),
+                }
+            }
+
+            for (pc, instruction) in &instructions {
+                if labels.contains(pc) {
+                    self.label(*pc);
+                }
+                for line_number in line_numbers.remove(pc).unwrap_or_default() {
+                    self.line(&format!(".line {}", line_number));
+                }
+                if let Some(kind) = frames.remove(pc) {
+                    let frame = self.frame(kind)?;
+                    self.line(&format!(".stack {}", frame));
+                }
+                self.context.push(format!("pc {}", pc));
+                self.instruction(instruction)?;
+                self.context.pop();
+            }
+            if labels.contains(&code_length) {
+                self.label(code_length);
+            }
+            self.context.pop();
+        }
+        self.indent -= 4;
+        self.line(".end method");
+        Ok(())
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) -> Result<(), ClassFormatError> {
+        let mnemonic = instruction.mnemonic();
+        let operands = match instruction {
+            Instruction::Bipush(value) => value.to_string(),
+            Instruction::Sipush(value) => value.to_string(),
+            Instruction::Ldc(index) => self.constant(*index as u16, NumberType::Int, NumberType::Float)?,
+            Instruction::LdcW(index) => self.constant(*index, NumberType::Int, NumberType::Float)?,
+            Instruction::Ldc2W(index) => self.constant(*index, NumberType::Long, NumberType::Double)?,
+            Instruction::Iload(index) |
+            Instruction::Lload(index) |
+            Instruction::Fload(index) |
+            Instruction::Dload(index) |
+            Instruction::Aload(index) |
+            Instruction::Istore(index) |
+            Instruction::Lstore(index) |
+            Instruction::Fstore(index) |
+            Instruction::Dstore(index) |
+            Instruction::Astore(index) |
+            Instruction::Ret(index) => index.to_string(),
+            Instruction::Iinc { index, constant } => format!("{} {}", index, constant),
+            Instruction::Wide(wide) => {
+                let operands = match wide {
+                    WideInstruction::Iinc { index, constant } => format!("{} {}", index, constant),
+                    WideInstruction::Iload(index) |
+                    WideInstruction::Lload(index) |
+                    WideInstruction::Fload(index) |
+                    WideInstruction::Dload(index) |
+                    WideInstruction::Aload(index) |
+                    WideInstruction::Istore(index) |
+                    WideInstruction::Lstore(index) |
+                    WideInstruction::Fstore(index) |
+                    WideInstruction::Dstore(index) |
+                    WideInstruction::Astore(index) |
+                    WideInstruction::Ret(index) => index.to_string(),
+                };
+                format!("{} {}", wide.mnemonic(), operands)
+            }
+            Instruction::Tableswitch { default, low, high, targets } => {
+                self.line(&format!("tableswitch {} {}", low, high));
+                self.indent += 2;
+                for target in targets {
+                    self.line(&format!("L{}", target));
+                }
+                self.line(&format!("default : L{}", default));
+                self.indent -= 2;
+                return Ok(());
+            }
+            Instruction::Lookupswitch { default, pairs } => {
+                self.line("lookupswitch");
+                self.indent += 2;
+                for (key, target) in pairs {
+                    self.line(&format!("{} : L{}", key, target));
+                }
+                self.line(&format!("default : L{}", default));
+                self.indent -= 2;
+                return Ok(());
+            }
+            Instruction::Getstatic(index) |
+            Instruction::Putstatic(index) |
+            Instruction::Getfield(index) |
+            Instruction::Putfield(index) => self.field_ref(*index)?,
+            Instruction::Invokevirtual(index) |
+            Instruction::Invokespecial(index) |
+            Instruction::Invokestatic(index) => self.method_ref(*index, false)?,
+            Instruction::Invokeinterface { index, count } => format!("{} {}", self.method_ref(*index, true)?, count),
+            Instruction::Invokedynamic(index) => match self.constant_info(*index)? {
+                ConstantPoolInfo::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                    let (name, descriptor) = self.name_and_type(*name_and_type_index)?;
+                    format!("{} {}", bootstrap_method_attr_index, word(&format!("{}{}", name, descriptor)))
+                }
+                _ => return Err(self.error(ClassFormatErrorKind::WrongConstantKind { index: *index, expected: "InvokeDynamic" })),
+            },
+            Instruction::New(index) |
+            Instruction::Anewarray(index) |
+            Instruction::Checkcast(index) |
+            Instruction::Instanceof(index) => word(self.class_name(*index)?),
+            Instruction::Newarray(atype) => match ARRAY_TYPES.iter().find(|(number, _)| number == atype) {
+                Some((_, name)) => name.to_string(),
+                None => atype.to_string(),
+            },
+            Instruction::Multianewarray { index, dimensions } => format!("{} {}", word(self.class_name(*index)?), dimensions),
+            instruction => match instruction.branch_targets().first() {
+                Some(target) => format!("L{}", target),
+                None => String::new(),
+            },
+        };
+        let line = match operands.is_empty() {
+            true => mnemonic.to_string(),
+            false => format!("{} {}", mnemonic, operands),
+        };
+        self.line(&line);
+        Ok(())
+    }
+
+    // A loadable constant, with the type written out where a bare number would be read as another one
+    fn constant(&self, index: u16, integer_type: NumberType, decimal_type: NumberType) -> Result<String, ClassFormatError> {
+        let typed = |number_type: NumberType, expected: NumberType, name: &str, number: String| match number_type == expected {
+            true => number,
+            false => format!("{} {}", name, number),
+        };
+        Ok(match self.constant_info(index)? {
+            ConstantPoolInfo::Integer { bytes } => typed(integer_type, NumberType::Int, "int", (*bytes as i32).to_string()),
+            ConstantPoolInfo::Long { high_bytes, low_bytes } => {
+                let value = ((*high_bytes as u64) << 32 | *low_bytes as u64) as i64;
+                typed(integer_type, NumberType::Long, "long", value.to_string())
+            }
+            ConstantPoolInfo::Float { bytes } => {
+                let value = f32::from_bits(*bytes);
+                match value.is_nan() && *bytes != f32::NAN.to_bits() {
+                    // Parsing "NaN" only gives back the one NaN
+                    true => format!("float {:#x}", bytes),
+                    false => typed(decimal_type, NumberType::Float, "float", format!("{:?}", value)),
+                }
+            }
+            ConstantPoolInfo::Double { high_bytes, low_bytes } => {
+                let bits = (*high_bytes as u64) << 32 | *low_bytes as u64;
+                let value = f64::from_bits(bits);
+                match value.is_nan() && bits != f64::NAN.to_bits() {
+                    true => format!("double {:#x}", bits),
+                    false => typed(decimal_type, NumberType::Double, "double", format!("{:?}", value)),
+                }
+            }
+            // Always quoted, so that it can't be taken for a number or keyword
+            ConstantPoolInfo::String { string_index } => match self.constant_info(*string_index)? {
+                ConstantPoolInfo::Utf8 { string } => quote(&string.to_utf16()),
+                _ => return Err(self.error(ClassFormatErrorKind::WrongConstantKind { index: *string_index, expected: "Utf8" })),
+            },
+            ConstantPoolInfo::Class { name_index } => format!("class {}", word(self.utf8(*name_index)?)),
+            ConstantPoolInfo::MethodType { descriptor_index } => format!("methodtype {}", word(self.utf8(*descriptor_index)?)),
+            ConstantPoolInfo::MethodHandle { .. } => format!("methodhandle {}", self.method_handle(index)?),
+            ConstantPoolInfo::Dynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                let (name, descriptor) = self.name_and_type(*name_and_type_index)?;
+                format!("dynamic {} {} {}", bootstrap_method_attr_index, word(name), word(descriptor))
+            }
+            _ => return Err(self.error(ClassFormatErrorKind::WrongConstantKind { index, expected: "loadable constant" })),
+        })
+    }
+
+    // The frame type and its types, see the assembler
+    fn frame(&self, kind: &StackMapFrameKind) -> Result<String, ClassFormatError> {
+        Ok(match kind {
+            StackMapFrameKind::Same => "same".to_string(),
+            StackMapFrameKind::SameExtended => "same_extended".to_string(),
+            StackMapFrameKind::SameLocals1StackItem { stack } =>
+                format!("same_locals_1_stack_item {}", self.verification_type(stack)?),
+            StackMapFrameKind::SameLocals1StackItemExtended { stack } =>
+                format!("same_locals_1_stack_item_extended {}", self.verification_type(stack)?),
+            StackMapFrameKind::Chop { absent_locals } => format!("chop {}", absent_locals),
+            StackMapFrameKind::Append { locals } => format!("append{}", self.verification_types(locals)?),
+            StackMapFrameKind::Full { locals, stack } =>
+                format!("full locals{} stack{}", self.verification_types(locals)?, self.verification_types(stack)?),
+        })
+    }
+
+    // Each type with a space in front of it
+    fn verification_types(&self, types: &[VerificationTypeInfo]) -> Result<String, ClassFormatError> {
+        let mut text = String::new();
+        for info in types {
+            text.push(' ');
+            text.push_str(&self.verification_type(info)?);
+        }
+        Ok(text)
+    }
+
+    fn verification_type(&self, info: &VerificationTypeInfo) -> Result<String, ClassFormatError> {
+        Ok(match info {
+            VerificationTypeInfo::Top => "top".to_string(),
+            VerificationTypeInfo::Integer => "int".to_string(),
+            VerificationTypeInfo::Float => "float".to_string(),
+            VerificationTypeInfo::Double => "double".to_string(),
+            VerificationTypeInfo::Long => "long".to_string(),
+            VerificationTypeInfo::Null => "null".to_string(),
+            VerificationTypeInfo::UninitializedThis => "this".to_string(),
+            VerificationTypeInfo::Object { cpool_index } => format!("class {}", word(self.class_name(*cpool_index)?)),
+            VerificationTypeInfo::Uninitialized { offset } => format!("uninitialized L{}", offset),
+        })
+    }
+
+    // kind [interface] member
+    fn method_handle(&self, index: u16) -> Result<String, ClassFormatError> {
+        match self.constant_info(index)? {
+            ConstantPoolInfo::MethodHandle { reference_kind, reference_index } => {
+                let kind = REFERENCE_KINDS.iter().find(|(number, _)| number == reference_kind)
+                    .map(|(_, name)| *name)
+                    .ok_or_else(|| self.error(ClassFormatErrorKind::WrongConstantKind { index, expected: "MethodHandle" }))?;
+                let member = match reference_kind {
+                    1..=4 => self.field_ref(*reference_index)?,
+                    9 => self.method_ref(*reference_index, true)?,
+                    _ => self.method_ref(*reference_index, false)?,
+                };
+                Ok(format!("{} {}", kind, member))
+            }
+            _ => Err(self.error(ClassFormatErrorKind::WrongConstantKind { index, expected: "MethodHandle" })),
+        }
+    }
+
+    // class/name descriptor
+    fn field_ref(&self, index: u16) -> Result<String, ClassFormatError> {
+        let (class_name, name, descriptor) = self.class_file.constant_pool.member_ref(index).map_err(|kind| self.error(kind))?;
+        Ok(format!("{} {}", word(&format!("{}/{}", class_name, name)), word(descriptor)))
+    }
+
+    // class/name(parameters)return, after `interface` for an interface method unless the instruction
+    // can only call those
+    fn method_ref(&self, index: u16, interface_only: bool) -> Result<String, ClassFormatError> {
+        let (class_name, name, descriptor) = self.class_file.constant_pool.member_ref(index).map_err(|kind| self.error(kind))?;
+        let member = word(&format!("{}/{}{}", class_name, name, descriptor));
+        match (interface_only, self.constant_info(index)?) {
+            (false, ConstantPoolInfo::InterfaceMethodRef { .. }) => Ok(format!("interface {}", member)),
+            _ => Ok(member),
+        }
+    }
+
+    fn name_and_type(&self, index: u16) -> Result<(&'a str, &'a str), ClassFormatError> {
+        self.class_file.constant_pool.name_and_type(index).map_err(|kind| self.error(kind))
+    }
+
+    fn constant_info(&self, index: u16) -> Result<&'a ConstantPoolInfo, ClassFormatError> {
+        self.class_file.constant_pool.get(index).map_err(|kind| self.error(kind))
+    }
+
+    fn class_name(&self, index: u16) -> Result<&'a str, ClassFormatError> {
+        self.class_file.constant_pool.class_name(index).map_err(|kind| self.error(kind))
+    }
+
+    fn utf8(&self, index: u16) -> Result<&'a str, ClassFormatError> {
+        self.class_file.constant_pool.utf8(index).map_err(|kind| self.error(kind))
+    }
+
+    // A Utf8 constant as a word, or quoted so that it comes back exactly, unpaired surrogates and all
+    fn utf8_text(&self, index: u16) -> Result<String, ClassFormatError> {
+        match self.constant_info(index)? {
+            ConstantPoolInfo::Utf8 { string } => Ok(text(string)),
+            _ => Err(self.error(ClassFormatErrorKind::WrongConstantKind { index, expected: "Utf8" })),
+        }
+    }
+
+    fn left_out(&mut self, attribute: &AttributeInfo) {
+        self.line(&format!("; {} attribute left out", attribute.name()));
+    }
+
+    fn label(&mut self, pc: u32) {
+        self.indent -= 2;
+        self.line(&format!("L{}:", pc));
+        self.indent += 2;
+    }
+
+    fn line(&mut self, text: &str) {
+        self.out.push_str(&" ".repeat(self.indent));
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn blank_line(&mut self) {
+        self.out.push('\n');
+    }
+
+    fn error(&self, kind: ClassFormatErrorKind) -> ClassFormatError {
+        ClassFormatError::new(kind, None, self.context.join(" "))
+    }
+}
+
+// The keywords of the flags, each followed by a space, and any bits without one in hexadecimal
+fn flag_words(flags: u16, table: &[(u16, &str)]) -> String {
+    let mut words = String::new();
+    let mut remaining = flags;
+    for &(flag, name) in table {
+        if flags & flag != 0 {
+            words.push_str(name);
+            words.push(' ');
+            remaining &= !flag;
+        }
+    }
+    if remaining != 0 {
+        words.push_str(&format!("{:#06x} ", remaining));
+    }
+    words
+}
+
+// A name as is, or quoted if it wouldn't read back as one word
+fn word(name: &str) -> String {
+    match is_plain_word(name) {
+        true => name.to_string(),
+        false => quote(&name.encode_utf16().collect::<Vec<_>>()),
+    }
+}
+
+// A name after flags, quoted if it would be read as one
+fn name_word(name: &str, table: &[(u16, &str)]) -> String {
+    match name.starts_with("0x") || table.iter().any(|(_, flag)| *flag == name) {
+        true => quote(&name.encode_utf16().collect::<Vec<_>>()),
+        false => word(name),
+    }
+}
+
+fn text(string: &ModifiedUtf8) -> String {
+    let units = string.to_utf16();
+    match is_plain_word(string.as_str()) && char::decode_utf16(units.iter().copied()).all(|c| c.is_ok()) {
+        true => string.as_str().to_string(),
+        false => quote(&units),
+    }
+}
+
+// A Java string in quotes, with the escapes the assembler reads
+fn quote(units: &[u16]) -> String {
+    let mut quoted = String::from("\"");
+    for c in char::decode_utf16(units.iter().copied()) {
+        match c {
+            Ok('"') => quoted.push_str("\\\""),
+            Ok('\\') => quoted.push_str("\\\\"),
+            Ok('\n') => quoted.push_str("\\n"),
+            Ok('\t') => quoted.push_str("\\t"),
+            Ok('\r') => quoted.push_str("\\r"),
+            Ok(c) if !c.is_control() => quoted.push(c),
+            Ok(c) => {
+                let mut buffer = [0; 2];
+                for unit in c.encode_utf16(&mut buffer) {
+                    quoted.push_str(&format!("\\u{:04x}", unit));
+                }
+            }
+            Err(error) => quoted.push_str(&format!("\\u{:04x}", error.unpaired_surrogate())),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,224 @@
+use crate::attr::{Attribute, ExceptionTable, StackMapFrame, StackMapFrameKind, VerificationTypeInfo};
+use crate::bytecode::{self, Instruction, WideInstruction};
+use crate::class::error::{ClassFormatError, ClassFormatErrorKind};
+
+use super::Printer;
+use super::syntax::check_name;
+
+impl<'a> Printer<'a> {
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.3
+    pub(super) fn code(&mut self, max_stack: u16, max_locals: u16, code: &[u8], exception_tables: &[ExceptionTable],
+                       attributes: &'a [Attribute]) -> Result<(), ClassFormatError> {
+        self.line("Code:");
+        self.indent += 2;
+        self.line(&format!("stack={}, locals={}, args_size={}", max_stack, max_locals, self.args_size));
+        let instructions = bytecode::decode(code).map_err(|error| self.error(ClassFormatErrorKind::BadInstruction(error)))?;
+        for (pc, instruction) in &instructions {
+            self.instruction(*pc, instruction)?;
+        }
+        if !exception_tables.is_empty() {
+            self.line("Exception table:");
+            self.indent += 3;
+            self.line("from    to  target type");
+            for exception_table in exception_tables {
+                let catch_type = match exception_table.catch_type {
+                    0 => "any".to_string(),
+                    catch_type => format!("Class {}", check_name(self.class_name(catch_type)?)),
+                };
+                self.line(&format!("{:>5} {:>5} {:>5}   {}",
+                    exception_table.start_pc, exception_table.end_pc, exception_table.handler_pc, catch_type));
+            }
+            self.indent -= 3;
+        }
+        self.attributes(attributes)?;
+        self.indent -= 2;
+        Ok(())
+    }
+
+    fn instruction(&mut self, pc: u32, instruction: &Instruction) -> Result<(), ClassFormatError> {
+        let mnemonic = instruction.mnemonic();
+        // Instructions with a constant pool operand get the constant as a comment
+        let constant_index = match instruction {
+            Instruction::Ldc(index) => Some(*index as u16),
+            Instruction::LdcW(index) |
+            Instruction::Ldc2W(index) |
+            Instruction::Getstatic(index) |
+            Instruction::Putstatic(index) |
+            Instruction::Getfield(index) |
+            Instruction::Putfield(index) |
+            Instruction::Invokevirtual(index) |
+            Instruction::Invokespecial(index) |
+            Instruction::Invokestatic(index) |
+            Instruction::Invokeinterface { index, .. } |
+            Instruction::Invokedynamic(index) |
+            Instruction::New(index) |
+            Instruction::Anewarray(index) |
+            Instruction::Checkcast(index) |
+            Instruction::Instanceof(index) |
+            Instruction::Multianewarray { index, .. } => Some(*index),
+            _ => None,
+        };
+        if let Some(index) = constant_index {
+            let operands = match instruction {
+                Instruction::Invokeinterface { count, .. } => format!("#{},  {}", index, count),
+                Instruction::Invokedynamic(_) => format!("#{},  0", index),
+                Instruction::Multianewarray { dimensions, .. } => format!("#{},  {}", index, dimensions),
+                _ => format!("#{}", index),
+            };
+            let comment = self.constant_comment(index)?;
+            self.line_with_comment(&format!("{:>4}: {:<13} {}", pc, mnemonic, operands), &comment);
+            return Ok(());
+        }
+
+        match instruction {
+            Instruction::Tableswitch { default, low, high, targets } => {
+                self.line(&format!("{:>4}: {:<13} {{ // {} to {}", pc, mnemonic, low, high));
+                for (key, target) in (*low..=*high).zip(targets) {
+                    self.line(&format!("{:>18}: {}", key, target));
+                }
+                self.switch_end(*default);
+                return Ok(());
+            }
+            Instruction::Lookupswitch { default, pairs } => {
+                self.line(&format!("{:>4}: {:<13} {{ // {}", pc, mnemonic, pairs.len()));
+                for (key, target) in pairs {
+                    self.line(&format!("{:>18}: {}", key, target));
+                }
+                self.switch_end(*default);
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let operands = match instruction {
+            Instruction::Bipush(value) => Some(value.to_string()),
+            Instruction::Sipush(value) => Some(value.to_string()),
+            Instruction::Iload(index) |
+            Instruction::Lload(index) |
+            Instruction::Fload(index) |
+            Instruction::Dload(index) |
+            Instruction::Aload(index) |
+            Instruction::Istore(index) |
+            Instruction::Lstore(index) |
+            Instruction::Fstore(index) |
+            Instruction::Dstore(index) |
+            Instruction::Astore(index) |
+            Instruction::Ret(index) => Some(index.to_string()),
+            Instruction::Iinc { index, constant } => Some(format!("{}, {}", index, constant)),
+            // javap puts one more space in front of the array type
+            Instruction::Newarray(atype) => Some(format!(" {}", array_type_name(*atype))),
+            Instruction::Wide(wide) => {
+                let mnemonic = format!("{}_w", wide.mnemonic());
+                let operands = match wide {
+                    WideInstruction::Iinc { index, constant } => format!("{}, {}", index, constant),
+                    WideInstruction::Iload(index) |
+                    WideInstruction::Lload(index) |
+                    WideInstruction::Fload(index) |
+                    WideInstruction::Dload(index) |
+                    WideInstruction::Aload(index) |
+                    WideInstruction::Istore(index) |
+                    WideInstruction::Lstore(index) |
+                    WideInstruction::Fstore(index) |
+                    WideInstruction::Dstore(index) |
+                    WideInstruction::Astore(index) |
+                    WideInstruction::Ret(index) => index.to_string(),
+                };
+                self.line(&format!("{:>4}: {:<13} {}", pc, mnemonic, operands));
+                return Ok(());
+            }
+            _ => instruction.branch_targets().first().map(u32::to_string),
+        };
+        match operands {
+            Some(operands) => self.line(&format!("{:>4}: {:<13} {}", pc, mnemonic, operands)),
+            None => self.line(&format!("{:>4}: {}", pc, mnemonic)),
+        }
+        Ok(())
+    }
+
+    fn switch_end(&mut self, default: u32) {
+        self.line(&format!("{:>18}: {}", "default", default));
+        self.line("      }");
+    }
+
+    // Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.4
+    pub(super) fn stack_map_table(&mut self, entries: &[StackMapFrame]) -> Result<(), ClassFormatError> {
+        self.line(&format!("StackMapTable: number_of_entries = {}", entries.len()));
+        self.indent += 2;
+        for entry in entries {
+            let (frame_type, name) = match &entry.kind {
+                StackMapFrameKind::Same => (entry.offset_delta, "same"),
+                StackMapFrameKind::SameLocals1StackItem { .. } => (entry.offset_delta + 64, "same_locals_1_stack_item"),
+                StackMapFrameKind::SameLocals1StackItemExtended { .. } =>
+                    (247, "same_locals_1_stack_item_frame_extended"),
+                StackMapFrameKind::Chop { absent_locals } => (251 - *absent_locals as u16, "chop"),
+                StackMapFrameKind::SameExtended => (251, "same_frame_extended"),
+                StackMapFrameKind::Append { locals } => (251 + locals.len() as u16, "append"),
+                StackMapFrameKind::Full { .. } => (255, "full_frame"),
+            };
+            self.line(&format!("frame_type = {} /* {} */", frame_type, name));
+            self.indent += 2;
+            // The offset_delta of same and same_locals_1_stack_item frames is part of their frame_type
+            if frame_type >= 247 {
+                self.line(&format!("offset_delta = {}", entry.offset_delta));
+            }
+            match &entry.kind {
+                StackMapFrameKind::SameLocals1StackItem { stack } |
+                StackMapFrameKind::SameLocals1StackItemExtended { stack } => {
+                    let stack = self.verification_types(std::slice::from_ref(stack))?;
+                    self.line(&format!("stack = {}", stack));
+                }
+                StackMapFrameKind::Append { locals } => {
+                    let locals = self.verification_types(locals)?;
+                    self.line(&format!("locals = {}", locals));
+                }
+                StackMapFrameKind::Full { locals, stack } => {
+                    let locals = self.verification_types(locals)?;
+                    self.line(&format!("locals = {}", locals));
+                    let stack = self.verification_types(stack)?;
+                    self.line(&format!("stack = {}", stack));
+                }
+                _ => {}
+            }
+            self.indent -= 2;
+        }
+        self.indent -= 2;
+        Ok(())
+    }
+
+    // [ int, class java/lang/String ], or [] if there are none
+    fn verification_types(&self, types: &[VerificationTypeInfo]) -> Result<String, ClassFormatError> {
+        if types.is_empty() {
+            return Ok("[]".to_string());
+        }
+        let mut names = Vec::with_capacity(types.len());
+        for verification_type in types {
+            names.push(match verification_type {
+                VerificationTypeInfo::Top => "top".to_string(),
+                VerificationTypeInfo::Integer => "int".to_string(),
+                VerificationTypeInfo::Float => "float".to_string(),
+                VerificationTypeInfo::Double => "double".to_string(),
+                VerificationTypeInfo::Long => "long".to_string(),
+                VerificationTypeInfo::Null => "null".to_string(),
+                VerificationTypeInfo::UninitializedThis => "this".to_string(),
+                VerificationTypeInfo::Object { cpool_index } => format!("class {}", check_name(self.class_name(*cpool_index)?)),
+                VerificationTypeInfo::Uninitialized { offset } => format!("uninitialized {}", offset),
+            });
+        }
+        Ok(format!("[ {} ]", names.join(", ")))
+    }
+}
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5.newarray
+fn array_type_name(atype: u8) -> String {
+    match atype {
+        4 => "boolean".to_string(),
+        5 => "char".to_string(),
+        6 => "float".to_string(),
+        7 => "double".to_string(),
+        8 => "byte".to_string(),
+        9 => "short".to_string(),
+        10 => "int".to_string(),
+        11 => "long".to_string(),
+        atype => atype.to_string(),
+    }
+}
@@ -0,0 +1,1108 @@
+mod code;
+mod syntax;
+
+use crate::attr::annotation::{Annotation, ElementValue, TargetInfo, TypeAnnotation};
+use crate::attr::module::Module;
+use crate::attr::{self, Attribute, AttributeInfo};
+use crate::class::{ClassAccessFlags, ClassFile};
+use crate::class::error::{ClassFormatError, ClassFormatErrorKind};
+use crate::constant_pool::ConstantPoolInfo;
+use crate::descriptor::{FieldType, MethodDescriptor};
+use crate::field::{FieldAccessFlags, FieldInfo};
+use crate::method::{MethodAccessFlags, MethodInfo};
+use crate::signature::{ClassSignature, FieldSignature, MethodSignature};
+use crate::util::number::{java_double, java_float};
+
+use syntax::{check_name, escape, java_name};
+
+// Prints a class file the way `javap -c -v -p` does, so the two can be diffed. Only the lines with the
+// modification time and checksum of the file are left out.
+pub fn disassemble(class_file: &ClassFile, path: &str) -> Result<String, ClassFormatError> {
+    let mut printer = Printer {
+        class_file,
+        out: String::new(),
+        indent: 0,
+        args_size: 0,
+        context: Vec::new(),
+    };
+    printer.class(path)?;
+    Ok(printer.out)
+}
+
+// javap lines up comments this many columns past the indentation
+const COMMENT_COLUMN: usize = 40;
+
+// The flags javap names after "flags:", in the order it names them
+const CLASS_FLAGS: [(u16, &str); 9] = [
+    (ClassAccessFlags::PUBLIC.0, "ACC_PUBLIC"),
+    (ClassAccessFlags::FINAL.0, "ACC_FINAL"),
+    (ClassAccessFlags::SUPER.0, "ACC_SUPER"),
+    (ClassAccessFlags::INTERFACE.0, "ACC_INTERFACE"),
+    (ClassAccessFlags::ABSTRACT.0, "ACC_ABSTRACT"),
+    (ClassAccessFlags::SYNTHETIC.0, "ACC_SYNTHETIC"),
+    (ClassAccessFlags::ANNOTATION.0, "ACC_ANNOTATION"),
+    (ClassAccessFlags::ENUM.0, "ACC_ENUM"),
+    (ClassAccessFlags::MODULE.0, "ACC_MODULE"),
+];
+const FIELD_FLAGS: [(u16, &str); 9] = [
+    (FieldAccessFlags::PUBLIC.0, "ACC_PUBLIC"),
+    (FieldAccessFlags::PRIVATE.0, "ACC_PRIVATE"),
+    (FieldAccessFlags::PROTECTED.0, "ACC_PROTECTED"),
+    (FieldAccessFlags::STATIC.0, "ACC_STATIC"),
+    (FieldAccessFlags::FINAL.0, "ACC_FINAL"),
+    (FieldAccessFlags::VOLATILE.0, "ACC_VOLATILE"),
+    (FieldAccessFlags::TRANSIENT.0, "ACC_TRANSIENT"),
+    (FieldAccessFlags::SYNTHETIC.0, "ACC_SYNTHETIC"),
+    (FieldAccessFlags::ENUM.0, "ACC_ENUM"),
+];
+const METHOD_FLAGS: [(u16, &str); 12] = [
+    (MethodAccessFlags::PUBLIC.0, "ACC_PUBLIC"),
+    (MethodAccessFlags::PRIVATE.0, "ACC_PRIVATE"),
+    (MethodAccessFlags::PROTECTED.0, "ACC_PROTECTED"),
+    (MethodAccessFlags::STATIC.0, "ACC_STATIC"),
+    (MethodAccessFlags::FINAL.0, "ACC_FINAL"),
+    (MethodAccessFlags::SYNCHRONIZED.0, "ACC_SYNCHRONIZED"),
+    (MethodAccessFlags::BRIDGE.0, "ACC_BRIDGE"),
+    (MethodAccessFlags::VARARGS.0, "ACC_VARARGS"),
+    (MethodAccessFlags::NATIVE.0, "ACC_NATIVE"),
+    (MethodAccessFlags::ABSTRACT.0, "ACC_ABSTRACT"),
+    (MethodAccessFlags::STRICT.0, "ACC_STRICT"),
+    (MethodAccessFlags::SYNTHETIC.0, "ACC_SYNTHETIC"),
+];
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.25
+const MODULE_FLAGS: [(u16, &str); 3] = [(0x0020, "ACC_OPEN"), (0x1000, "ACC_SYNTHETIC"), (0x8000, "ACC_MANDATED")];
+const REQUIRES_FLAGS: [(u16, &str); 4] =
+    [(0x0020, "ACC_TRANSITIVE"), (0x0040, "ACC_STATIC_PHASE"), (0x1000, "ACC_SYNTHETIC"), (0x8000, "ACC_MANDATED")];
+const EXPORTS_FLAGS: [(u16, &str); 2] = [(0x1000, "ACC_SYNTHETIC"), (0x8000, "ACC_MANDATED")];
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.24
+const PARAMETER_FLAGS: [(u16, &str); 3] = [(0x0010, "final"), (0x1000, "synthetic"), (0x8000, "mandated")];
+// Flags of the JDK's ModuleResolution attribute
+const MODULE_RESOLUTION_FLAGS: [(u16, &str); 4] = [
+    (0x0001, "DO_NOT_RESOLVE_BY_DEFAULT"),
+    (0x0002, "WARN_DEPRECATED"),
+    (0x0004, "WARN_DEPRECATED_FOR_REMOVAL"),
+    (0x0008, "WARN_INCUBATING"),
+];
+
+// The modifiers javap puts in declarations, in its order
+const CLASS_MODIFIERS: [(u16, &str); 3] = [
+    (ClassAccessFlags::PUBLIC.0, "public"),
+    (ClassAccessFlags::FINAL.0, "final"),
+    (ClassAccessFlags::ABSTRACT.0, "abstract"),
+];
+const INNER_CLASS_MODIFIERS: [(u16, &str); 6] = [
+    (0x0001, "public"),
+    (0x0002, "private"),
+    (0x0004, "protected"),
+    (0x0008, "static"),
+    (0x0010, "final"),
+    (0x0400, "abstract"),
+];
+const FIELD_MODIFIERS: [(u16, &str); 7] = [
+    (FieldAccessFlags::PUBLIC.0, "public"),
+    (FieldAccessFlags::PRIVATE.0, "private"),
+    (FieldAccessFlags::PROTECTED.0, "protected"),
+    (FieldAccessFlags::STATIC.0, "static"),
+    (FieldAccessFlags::FINAL.0, "final"),
+    (FieldAccessFlags::VOLATILE.0, "volatile"),
+    (FieldAccessFlags::TRANSIENT.0, "transient"),
+];
+const METHOD_MODIFIERS: [(u16, &str); 9] = [
+    (MethodAccessFlags::PUBLIC.0, "public"),
+    (MethodAccessFlags::PRIVATE.0, "private"),
+    (MethodAccessFlags::PROTECTED.0, "protected"),
+    (MethodAccessFlags::STATIC.0, "static"),
+    (MethodAccessFlags::FINAL.0, "final"),
+    (MethodAccessFlags::SYNCHRONIZED.0, "synchronized"),
+    (MethodAccessFlags::NATIVE.0, "native"),
+    (MethodAccessFlags::ABSTRACT.0, "abstract"),
+    (MethodAccessFlags::STRICT.0, "strictfp"),
+];
+
+fn flag_names(flags: u16, table: &[(u16, &'static str)]) -> Vec<&'static str> {
+    table.iter().filter(|(flag, _)| flags & flag != 0).map(|(_, name)| *name).collect()
+}
+
+// flags: (0x0021) ACC_PUBLIC, ACC_SUPER
+fn flags_line(flags: u16, table: &[(u16, &'static str)]) -> String {
+    let names = flag_names(flags, table);
+    match names.is_empty() {
+        true => format!("flags: (0x{:04x})", flags),
+        false => format!("flags: (0x{:04x}) {}", flags, names.join(", ")),
+    }
+}
+
+// Modifiers followed by a space, ready to go in front of the rest of a declaration
+fn modifiers(flags: u16, table: &[(u16, &'static str)]) -> String {
+    flag_names(flags, table).iter().map(|modifier| format!("{} ", modifier)).collect()
+}
+
+struct Printer<'a> {
+    class_file: &'a ClassFile,
+    out: String,
+    // Spaces in front of every line
+    indent: usize,
+    // Parameters of the method being printed, `this` included, which Code shows. javap counts them
+    // rather than the slots they take up, so a long is one.
+    args_size: u16,
+    // The structures currently being printed, outermost first, for error reporting
+    context: Vec<String>,
+}
+
+impl<'a> Printer<'a> {
+    fn class(&mut self, path: &str) -> Result<(), ClassFormatError> {
+        let class_file = self.class_file;
+        self.line(&format!("Classfile {}", path));
+        self.indent = 2;
+        let source_file = class_file.attributes.iter().find_map(|attribute| match &attribute.info {
+            AttributeInfo::SourceFile { sourcefile_index } => Some(*sourcefile_index),
+            _ => None
+        });
+        if let Some(index) = source_file {
+            let source_file = self.utf8(index)?;
+            self.line(&format!("Compiled from \"{}\"", escape(source_file)));
+        }
+        self.indent = 0;
+        let declaration = self.class_declaration()?;
+        self.line(&declaration);
+
+        self.indent = 2;
+        self.line(&format!("minor version: {}", class_file.minor_version));
+        self.line(&format!("major version: {}", class_file.major_version));
+        self.line(&flags_line(class_file.access_flags.0, &CLASS_FLAGS));
+        let this_class = check_name(self.class_name(class_file.this_class)?);
+        self.line_with_comment(&format!("this_class: #{}", class_file.this_class), &this_class);
+        match class_file.super_class {
+            0 => self.line("super_class: #0"),
+            index => {
+                let super_class = check_name(self.class_name(index)?);
+                self.line_with_comment(&format!("super_class: #{}", index), &super_class);
+            }
+        }
+        self.line(&format!("interfaces: {}, fields: {}, methods: {}, attributes: {}",
+                           class_file.interfaces.len(), class_file.fields.len(), class_file.methods.len(),
+                           class_file.attributes.len()));
+
+        self.indent = 0;
+        self.line("Constant pool:");
+        self.constant_pool()?;
+
+        self.line("{");
+        self.indent = 2;
+        for (i, field) in class_file.fields.iter().enumerate() {
+            if i > 0 {
+                self.blank_line();
+            }
+            self.context.push(format!("field #{}", i));
+            self.field(field)?;
+            self.context.pop();
+        }
+        for (i, method) in class_file.methods.iter().enumerate() {
+            if i > 0 || !class_file.fields.is_empty() {
+                self.blank_line();
+            }
+            self.context.push(format!("method #{}", i));
+            self.method(method)?;
+            self.context.pop();
+        }
+        // javap separates the fields from the methods even when there aren't any methods
+        if !class_file.fields.is_empty() && class_file.methods.is_empty() {
+            self.blank_line();
+        }
+        self.indent = 0;
+        self.line("}");
+        self.attributes(&class_file.attributes)
+    }
+
+    // public class Foo<T> extends Bar implements Baz<T>
+    fn class_declaration(&mut self) -> Result<String, ClassFormatError> {
+        let class_file = self.class_file;
+        if class_file.access_flags.is_module() {
+            let module = class_file.attributes.iter().find_map(|attribute| match &attribute.info {
+                AttributeInfo::Module { module } => Some(module),
+                _ => None
+            });
+            return match module {
+                Some(module) => {
+                    let open = if module.module_flags & 0x0020 != 0 { "open " } else { "" };
+                    let name = self.module_name(module.module_name_index)?;
+                    match module.module_version_index {
+                        0 => Ok(format!("{}module {}", open, name)),
+                        index => Ok(format!("{}module {}@{}", open, name, self.utf8(index)?)),
+                    }
+                }
+                None => Ok("module".to_string()),
+            };
+        }
+
+        let is_interface = class_file.access_flags.is_interface();
+        // Interfaces are abstract by definition, so javap leaves that out
+        let flags = match is_interface {
+            true => class_file.access_flags.0 & !ClassAccessFlags::ABSTRACT.0,
+            false => class_file.access_flags.0,
+        };
+        let mut declaration = modifiers(flags, &CLASS_MODIFIERS);
+        declaration.push_str(if is_interface { "interface " } else { "class " });
+        declaration.push_str(&java_name(self.class_name(class_file.this_class)?));
+
+        // Signatures that don't parse are ignored, like the JVM does
+        let signature = match class_file.signature_index() {
+            Some(index) => ClassSignature::parse(self.utf8(index)?).ok(),
+            None => None,
+        };
+        match signature {
+            Some(signature) => declaration.push_str(&syntax::class_signature(&signature, is_interface)),
+            None => {
+                if !is_interface && class_file.super_class != 0 {
+                    let super_class = self.class_name(class_file.super_class)?;
+                    if super_class != "java/lang/Object" {
+                        declaration.push_str(&format!(" extends {}", java_name(super_class)));
+                    }
+                }
+                if !class_file.interfaces.is_empty() {
+                    let mut interfaces = Vec::new();
+                    for &interface in &class_file.interfaces {
+                        interfaces.push(java_name(self.class_name(interface)?));
+                    }
+                    // Unlike with a signature, javap leaves out the space after the commas here
+                    let keyword = if is_interface { "extends" } else { "implements" };
+                    declaration.push_str(&format!(" {} {}", keyword, interfaces.join(",")));
+                }
+            }
+        }
+        Ok(declaration)
+    }
+
+    fn constant_pool(&mut self) -> Result<(), ClassFormatError> {
+        let constant_pool = &self.class_file.constant_pool;
+        // Indexes are right-aligned, as wide as constant_pool_count would be
+        let width = format!("#{}", constant_pool.count()).len();
+        self.indent = 2;
+        for (index, info) in constant_pool.iter() {
+            self.context.push(format!("constant_pool[{}]", index));
+            let (kind, operands) = match info {
+                ConstantPoolInfo::Utf8 { string } => ("Utf8", escape(string.as_str())),
+                ConstantPoolInfo::Integer { .. } => ("Integer", self.constant_value(index)?),
+                ConstantPoolInfo::Float { .. } => ("Float", self.constant_value(index)?),
+                ConstantPoolInfo::Long { .. } => ("Long", self.constant_value(index)?),
+                ConstantPoolInfo::Double { .. } => ("Double", self.constant_value(index)?),
+                ConstantPoolInfo::Class { name_index } => ("Class", format!("#{}", name_index)),
+                ConstantPoolInfo::String { string_index } => ("String", format!("#{}", string_index)),
+                ConstantPoolInfo::FieldRef { class_index, name_and_type_index } =>
+                    ("Fieldref", format!("#{}.#{}", class_index, name_and_type_index)),
+                ConstantPoolInfo::MethodRef { class_index, name_and_type_index } =>
+                    ("Methodref", format!("#{}.#{}", class_index, name_and_type_index)),
+                ConstantPoolInfo::InterfaceMethodRef { class_index, name_and_type_index } =>
+                    ("InterfaceMethodref", format!("#{}.#{}", class_index, name_and_type_index)),
+                ConstantPoolInfo::NameAndType { name_index, descriptor_index } =>
+                    ("NameAndType", format!("#{}:#{}", name_index, descriptor_index)),
+                ConstantPoolInfo::MethodHandle { reference_kind, reference_index } =>
+                    ("MethodHandle", format!("{}:#{}", reference_kind, reference_index)),
+                ConstantPoolInfo::MethodType { descriptor_index } => ("MethodType", format!("#{}", descriptor_index)),
+                ConstantPoolInfo::Dynamic { bootstrap_method_attr_index, name_and_type_index } =>
+                    ("Dynamic", format!("#{}:#{}", bootstrap_method_attr_index, name_and_type_index)),
+                ConstantPoolInfo::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } =>
+                    ("InvokeDynamic", format!("#{}:#{}", bootstrap_method_attr_index, name_and_type_index)),
+                ConstantPoolInfo::Module { name_index } => ("Module", format!("#{}", name_index)),
+                ConstantPoolInfo::Package { name_index } => ("Package", format!("#{}", name_index)),
+                ConstantPoolInfo::Unusable => unreachable!("ConstantPool::iter skips unusable entries"),
+            };
+            let text = format!("{:>width$} = {:<18} {}", format!("#{}", index), kind, operands, width = width);
+            match info {
+                ConstantPoolInfo::Utf8 { .. } | ConstantPoolInfo::Integer { .. } | ConstantPoolInfo::Float { .. } |
+                ConstantPoolInfo::Long { .. } | ConstantPoolInfo::Double { .. } => self.line(&text),
+                // javap leaves an extra space in front of method types
+                ConstantPoolInfo::MethodType { .. } =>
+                    self.line_with_comment(&text, &format!(" {}", self.constant_value(index)?)),
+                _ => self.line_with_comment(&text, &self.constant_value(index)?),
+            }
+            self.context.pop();
+        }
+        self.indent = 0;
+        Ok(())
+    }
+
+    fn field(&mut self, field: &'a FieldInfo) -> Result<(), ClassFormatError> {
+        let name = self.utf8(field.name_index)?;
+        let descriptor = self.utf8(field.descriptor_index)?;
+        let field_type = FieldType::parse(descriptor)
+            .map_err(|error| self.error(ClassFormatErrorKind::InvalidDescriptor(error)))?;
+        let signature = match field.signature_index() {
+            Some(index) => FieldSignature::parse(self.utf8(index)?).ok(),
+            None => None,
+        };
+        let java_type = match signature {
+            Some(signature) => syntax::type_signature(&signature.0),
+            None => syntax::field_type(&field_type),
+        };
+        self.line(&format!("{}{} {};", modifiers(field.access_flags.0, &FIELD_MODIFIERS), java_type, name));
+        self.indent += 2;
+        self.line(&format!("descriptor: {}", descriptor));
+        self.line(&flags_line(field.access_flags.0, &FIELD_FLAGS));
+        self.attributes(&field.attributes)?;
+        self.indent -= 2;
+        Ok(())
+    }
+
+    fn method(&mut self, method: &'a MethodInfo) -> Result<(), ClassFormatError> {
+        let class_file = self.class_file;
+        let name = self.utf8(method.name_index)?;
+        let descriptor = self.utf8(method.descriptor_index)?;
+        let method_descriptor = MethodDescriptor::parse(descriptor)
+            .map_err(|error| self.error(ClassFormatErrorKind::InvalidDescriptor(error)))?;
+        let flags = method.access_flags;
+
+        let mut declaration = modifiers(flags.0, &METHOD_MODIFIERS);
+        if class_file.access_flags.is_interface() && !flags.is_abstract() && !flags.is_static() && !flags.is_private() {
+            declaration.push_str("default ");
+        }
+        let signature = match method.signature_index() {
+            Some(index) => MethodSignature::parse(self.utf8(index)?).ok(),
+            None => None,
+        };
+        let (mut parameters, return_type, mut throws): (Vec<String>, String, Vec<String>) = match &signature {
+            Some(signature) => {
+                let type_parameters = syntax::type_parameters(&signature.type_parameters);
+                if !type_parameters.is_empty() {
+                    declaration.push_str(&format!("{} ", type_parameters));
+                }
+                (signature.parameters.iter().map(syntax::type_signature).collect(),
+                 signature.result.as_ref().map_or("void".to_string(), syntax::type_signature),
+                 signature.throws.iter().map(syntax::type_signature).collect())
+            }
+            None => (method_descriptor.parameters.iter().map(syntax::field_type).collect(),
+                     method_descriptor.return_type.as_ref().map_or("void".to_string(), syntax::field_type),
+                     Vec::new()),
+        };
+        if throws.is_empty() {
+            for &exception in method.exception_indexes() {
+                throws.push(java_name(self.class_name(exception)?));
+            }
+        }
+        if flags.is_varargs() {
+            if let Some(last) = parameters.last_mut() {
+                if let Some(component_type) = last.strip_suffix("[]") {
+                    *last = format!("{}...", component_type);
+                }
+            }
+        }
+        match name {
+            "<clinit>" => declaration.push_str("{}"),
+            "<init>" => {
+                let class_name = java_name(self.class_name(class_file.this_class)?);
+                declaration.push_str(&format!("{}({})", class_name, parameters.join(", ")));
+            }
+            _ => declaration.push_str(&format!("{} {}({})", return_type, name, parameters.join(", "))),
+        }
+        if !throws.is_empty() {
+            declaration.push_str(&format!(" throws {}", throws.join(", ")));
+        }
+        declaration.push(';');
+        self.line(&declaration);
+
+        self.indent += 2;
+        self.line(&format!("descriptor: {}", descriptor));
+        self.line(&flags_line(flags.0, &METHOD_FLAGS));
+        self.args_size = method_descriptor.parameters.len() as u16 + if flags.is_static() { 0 } else { 1 };
+        self.attributes(&method.attributes)?;
+        self.indent -= 2;
+        Ok(())
+    }
+
+    fn attributes(&mut self, attributes: &'a [Attribute]) -> Result<(), ClassFormatError> {
+        for attribute in attributes {
+            self.context.push(format!("attribute {}", attribute.name()));
+            self.attribute(&attribute.info)?;
+            self.context.pop();
+        }
+        Ok(())
+    }
+
+    fn attribute(&mut self, attribute: &'a AttributeInfo) -> Result<(), ClassFormatError> {
+        match attribute {
+            AttributeInfo::ConstantValue { constantvalue_index } => {
+                let value = self.constant_comment(*constantvalue_index)?;
+                self.line(&format!("ConstantValue: {}", value));
+            }
+            AttributeInfo::Code { max_stack, max_locals, code, exception_tables, attributes } =>
+                self.code(*max_stack, *max_locals, code, exception_tables, attributes)?,
+            AttributeInfo::LineNumberTable { entries } => {
+                self.line("LineNumberTable:");
+                self.indent += 2;
+                for entry in entries {
+                    self.line(&format!("line {}: {}", entry.line_number, entry.start_pc));
+                }
+                self.indent -= 2;
+            }
+            AttributeInfo::LocalVariableTable { entries } => {
+                let mut rows = Vec::new();
+                for entry in entries {
+                    rows.push((entry.start_pc, entry.length, entry.index, entry.name_index, entry.descriptor_index));
+                }
+                self.local_variables("LocalVariableTable", &rows)?;
+            }
+            AttributeInfo::LocalVariableTypeTable { entries } => {
+                let mut rows = Vec::new();
+                for entry in entries {
+                    rows.push((entry.start_pc, entry.length, entry.index, entry.name_index, entry.signature_index));
+                }
+                self.local_variables("LocalVariableTypeTable", &rows)?;
+            }
+            AttributeInfo::StackMapTable { entries } => self.stack_map_table(entries)?,
+            AttributeInfo::SourceFile { sourcefile_index } => {
+                let source_file = self.utf8(*sourcefile_index)?;
+                self.line(&format!("SourceFile: \"{}\"", escape(source_file)));
+            }
+            AttributeInfo::BootstrapMethods { bootstrap_methods } => {
+                self.line("BootstrapMethods:");
+                self.indent += 2;
+                for (i, bootstrap_method) in bootstrap_methods.iter().enumerate() {
+                    let method_handle = self.constant_value(bootstrap_method.bootstrap_method_ref)?;
+                    self.line(&format!("{}: #{} {}", i, bootstrap_method.bootstrap_method_ref, method_handle));
+                    self.indent += 2;
+                    self.line("Method arguments:");
+                    self.indent += 2;
+                    for &argument in &bootstrap_method.bootstrap_arguments {
+                        let value = self.constant_value(argument)?;
+                        self.line(&format!("#{} {}", argument, value));
+                    }
+                    self.indent -= 4;
+                }
+                self.indent -= 2;
+            }
+            AttributeInfo::NestHost { host_class_index } => {
+                let host_class = check_name(self.class_name(*host_class_index)?);
+                self.line(&format!("NestHost: class {}", host_class));
+            }
+            AttributeInfo::NestMembers { classes } => self.class_list("NestMembers", classes)?,
+            AttributeInfo::PermittedSubclasses { classes } => self.class_list("PermittedSubclasses", classes)?,
+            AttributeInfo::Record { components } => {
+                self.line("Record:");
+                self.indent += 2;
+                for (i, component) in components.iter().enumerate() {
+                    self.context.push(format!("component #{}", i));
+                    let name = self.utf8(component.name_index)?;
+                    let descriptor = self.utf8(component.descriptor_index)?;
+                    let signature = match attr::signature_index(&component.attributes) {
+                        Some(index) => FieldSignature::parse(self.utf8(index)?).ok(),
+                        None => None,
+                    };
+                    let java_type = match (signature, FieldType::parse(descriptor)) {
+                        (Some(signature), _) => syntax::type_signature(&signature.0),
+                        (None, Ok(field_type)) => syntax::field_type(&field_type),
+                        (None, Err(error)) => return Err(self.error(ClassFormatErrorKind::InvalidDescriptor(error))),
+                    };
+                    self.line(&format!("{} {};", java_type, name));
+                    self.indent += 2;
+                    self.line(&format!("descriptor: {}", descriptor));
+                    self.attributes(&component.attributes)?;
+                    self.indent -= 2;
+                    self.blank_line();
+                    self.context.pop();
+                }
+                self.indent -= 2;
+            }
+            AttributeInfo::InnerClasses { classes } => {
+                self.line("InnerClasses:");
+                self.indent += 2;
+                for inner_class in classes {
+                    let mut flags = inner_class.inner_class_access_flags;
+                    if flags & ClassAccessFlags::INTERFACE.0 != 0 {
+                        flags &= !ClassAccessFlags::ABSTRACT.0;
+                    }
+                    let mut text = modifiers(flags, &INNER_CLASS_MODIFIERS);
+                    let mut comment = String::new();
+                    if inner_class.inner_name_index != 0 {
+                        text.push_str(&format!("#{}= ", inner_class.inner_name_index));
+                        comment.push_str(&format!("{}=", self.utf8(inner_class.inner_name_index)?));
+                    }
+                    text.push_str(&format!("#{}", inner_class.inner_class_info_index));
+                    comment.push_str(&format!("class {}", check_name(self.class_name(inner_class.inner_class_info_index)?)));
+                    if inner_class.outer_class_info_index != 0 {
+                        text.push_str(&format!(" of #{}", inner_class.outer_class_info_index));
+                        let outer_class = check_name(self.class_name(inner_class.outer_class_info_index)?);
+                        comment.push_str(&format!(" of class {}", outer_class));
+                    }
+                    text.push(';');
+                    self.line_with_comment(&text, &comment);
+                }
+                self.indent -= 2;
+            }
+            AttributeInfo::EnclosingMethod { class_index, method_index } => {
+                let mut comment = java_name(self.class_name(*class_index)?);
+                if *method_index != 0 {
+                    let (name, _) = self.class_file.constant_pool.name_and_type(*method_index)
+                        .map_err(|kind| self.error(kind))?;
+                    comment.push_str(&format!(".{}", name));
+                }
+                self.line_with_comment(&format!("EnclosingMethod: #{}.#{}", class_index, method_index), &comment);
+            }
+            AttributeInfo::Signature { signature_index } => {
+                let signature = self.utf8(*signature_index)?;
+                self.line_with_comment(&format!("Signature: #{}", signature_index), signature);
+            }
+            AttributeInfo::Exceptions { exception_index_table } => {
+                self.line("Exceptions:");
+                let mut exceptions = Vec::new();
+                for &exception in exception_index_table {
+                    exceptions.push(java_name(self.class_name(exception)?));
+                }
+                self.indent += 2;
+                self.line(&format!("throws {}", exceptions.join(", ")));
+                self.indent -= 2;
+            }
+            AttributeInfo::Synthetic => self.line("Synthetic: true"),
+            AttributeInfo::Deprecated => self.line("Deprecated: true"),
+            AttributeInfo::MethodParameters { parameters } => {
+                self.line("MethodParameters:");
+                self.indent += 2;
+                self.line(&format!("{:<30} {}", "Name", "Flags"));
+                for parameter in parameters {
+                    let name = match parameter.name_index {
+                        0 => "<no name>",
+                        index => self.utf8(index)?,
+                    };
+                    let flags = flag_names(parameter.access_flags, &PARAMETER_FLAGS).join(" ");
+                    self.line(format!("{:<30} {}", name, flags).trim_end());
+                }
+                self.indent -= 2;
+            }
+            AttributeInfo::SourceDebugExtension { debug_extension } => {
+                self.line("SourceDebugExtension:");
+                self.indent += 2;
+                for line in String::from_utf8_lossy(debug_extension).lines() {
+                    self.line(line);
+                }
+                self.indent -= 2;
+            }
+            AttributeInfo::RuntimeVisibleAnnotations { annotations } |
+            AttributeInfo::RuntimeInvisibleAnnotations { annotations } => {
+                self.line(&format!("{}:", attribute.name()));
+                self.indent += 2;
+                self.annotations(annotations)?;
+                self.indent -= 2;
+            }
+            AttributeInfo::RuntimeVisibleParameterAnnotations { parameter_annotations } |
+            AttributeInfo::RuntimeInvisibleParameterAnnotations { parameter_annotations } => {
+                self.line(&format!("{}:", attribute.name()));
+                self.indent += 2;
+                for (i, annotations) in parameter_annotations.iter().enumerate() {
+                    self.line(&format!("parameter {}:", i));
+                    self.indent += 2;
+                    self.annotations(annotations)?;
+                    self.indent -= 2;
+                }
+                self.indent -= 2;
+            }
+            AttributeInfo::RuntimeVisibleTypeAnnotations { annotations } |
+            AttributeInfo::RuntimeInvisibleTypeAnnotations { annotations } => {
+                self.line(&format!("{}:", attribute.name()));
+                self.indent += 2;
+                for (i, type_annotation) in annotations.iter().enumerate() {
+                    self.line(&format!("{}: {}: {}", i, raw_annotation(&type_annotation.annotation),
+                                       type_annotation_target(type_annotation)));
+                    let text = self.annotation_text(&type_annotation.annotation, 0)?;
+                    self.indent += 2;
+                    self.lines(&text);
+                    self.indent -= 2;
+                }
+                self.indent -= 2;
+            }
+            AttributeInfo::AnnotationDefault { default_value } => {
+                self.line("AnnotationDefault:");
+                self.indent += 2;
+                self.line(&format!("default_value: {}", raw_element_value(default_value)));
+                let text = self.element_value_text(default_value, 0)?;
+                self.indent += 2;
+                self.lines(&text);
+                self.indent -= 4;
+            }
+            AttributeInfo::Module { module } => self.module(module)?,
+            AttributeInfo::ModulePackages { package_index } => {
+                self.line("ModulePackages:");
+                self.indent += 2;
+                for &package in package_index {
+                    let name = java_name(self.package_name(package)?);
+                    self.line_with_comment(&format!("#{}", package), &name);
+                }
+                self.indent -= 2;
+            }
+            AttributeInfo::ModuleMainClass { main_class_index } => {
+                let main_class = java_name(self.class_name(*main_class_index)?);
+                self.line_with_comment(&format!("ModuleMainClass: #{}", main_class_index), &main_class);
+            }
+            AttributeInfo::Unknown { name, bytes } if self.jdk_attribute(name, bytes)? => {}
+            // javap indents these further than the attributes it knows
+            AttributeInfo::Unknown { name, bytes } => {
+                self.indent += 2;
+                self.line(&format!("{}: length = 0x{:X} (unknown attribute)", name, bytes.len()));
+                self.indent += 1;
+                for chunk in bytes.chunks(16) {
+                    let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02X}", byte)).collect();
+                    self.line(&hex.join(" "));
+                }
+                if bytes.is_empty() {
+                    self.blank_line();
+                }
+                self.indent -= 3;
+            }
+        }
+        Ok(())
+    }
+
+    // The attributes the JDK adds to its own module-info classes, which aren't in the JVMS.
+    // Returns false if the attribute isn't one of them or doesn't parse, so it gets dumped instead.
+    fn jdk_attribute(&mut self, name: &str, bytes: &[u8]) -> Result<bool, ClassFormatError> {
+        let u2 = |offset: usize| bytes.get(offset..offset + 2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+        match name {
+            "ModuleTarget" if bytes.len() == 2 => {
+                let index = u2(0).unwrap_or(0);
+                let platform = match index {
+                    0 => String::new(),
+                    index => self.utf8(index)?.to_string(),
+                };
+                self.line("ModuleTarget:");
+                self.indent += 2;
+                self.line_with_comment(&format!("target_platform: #{}", index), &platform);
+                self.indent -= 2;
+            }
+            "ModuleResolution" if bytes.len() == 2 => {
+                let flags = u2(0).unwrap_or(0);
+                let names: String = MODULE_RESOLUTION_FLAGS.iter()
+                    .filter(|(flag, _)| flags & flag != 0)
+                    .map(|(_, name)| format!(" {}", name))
+                    .collect();
+                self.line("ModuleResolution:");
+                self.indent += 2;
+                self.line_with_comment(&flags.to_string(), &names);
+                self.indent -= 2;
+            }
+            "ModuleHashes" => {
+                // algorithm_index, then hashes_table_length entries of module_name_index and a length-prefixed hash
+                let mut hashes = Vec::new();
+                let mut offset = 4;
+                for _ in 0..u2(2).unwrap_or(0) {
+                    let (Some(module_index), Some(length)) = (u2(offset), u2(offset + 2)) else { return Ok(false) };
+                    let Some(hash) = bytes.get(offset + 4..offset + 4 + length as usize) else { return Ok(false) };
+                    hashes.push((module_index, hash));
+                    offset += 4 + length as usize;
+                }
+                let Some(algorithm_index) = u2(0).filter(|_| offset == bytes.len()) else { return Ok(false) };
+                let algorithm = self.utf8(algorithm_index)?;
+                self.line("ModuleHashes:");
+                self.indent += 2;
+                self.line_with_comment(&format!("algorithm: #{}", algorithm_index), algorithm);
+                self.line_with_comment(&hashes.len().to_string(), "hashes");
+                for (module_index, hash) in hashes {
+                    let module_name = self.module_name(module_index)?;
+                    self.line_with_comment(&format!("#{}", module_index), module_name);
+                    self.line(&format!("hash_length: {}", hash.len()));
+                    let hex: String = hash.iter().map(|byte| format!("{:02x}", byte)).collect();
+                    self.line(&format!("hash: [{}]", hex));
+                }
+                self.indent -= 2;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // Rows of (start_pc, length, slot, name_index, descriptor or signature index)
+    fn local_variables(&mut self, name: &str, rows: &[(u16, u16, u16, u16, u16)]) -> Result<(), ClassFormatError> {
+        self.line(&format!("{}:", name));
+        self.indent += 2;
+        self.line("Start  Length  Slot  Name   Signature");
+        for &(start_pc, length, slot, name_index, type_index) in rows {
+            let name = self.utf8(name_index)?;
+            let type_text = self.utf8(type_index)?;
+            self.line(&format!("{:>5} {:>7} {:>5} {:>5}   {}", start_pc, length, slot, name, type_text));
+        }
+        self.indent -= 2;
+        Ok(())
+    }
+
+    fn class_list(&mut self, name: &str, classes: &[u16]) -> Result<(), ClassFormatError> {
+        self.line(&format!("{}:", name));
+        self.indent += 2;
+        for &class in classes {
+            let class_name = check_name(self.class_name(class)?);
+            self.line(&class_name);
+        }
+        self.indent -= 2;
+        Ok(())
+    }
+
+    fn annotations(&mut self, annotations: &[Annotation]) -> Result<(), ClassFormatError> {
+        for (i, annotation) in annotations.iter().enumerate() {
+            self.line(&format!("{}: {}", i, raw_annotation(annotation)));
+            let text = self.annotation_text(annotation, 0)?;
+            self.indent += 2;
+            self.lines(&text);
+            self.indent -= 2;
+        }
+        Ok(())
+    }
+
+    // The annotation with its constants looked up, one element per line, e.g.
+    // java.lang.annotation.Retention(
+    //   value=Ljava/lang/annotation/RetentionPolicy;.RUNTIME
+    // )
+    fn annotation_text(&self, annotation: &Annotation, indent: usize) -> Result<String, ClassFormatError> {
+        let type_name = self.utf8(annotation.type_index)?;
+        let mut text = match FieldType::parse(type_name) {
+            Ok(field_type) => syntax::field_type(&field_type),
+            Err(_) => type_name.to_string(),
+        };
+        if !annotation.element_value_pairs.is_empty() {
+            text.push_str("(\n");
+            for pair in &annotation.element_value_pairs {
+                let name = self.utf8(pair.element_name_index)?;
+                let value = self.element_value_text(&pair.value, indent + 2)?;
+                text.push_str(&format!("{}{}={}\n", " ".repeat(indent + 2), name, value));
+            }
+            text.push_str(&format!("{})", " ".repeat(indent)));
+        }
+        Ok(text)
+    }
+
+    fn element_value_text(&self, value: &ElementValue, indent: usize) -> Result<String, ClassFormatError> {
+        Ok(match value {
+            ElementValue::Const { tag, const_value_index } => {
+                let index = *const_value_index;
+                match tag {
+                    b'B' => format!("(byte) {}", self.integer(index)?),
+                    b'S' => format!("(short) {}", self.integer(index)?),
+                    b'C' => {
+                        let c = char::from_u32(self.integer(index)? as u32).unwrap_or(char::REPLACEMENT_CHARACTER);
+                        format!("'{}'", escape(&c.to_string()))
+                    }
+                    b'Z' => (self.integer(index)? != 0).to_string(),
+                    b's' => format!("\"{}\"", escape(self.utf8(index)?)),
+                    _ => self.constant_value(index)?,
+                }
+            }
+            ElementValue::Enum { type_name_index, const_name_index } =>
+                format!("{}.{}", self.utf8(*type_name_index)?, self.utf8(*const_name_index)?),
+            ElementValue::Class { class_info_index } => format!("class {}", self.utf8(*class_info_index)?),
+            ElementValue::Annotation(annotation) => format!("@{}", self.annotation_text(annotation, indent)?),
+            ElementValue::Array(values) => {
+                let mut texts = Vec::new();
+                for value in values {
+                    texts.push(self.element_value_text(value, indent)?);
+                }
+                format!("[{}]", texts.join(","))
+            }
+        })
+    }
+
+    fn module(&mut self, module: &Module) -> Result<(), ClassFormatError> {
+        self.line("Module:");
+        self.indent += 2;
+        let name = self.module_name(module.module_name_index)?;
+        self.flagged_line(module.module_name_index, module.module_flags, &check_name(name), &MODULE_FLAGS);
+        self.version_line(module.module_version_index)?;
+
+        self.line_with_comment(&module.requires.len().to_string(), "requires");
+        self.indent += 2;
+        for requires in &module.requires {
+            let name = self.module_name(requires.requires_index)?;
+            self.flagged_line(requires.requires_index, requires.requires_flags, &check_name(name), &REQUIRES_FLAGS);
+            self.version_line(requires.requires_version_index)?;
+        }
+        self.indent -= 2;
+
+        let packages = [("exports", &module.exports.iter()
+                            .map(|exports| (exports.exports_index, exports.exports_flags, &exports.exports_to_index))
+                            .collect::<Vec<_>>()),
+                        ("opens", &module.opens.iter()
+                            .map(|opens| (opens.opens_index, opens.opens_flags, &opens.opens_to_index))
+                            .collect::<Vec<_>>())];
+        for (keyword, entries) in packages {
+            self.line_with_comment(&entries.len().to_string(), keyword);
+            self.indent += 2;
+            for &(package, flags, to) in entries.iter() {
+                let mut name = check_name(self.package_name(package)?);
+                if !to.is_empty() {
+                    name.push_str(&format!(" to ... {}", to.len()));
+                }
+                self.flagged_line(package, flags, &name, &EXPORTS_FLAGS);
+                self.indent += 2;
+                for &module in to.iter() {
+                    let module_name = check_name(self.module_name(module)?);
+                    self.line_with_comment(&format!("#{}", module), &format!("... to {}", module_name));
+                }
+                self.indent -= 2;
+            }
+            self.indent -= 2;
+        }
+
+        self.line_with_comment(&module.uses_index.len().to_string(), "uses");
+        self.indent += 2;
+        for &service in &module.uses_index {
+            let service_name = check_name(self.class_name(service)?);
+            self.line_with_comment(&format!("#{}", service), &service_name);
+        }
+        self.indent -= 2;
+
+        self.line_with_comment(&module.provides.len().to_string(), "provides");
+        self.indent += 2;
+        for provides in &module.provides {
+            let service_name = check_name(self.class_name(provides.provides_index)?);
+            self.line_with_comment(&format!("#{}", provides.provides_index),
+                                   &format!("{} with ... {}", service_name, provides.provides_with_index.len()));
+            self.indent += 2;
+            for &implementation in &provides.provides_with_index {
+                let implementation_name = check_name(self.class_name(implementation)?);
+                self.line_with_comment(&format!("#{}", implementation), &format!("... with {}", implementation_name));
+            }
+            self.indent -= 2;
+        }
+        self.indent -= 4;
+        Ok(())
+    }
+
+    // #8,8000    // "java.base" ACC_MANDATED
+    fn flagged_line(&mut self, index: u16, flags: u16, name: &str, table: &[(u16, &'static str)]) {
+        let mut comment = name.to_string();
+        for flag_name in flag_names(flags, table) {
+            comment.push_str(&format!(" {}", flag_name));
+        }
+        self.line_with_comment(&format!("#{},{:x}", index, flags), &comment);
+    }
+
+    // Zero if there is no version
+    fn version_line(&mut self, version_index: u16) -> Result<(), ClassFormatError> {
+        match version_index {
+            0 => self.line("#0"),
+            index => {
+                let version = escape(self.utf8(index)?);
+                self.line_with_comment(&format!("#{}", index), &version);
+            }
+        }
+        Ok(())
+    }
+
+    // A constant the way javap shows it in comments, e.g. java/lang/Object."<init>":()V for a Methodref
+    fn constant_value(&self, index: u16) -> Result<String, ClassFormatError> {
+        let constant_pool = &self.class_file.constant_pool;
+        Ok(match self.constant(index)? {
+            ConstantPoolInfo::Utf8 { string } => escape(string.as_str()),
+            ConstantPoolInfo::Integer { bytes } => (*bytes as i32).to_string(),
+            ConstantPoolInfo::Float { bytes } => format!("{}f", java_float(f32::from_bits(*bytes))),
+            ConstantPoolInfo::Long { high_bytes, low_bytes } =>
+                format!("{}l", ((*high_bytes as u64) << 32 | *low_bytes as u64) as i64),
+            ConstantPoolInfo::Double { high_bytes, low_bytes } =>
+                format!("{}d", java_double(f64::from_bits((*high_bytes as u64) << 32 | *low_bytes as u64))),
+            ConstantPoolInfo::Class { name_index } => check_name(self.utf8(*name_index)?),
+            ConstantPoolInfo::String { string_index } => escape(self.utf8(*string_index)?),
+            ConstantPoolInfo::FieldRef { .. } |
+            ConstantPoolInfo::MethodRef { .. } |
+            ConstantPoolInfo::InterfaceMethodRef { .. } => self.member_value(index, false)?,
+            ConstantPoolInfo::NameAndType { .. } => {
+                let (name, descriptor) = constant_pool.name_and_type(index).map_err(|kind| self.error(kind))?;
+                format!("{}:{}", check_name(name), descriptor)
+            }
+            ConstantPoolInfo::MethodHandle { reference_kind, reference_index } =>
+                format!("{} {}", reference_kind_name(*reference_kind), self.member_value(*reference_index, false)?),
+            ConstantPoolInfo::MethodType { descriptor_index } => self.utf8(*descriptor_index)?.to_string(),
+            ConstantPoolInfo::Dynamic { bootstrap_method_attr_index, name_and_type_index } |
+            ConstantPoolInfo::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                let (name, descriptor) = constant_pool.name_and_type(*name_and_type_index)
+                    .map_err(|kind| self.error(kind))?;
+                format!("#{}:{}:{}", bootstrap_method_attr_index, check_name(name), descriptor)
+            }
+            ConstantPoolInfo::Module { name_index } | ConstantPoolInfo::Package { name_index } =>
+                check_name(self.utf8(*name_index)?),
+            ConstantPoolInfo::Unusable => unreachable!("ConstantPool::get rejects unusable entries"),
+        })
+    }
+
+    // class.name:descriptor for a Fieldref, Methodref or InterfaceMethodref. Instructions leave out the
+    // class if it's the one being printed.
+    fn member_value(&self, index: u16, omit_this_class: bool) -> Result<String, ClassFormatError> {
+        let constant_pool = &self.class_file.constant_pool;
+        let (class_name, name, descriptor) = constant_pool.member_ref(index).map_err(|kind| self.error(kind))?;
+        let this_class = constant_pool.class_name(self.class_file.this_class).ok();
+        Ok(match omit_this_class && this_class == Some(class_name) {
+            true => format!("{}:{}", check_name(name), descriptor),
+            false => format!("{}.{}:{}", check_name(class_name), check_name(name), descriptor),
+        })
+    }
+
+    // A constant with its kind in front, the way javap shows constant operands, e.g. "String hello"
+    fn constant_comment(&self, index: u16) -> Result<String, ClassFormatError> {
+        let kind = match self.constant(index)? {
+            ConstantPoolInfo::FieldRef { .. } => return Ok(format!("Field {}", self.member_value(index, true)?)),
+            ConstantPoolInfo::MethodRef { .. } => return Ok(format!("Method {}", self.member_value(index, true)?)),
+            ConstantPoolInfo::InterfaceMethodRef { .. } =>
+                return Ok(format!("InterfaceMethod {}", self.member_value(index, true)?)),
+            ConstantPoolInfo::Class { .. } => "class",
+            ConstantPoolInfo::String { .. } => "String",
+            ConstantPoolInfo::Integer { .. } => "int",
+            ConstantPoolInfo::Float { .. } => "float",
+            ConstantPoolInfo::Long { .. } => "long",
+            ConstantPoolInfo::Double { .. } => "double",
+            ConstantPoolInfo::MethodType { .. } => "MethodType",
+            ConstantPoolInfo::MethodHandle { .. } => "MethodHandle",
+            ConstantPoolInfo::Dynamic { .. } => "Dynamic",
+            ConstantPoolInfo::InvokeDynamic { .. } => "InvokeDynamic",
+            _ => return self.constant_value(index),
+        };
+        Ok(format!("{} {}", kind, self.constant_value(index)?))
+    }
+
+    fn constant(&self, index: u16) -> Result<&'a ConstantPoolInfo, ClassFormatError> {
+        self.class_file.constant_pool.get(index).map_err(|kind| self.error(kind))
+    }
+
+    fn utf8(&self, index: u16) -> Result<&'a str, ClassFormatError> {
+        self.class_file.constant_pool.utf8(index).map_err(|kind| self.error(kind))
+    }
+
+    fn class_name(&self, index: u16) -> Result<&'a str, ClassFormatError> {
+        self.class_file.constant_pool.class_name(index).map_err(|kind| self.error(kind))
+    }
+
+    fn module_name(&self, index: u16) -> Result<&'a str, ClassFormatError> {
+        self.class_file.constant_pool.module_name(index).map_err(|kind| self.error(kind))
+    }
+
+    fn package_name(&self, index: u16) -> Result<&'a str, ClassFormatError> {
+        self.class_file.constant_pool.package_name(index).map_err(|kind| self.error(kind))
+    }
+
+    fn integer(&self, index: u16) -> Result<i32, ClassFormatError> {
+        match self.constant(index)? {
+            ConstantPoolInfo::Integer { bytes } => Ok(*bytes as i32),
+            _ => Err(self.error(ClassFormatErrorKind::WrongConstantKind { index, expected: "Integer" })),
+        }
+    }
+
+    // Like javap, never leaves spaces at the end of a line, even when a string constant ends in one
+    fn line(&mut self, text: &str) {
+        self.out.push_str(&" ".repeat(self.indent));
+        self.out.push_str(text);
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        self.out.push('\n');
+    }
+
+    // Text with a newline in it, as separate lines
+    fn lines(&mut self, text: &str) {
+        for line in text.lines() {
+            self.line(line);
+        }
+    }
+
+    fn line_with_comment(&mut self, text: &str, comment: &str) {
+        let padding = COMMENT_COLUMN.saturating_sub(text.chars().count()).max(1);
+        self.line(&format!("{}{}// {}", text, " ".repeat(padding), comment));
+    }
+
+    fn blank_line(&mut self) {
+        self.out.push('\n');
+    }
+
+    fn error(&self, kind: ClassFormatErrorKind) -> ClassFormatError {
+        ClassFormatError::new(kind, None, self.context.join(" "))
+    }
+}
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.3.5
+fn reference_kind_name(reference_kind: u8) -> String {
+    match reference_kind {
+        1 => "REF_getField".to_string(),
+        2 => "REF_getStatic".to_string(),
+        3 => "REF_putField".to_string(),
+        4 => "REF_putStatic".to_string(),
+        5 => "REF_invokeVirtual".to_string(),
+        6 => "REF_invokeStatic".to_string(),
+        7 => "REF_invokeSpecial".to_string(),
+        8 => "REF_newInvokeSpecial".to_string(),
+        9 => "REF_invokeInterface".to_string(),
+        _ => format!("REF_{}", reference_kind),
+    }
+}
+
+// The annotation with constant pool indexes rather than values, e.g. #27(#28=e#29.#30)
+fn raw_annotation(annotation: &Annotation) -> String {
+    let pairs: Vec<String> = annotation.element_value_pairs.iter()
+        .map(|pair| format!("#{}={}", pair.element_name_index, raw_element_value(&pair.value)))
+        .collect();
+    format!("#{}({})", annotation.type_index, pairs.join(","))
+}
+
+fn raw_element_value(value: &ElementValue) -> String {
+    match value {
+        ElementValue::Const { tag, const_value_index } => format!("{}#{}", *tag as char, const_value_index),
+        ElementValue::Enum { type_name_index, const_name_index } => format!("e#{}.#{}", type_name_index, const_name_index),
+        ElementValue::Class { class_info_index } => format!("c#{}", class_info_index),
+        ElementValue::Annotation(annotation) => format!("@{}", raw_annotation(annotation)),
+        ElementValue::Array(values) => {
+            let values: Vec<String> = values.iter().map(raw_element_value).collect();
+            format!("[{}]", values.join(","))
+        }
+    }
+}
+
+// What a type annotation applies to, e.g. METHOD_FORMAL_PARAMETER, param_index=1
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.20.1
+fn type_annotation_target(type_annotation: &TypeAnnotation) -> String {
+    let mut text = match type_annotation.target_type {
+        0x00 => "CLASS_TYPE_PARAMETER",
+        0x01 => "METHOD_TYPE_PARAMETER",
+        0x10 => "CLASS_EXTENDS",
+        0x11 => "CLASS_TYPE_PARAMETER_BOUND",
+        0x12 => "METHOD_TYPE_PARAMETER_BOUND",
+        0x13 => "FIELD",
+        0x14 => "METHOD_RETURN",
+        0x15 => "METHOD_RECEIVER",
+        0x16 => "METHOD_FORMAL_PARAMETER",
+        0x17 => "THROWS",
+        0x40 => "LOCAL_VARIABLE",
+        0x41 => "RESOURCE_VARIABLE",
+        0x42 => "EXCEPTION_PARAMETER",
+        0x43 => "INSTANCEOF",
+        0x44 => "NEW",
+        0x45 => "CONSTRUCTOR_REFERENCE",
+        0x46 => "METHOD_REFERENCE",
+        0x47 => "CAST",
+        0x48 => "CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT",
+        0x49 => "METHOD_INVOCATION_TYPE_ARGUMENT",
+        0x4a => "CONSTRUCTOR_REFERENCE_TYPE_ARGUMENT",
+        0x4b => "METHOD_REFERENCE_TYPE_ARGUMENT",
+        _ => "UNKNOWN",
+    }.to_string();
+    match &type_annotation.target_info {
+        TargetInfo::TypeParameter { type_parameter_index } =>
+            text.push_str(&format!(", param_index={}", type_parameter_index)),
+        TargetInfo::Supertype { supertype_index } => text.push_str(&format!(", type_index={}", supertype_index)),
+        TargetInfo::TypeParameterBound { type_parameter_index, bound_index } =>
+            text.push_str(&format!(", param_index={}, bound_index={}", type_parameter_index, bound_index)),
+        TargetInfo::Empty => {}
+        TargetInfo::FormalParameter { formal_parameter_index } =>
+            text.push_str(&format!(", param_index={}", formal_parameter_index)),
+        TargetInfo::Throws { throws_type_index } => text.push_str(&format!(", type_index={}", throws_type_index)),
+        TargetInfo::Localvar { table } => {
+            let entries: Vec<String> = table.iter()
+                .map(|entry| format!("start_pc={}, length={}, index={}", entry.start_pc, entry.length, entry.index))
+                .collect();
+            text.push_str(&format!(", {{{}}}", entries.join("; ")));
+        }
+        TargetInfo::Catch { exception_table_index } =>
+            text.push_str(&format!(", exception_index={}", exception_table_index)),
+        TargetInfo::Offset { offset } => text.push_str(&format!(", offset={}", offset)),
+        TargetInfo::TypeArgument { offset, type_argument_index } =>
+            text.push_str(&format!(", offset={}, type_index={}", offset, type_argument_index)),
+    }
+    if !type_annotation.target_path.is_empty() {
+        let path: Vec<String> = type_annotation.target_path.iter().map(|entry| match entry.type_path_kind {
+            0 => "ARRAY".to_string(),
+            1 => "INNER_TYPE".to_string(),
+            2 => "WILDCARD".to_string(),
+            _ => format!("TYPE_ARGUMENT({})", entry.type_argument_index),
+        }).collect();
+        text.push_str(&format!(", location=[{}]", path.join(", ")));
+    }
+    text
+}
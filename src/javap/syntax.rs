@@ -0,0 +1,155 @@
+use crate::descriptor::FieldType;
+use crate::signature::{BaseType, ClassSignature, ClassTypeSignature, TypeArgument, TypeParameter, TypeSignature};
+
+// Java source syntax for the types in descriptors and signatures, the way javap prints declarations.
+// Class names keep their '$', since without the InnerClasses attribute there is no telling where a
+// nested class name starts.
+
+// java/util/Map$Entry becomes java.util.Map$Entry
+pub fn java_name(binary_name: &str) -> String {
+    binary_name.replace('/', ".")
+}
+
+pub fn field_type(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::Byte => "byte".to_string(),
+        FieldType::Char => "char".to_string(),
+        FieldType::Double => "double".to_string(),
+        FieldType::Float => "float".to_string(),
+        FieldType::Int => "int".to_string(),
+        FieldType::Long => "long".to_string(),
+        FieldType::Short => "short".to_string(),
+        FieldType::Boolean => "boolean".to_string(),
+        FieldType::Object(class_name) => java_name(class_name),
+        FieldType::Array(component_type) => format!("{}[]", self::field_type(component_type)),
+    }
+}
+
+pub fn type_signature(signature: &TypeSignature) -> String {
+    match signature {
+        TypeSignature::Base(base_type) => match base_type {
+            BaseType::Byte => "byte",
+            BaseType::Char => "char",
+            BaseType::Double => "double",
+            BaseType::Float => "float",
+            BaseType::Int => "int",
+            BaseType::Long => "long",
+            BaseType::Short => "short",
+            BaseType::Boolean => "boolean",
+        }.to_string(),
+        TypeSignature::Class(class_type) => self::class_type(class_type),
+        TypeSignature::TypeVariable(name) => name.clone(),
+        TypeSignature::Array(component_type) => format!("{}[]", type_signature(component_type)),
+    }
+}
+
+pub fn class_type(class_type: &ClassTypeSignature) -> String {
+    let mut text = String::new();
+    if !class_type.package.is_empty() {
+        text.push_str(&java_name(&class_type.package));
+        text.push('.');
+    }
+    for (i, class) in class_type.classes.iter().enumerate() {
+        if i > 0 {
+            text.push('.');
+        }
+        text.push_str(&class.name);
+        if !class.type_arguments.is_empty() {
+            let type_arguments: Vec<String> = class.type_arguments.iter().map(type_argument).collect();
+            text.push_str(&format!("<{}>", type_arguments.join(", ")));
+        }
+    }
+    text
+}
+
+fn type_argument(type_argument: &TypeArgument) -> String {
+    match type_argument {
+        TypeArgument::Any => "?".to_string(),
+        TypeArgument::Exact(signature) => type_signature(signature),
+        TypeArgument::Extends(signature) => format!("? extends {}", type_signature(signature)),
+        TypeArgument::Super(signature) => format!("? super {}", type_signature(signature)),
+    }
+}
+
+// <T extends java.lang.Comparable<T>, U extends java.lang.Object>, or nothing if there are none.
+// A class bound of Object is printed anyway, as javap -v does.
+pub fn type_parameters(type_parameters: &[TypeParameter]) -> String {
+    if type_parameters.is_empty() {
+        return String::new();
+    }
+    let type_parameters: Vec<String> = type_parameters.iter().map(|type_parameter| {
+        let bounds: Vec<String> = type_parameter.class_bound.iter()
+            .chain(type_parameter.interface_bounds.iter())
+            .map(type_signature)
+            .collect();
+        match bounds.is_empty() {
+            true => type_parameter.name.clone(),
+            false => format!("{} extends {}", type_parameter.name, bounds.join(" & ")),
+        }
+    }).collect();
+    format!("<{}>", type_parameters.join(", "))
+}
+
+// Everything in a class declaration after the class name
+pub fn class_signature(signature: &ClassSignature, is_interface: bool) -> String {
+    let mut text = type_parameters(&signature.type_parameters);
+    let superinterfaces: Vec<String> = signature.superinterfaces.iter().map(class_type).collect();
+    if is_interface {
+        if !superinterfaces.is_empty() {
+            text.push_str(&format!(" extends {}", superinterfaces.join(", ")));
+        }
+    } else {
+        text.push_str(&format!(" extends {}", class_type(&signature.superclass)));
+        if !superinterfaces.is_empty() {
+            text.push_str(&format!(" implements {}", superinterfaces.join(", ")));
+        }
+    }
+    text
+}
+
+// Escapes a string the way javap does, which is mostly the way Java source does
+pub fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '"' => escaped.push_str("\\\""),
+            '\'' => escaped.push_str("\\'"),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Quotes names that aren't made up of Java identifiers separated by '/', like "<init>", "[I" or "my.module"
+pub fn check_name(name: &str) -> String {
+    let mut previous = '/';
+    for c in name.chars() {
+        let valid = match previous {
+            '/' => is_identifier_start(c),
+            _ => c == '/' || is_identifier_part(c),
+        };
+        if !valid {
+            return format!("\"{}\"", escape(name));
+        }
+        previous = c;
+    }
+    match name.is_empty() {
+        true => "\"\"".to_string(),
+        false => name.to_string(),
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_identifier_part(c: char) -> bool {
+    is_identifier_start(c) || c.is_numeric()
+}
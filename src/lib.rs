@@ -0,0 +1,14 @@
+pub mod attr;
+pub mod bytecode;
+pub mod class;
+pub mod classpath;
+pub mod constant_pool;
+pub mod descriptor;
+pub mod field;
+pub mod interpreter;
+pub mod jasmin;
+pub mod javap;
+pub mod method;
+pub mod signature;
+pub mod util;
+pub mod zip;
@@ -1,146 +1,281 @@
-extern crate core;
-
-mod attr;
-mod class;
-mod method;
-mod constant_pool;
-mod field;
-mod util;
-mod frame;
-
-use crate::class::ClassFile;
-use crate::class::loading::ClassFileLoader;
-use crate::attr::AttributeInfo;
-use crate::frame::Frame;
-use crate::constant_pool::ConstantPoolInfo;
-use crate::util::ByteReader;
+use jvmmy::class::format_check::is_binary_name;
+use jvmmy::class::loading::ClassFileLoader;
+use jvmmy::classpath::manifest::{self, Manifest};
+use jvmmy::classpath::{self, ClassPath, ClassPathEntry};
+use jvmmy::interpreter::{self, Vm};
+use jvmmy::jasmin;
+use jvmmy::javap::disassemble;
+use jvmmy::util::map_file;
+use jvmmy::zip::ZipArchive;
 
+use std::env;
 use std::fs;
-use std::fs::read;
-use std::borrow::Borrow;
+use std::path::{Component, Path};
+use std::process;
+use std::thread;
 
 fn main() {
-    let dir = "/home/vysk/dev/jvm/jvmmy";
-    let filename = "OnePlusOne.class";
-    let path = format!("{}/{}", dir, filename);
-    let contents = read(path).expect("Failed to read file");
-
-    let class: Class;
-    {
-        let mut loader = ClassFileLoader::new(contents);
-        let mut class_file = loader.load();
-
-        // This is where we start resolving the class file into a run-time class representation
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("javap") => {
+            javap(&args[1..]);
+            return;
+        }
+        Some("assemble") => {
+            assemble(&args[1..]);
+            return;
+        }
+        Some("disassemble") => {
+            disassemble_jasmin(&args[1..]);
+            return;
+        }
+        _ => {}
+    }
 
-        let class_name = match class_file.constant_pool[(class_file.this_class as usize) - 1] {
-            ConstantPoolInfo::Class { name_index } => {
-                class_file.find_name(name_index)
-            }
-            _ => panic!("Expected Class attribute at constant_pool[{}]", class_file.this_class.to_owned())
-        };
+    launch(&args);
+}
 
-        let super_class_name = match class_file.constant_pool[(class_file.super_class as usize) - 1] {
-            ConstantPoolInfo::Class { name_index } => {
-                class_file.find_name(name_index)
+// jvmmy [options] <main class> [args...], runs the main method of a class like the java launcher does.
+// jvmmy [options] -jar <jar file> [args...] runs the main class a JAR's manifest names.
+fn launch(args: &[String]) {
+    let mut class_path = None;
+    let mut jar = false;
+    let mut properties = Vec::new();
+    let mut enable_preview = false;
+    let mut index = 0;
+    // Options come before the main class; everything after it is for the program
+    while let Some(arg) = args.get(index).filter(|arg| arg.starts_with('-')) {
+        index += 1;
+        match arg.as_str() {
+            "-cp" | "-classpath" | "--class-path" => match args.get(index) {
+                Some(path) => {
+                    class_path = Some(path.clone());
+                    index += 1;
+                }
+                None => {
+                    eprintln!("Error: {} requires class path specification", arg);
+                    process::exit(1);
+                }
+            },
+            // The JAR takes the place of the main class, so the arguments after it are the program's
+            "-jar" => match args.get(index).is_some() {
+                true => {
+                    jar = true;
+                    break;
+                }
+                false => {
+                    eprintln!("Error: -jar requires jar file specification");
+                    process::exit(1);
+                }
+            },
+            "--enable-preview" => enable_preview = true,
+            "-h" | "-help" | "--help" | "-?" => {
+                print!("{}", usage());
+                return;
             }
-            _ => panic!("Expected Class attribute at constant_pool[{}]", class_file.super_class.to_owned())
-        };
-
-        let mut methods: Vec<Method> = Vec::new();
-
-        while let Some(method_info) = class_file.methods.pop() {
-            // let attributes = method_info.attributes.iter().filter(|&attr| match attr {
-            //     AttributeInfo::Code { .. } => true,
-            //     _ => false
-            // });
-
-            methods.push(Method {
-                name: class_file.find_name(method_info.name_index).to_string(),
-                descriptor: class_file.find_name(method_info.descriptor_index).to_string(),
-                class_name: class_name.to_string(),
-                attributes: method_info.attributes,
-            });
+            _ => match (arg.strip_prefix("--class-path="), arg.strip_prefix("-D")) {
+                (Some(path), _) => class_path = Some(path.to_string()),
+                // -Dname on its own sets the property to an empty string
+                (None, Some(property)) if !property.is_empty() && !property.starts_with('=') => {
+                    let (name, value) = property.split_once('=').unwrap_or((property, ""));
+                    properties.push((name.to_string(), value.to_string()));
+                }
+                _ => {
+                    eprintln!("Unrecognized option: {}", arg);
+                    eprintln!("Error: Could not create the Java Virtual Machine.");
+                    eprintln!("Error: A fatal exception has occurred. Program will exit.");
+                    process::exit(1);
+                }
+            },
+        }
+    }
+    let Some(main_class) = args.get(index).cloned() else {
+        eprint!("{}", usage());
+        process::exit(1);
+    };
+    let program_args = args[index + 1..].to_vec();
+    let (main_class, class_path) = match jar {
+        true => {
+            // The class path is the JAR's alone, -cp and CLASSPATH don't count, and not even -D can change
+            // java.class.path
+            properties.push(("java.class.path".to_string(), main_class.clone()));
+            jar_main(&main_class)
+        }
+        false => {
+            // Without -cp, the CLASSPATH environment variable, and without that the current directory
+            let class_path = class_path.or_else(|| env::var("CLASSPATH").ok()).unwrap_or_else(|| ".".to_string());
+            (main_class, ClassPath::parse(&class_path))
         }
+    };
 
-        class = Class {
-            name: class_name.to_string(),
-            super_class_name: super_class_name.to_string(),
-            methods,
-            constant_pool: class_file.constant_pool,
-        };
+    // The VM and its class path aren't Send, so they are made on the thread the VM runs on, which needs a
+    // deep stack for deep Java calls
+    let class_path = class_path.entries;
+    let vm_thread = thread::Builder::new().name("main".to_string()).stack_size(interpreter::STACK_SIZE).spawn(move || {
+        let vm = Vm::new(ClassPath::new(class_path)).enable_preview(enable_preview);
+        let mut vm = properties.iter().fold(vm, |vm, (name, value)| vm.property(name, value));
+        vm.run_main(&main_class, &program_args)
+    });
+    let result = match vm_thread {
+        Ok(vm_thread) => vm_thread.join(),
+        Err(error) => {
+            eprintln!("Error: Could not start the main thread: {}", error);
+            process::exit(1);
+        }
+    };
+    match result {
+        Ok(Ok(status)) => process::exit(status),
+        Ok(Err(error)) => {
+            eprintln!("Error: {}", error);
+            process::exit(1);
+        }
+        // The panic has been printed already
+        Err(_) => process::exit(1),
     }
+}
 
-    // This is where we start interpreting code
+// The main class and class path of a JAR run with -jar: the Main-Class and Class-Path attributes of its
+// manifest. Exits with the java launcher's messages when the JAR can't be read or has no main class.
+fn jar_main(path: &str) -> (String, ClassPath) {
+    let Ok(mmap) = map_file(path) else {
+        eprintln!("Error: Unable to access jarfile {}", path);
+        process::exit(1);
+    };
+    let manifest = match ZipArchive::new(mmap).and_then(|archive| archive.read(manifest::PATH)) {
+        Ok(manifest) => manifest.map(|bytes| Manifest::parse(&bytes)).unwrap_or_default(),
+        Err(_) => {
+            eprintln!("Error: Invalid or corrupt jarfile {}", path);
+            process::exit(1);
+        }
+    };
+    let Some(main_class) = manifest.main_attribute("Main-Class").map(str::trim).filter(|name| !name.is_empty()) else {
+        eprintln!("no main manifest attribute, in {}", path);
+        process::exit(1);
+    };
+    let mut entries = vec![ClassPathEntry::Archive(path.into())];
+    entries.extend(manifest.class_path(Path::new(path)));
+    (main_class.to_string(), ClassPath::new(entries))
+}
 
-    let mut frame_stack: Vec<Frame> = Vec::new();
+fn usage() -> String {
+    let separator = classpath::SEPARATOR;
+    format!("Usage: jvmmy [options] <main class> [args...]
+       jvmmy [options] -jar <jar file> [args...]
+       jvmmy javap <class files...>
+       jvmmy assemble [-d <directory>] <source files...>
+       jvmmy disassemble <class files...>
 
-    for method in class.methods {
-        frame_stack.push(Frame {
-            local_vars: Vec::new(),
-            local_var_types: Vec::new(),
-            op_stack: Vec::new(),
-            constant_pool: class.constant_pool.borrow(),
-        });
-        for attribute in method.attributes {
-            match attribute {
-                AttributeInfo::Code { code, .. } => {
-                    let mut reader = ByteReader::new(code.to_vec());
-                    let instruction = reader.read_u8();
+Options:
+    -cp, -classpath, --class-path <class search path>
+                  directories and JAR files separated by {}, where dir/* stands for the JAR files in dir
+    -D<name>=<value>
+                  sets a system property
+    --enable-preview
+                  allows classes to use preview features
+    -h, -help, --help, -?
+                  prints this help message
+", separator)
+}
 
-                    match instruction {
-                        // aload_0
-                        42 => {
-                            println!("aload_0");
-                            // let var = frame.local_vars[0];
-                            // frame.op_stack.push(var);
-                        }
-                        // invokespecial
-                        183 => {
-                            println!("invokespecial");
-                            let arg1 = reader.read_u8();
-                            let arg2 = reader.read_u8();
-                            let index: u16 = (arg1 as u16) << 8 | (arg2 as u16) << 0;
-                            // &frame.constant_pool[index as usize];
-                        }
-                        // iconst_2
-                        5 => {
-                            println!("iconst_2");
-                            let frame = frame_stack.last_mut()
-                                .expect("Frame stack should contain at least 1 frame");
-                            frame.op_stack.push(2);
-                        }
-                        _ => {}
-                    }
-                }
-                _ => continue
+// jvmmy javap <class files...>, prints the class files the way javap -c -v -p does
+fn javap(paths: &[String]) {
+    if paths.is_empty() {
+        eprintln!("Usage: jvmmy javap <class files...>");
+        process::exit(2);
+    }
+    let mut failed = false;
+    for path in paths {
+        let contents = match map_file(path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                failed = true;
+                continue;
+            }
+        };
+        let absolute_path = fs::canonicalize(path)
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|_| path.clone());
+        let output = ClassFileLoader::new(&contents).enable_preview(true).load()
+            .and_then(|class_file| disassemble(&class_file, &absolute_path));
+        match output {
+            Ok(output) => print!("{}", output),
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                failed = true;
             }
         }
-
-        // frame_stack.pop(); // TODO: Check spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-2.html#jvms-2.6
+    }
+    if failed {
+        process::exit(1);
     }
 }
 
-pub struct Class {
-    // https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.1
-    // Either a binary name or an interface name
-    name: String,
-    // Refers to the binary (a.k.a. internal) name of the class
-    super_class_name: String,
-    methods: Vec<Method>,
-    // TODO: Should be run-time constant pool, i.e. not the constant pool *info*
-    constant_pool: Vec<ConstantPoolInfo>,
+// jvmmy assemble [-d <directory>] <source files...>, writes each class to <directory>/<class name>.class
+fn assemble(args: &[String]) {
+    let (directory, paths) = match args {
+        [flag, directory, paths @ ..] if flag == "-d" => (directory.as_str(), paths),
+        paths => (".", paths),
+    };
+    if paths.is_empty() {
+        eprintln!("Usage: jvmmy assemble [-d <directory>] <source files...>");
+        process::exit(2);
+    }
+    let mut failed = false;
+    for path in paths {
+        let result = fs::read_to_string(path).map_err(|error| error.to_string())
+            .and_then(|source| jasmin::assemble(&source).map_err(|error| error.to_string()))
+            .and_then(|class_file| {
+                let name = class_file.find_class_name(class_file.this_class).map_err(|error| error.to_string())?;
+                // The name becomes a path under the directory, so it can't be allowed to lead out of it
+                let inside = Path::new(&name).components().all(|component| matches!(component, Component::Normal(_)));
+                if !is_binary_name(&name) || !inside {
+                    return Err(format!("class name {} can't be written as a path under {}", name, directory));
+                }
+                let bytes = class_file.to_bytes().map_err(|error| error.to_string())?;
+                let output = Path::new(directory).join(format!("{}.class", name));
+                if let Some(parent) = output.parent() {
+                    fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+                }
+                fs::write(&output, bytes).map_err(|error| format!("{}: {}", output.display(), error))
+            });
+        if let Err(error) = result {
+            eprintln!("{}: {}", path, error);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
 }
 
-pub struct Method {
-    // The name of the method (simply as it appears in source code)
-    name: String,
-    // The method descriptor for the method:
-    // Object m(int i, double d, Thread t) {...}
-    // is:
-    // (IDLjava/lang/Thread;)Ljava/lang/Object;
-    descriptor: String,
-    // Refers to the binary (a.k.a. internal) name of the class
-    class_name: String,
-    // Should be Code attributes, mostly
-    attributes: Vec<AttributeInfo>,
+// jvmmy disassemble <class files...>, prints the class files in the format assemble reads
+fn disassemble_jasmin(paths: &[String]) {
+    if paths.is_empty() {
+        eprintln!("Usage: jvmmy disassemble <class files...>");
+        process::exit(2);
+    }
+    let mut failed = false;
+    for path in paths {
+        let contents = match map_file(path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                failed = true;
+                continue;
+            }
+        };
+        let output = ClassFileLoader::new(&contents).enable_preview(true).load()
+            .and_then(|class_file| jasmin::print(&class_file));
+        match output {
+            Ok(output) => print!("{}", output),
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
 }
@@ -1,40 +1,209 @@
+use std::fmt;
+use std::ops::BitOr;
+
 use crate::attr;
-use attr::AttributeInfo;
+use crate::class::error::ClassFormatErrorKind;
+use attr::{Attribute, AttributeInfo};
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct MethodInfo {
-    // TODO: Should be of type MethodFlags?
-    pub access_flags: u16,
+    pub access_flags: MethodAccessFlags,
     pub name_index: u16,
     pub descriptor_index: u16,
-    pub attributes: Vec<AttributeInfo>,
+    pub attributes: Vec<Attribute>,
+}
+
+impl MethodInfo {
+    // Generic type of the method, if it has one
+    pub fn signature_index(&self) -> Option<u16> {
+        attr::signature_index(&self.attributes)
+    }
+
+    // The checked exceptions the method declares in its throws clause
+    pub fn exception_indexes(&self) -> &[u16] {
+        self.attributes.iter().find_map(|attribute| match &attribute.info {
+            AttributeInfo::Exceptions { exception_index_table } => Some(exception_index_table.as_slice()),
+            _ => None
+        }).unwrap_or(&[])
+    }
+
+    pub fn is_deprecated(&self) -> bool {
+        attr::is_deprecated(&self.attributes)
+    }
 }
 
-pub trait MethodFlags {
-    fn is_public(&self) -> bool;
-    fn is_private(&self) -> bool;
-    fn is_protected(&self) -> bool;
-    fn is_static(&self) -> bool;
-    fn is_final(&self) -> bool;
-    fn is_synchronized(&self) -> bool;
-    fn is_bridge(&self) -> bool;
-    fn is_varargs(&self) -> bool;
-    fn is_native(&self) -> bool;
-    fn is_abstract(&self) -> bool;
-    fn is_strict(&self) -> bool;
-    fn is_synthetic(&self) -> bool;
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.6-200-A.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MethodAccessFlags(pub u16);
+
+impl MethodAccessFlags {
+    pub const PUBLIC: MethodAccessFlags = MethodAccessFlags(0x0001);
+    pub const PRIVATE: MethodAccessFlags = MethodAccessFlags(0x0002);
+    pub const PROTECTED: MethodAccessFlags = MethodAccessFlags(0x0004);
+    pub const STATIC: MethodAccessFlags = MethodAccessFlags(0x0008);
+    pub const FINAL: MethodAccessFlags = MethodAccessFlags(0x0010);
+    pub const SYNCHRONIZED: MethodAccessFlags = MethodAccessFlags(0x0020);
+    pub const BRIDGE: MethodAccessFlags = MethodAccessFlags(0x0040);
+    pub const VARARGS: MethodAccessFlags = MethodAccessFlags(0x0080);
+    pub const NATIVE: MethodAccessFlags = MethodAccessFlags(0x0100);
+    pub const ABSTRACT: MethodAccessFlags = MethodAccessFlags(0x0400);
+    pub const STRICT: MethodAccessFlags = MethodAccessFlags(0x0800);
+    pub const SYNTHETIC: MethodAccessFlags = MethodAccessFlags(0x1000);
+
+    pub fn contains(self, flags: MethodAccessFlags) -> bool { self.0 & flags.0 == flags.0 }
+    pub fn is_public(self) -> bool { self.contains(Self::PUBLIC) }
+    pub fn is_private(self) -> bool { self.contains(Self::PRIVATE) }
+    pub fn is_protected(self) -> bool { self.contains(Self::PROTECTED) }
+    pub fn is_static(self) -> bool { self.contains(Self::STATIC) }
+    pub fn is_final(self) -> bool { self.contains(Self::FINAL) }
+    pub fn is_synchronized(self) -> bool { self.contains(Self::SYNCHRONIZED) }
+    pub fn is_bridge(self) -> bool { self.contains(Self::BRIDGE) }
+    pub fn is_varargs(self) -> bool { self.contains(Self::VARARGS) }
+    pub fn is_native(self) -> bool { self.contains(Self::NATIVE) }
+    pub fn is_abstract(self) -> bool { self.contains(Self::ABSTRACT) }
+    pub fn is_strict(self) -> bool { self.contains(Self::STRICT) }
+    pub fn is_synthetic(self) -> bool { self.contains(Self::SYNTHETIC) }
+
+    // <clinit> is not checked, the spec says to ignore all its flags but ACC_STATIC
+    pub fn validate(self, name: &str, in_interface: bool, major_version: u16) -> Result<(), ClassFormatErrorKind> {
+        let illegal = |rule| Err(ClassFormatErrorKind::IllegalAccessFlags { flags: self.0, rule });
+        if name == "<clinit>" {
+            return Ok(());
+        }
+        let visibilities = [self.is_public(), self.is_private(), self.is_protected()];
+        if visibilities.iter().filter(|&&set| set).count() > 1 {
+            return illegal("at most one of public, private and protected may be set");
+        }
+        if name == "<init>" {
+            let allowed = Self::PUBLIC | Self::PRIVATE | Self::PROTECTED | Self::VARARGS | Self::STRICT | Self::SYNTHETIC;
+            if self.0 & !allowed.0 != 0 {
+                return illegal("<init> may only be public, private, protected, varargs, strict or synthetic");
+            }
+            return Ok(());
+        }
+        if in_interface {
+            if self.is_protected() || self.is_final() || self.is_synchronized() || self.is_native() {
+                return illegal("an interface method must not be protected, final, synchronized or native");
+            }
+            // Spec: before 52 interfaces only had public abstract methods, from 52 on they may have
+            // private and static ones too
+            if major_version < 52 && !(self.is_public() && self.is_abstract()) {
+                return illegal("an interface method must be public and abstract before version 52");
+            }
+            if major_version >= 52 && self.is_public() == self.is_private() {
+                return illegal("an interface method must be exactly one of public and private");
+            }
+        }
+        if self.is_abstract() {
+            if self.is_private() || self.is_static() || self.is_final() || self.is_synchronized() || self.is_native() {
+                return illegal("an abstract method must not be private, static, final, synchronized or native");
+            }
+            // Spec: ACC_STRICT means nothing before 46 and from 61 on
+            if (46..61).contains(&major_version) && self.is_strict() {
+                return illegal("an abstract method must not be strict");
+            }
+        }
+        Ok(())
+    }
 }
 
-impl MethodFlags for u16 {
-    fn is_public(&self) -> bool { self & 0x0001 == 0x0001 }
-    fn is_private(&self) -> bool { self & 0x0002 == 0x0002 }
-    fn is_protected(&self) -> bool { self & 0x0004 == 0x0004 }
-    fn is_static(&self) -> bool { self & 0x0008 == 0x0008 }
-    fn is_final(&self) -> bool { self & 0x0010 == 0x0010 }
-    fn is_synchronized(&self) -> bool { self & 0x0020 == 0x0020 }
-    fn is_bridge(&self) -> bool { self & 0x0040 == 0x0040 }
-    fn is_varargs(&self) -> bool { self & 0x0080 == 0x0080 }
-    fn is_native(&self) -> bool { self & 0x0100 == 0x0100 }
-    fn is_abstract(&self) -> bool { self & 0x0400 == 0x0400 }
-    fn is_strict(&self) -> bool { self & 0x0800 == 0x0800 }
-    fn is_synthetic(&self) -> bool { self & 0x1000 == 0x1000 }
+impl BitOr for MethodAccessFlags {
+    type Output = MethodAccessFlags;
+
+    fn bitor(self, flags: MethodAccessFlags) -> MethodAccessFlags {
+        MethodAccessFlags(self.0 | flags.0)
+    }
+}
+
+// The Java modifiers, in the order the JLS recommends. ACC_BRIDGE, ACC_VARARGS and ACC_SYNTHETIC are
+// not modifiers and are left out.
+impl fmt::Display for MethodAccessFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.is_public(), "public"),
+            (self.is_protected(), "protected"),
+            (self.is_private(), "private"),
+            (self.is_abstract(), "abstract"),
+            (self.is_static(), "static"),
+            (self.is_final(), "final"),
+            (self.is_synchronized(), "synchronized"),
+            (self.is_native(), "native"),
+            (self.is_strict(), "strictfp"),
+        ];
+        let modifiers: Vec<&str> = modifiers.iter().filter(|(set, _)| *set).map(|(_, modifier)| *modifier).collect();
+        write!(f, "{}", modifiers.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC: MethodAccessFlags = MethodAccessFlags::PUBLIC;
+    const PRIVATE: MethodAccessFlags = MethodAccessFlags::PRIVATE;
+    const PROTECTED: MethodAccessFlags = MethodAccessFlags::PROTECTED;
+    const ABSTRACT: MethodAccessFlags = MethodAccessFlags::ABSTRACT;
+
+    // The rule the flags of a method called name break, if any
+    fn broken_rule(flags: MethodAccessFlags, name: &str, in_interface: bool, major_version: u16) -> Option<&'static str> {
+        match flags.validate(name, in_interface, major_version) {
+            Ok(()) => None,
+            Err(ClassFormatErrorKind::IllegalAccessFlags { rule, .. }) => Some(rule),
+            Err(kind) => panic!("{}", kind),
+        }
+    }
+
+    #[test]
+    fn visibility() {
+        for name in ["f", "<init>"] {
+            for flags in [PUBLIC | PRIVATE, PUBLIC | PROTECTED, PRIVATE | PROTECTED, PUBLIC | PRIVATE | PROTECTED] {
+                assert_eq!(broken_rule(flags, name, false, 61),
+                           Some("at most one of public, private and protected may be set"));
+            }
+        }
+        // The class initializer's flags are ignored
+        assert_eq!(broken_rule(PUBLIC | PRIVATE, "<clinit>", false, 61), None);
+    }
+
+    #[test]
+    fn abstract_methods() {
+        for flag in [PRIVATE, MethodAccessFlags::STATIC, MethodAccessFlags::FINAL, MethodAccessFlags::SYNCHRONIZED,
+                     MethodAccessFlags::NATIVE] {
+            assert_eq!(broken_rule(ABSTRACT | flag, "f", false, 61),
+                       Some("an abstract method must not be private, static, final, synchronized or native"));
+        }
+        assert_eq!(broken_rule(PUBLIC | ABSTRACT, "f", false, 61), None);
+        // ACC_STRICT only counts from 46 up to 60
+        let flags = PUBLIC | ABSTRACT | MethodAccessFlags::STRICT;
+        assert_eq!(broken_rule(flags, "f", false, 46), Some("an abstract method must not be strict"));
+        assert_eq!(broken_rule(flags, "f", false, 60), Some("an abstract method must not be strict"));
+        assert_eq!(broken_rule(flags, "f", false, 45), None);
+        assert_eq!(broken_rule(flags, "f", false, 61), None);
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!(broken_rule(PUBLIC | MethodAccessFlags::VARARGS, "<init>", false, 61), None);
+        for flag in [MethodAccessFlags::STATIC, MethodAccessFlags::FINAL, ABSTRACT, MethodAccessFlags::NATIVE,
+                     MethodAccessFlags::BRIDGE] {
+            assert_eq!(broken_rule(PUBLIC | flag, "<init>", false, 61),
+                       Some("<init> may only be public, private, protected, varargs, strict or synthetic"));
+        }
+    }
+
+    #[test]
+    fn interface_methods() {
+        assert_eq!(broken_rule(PUBLIC | ABSTRACT, "f", true, 51), None);
+        assert_eq!(broken_rule(PUBLIC | MethodAccessFlags::STATIC, "f", true, 51),
+                   Some("an interface method must be public and abstract before version 52"));
+        assert_eq!(broken_rule(PUBLIC | MethodAccessFlags::STATIC, "f", true, 52), None);
+        assert_eq!(broken_rule(PRIVATE, "f", true, 52), None);
+        assert_eq!(broken_rule(MethodAccessFlags(0), "f", true, 52),
+                   Some("an interface method must be exactly one of public and private"));
+        for flags in [PROTECTED, PUBLIC | MethodAccessFlags::FINAL, PUBLIC | MethodAccessFlags::SYNCHRONIZED,
+                      PRIVATE | MethodAccessFlags::NATIVE] {
+            assert_eq!(broken_rule(flags, "f", true, 61),
+                       Some("an interface method must not be protected, final, synchronized or native"));
+        }
+    }
 }
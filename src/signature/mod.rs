@@ -0,0 +1,633 @@
+use std::error::Error;
+use std::fmt;
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.9.1
+// Signatures encode the generic types that descriptors erase, e.g. for
+// class Foo<T extends Comparable<? super T>> implements List<T>
+// the class signature is:
+// <T::Ljava/lang/Comparable<-TT;>;>Ljava/lang/Object;Ljava/util/List<TT;>;
+
+// JavaTypeSignature. Wherever the spec calls for a ReferenceTypeSignature, the parser rejects Base.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypeSignature {
+    Base(BaseType),
+    Class(ClassTypeSignature),
+    // T Identifier ;
+    TypeVariable(String),
+    // [ JavaTypeSignature
+    Array(Box<TypeSignature>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BaseType {
+    // B
+    Byte,
+    // C
+    Char,
+    // D
+    Double,
+    // F
+    Float,
+    // I
+    Int,
+    // J
+    Long,
+    // S
+    Short,
+    // Z
+    Boolean,
+}
+
+// L [PackageSpecifier] SimpleClassTypeSignature {ClassTypeSignatureSuffix} ;
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClassTypeSignature {
+    // Package in internal form without the trailing '/', e.g. java/util. Empty for the unnamed package.
+    pub package: String,
+    // The outermost class first, followed by one entry per .Inner suffix
+    pub classes: Vec<SimpleClassTypeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimpleClassTypeSignature {
+    pub name: String,
+    pub type_arguments: Vec<TypeArgument>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypeArgument {
+    // *
+    Any,
+    Exact(TypeSignature),
+    // + ReferenceTypeSignature, i.e. ? extends
+    Extends(TypeSignature),
+    // - ReferenceTypeSignature, i.e. ? super
+    Super(TypeSignature),
+}
+
+// Identifier ClassBound {InterfaceBound}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeParameter {
+    pub name: String,
+    // The class bound may be left out, e.g. <T::Ljava/lang/Comparable<TT;>;> only has an interface bound
+    pub class_bound: Option<TypeSignature>,
+    pub interface_bounds: Vec<TypeSignature>,
+}
+
+// [TypeParameters] SuperclassSignature {SuperinterfaceSignature}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClassSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub superclass: ClassTypeSignature,
+    pub superinterfaces: Vec<ClassTypeSignature>,
+}
+
+// [TypeParameters] ( {JavaTypeSignature} ) Result {ThrowsSignature}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub parameters: Vec<TypeSignature>,
+    // None for void
+    pub result: Option<TypeSignature>,
+    // Class types or type variables
+    pub throws: Vec<TypeSignature>,
+}
+
+// ReferenceTypeSignature
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FieldSignature(pub TypeSignature);
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SignatureError {
+    pub signature: String,
+    // Byte offset into the signature where parsing went wrong
+    pub position: usize,
+    pub kind: SignatureErrorKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignatureErrorKind {
+    UnexpectedEnd,
+    UnexpectedCharacter(char),
+    EmptyIdentifier,
+    // A primitive type where only class types, type variables and arrays are allowed
+    ExpectedReferenceType,
+    // Anything left after a complete signature
+    TrailingCharacters,
+    // Arrays and type arguments nested more than MAX_NESTING deep
+    NestedTooDeeply,
+}
+
+impl ClassSignature {
+    pub fn parse(signature: &str) -> Result<ClassSignature, SignatureError> {
+        let mut parser = Parser { signature, position: 0, depth: 0 };
+        let type_parameters = parser.type_parameters()?;
+        let superclass = parser.class_type()?;
+        let mut superinterfaces = Vec::new();
+        while parser.peek().is_some() {
+            superinterfaces.push(parser.class_type()?);
+        }
+        Ok(ClassSignature { type_parameters, superclass, superinterfaces })
+    }
+}
+
+impl MethodSignature {
+    pub fn parse(signature: &str) -> Result<MethodSignature, SignatureError> {
+        let mut parser = Parser { signature, position: 0, depth: 0 };
+        let type_parameters = parser.type_parameters()?;
+        parser.expect(b'(')?;
+        let mut parameters = Vec::new();
+        while parser.peek() != Some(b')') {
+            parameters.push(parser.java_type()?);
+        }
+        parser.expect(b')')?;
+        let result = match parser.peek() {
+            Some(b'V') => {
+                parser.position += 1;
+                None
+            }
+            _ => Some(parser.java_type()?),
+        };
+        let mut throws = Vec::new();
+        while parser.peek() == Some(b'^') {
+            parser.position += 1;
+            let start = parser.position;
+            match parser.reference_type()? {
+                TypeSignature::Array(_) => return Err(parser.error_at(start, SignatureErrorKind::UnexpectedCharacter('['))),
+                thrown => throws.push(thrown),
+            }
+        }
+        parser.end()?;
+        Ok(MethodSignature { type_parameters, parameters, result, throws })
+    }
+}
+
+impl FieldSignature {
+    pub fn parse(signature: &str) -> Result<FieldSignature, SignatureError> {
+        let mut parser = Parser { signature, position: 0, depth: 0 };
+        let field_type = parser.reference_type()?;
+        parser.end()?;
+        Ok(FieldSignature(field_type))
+    }
+}
+
+// Enough for the 255 array dimensions a descriptor may have plus some type arguments around them. The
+// parser recurses for each level, so a limit keeps a malformed signature from exhausting the stack.
+const MAX_NESTING: usize = 300;
+
+struct Parser<'a> {
+    signature: &'a str,
+    position: usize,
+    // How many reference types are being parsed, one inside the other
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    // JavaTypeSignature
+    fn java_type(&mut self) -> Result<TypeSignature, SignatureError> {
+        let base_type = match self.peek() {
+            Some(b'B') => BaseType::Byte,
+            Some(b'C') => BaseType::Char,
+            Some(b'D') => BaseType::Double,
+            Some(b'F') => BaseType::Float,
+            Some(b'I') => BaseType::Int,
+            Some(b'J') => BaseType::Long,
+            Some(b'S') => BaseType::Short,
+            Some(b'Z') => BaseType::Boolean,
+            _ => return self.reference_type(),
+        };
+        self.position += 1;
+        Ok(TypeSignature::Base(base_type))
+    }
+
+    // ReferenceTypeSignature
+    fn reference_type(&mut self) -> Result<TypeSignature, SignatureError> {
+        if self.depth == MAX_NESTING {
+            return Err(self.error_at(self.position, SignatureErrorKind::NestedTooDeeply));
+        }
+        self.depth += 1;
+        let reference_type = self.nested_reference_type();
+        self.depth -= 1;
+        reference_type
+    }
+
+    fn nested_reference_type(&mut self) -> Result<TypeSignature, SignatureError> {
+        match self.peek() {
+            Some(b'L') => Ok(TypeSignature::Class(self.class_type()?)),
+            Some(b'T') => {
+                self.position += 1;
+                let name = self.identifier()?;
+                self.expect(b';')?;
+                Ok(TypeSignature::TypeVariable(name))
+            }
+            Some(b'[') => {
+                self.position += 1;
+                Ok(TypeSignature::Array(Box::new(self.java_type()?)))
+            }
+            Some(b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z') =>
+                Err(self.error_at(self.position, SignatureErrorKind::ExpectedReferenceType)),
+            Some(_) => Err(self.unexpected_character()),
+            None => Err(self.error_at(self.position, SignatureErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    // ClassTypeSignature
+    fn class_type(&mut self) -> Result<ClassTypeSignature, SignatureError> {
+        self.expect(b'L')?;
+        let mut package = String::new();
+        let mut name = self.identifier()?;
+        while self.peek() == Some(b'/') {
+            self.position += 1;
+            if !package.is_empty() {
+                package.push('/');
+            }
+            package.push_str(&name);
+            name = self.identifier()?;
+        }
+
+        let mut classes = vec![SimpleClassTypeSignature {
+            name,
+            type_arguments: self.type_arguments()?,
+        }];
+        while self.peek() == Some(b'.') {
+            self.position += 1;
+            classes.push(SimpleClassTypeSignature {
+                name: self.identifier()?,
+                type_arguments: self.type_arguments()?,
+            });
+        }
+        self.expect(b';')?;
+        Ok(ClassTypeSignature { package, classes })
+    }
+
+    // [TypeArguments]
+    fn type_arguments(&mut self) -> Result<Vec<TypeArgument>, SignatureError> {
+        let mut type_arguments = Vec::new();
+        if self.peek() != Some(b'<') {
+            return Ok(type_arguments);
+        }
+        self.position += 1;
+        // Spec: there is at least one type argument
+        loop {
+            let type_argument = match self.peek() {
+                Some(b'*') => {
+                    self.position += 1;
+                    TypeArgument::Any
+                }
+                Some(b'+') => {
+                    self.position += 1;
+                    TypeArgument::Extends(self.reference_type()?)
+                }
+                Some(b'-') => {
+                    self.position += 1;
+                    TypeArgument::Super(self.reference_type()?)
+                }
+                _ => TypeArgument::Exact(self.reference_type()?),
+            };
+            type_arguments.push(type_argument);
+            if self.peek() == Some(b'>') {
+                self.position += 1;
+                return Ok(type_arguments);
+            }
+        }
+    }
+
+    // [TypeParameters]
+    fn type_parameters(&mut self) -> Result<Vec<TypeParameter>, SignatureError> {
+        let mut type_parameters = Vec::new();
+        if self.peek() != Some(b'<') {
+            return Ok(type_parameters);
+        }
+        self.position += 1;
+        // Spec: there is at least one type parameter
+        loop {
+            let name = self.identifier()?;
+            self.expect(b':')?;
+            let class_bound = match self.peek() {
+                Some(b':') | Some(b'>') => None,
+                _ => Some(self.reference_type()?),
+            };
+            let mut interface_bounds = Vec::new();
+            while self.peek() == Some(b':') {
+                self.position += 1;
+                interface_bounds.push(self.reference_type()?);
+            }
+            type_parameters.push(TypeParameter { name, class_bound, interface_bounds });
+            if self.peek() == Some(b'>') {
+                self.position += 1;
+                return Ok(type_parameters);
+            }
+        }
+    }
+
+    // Spec: any sequence of characters other than . ; [ / < > :
+    fn identifier(&mut self) -> Result<String, SignatureError> {
+        let start = self.position;
+        let length = self.signature[start..]
+            .find(['.', ';', '[', '/', '<', '>', ':'])
+            .unwrap_or(self.signature.len() - start);
+        if length == 0 {
+            return Err(match self.peek() {
+                Some(_) => self.error_at(start, SignatureErrorKind::EmptyIdentifier),
+                None => self.error_at(start, SignatureErrorKind::UnexpectedEnd),
+            });
+        }
+        self.position += length;
+        Ok(self.signature[start..self.position].to_string())
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), SignatureError> {
+        match self.peek() {
+            Some(b) if b == expected => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(_) => Err(self.unexpected_character()),
+            None => Err(self.error_at(self.position, SignatureErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    fn end(&self) -> Result<(), SignatureError> {
+        match self.position == self.signature.len() {
+            true => Ok(()),
+            false => Err(self.error_at(self.position, SignatureErrorKind::TrailingCharacters)),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.signature.as_bytes().get(self.position).copied()
+    }
+
+    fn unexpected_character(&self) -> SignatureError {
+        let c = self.signature[self.position..].chars().next().unwrap_or(char::REPLACEMENT_CHARACTER);
+        self.error_at(self.position, SignatureErrorKind::UnexpectedCharacter(c))
+    }
+
+    fn error_at(&self, position: usize, kind: SignatureErrorKind) -> SignatureError {
+        SignatureError { signature: self.signature.to_string(), position, kind }
+    }
+}
+
+// Everything prints in signature syntax, so parsing and printing round-trips
+impl fmt::Display for TypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeSignature::Base(base_type) => write!(f, "{}", base_type),
+            TypeSignature::Class(class_type) => write!(f, "{}", class_type),
+            TypeSignature::TypeVariable(name) => write!(f, "T{};", name),
+            TypeSignature::Array(component_type) => write!(f, "[{}", component_type),
+        }
+    }
+}
+
+impl fmt::Display for BaseType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = match self {
+            BaseType::Byte => 'B',
+            BaseType::Char => 'C',
+            BaseType::Double => 'D',
+            BaseType::Float => 'F',
+            BaseType::Int => 'I',
+            BaseType::Long => 'J',
+            BaseType::Short => 'S',
+            BaseType::Boolean => 'Z',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+impl fmt::Display for ClassTypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "L")?;
+        if !self.package.is_empty() {
+            write!(f, "{}/", self.package)?;
+        }
+        for (i, class) in self.classes.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", class)?;
+        }
+        write!(f, ";")
+    }
+}
+
+impl fmt::Display for SimpleClassTypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.type_arguments.is_empty() {
+            write!(f, "<")?;
+            for type_argument in &self.type_arguments {
+                write!(f, "{}", type_argument)?;
+            }
+            write!(f, ">")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TypeArgument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeArgument::Any => write!(f, "*"),
+            TypeArgument::Exact(type_signature) => write!(f, "{}", type_signature),
+            TypeArgument::Extends(type_signature) => write!(f, "+{}", type_signature),
+            TypeArgument::Super(type_signature) => write!(f, "-{}", type_signature),
+        }
+    }
+}
+
+impl fmt::Display for TypeParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.name)?;
+        if let Some(class_bound) = &self.class_bound {
+            write!(f, "{}", class_bound)?;
+        }
+        for interface_bound in &self.interface_bounds {
+            write!(f, ":{}", interface_bound)?;
+        }
+        Ok(())
+    }
+}
+
+fn fmt_type_parameters(type_parameters: &[TypeParameter], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if !type_parameters.is_empty() {
+        write!(f, "<")?;
+        for type_parameter in type_parameters {
+            write!(f, "{}", type_parameter)?;
+        }
+        write!(f, ">")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for ClassSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_type_parameters(&self.type_parameters, f)?;
+        write!(f, "{}", self.superclass)?;
+        for superinterface in &self.superinterfaces {
+            write!(f, "{}", superinterface)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MethodSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_type_parameters(&self.type_parameters, f)?;
+        write!(f, "(")?;
+        for parameter in &self.parameters {
+            write!(f, "{}", parameter)?;
+        }
+        write!(f, ")")?;
+        match &self.result {
+            Some(result) => write!(f, "{}", result)?,
+            None => write!(f, "V")?,
+        }
+        for thrown in &self.throws {
+            write!(f, "^{}", thrown)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FieldSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid signature {:?} at position {}: ", self.signature, self.position)?;
+        match &self.kind {
+            SignatureErrorKind::UnexpectedEnd => write!(f, "unexpected end"),
+            SignatureErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character {:?}", c),
+            SignatureErrorKind::EmptyIdentifier => write!(f, "expected an identifier"),
+            SignatureErrorKind::ExpectedReferenceType => write!(f, "expected a class type, type variable or array"),
+            SignatureErrorKind::TrailingCharacters => write!(f, "unexpected characters after the signature"),
+            SignatureErrorKind::NestedTooDeeply => write!(f, "types are nested more than {} deep", MAX_NESTING),
+        }
+    }
+}
+
+impl Error for SignatureError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class_type(package: &str, classes: &[(&str, Vec<TypeArgument>)]) -> ClassTypeSignature {
+        ClassTypeSignature {
+            package: package.to_string(),
+            classes: classes.iter()
+                .map(|(name, type_arguments)| SimpleClassTypeSignature { name: name.to_string(), type_arguments: type_arguments.clone() })
+                .collect(),
+        }
+    }
+
+    fn type_variable(name: &str) -> TypeSignature {
+        TypeSignature::TypeVariable(name.to_string())
+    }
+
+    #[test]
+    fn class_signatures() {
+        let signature = "<T::Ljava/lang/Comparable<-TT;>;>Ljava/lang/Object;Ljava/util/List<TT;>;";
+        let parsed = ClassSignature::parse(signature).unwrap();
+        let comparable = class_type("java/lang", &[("Comparable", vec![TypeArgument::Super(type_variable("T"))])]);
+        assert_eq!(parsed, ClassSignature {
+            type_parameters: vec![TypeParameter {
+                name: "T".to_string(),
+                class_bound: None,
+                interface_bounds: vec![TypeSignature::Class(comparable)],
+            }],
+            superclass: class_type("java/lang", &[("Object", vec![])]),
+            superinterfaces: vec![class_type("java/util", &[("List", vec![TypeArgument::Exact(type_variable("T"))])])],
+        });
+        assert_eq!(parsed.to_string(), signature);
+
+        for signature in [
+            "<K:Ljava/lang/Object;V::Ljava/lang/Runnable;:Ljava/io/Serializable;>Ljava/util/AbstractMap<TK;TV;>;Ljava/lang/Cloneable;",
+            "<T:>LBase<[TT;>;",
+            "LOuter<*>.Inner<+LOuter<*>.Inner<*>;>;",
+        ] {
+            assert_eq!(ClassSignature::parse(signature).unwrap().to_string(), signature);
+        }
+        let parsed = ClassSignature::parse("<K:Ljava/lang/Object;V::Ljava/lang/Runnable;:Ljava/io/Serializable;>Ljava/lang/Object;").unwrap();
+        assert!(parsed.type_parameters[0].class_bound.is_some() && parsed.type_parameters[0].interface_bounds.is_empty());
+        assert!(parsed.type_parameters[1].class_bound.is_none() && parsed.type_parameters[1].interface_bounds.len() == 2);
+    }
+
+    #[test]
+    fn method_signatures() {
+        let signature = "<E:Ljava/lang/Exception;>(Ljava/util/List<+Ljava/lang/Number;>;Ljava/util/Map<*-TE;>;[[I)Ljava/util/Map$Entry<TE;*>;^TE;^Ljava/io/IOException;";
+        let parsed = MethodSignature::parse(signature).unwrap();
+        assert_eq!(parsed.parameters[1], TypeSignature::Class(class_type("java/util", &[
+            ("Map", vec![TypeArgument::Any, TypeArgument::Super(type_variable("E"))]),
+        ])));
+        assert_eq!(parsed.parameters[2], TypeSignature::Array(Box::new(TypeSignature::Array(Box::new(TypeSignature::Base(BaseType::Int))))));
+        assert_eq!(parsed.throws, [type_variable("E"), TypeSignature::Class(class_type("java/io", &[("IOException", vec![])]))]);
+        assert_eq!(parsed.to_string(), signature);
+
+        let parsed = MethodSignature::parse("()V").unwrap();
+        assert_eq!(parsed, MethodSignature { type_parameters: vec![], parameters: vec![], result: None, throws: vec![] });
+        assert_eq!(parsed.to_string(), "()V");
+        for signature in ["(TT;JZ)[TT;^TX;", "<T:Ljava/lang/Object;>(TT;)V^Ljava/lang/Exception;"] {
+            assert_eq!(MethodSignature::parse(signature).unwrap().to_string(), signature);
+        }
+    }
+
+    #[test]
+    fn field_signatures() {
+        let signature = "Lcom/example/Outer<TT;>.Inner<Ljava/lang/String;>.Deeper;";
+        let parsed = FieldSignature::parse(signature).unwrap();
+        assert_eq!(parsed, FieldSignature(TypeSignature::Class(class_type("com/example", &[
+            ("Outer", vec![TypeArgument::Exact(type_variable("T"))]),
+            ("Inner", vec![TypeArgument::Exact(TypeSignature::Class(class_type("java/lang", &[("String", vec![])])))]),
+            ("Deeper", vec![]),
+        ]))));
+        assert_eq!(parsed.to_string(), signature);
+
+        for signature in ["TT;", "[[TT;", "LUnnamed;", "Ljava/util/List<[Ljava/util/List<*>;>;"] {
+            assert_eq!(FieldSignature::parse(signature).unwrap().to_string(), signature);
+        }
+    }
+
+    #[test]
+    fn error_positions() {
+        let field = |signature: &str| FieldSignature::parse(signature).map(|_| ()).unwrap_err();
+        let method = |signature: &str| MethodSignature::parse(signature).map(|_| ()).unwrap_err();
+        let class = |signature: &str| ClassSignature::parse(signature).map(|_| ()).unwrap_err();
+        let errors = [
+            (field("I"), 0, SignatureErrorKind::ExpectedReferenceType),
+            (field("Ljava/util/List<I>;"), 16, SignatureErrorKind::ExpectedReferenceType),
+            (field("Ljava/lang/String"), 17, SignatureErrorKind::UnexpectedEnd),
+            (field("TT;X"), 3, SignatureErrorKind::TrailingCharacters),
+            (field("Ljava//String;"), 6, SignatureErrorKind::EmptyIdentifier),
+            (field("Ljava/util/List<>;"), 16, SignatureErrorKind::UnexpectedCharacter('>')),
+            // Positions are byte offsets, and é takes two
+            (field("Lcafé<I>;"), 7, SignatureErrorKind::ExpectedReferenceType),
+            (method("(I"), 2, SignatureErrorKind::UnexpectedEnd),
+            (method("()V^[Ljava/lang/Exception;"), 4, SignatureErrorKind::UnexpectedCharacter('[')),
+            (method("()I^I"), 4, SignatureErrorKind::ExpectedReferenceType),
+            (method("(V)V"), 1, SignatureErrorKind::UnexpectedCharacter('V')),
+            (method("()VV"), 3, SignatureErrorKind::TrailingCharacters),
+            (class("<>Ljava/lang/Object;"), 1, SignatureErrorKind::EmptyIdentifier),
+            (class("<T>Ljava/lang/Object;"), 2, SignatureErrorKind::UnexpectedCharacter('>')),
+            (class("Ljava/lang/Object;X"), 18, SignatureErrorKind::UnexpectedCharacter('X')),
+            (class("<T::I>Ljava/lang/Object;"), 4, SignatureErrorKind::ExpectedReferenceType),
+        ];
+        for (error, position, kind) in errors {
+            assert_eq!((error.position, &error.kind), (position, &kind), "{}", error);
+        }
+    }
+
+    #[test]
+    fn nesting() {
+        let signature = format!("{}I", "[".repeat(MAX_NESTING));
+        assert_eq!(FieldSignature::parse(&signature).unwrap().to_string(), signature);
+        let error = FieldSignature::parse(&format!("{}I", "[".repeat(MAX_NESTING + 1))).unwrap_err();
+        assert_eq!((error.position, error.kind), (MAX_NESTING, SignatureErrorKind::NestedTooDeeply));
+        let error = FieldSignature::parse(&"LList<".repeat(MAX_NESTING + 1)).unwrap_err();
+        assert_eq!((error.position, error.kind), (6 * MAX_NESTING, SignatureErrorKind::NestedTooDeeply));
+    }
+}
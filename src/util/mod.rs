@@ -1,47 +1,124 @@
-pub struct ByteReader {
+pub mod mutf8;
+pub mod number;
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use mutf8::ModifiedUtf8;
+
+// Maps a file into memory read-only, so it can be loaded or viewed without first being read into the heap
+pub fn map_file(path: impl AsRef<Path>) -> io::Result<Mmap> {
+    let file = File::open(path)?;
+    // Safety: the mapped bytes change if someone else writes to the file, and reading past its end after
+    // it is truncated crashes. Like the JVM itself, we assume class files are left alone while we run.
+    unsafe { Mmap::map(&file) }
+}
+
+pub struct ByteReader<'a> {
     pub index: usize,
-    pub contents: Vec<u8>,
+    pub contents: &'a [u8],
+}
+
+#[derive(Debug)]
+pub enum ReadError {
+    // Fewer bytes are left than the read asked for
+    UnexpectedEof {
+        wanted: usize,
+        available: usize,
+    },
+    InvalidModifiedUtf8 {
+        // Offset of the offending byte within the string
+        offset: usize,
+    },
 }
 
-impl ByteReader {
-    pub const fn new(contents: Vec<u8>) -> Self {
+// Reads never advance the index when they fail, so `index` is the offset of the failed read.
+impl<'a> ByteReader<'a> {
+    pub const fn new(contents: &'a [u8]) -> Self {
         ByteReader { index: 0, contents }
     }
 
-    pub fn read_u8(&mut self) -> u8 {
-        let result: u8 = self.contents[self.index];
-        self.index += 1;
-        result
+    pub fn remaining(&self) -> usize {
+        self.contents.len().saturating_sub(self.index)
     }
 
-    pub fn read_u16(&mut self) -> u16 {
-        let result: u16 = (self.contents[self.index] as u16) << 8
-            | (self.contents[self.index + 1] as u16) << 0;
-        self.index += 2;
-        result
+    pub fn read_u8(&mut self) -> Result<u8, ReadError> {
+        let bytes = self.read_slice(1)?;
+        Ok(bytes[0])
     }
 
-    pub fn read_u32(&mut self) -> u32 {
-        let result: u32 = (self.contents[self.index] as u32) << 24
-            | (self.contents[self.index + 1] as u32) << 16
-            | (self.contents[self.index + 2] as u32) << 8
-            | (self.contents[self.index + 3] as u32) << 0;
-        self.index += 4;
-        result
+    pub fn read_u16(&mut self) -> Result<u16, ReadError> {
+        let bytes = self.read_slice(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
     }
 
-    pub fn read_str(&mut self, length: usize) -> &str {
-        let range = self.index..self.index + length;
-        let result: &str = std::str::from_utf8(&self.contents[range])
-            .expect("Invalid UTF-8 value");
-        self.index += length;
-        result
+    pub fn read_u32(&mut self) -> Result<u32, ReadError> {
+        let bytes = self.read_slice(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn read_str(&mut self, length: usize) -> Result<ModifiedUtf8, ReadError> {
+        let start = self.index;
+        let bytes = self.read_vec_u8(length)?;
+        ModifiedUtf8::from_bytes(bytes).map_err(|e| {
+            self.index = start;
+            ReadError::InvalidModifiedUtf8 { offset: e.offset }
+        })
     }
 
-    pub fn read_vec_u8(&mut self, length: usize) -> Vec<u8> {
+    pub fn read_vec_u8(&mut self, length: usize) -> Result<Vec<u8>, ReadError> {
+        Ok(Vec::from(self.read_slice(length)?))
+    }
+
+    // Borrows from the contents rather than the reader, so the slice outlives it
+    pub fn read_slice(&mut self, length: usize) -> Result<&'a [u8], ReadError> {
+        let available = self.remaining();
+        if length > available {
+            return Err(ReadError::UnexpectedEof { wanted: length, available });
+        }
         let range = self.index..self.index + length;
-        let result: Vec<u8> = Vec::from(&self.contents[range]);
         self.index += length;
-        result
+        Ok(&self.contents[range])
+    }
+}
+
+pub struct ByteWriter {
+    pub contents: Vec<u8>,
+}
+
+// The counterpart of ByteReader, everything is written big-endian
+impl ByteWriter {
+    pub const fn new() -> Self {
+        ByteWriter { contents: Vec::new() }
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.contents.push(value);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.contents.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.contents.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn write_slice(&mut self, bytes: &[u8]) {
+        self.contents.extend_from_slice(bytes);
+    }
+
+    // Overwrites a u4 written earlier, e.g. a length that is only known once the contents are written
+    pub fn patch_u32(&mut self, index: usize, value: u32) {
+        self.contents[index..index + 4].copy_from_slice(&value.to_be_bytes());
+    }
+}
+
+impl Default for ByteWriter {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,184 @@
+use std::fmt;
+
+// Spec: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4.7
+// Class files encode strings in "modified UTF-8":
+// - NUL is encoded as the two bytes C0 80, so no byte is ever zero
+// - Supplementary characters are encoded as a surrogate pair, each surrogate taking three bytes
+// - The four-byte UTF-8 forms are never used
+// We keep the original bytes so the string can be written back exactly as it was read, and a decoded
+// String for everything that just wants to look at it.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct ModifiedUtf8 {
+    bytes: Vec<u8>,
+    // Unpaired surrogates (legal in Java strings, not in Rust ones) are replaced with U+FFFD
+    string: String,
+}
+
+#[derive(Debug)]
+pub struct InvalidModifiedUtf8 {
+    // Offset of the offending byte within the string
+    pub offset: usize,
+}
+
+impl ModifiedUtf8 {
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, InvalidModifiedUtf8> {
+        let units = decode(&bytes)?;
+        let string = char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+        Ok(ModifiedUtf8 { bytes, string })
+    }
+
+    pub fn from_utf16(units: &[u16]) -> Self {
+        let mut bytes = Vec::with_capacity(units.len());
+        for &unit in units {
+            encode_unit(unit, &mut bytes);
+        }
+        let string = char::decode_utf16(units.iter().copied())
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+        ModifiedUtf8 { bytes, string }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+
+    // The exact bytes as they appear (or will appear) in the class file
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    // The UTF-16 code units of the Java String this represents, unpaired surrogates included
+    pub fn to_utf16(&self) -> Vec<u16> {
+        decode(&self.bytes).expect("bytes were validated on construction")
+    }
+}
+
+impl From<&str> for ModifiedUtf8 {
+    fn from(string: &str) -> Self {
+        let mut bytes = Vec::with_capacity(string.len());
+        let mut buffer = [0u16; 2];
+        for c in string.chars() {
+            for &unit in c.encode_utf16(&mut buffer).iter() {
+                encode_unit(unit, &mut bytes);
+            }
+        }
+        ModifiedUtf8 { bytes, string: string.to_string() }
+    }
+}
+
+impl fmt::Display for ModifiedUtf8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.string, f)
+    }
+}
+
+impl fmt::Debug for ModifiedUtf8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.string, f)
+    }
+}
+
+fn decode(bytes: &[u8]) -> Result<Vec<u16>, InvalidModifiedUtf8> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let continuation = |offset: usize| match bytes.get(offset) {
+            Some(&byte) if byte & 0xc0 == 0x80 => Ok((byte & 0x3f) as u16),
+            _ => Err(InvalidModifiedUtf8 { offset }),
+        };
+        let x = bytes[i];
+        match x {
+            // Spec: no byte may have the value 0 or lie in the range f0 to ff
+            0x00 | 0xf0..=0xff => return Err(InvalidModifiedUtf8 { offset: i }),
+            0x01..=0x7f => {
+                units.push(x as u16);
+                i += 1;
+            }
+            0xc0..=0xdf => {
+                let y = continuation(i + 1)?;
+                units.push(((x & 0x1f) as u16) << 6 | y);
+                i += 2;
+            }
+            0xe0..=0xef => {
+                let y = continuation(i + 1)?;
+                let z = continuation(i + 2)?;
+                units.push(((x & 0x0f) as u16) << 12 | y << 6 | z);
+                i += 3;
+            }
+            // A continuation byte without a leading byte
+            _ => return Err(InvalidModifiedUtf8 { offset: i }),
+        }
+    }
+    Ok(units)
+}
+
+fn encode_unit(unit: u16, bytes: &mut Vec<u8>) {
+    match unit {
+        0x0001..=0x007f => bytes.push(unit as u8),
+        // NUL takes the two-byte form along with everything else up to 0x7ff
+        0x0000 | 0x0080..=0x07ff => {
+            bytes.push(0xc0 | (unit >> 6) as u8);
+            bytes.push(0x80 | (unit & 0x3f) as u8);
+        }
+        _ => {
+            bytes.push(0xe0 | (unit >> 12) as u8);
+            bytes.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+            bytes.push(0x80 | (unit & 0x3f) as u8);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nul_is_two_bytes() {
+        let string = ModifiedUtf8::from_bytes(vec![b'a', 0xc0, 0x80, b'b']).unwrap();
+        assert_eq!(string.as_str(), "a\0b");
+        assert_eq!(string.to_utf16(), [0x61, 0x00, 0x62]);
+        assert_eq!(ModifiedUtf8::from("a\0b").as_bytes(), [b'a', 0xc0, 0x80, b'b']);
+    }
+
+    #[test]
+    fn surrogate_pair_is_a_supplementary_character() {
+        // U+1F600 is the surrogate pair D83D DE00, each surrogate in three bytes
+        let bytes = vec![0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80];
+        let string = ModifiedUtf8::from_bytes(bytes.clone()).unwrap();
+        assert_eq!(string.as_str(), "\u{1f600}");
+        assert_eq!(string.to_utf16(), [0xd83d, 0xde00]);
+        assert_eq!(ModifiedUtf8::from("\u{1f600}").as_bytes(), bytes);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let cases: [&[u8]; 5] = [
+            b"",
+            b"java/lang/Object",
+            &[0xc0, 0x80],
+            // An unpaired surrogate, which only survives because the bytes are kept
+            &[b'x', 0xed, 0xa0, 0xbd, b'y'],
+            // A non-shortest form of 'A', which decodes but isn't how it would be encoded
+            &[0xc1, 0x81],
+        ];
+        for bytes in cases {
+            let string = ModifiedUtf8::from_bytes(bytes.to_vec()).unwrap();
+            assert_eq!(string.as_bytes(), bytes);
+            assert_eq!(ModifiedUtf8::from_utf16(&string.to_utf16()).to_utf16(), string.to_utf16());
+        }
+        assert_eq!(ModifiedUtf8::from_bytes(vec![b'x', 0xed, 0xa0, 0xbd]).unwrap().as_str(), "x\u{fffd}");
+    }
+
+    #[test]
+    fn rejects_zero_and_four_byte_forms() {
+        assert_eq!(ModifiedUtf8::from_bytes(vec![b'a', 0x00]).unwrap_err().offset, 1);
+        for byte in 0xf0..=0xff {
+            assert_eq!(ModifiedUtf8::from_bytes(vec![b'a', b'b', byte, 0x80, 0x80, 0x80]).unwrap_err().offset, 2);
+        }
+        // Truncated and stray continuation bytes
+        assert_eq!(ModifiedUtf8::from_bytes(vec![0xe0, 0x80]).unwrap_err().offset, 2);
+        assert_eq!(ModifiedUtf8::from_bytes(vec![0x80]).unwrap_err().offset, 0);
+    }
+}
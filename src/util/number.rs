@@ -0,0 +1,62 @@
+// Double.toString: plain notation from 10^-3 up to 10^7, computerized scientific notation outside of it
+pub fn java_double(value: f64) -> String {
+    match value {
+        value if value.is_nan() => "NaN".to_string(),
+        value if value.is_infinite() => if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string(),
+        _ => {
+            let plain = (1e-3..1e7).contains(&value.abs());
+            let scientific = match format!("{:e}", value) {
+                // Java always writes a digit after the point, and picks the one closest to the value
+                scientific if !plain && !scientific.contains('.') => format!("{:.1e}", value),
+                scientific => scientific,
+            };
+            java_decimal(&scientific, plain)
+        }
+    }
+}
+
+// Float.toString, which has the same rules but only as many digits as a float needs
+pub fn java_float(value: f32) -> String {
+    match value {
+        value if value.is_nan() => "NaN".to_string(),
+        value if value.is_infinite() => if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string(),
+        _ => {
+            let plain = (1e-3..1e7).contains(&value.abs());
+            let scientific = match format!("{:e}", value) {
+                // Java always writes a digit after the point, and picks the one closest to the value
+                scientific if !plain && !scientific.contains('.') => format!("{:.1e}", value),
+                scientific => scientific,
+            };
+            java_decimal(&scientific, plain)
+        }
+    }
+}
+
+// Turns Rust's shortest round-trip scientific notation, like -1.5e-7, into Java's
+fn java_decimal(scientific: &str, plain: bool) -> String {
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    if digits.chars().all(|c| c == '0') {
+        return format!("{}0.0", sign);
+    }
+    if !plain {
+        let fraction = match &digits[1..] {
+            "" => "0",
+            fraction => fraction,
+        };
+        return format!("{}{}.{}E{}", sign, &digits[..1], fraction, exponent);
+    }
+    if exponent < 0 {
+        return format!("{}0.{}{}", sign, "0".repeat((-exponent - 1) as usize), digits);
+    }
+    let point = exponent as usize + 1;
+    match digits.len() > point {
+        true => format!("{}{}.{}", sign, &digits[..point], &digits[point..]),
+        false => format!("{}{}{}.0", sign, digits, "0".repeat(point - digits.len())),
+    }
+}
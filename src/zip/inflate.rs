@@ -0,0 +1,263 @@
+use super::ZipErrorKind;
+
+// Spec: https://www.rfc-editor.org/rfc/rfc1951
+// Decompresses raw deflate data, the way zip files store it, into exactly `size` bytes. Data that would
+// come out longer is an error as soon as it does, so a small entry can't unpack into a huge one.
+pub fn inflate(input: &[u8], size: usize) -> Result<Vec<u8>, ZipErrorKind> {
+    let mut inflater = Inflater {
+        input: BitReader { bytes: input, index: 0, bits: 0, bit_count: 0 },
+        // The size is only what the entry claims, so no more is allocated up front than the input can expand to
+        output: Vec::with_capacity(size.min(input.len().saturating_mul(MAX_EXPANSION))),
+        size,
+    };
+    loop {
+        let last = inflater.input.bits(1)? == 1;
+        match inflater.input.bits(2)? {
+            0 => inflater.stored_block()?,
+            1 => {
+                let (lengths, distances) = fixed_codes();
+                inflater.compressed_block(&lengths, &distances)?;
+            }
+            2 => {
+                let (lengths, distances) = inflater.dynamic_codes()?;
+                inflater.compressed_block(&lengths, &distances)?;
+            }
+            _ => return Err(ZipErrorKind::BadDeflate("reserved block type 3")),
+        }
+        if last {
+            break;
+        }
+    }
+    match inflater.output.len() == size {
+        true => Ok(inflater.output),
+        false => Err(ZipErrorKind::SizeMismatch { expected: size as u64, actual: inflater.output.len() as u64 }),
+    }
+}
+
+struct Inflater<'a> {
+    input: BitReader<'a>,
+    output: Vec<u8>,
+    size: usize,
+}
+
+// Deflate packs bits starting at the least significant bit of each byte
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    index: usize,
+    bits: u32,
+    bit_count: u32,
+}
+
+// A canonical Huffman code, decoded a bit at a time: counts[n] is how many codes are n bits long, and
+// symbols lists the symbols ordered by code
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+const MAX_BITS: usize = 15;
+// Deflate data expands at most 1032 times, with a 258 byte match for every 2 bits
+const MAX_EXPANSION: usize = 1032;
+
+// Spec: 3.2.5, the base lengths and distances of the length and distance symbols, and their extra bits
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+// Spec: 3.2.7, the order the code lengths of the code length code come in
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32, ZipErrorKind> {
+        while self.bit_count < count {
+            let byte = *self.bytes.get(self.index).ok_or(ZipErrorKind::BadDeflate("compressed data ends early"))?;
+            self.index += 1;
+            self.bits |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bits & ((1u64 << count) - 1) as u32;
+        self.bits >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    // Stored blocks start at a byte boundary
+    fn align(&mut self) {
+        self.bits = 0;
+        self.bit_count = 0;
+    }
+}
+
+impl Huffman {
+    // Builds the code from the bit length of each symbol, 0 for symbols that aren't used. Codes that use
+    // up more than all bit patterns are invalid; ones that leave some unused are allowed, and running
+    // into an unused pattern is an error when decoding.
+    fn new(lengths: &[u8]) -> Result<Huffman, ZipErrorKind> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = left * 2 - count as i32;
+            if left < 0 {
+                return Err(ZipErrorKind::BadDeflate("over-subscribed Huffman code"));
+            }
+        }
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; offsets[MAX_BITS + 1] as usize];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        counts[0] = 0;
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, input: &mut BitReader) -> Result<u16, ZipErrorKind> {
+        // code is the bits read so far, first the code of the first symbol of this length and index
+        // where that symbol is in symbols
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for length in 1..=MAX_BITS {
+            code |= input.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(ZipErrorKind::BadDeflate("invalid Huffman code"))
+    }
+}
+
+impl Inflater<'_> {
+    fn push(&mut self, byte: u8) -> Result<(), ZipErrorKind> {
+        if self.output.len() == self.size {
+            return Err(self.too_long());
+        }
+        self.output.push(byte);
+        Ok(())
+    }
+
+    fn too_long(&self) -> ZipErrorKind {
+        ZipErrorKind::BadDeflate("decompresses to more bytes than the entry's size")
+    }
+
+    // Spec: 3.2.4
+    fn stored_block(&mut self) -> Result<(), ZipErrorKind> {
+        self.input.align();
+        let length = self.input.bits(16)?;
+        let complement = self.input.bits(16)?;
+        if length != !complement & 0xffff {
+            return Err(ZipErrorKind::BadDeflate("stored block length doesn't match its complement"));
+        }
+        let start = self.input.index;
+        let bytes = self.input.bytes.get(start..start + length as usize)
+            .ok_or(ZipErrorKind::BadDeflate("compressed data ends early"))?;
+        if self.output.len() + bytes.len() > self.size {
+            return Err(self.too_long());
+        }
+        self.output.extend_from_slice(bytes);
+        self.input.index += length as usize;
+        Ok(())
+    }
+
+    // Spec: 3.2.5, literals and (length, distance) pairs up to the end of block symbol
+    fn compressed_block(&mut self, lengths: &Huffman, distances: &Huffman) -> Result<(), ZipErrorKind> {
+        loop {
+            let symbol = lengths.decode(&mut self.input)?;
+            match symbol {
+                0..=255 => self.push(symbol as u8)?,
+                256 => return Ok(()),
+                _ => {
+                    let index = symbol as usize - 257;
+                    let (&base, &extra) = LENGTH_BASES.get(index).zip(LENGTH_EXTRA_BITS.get(index))
+                        .ok_or(ZipErrorKind::BadDeflate("invalid length symbol"))?;
+                    let length = base as usize + self.input.bits(extra as u32)? as usize;
+                    let index = distances.decode(&mut self.input)? as usize;
+                    let (&base, &extra) = DISTANCE_BASES.get(index).zip(DISTANCE_EXTRA_BITS.get(index))
+                        .ok_or(ZipErrorKind::BadDeflate("invalid distance symbol"))?;
+                    let distance = base as usize + self.input.bits(extra as u32)? as usize;
+                    if distance > self.output.len() {
+                        return Err(ZipErrorKind::BadDeflate("distance goes back before the start of the data"));
+                    }
+                    if self.output.len() + length > self.size {
+                        return Err(self.too_long());
+                    }
+                    // The copy may overlap what it produces, so it goes a byte at a time
+                    let start = self.output.len() - distance;
+                    for i in 0..length {
+                        let byte = self.output[start + i];
+                        self.output.push(byte);
+                    }
+                }
+            }
+        }
+    }
+
+    // Spec: 3.2.7, the codes of a block with dynamic Huffman codes, which are themselves compressed with a
+    // code for code lengths
+    fn dynamic_codes(&mut self) -> Result<(Huffman, Huffman), ZipErrorKind> {
+        let length_count = self.input.bits(5)? as usize + 257;
+        let distance_count = self.input.bits(5)? as usize + 1;
+        let code_length_count = self.input.bits(4)? as usize + 4;
+        if length_count > 286 || distance_count > 30 {
+            return Err(ZipErrorKind::BadDeflate("too many length or distance codes"));
+        }
+        let mut code_lengths = [0u8; 19];
+        for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+            code_lengths[symbol] = self.input.bits(3)? as u8;
+        }
+        let code_length_code = Huffman::new(&code_lengths)?;
+
+        // The lengths of both codes come as one sequence, and repeats may cross from one into the other
+        let mut lengths = Vec::with_capacity(length_count + distance_count);
+        while lengths.len() < length_count + distance_count {
+            let (length, repeat) = match code_length_code.decode(&mut self.input)? {
+                symbol @ 0..=15 => (symbol as u8, 1),
+                16 => {
+                    let &previous = lengths.last().ok_or(ZipErrorKind::BadDeflate("repeat with no previous length"))?;
+                    (previous, 3 + self.input.bits(2)?)
+                }
+                17 => (0, 3 + self.input.bits(3)?),
+                _ => (0, 11 + self.input.bits(7)?),
+            };
+            if lengths.len() + repeat as usize > length_count + distance_count {
+                return Err(ZipErrorKind::BadDeflate("code lengths repeat past the end"));
+            }
+            lengths.resize(lengths.len() + repeat as usize, length);
+        }
+        if lengths[256] == 0 {
+            return Err(ZipErrorKind::BadDeflate("no code for the end of block"));
+        }
+        Ok((Huffman::new(&lengths[..length_count])?, Huffman::new(&lengths[length_count..])?))
+    }
+}
+
+// Spec: 3.2.6
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    let lengths = Huffman::new(&lengths).expect("the fixed literal/length code is complete");
+    let distances = Huffman::new(&[5; 30]).expect("the fixed distance code is valid");
+    (lengths, distances)
+}
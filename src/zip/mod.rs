@@ -0,0 +1,380 @@
+mod inflate;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::ops::Deref;
+
+use inflate::inflate;
+
+// Spec: https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT
+// A zip file, like a JAR, read through its central directory. Entries are stored or deflated; zip64
+// archives and entries are supported, multi-disk and encrypted ones aren't.
+#[derive(Debug)]
+pub struct ZipArchive<B: Deref<Target = [u8]>> {
+    bytes: B,
+    pub entries: Vec<ZipEntry>,
+    // Index into entries by name. With duplicate names, the first one counts.
+    names: HashMap<String, usize>,
+    // Where offset 0 of the archive is in the bytes: more than 0 when something, like a launcher script,
+    // was put in front of the archive without fixing up its offsets
+    base: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZipEntry {
+    // With / separating directories, and ending in / for directories
+    pub name: String,
+    pub method: CompressionMethod,
+    pub flags: u16,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    // Offset of the entry's local header from the start of the archive
+    pub local_header_offset: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionMethod {
+    Stored,
+    Deflated,
+    Other(u16),
+}
+
+#[derive(Debug)]
+pub struct ZipError {
+    pub kind: ZipErrorKind,
+    // What was being read, e.g. "central directory" or "entry META-INF/MANIFEST.MF"
+    pub context: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ZipErrorKind {
+    // There is no end of central directory record, so this isn't a zip file
+    NotAZipFile,
+    Truncated,
+    BadSignature {
+        expected: u32,
+        found: u32,
+    },
+    MultiDisk,
+    Encrypted,
+    UnsupportedCompression(u16),
+    BadDeflate(&'static str),
+    SizeMismatch {
+        expected: u64,
+        actual: u64,
+    },
+    CrcMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    // Bigger than an entry is allowed to be when read into memory
+    TooLarge(u64),
+}
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_SIGNATURE: u32 = 0x06054b50;
+const ZIP64_END_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+// The end of central directory record is 22 bytes, followed by a comment of up to 65535 bytes
+const END_SIZE: usize = 22;
+const ZIP64_LOCATOR_SIZE: usize = 20;
+const FLAG_ENCRYPTED: u16 = 0x0001;
+// Entries are read into memory whole, so anything bigger than this is refused rather than allocated
+const MAX_ENTRY_SIZE: u64 = 1 << 30;
+
+// Little-endian reads at an offset, since zip structures are little-endian and found by offset
+struct Reader<'a> {
+    bytes: &'a [u8],
+    index: usize,
+}
+
+impl<B: Deref<Target = [u8]>> ZipArchive<B> {
+    // Reads the central directory. The entries themselves are only read by read().
+    pub fn new(bytes: B) -> Result<ZipArchive<B>, ZipError> {
+        let context = |kind| ZipError::new(kind, "end of central directory");
+        let end = find_end(&bytes).ok_or_else(|| context(ZipErrorKind::NotAZipFile))?;
+        let mut reader = Reader::at(&bytes, end + 4);
+        let disk = reader.u16().map_err(context)?;
+        let directory_disk = reader.u16().map_err(context)?;
+        let _disk_entries = reader.u16().map_err(context)?;
+        let mut entry_count = reader.u16().map_err(context)? as u64;
+        let mut directory_size = reader.u32().map_err(context)? as u64;
+        let mut directory_offset = reader.u32().map_err(context)? as u64;
+        let mut directory_end = end as u64;
+        if disk != 0 || directory_disk != 0 {
+            return Err(context(ZipErrorKind::MultiDisk));
+        }
+
+        // Spec: 4.3.15, zip64 archives have a locator right before the end record, pointing to a zip64 end
+        // record with the real sizes and offsets
+        if let Some(locator) = end.checked_sub(ZIP64_LOCATOR_SIZE) {
+            let mut reader = Reader::at(&bytes, locator);
+            if reader.u32() == Ok(ZIP64_LOCATOR_SIGNATURE) {
+                let context = |kind| ZipError::new(kind, "zip64 end of central directory");
+                let _disk = reader.u32().map_err(context)?;
+                let record_offset = reader.u64().map_err(context)?;
+                // The record comes right before the locator, which is where to look for it if the offset
+                // is off because of data in front of the archive
+                let record_size = 56;
+                let record = locator.checked_sub(record_size).ok_or_else(|| context(ZipErrorKind::Truncated))?;
+                let record = match Reader::at(&bytes, record_offset as usize).u32() {
+                    Ok(ZIP64_END_SIGNATURE) => record_offset as usize,
+                    _ => record,
+                };
+                let mut reader = Reader::at(&bytes, record);
+                reader.signature(ZIP64_END_SIGNATURE).map_err(context)?;
+                let _record_size = reader.u64().map_err(context)?;
+                let _version_made_by = reader.u16().map_err(context)?;
+                let _version_needed = reader.u16().map_err(context)?;
+                let disk = reader.u32().map_err(context)?;
+                let directory_disk = reader.u32().map_err(context)?;
+                if disk != 0 || directory_disk != 0 {
+                    return Err(context(ZipErrorKind::MultiDisk));
+                }
+                let _disk_entries = reader.u64().map_err(context)?;
+                entry_count = reader.u64().map_err(context)?;
+                directory_size = reader.u64().map_err(context)?;
+                directory_offset = reader.u64().map_err(context)?;
+                directory_end = record as u64;
+            }
+        }
+
+        let context = |kind| ZipError::new(kind, "central directory");
+        let base = directory_end.checked_sub(directory_size)
+            .and_then(|start| start.checked_sub(directory_offset))
+            .ok_or_else(|| context(ZipErrorKind::Truncated))?;
+        let mut reader = Reader::at(&bytes, (base + directory_offset) as usize);
+        // Every entry takes up at least 46 bytes, which keeps a made up count from allocating much
+        let mut entries = Vec::with_capacity(entry_count.min(directory_size / 46) as usize);
+        let mut names = HashMap::new();
+        for index in 0..entry_count {
+            let entry = read_central_header(&mut reader)
+                .map_err(|kind| ZipError::new(kind, format!("central directory entry {}", index)))?;
+            names.entry(entry.name.clone()).or_insert(entries.len());
+            entries.push(entry);
+        }
+        Ok(ZipArchive { bytes, entries, names, base })
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&ZipEntry> {
+        self.names.get(name).map(|&index| &self.entries[index])
+    }
+
+    // The uncompressed contents of an entry, None if there is no entry by that name
+    pub fn read(&self, name: &str) -> Result<Option<Vec<u8>>, ZipError> {
+        match self.entry(name) {
+            Some(entry) => self.read_entry(entry).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn read_entry(&self, entry: &ZipEntry) -> Result<Vec<u8>, ZipError> {
+        let context = |kind| ZipError::new(kind, format!("entry {}", entry.name));
+        if entry.flags & FLAG_ENCRYPTED != 0 {
+            return Err(context(ZipErrorKind::Encrypted));
+        }
+        if entry.uncompressed_size > MAX_ENTRY_SIZE {
+            return Err(context(ZipErrorKind::TooLarge(entry.uncompressed_size)));
+        }
+        // The local header repeats most of the central one, but its extra field can differ in length,
+        // so it has to be read to find where the data starts. The sizes in it may be zero when a data
+        // descriptor follows the data; the central directory's are used.
+        let offset = self.base.checked_add(entry.local_header_offset).ok_or_else(|| context(ZipErrorKind::Truncated))?;
+        let mut reader = Reader::at(&self.bytes, offset as usize);
+        reader.signature(LOCAL_HEADER_SIGNATURE).map_err(context)?;
+        reader.skip(22).map_err(context)?;
+        let name_length = reader.u16().map_err(context)? as usize;
+        let extra_length = reader.u16().map_err(context)? as usize;
+        reader.skip(name_length + extra_length).map_err(context)?;
+        let data = reader.slice(entry.compressed_size as usize).map_err(context)?;
+
+        let contents = match entry.method {
+            CompressionMethod::Stored if entry.compressed_size != entry.uncompressed_size =>
+                return Err(context(ZipErrorKind::SizeMismatch { expected: entry.uncompressed_size, actual: entry.compressed_size })),
+            CompressionMethod::Stored => data.to_vec(),
+            CompressionMethod::Deflated => inflate(data, entry.uncompressed_size as usize).map_err(context)?,
+            CompressionMethod::Other(method) => return Err(context(ZipErrorKind::UnsupportedCompression(method))),
+        };
+        let crc = crc32(&contents);
+        if crc != entry.crc32 {
+            return Err(context(ZipErrorKind::CrcMismatch { expected: entry.crc32, actual: crc }));
+        }
+        Ok(contents)
+    }
+}
+
+impl ZipError {
+    pub fn new(kind: ZipErrorKind, context: impl Into<String>) -> Self {
+        ZipError { kind, context: context.into() }
+    }
+}
+
+impl fmt::Display for ZipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid zip file, in {}: {}", self.context, self.kind)
+    }
+}
+
+impl fmt::Display for ZipErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZipErrorKind::NotAZipFile => write!(f, "no end of central directory record, not a zip file"),
+            ZipErrorKind::Truncated => write!(f, "truncated, or an offset or size points outside the file"),
+            ZipErrorKind::BadSignature { expected, found } =>
+                write!(f, "bad signature {:#010x}, expected {:#010x}", found, expected),
+            ZipErrorKind::MultiDisk => write!(f, "archives split across several disks are not supported"),
+            ZipErrorKind::Encrypted => write!(f, "encrypted entries are not supported"),
+            ZipErrorKind::UnsupportedCompression(method) => write!(f, "unsupported compression method {}", method),
+            ZipErrorKind::BadDeflate(problem) => write!(f, "bad deflate data, {}", problem),
+            ZipErrorKind::SizeMismatch { expected, actual } =>
+                write!(f, "size is {} bytes, but the entry says {}", actual, expected),
+            ZipErrorKind::CrcMismatch { expected, actual } =>
+                write!(f, "CRC-32 is {:#010x}, but the entry says {:#010x}", actual, expected),
+            ZipErrorKind::TooLarge(size) => write!(f, "{} bytes is too large to read", size),
+        }
+    }
+}
+
+impl Error for ZipError {}
+
+impl From<u16> for CompressionMethod {
+    fn from(method: u16) -> Self {
+        match method {
+            0 => CompressionMethod::Stored,
+            8 => CompressionMethod::Deflated,
+            method => CompressionMethod::Other(method),
+        }
+    }
+}
+
+impl<'a> Reader<'a> {
+    fn at(bytes: &'a [u8], index: usize) -> Reader<'a> {
+        Reader { bytes, index }
+    }
+
+    fn slice(&mut self, length: usize) -> Result<&'a [u8], ZipErrorKind> {
+        let bytes = self.index.checked_add(length)
+            .and_then(|end| self.bytes.get(self.index..end))
+            .ok_or(ZipErrorKind::Truncated)?;
+        self.index += length;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, length: usize) -> Result<(), ZipErrorKind> {
+        self.slice(length).map(|_| ())
+    }
+
+    fn u16(&mut self) -> Result<u16, ZipErrorKind> {
+        let bytes = self.slice(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, ZipErrorKind> {
+        let bytes = self.slice(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, ZipErrorKind> {
+        Ok(self.u32()? as u64 | (self.u32()? as u64) << 32)
+    }
+
+    fn signature(&mut self, expected: u32) -> Result<(), ZipErrorKind> {
+        match self.u32()? {
+            found if found == expected => Ok(()),
+            found => Err(ZipErrorKind::BadSignature { expected, found }),
+        }
+    }
+}
+
+// Spec: 4.3.16, the end of central directory record is at the end, before a comment of unknown length,
+// so it is searched for backwards. A candidate's comment has to reach exactly to the end of the file,
+// which keeps the signature showing up in the comment or in the data from being taken for it.
+fn find_end(bytes: &[u8]) -> Option<usize> {
+    let last = bytes.len().checked_sub(END_SIZE)?;
+    let first = last.saturating_sub(u16::MAX as usize);
+    (first..=last).rev().find(|&offset| {
+        let mut reader = Reader::at(bytes, offset);
+        reader.u32() == Ok(END_SIGNATURE) && {
+            reader.index = offset + 20;
+            reader.u16().is_ok_and(|comment_length| offset + END_SIZE + comment_length as usize == bytes.len())
+        }
+    })
+}
+
+// Spec: 4.3.12
+fn read_central_header(reader: &mut Reader) -> Result<ZipEntry, ZipErrorKind> {
+    reader.signature(CENTRAL_HEADER_SIGNATURE)?;
+    let _version_made_by = reader.u16()?;
+    let _version_needed = reader.u16()?;
+    let flags = reader.u16()?;
+    let method = CompressionMethod::from(reader.u16()?);
+    let _modification_time = reader.u16()?;
+    let _modification_date = reader.u16()?;
+    let crc32 = reader.u32()?;
+    let mut compressed_size = reader.u32()? as u64;
+    let mut uncompressed_size = reader.u32()? as u64;
+    let name_length = reader.u16()? as usize;
+    let extra_length = reader.u16()? as usize;
+    let comment_length = reader.u16()? as usize;
+    let disk = reader.u16()?;
+    let _internal_attributes = reader.u16()?;
+    let _external_attributes = reader.u32()?;
+    let mut local_header_offset = reader.u32()? as u64;
+    // Names are UTF-8 in JARs, which is what Java reads them as whether or not the UTF-8 flag is set
+    let name = String::from_utf8_lossy(reader.slice(name_length)?).into_owned();
+    let extra = reader.slice(extra_length)?;
+    reader.skip(comment_length)?;
+
+    // Spec: 4.5.3, the zip64 extra field has the 8 byte values of the fields set to 0xffffffff, in order
+    let mut extra_reader = Reader::at(extra, 0);
+    while extra_reader.index + 4 <= extra.len() {
+        let id = extra_reader.u16()?;
+        let size = extra_reader.u16()? as usize;
+        let mut field = Reader::at(extra_reader.slice(size)?, 0);
+        if id == ZIP64_EXTRA_ID {
+            if uncompressed_size == u32::MAX as u64 {
+                uncompressed_size = field.u64()?;
+            }
+            if compressed_size == u32::MAX as u64 {
+                compressed_size = field.u64()?;
+            }
+            if local_header_offset == u32::MAX as u64 {
+                local_header_offset = field.u64()?;
+            }
+        }
+    }
+    if disk != 0 && disk != u16::MAX {
+        return Err(ZipErrorKind::MultiDisk);
+    }
+    Ok(ZipEntry { name, method, flags, crc32, compressed_size, uncompressed_size, local_header_offset })
+}
+
+// Spec: 4.4.7, the CRC-32 of ISO 3309, as used by zlib and Java's CRC32
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => 0xedb88320 ^ (crc >> 1),
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
@@ -0,0 +1,119 @@
+// Decoding a code array and encoding the instructions again has to give back the same bytes: wide forms
+// stay wide, switches get the padding their pc needs, and branches go back to offsets from their own pc.
+
+use std::fs;
+use std::path::Path;
+
+use jvmmy::attr::AttributeInfo;
+use jvmmy::bytecode::{self, Instruction, WideInstruction};
+use jvmmy::class::loading::ClassFileLoader;
+
+fn identity(code: &[u8]) -> Vec<(u32, Instruction)> {
+    let decoded = bytecode::decode(code).unwrap();
+    let instructions: Vec<Instruction> = decoded.iter().map(|(_, instruction)| instruction.clone()).collect();
+    assert_eq!(bytecode::encode(&instructions).unwrap(), code);
+    // The pcs decode reports are where each instruction ends up when encoded
+    let mut pc = 0;
+    for (decoded_pc, instruction) in &decoded {
+        assert_eq!(*decoded_pc, pc);
+        pc += instruction.length(pc);
+    }
+    assert_eq!(pc as usize, code.len());
+    decoded
+}
+
+fn class_files(directory: &Path, paths: &mut Vec<String>) {
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        match path.is_dir() {
+            true => class_files(&path, paths),
+            false => paths.push(path.to_str().unwrap().to_string()),
+        }
+    }
+}
+
+#[test]
+fn every_code_attribute_encodes_back() {
+    let mut paths = vec!["HelloWorld.class".to_string(), "OnePlusOne.class".to_string()];
+    class_files(Path::new("tests/data/classes"), &mut paths);
+    let mut instructions = Vec::new();
+    for path in paths {
+        let bytes = fs::read(&path).unwrap();
+        let class_file = ClassFileLoader::new(&bytes).load().unwrap();
+        for method in &class_file.methods {
+            for attribute in &method.attributes {
+                if let AttributeInfo::Code { code, .. } = &attribute.info {
+                    instructions.extend(identity(code).into_iter().map(|(_, instruction)| instruction));
+                }
+            }
+        }
+    }
+    // The fixtures have to actually hold the forms that are easy to get wrong
+    assert!(instructions.iter().any(|instruction| matches!(instruction, Instruction::Wide(WideInstruction::Iinc { .. }))));
+    assert!(instructions.iter().any(|instruction| matches!(instruction, Instruction::Wide(_))));
+    assert!(instructions.iter().any(|instruction| matches!(instruction, Instruction::Tableswitch { .. })));
+    assert!(instructions.iter().any(|instruction| matches!(instruction, Instruction::Lookupswitch { .. })));
+}
+
+#[test]
+fn wide_forms() {
+    let code = [
+        0xc4, 0x15, 0x01, 0x2c, // wide iload 300
+        0xc4, 0x3a, 0x00, 0x05, // wide astore 5, which fits in a byte but stays wide
+        0xc4, 0x84, 0x01, 0x2c, 0xfc, 0x18, // wide iinc 300 -1000
+        0xc4, 0xa9, 0x01, 0x00, // wide ret 256
+        0x15, 0x05, // iload 5
+    ];
+    let decoded = identity(&code);
+    assert_eq!(decoded, [
+        (0, Instruction::Wide(WideInstruction::Iload(300))),
+        (4, Instruction::Wide(WideInstruction::Astore(5))),
+        (8, Instruction::Wide(WideInstruction::Iinc { index: 300, constant: -1000 })),
+        (14, Instruction::Wide(WideInstruction::Ret(256))),
+        (18, Instruction::Iload(5)),
+    ]);
+}
+
+#[test]
+fn switches_at_every_padding() {
+    for start in 0..4u32 {
+        // start nops, so the switch opcode is at pc start and 3 - start % 4 bytes of padding follow it
+        let mut instructions = vec![Instruction::Nop; start as usize];
+        let tableswitch_length = Instruction::Tableswitch { default: 0, low: 0, high: 1, targets: vec![0, 0] }.length(start);
+        let lookupswitch_pc = start + tableswitch_length;
+        let lookupswitch_length = Instruction::Lookupswitch { default: 0, pairs: vec![(-1, 0), (7, 0)] }.length(lookupswitch_pc);
+        let end = lookupswitch_pc + lookupswitch_length;
+        instructions.push(Instruction::Tableswitch { default: end, low: -1, high: 0, targets: vec![0, lookupswitch_pc] });
+        instructions.push(Instruction::Lookupswitch { default: start, pairs: vec![(-1, end), (7, 0)] });
+        instructions.push(Instruction::Return);
+
+        let code = bytecode::encode(&instructions).unwrap();
+        let padding = (3 - start % 4) as usize;
+        assert_eq!(tableswitch_length as usize, 1 + padding + 12 + 8);
+        assert!(code[start as usize + 1..][..padding].iter().all(|&byte| byte == 0), "padding at {}", start);
+        let decoded = identity(&code);
+        let decoded: Vec<Instruction> = decoded.into_iter().map(|(_, instruction)| instruction).collect();
+        assert_eq!(decoded, instructions, "switches at {}", start);
+    }
+}
+
+#[test]
+fn branch_targets_are_absolute() {
+    let code = [
+        0x00, // 0: nop
+        0x99, 0x00, 0x07, // 1: ifeq +7
+        0xa7, 0xff, 0xfc, // 4: goto -4
+        0x00, // 7: nop
+        0xc8, 0xff, 0xff, 0xff, 0xf8, // 8: goto_w -8
+        0xa7, 0x00, 0x00, // 13: goto +0
+    ];
+    let decoded = identity(&code);
+    assert_eq!(decoded, [
+        (0, Instruction::Nop),
+        (1, Instruction::Ifeq(8)),
+        (4, Instruction::Goto(0)),
+        (7, Instruction::Nop),
+        (8, Instruction::GotoW(0)),
+        (13, Instruction::Goto(13)),
+    ]);
+}
@@ -0,0 +1,7 @@
+package programs;
+
+public class Greeting {
+    public static String message() {
+        return "Hello from a JAR";
+    }
+}
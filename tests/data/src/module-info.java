@@ -0,0 +1,10 @@
+// A module with one of each directive, for the Module, ModulePackages and ModuleMainClass attributes
+@Deprecated
+module jvmmy.features {
+    requires java.logging;
+    requires transitive java.sql;
+    exports features;
+    opens features to java.logging;
+    uses java.lang.Runnable;
+    provides java.lang.Runnable with features.Features.Task;
+}
@@ -0,0 +1,11 @@
+package programs;
+
+// Prints each argument, and the system property named like it
+public class Echo {
+    public static void main(String[] args) {
+        System.out.println(args.length + " arguments");
+        for (String arg : args) {
+            System.out.println("[" + arg + "] " + System.getProperty(arg));
+        }
+    }
+}
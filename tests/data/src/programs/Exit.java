@@ -0,0 +1,17 @@
+package programs;
+
+// Exits with the status given as its argument, or throws if there is none
+public class Exit {
+    public static void main(String[] args) {
+        System.out.println("exiting");
+        if (args.length == 0) {
+            fail("no status");
+        }
+        System.exit(Integer.parseInt(args[0]));
+        System.out.println("not reached");
+    }
+
+    static void fail(String message) {
+        throw new IllegalStateException(message);
+    }
+}
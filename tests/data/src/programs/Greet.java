@@ -0,0 +1,8 @@
+package programs;
+
+// Greeting is only in tests/data/lib/greeting.jar, so this runs when that is on the class path
+public class Greet {
+    public static void main(String[] args) {
+        System.out.println(Greeting.message());
+    }
+}
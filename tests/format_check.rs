@@ -0,0 +1,244 @@
+// Format checking reports every violation it finds in one go. Each test breaks a loaded class in some
+// ways and expects all of them back, with where they are.
+//
+// HelloWorld.class is version 55 and has, among others, #1 Methodref java/lang/Object."<init>":()V,
+// #2 Fieldref java/lang/System.out, #4 Methodref java/io/PrintStream.println, #5 Class HelloWorld,
+// #6 Class java/lang/Object and #20 NameAndType println.
+
+use std::fs;
+
+use jvmmy::attr::AttributeInfo;
+use jvmmy::class::{ClassAccessFlags, ClassFile};
+use jvmmy::class::error::ClassFormatErrorKind;
+use jvmmy::class::format_check::check_format;
+use jvmmy::class::loading::ClassFileLoader;
+use jvmmy::constant_pool::ConstantPoolInfo;
+use jvmmy::field::{FieldAccessFlags, FieldInfo};
+use jvmmy::method::{MethodAccessFlags, MethodInfo};
+use jvmmy::util::mutf8::ModifiedUtf8;
+
+fn hello_world() -> ClassFile {
+    let bytes = fs::read("HelloWorld.class").unwrap();
+    ClassFileLoader::new(&bytes).load().unwrap()
+}
+
+fn utf8(class_file: &mut ClassFile, string: &str) -> u16 {
+    class_file.constant_pool.push(ConstantPoolInfo::Utf8 { string: ModifiedUtf8::from(string) }).unwrap()
+}
+
+// Abstract and native methods get no Code attribute, the others the one of HelloWorld's constructor
+fn add_method(class_file: &mut ClassFile, flags: u16, name: &str, descriptor: &str) {
+    let name_index = utf8(class_file, name);
+    let descriptor_index = utf8(class_file, descriptor);
+    let access_flags = MethodAccessFlags(flags);
+    let attributes = match access_flags.is_abstract() || access_flags.is_native() {
+        true => Vec::new(),
+        false => class_file.methods[0].attributes.iter()
+            .filter(|attribute| matches!(attribute.info, AttributeInfo::Code { .. }))
+            .cloned()
+            .collect(),
+    };
+    class_file.methods.push(MethodInfo { access_flags, name_index, descriptor_index, attributes });
+}
+
+fn add_field(class_file: &mut ClassFile, name_index: u16, descriptor_index: u16) {
+    class_file.fields.push(FieldInfo {
+        access_flags: FieldAccessFlags(0x0002),
+        name_index,
+        descriptor_index,
+        attributes: Vec::new(),
+    });
+}
+
+// The context and kind of every error
+fn violations(class_file: &ClassFile) -> Vec<(String, ClassFormatErrorKind)> {
+    check_format(class_file).err().unwrap_or_default().into_iter().map(|error| (error.context, error.kind)).collect()
+}
+
+#[test]
+fn hello_world_has_no_errors() {
+    assert!(violations(&hello_world()).is_empty());
+}
+
+#[test]
+fn too_many_parameter_slots() {
+    let mut class_file = hello_world();
+    // 127 longs and `this` fit in 255 slots, 128 longs don't, and neither do 32768 longs which add up to 65536
+    add_method(&mut class_file, 0x0101, "fits", &format!("({})V", "J".repeat(127)));
+    add_method(&mut class_file, 0x0109, "over", &format!("({})V", "J".repeat(128)));
+    add_method(&mut class_file, 0x0109, "wraps", &format!("({})V", "J".repeat(32768)));
+    let errors = violations(&class_file);
+    assert!(matches!(&errors[..], [
+        (over, ClassFormatErrorKind::TooManyParameterSlots(256)),
+        (wraps, ClassFormatErrorKind::TooManyParameterSlots(65536)),
+    ] if over == "method #3" && wraps == "method #4"), "{:?}", errors);
+}
+
+#[test]
+fn names_that_arent_utf8() {
+    let mut class_file = hello_world();
+    let descriptor = utf8(&mut class_file, "I");
+    // A field named by a Class entry and a method whose descriptor is an Integer
+    add_field(&mut class_file, 5, descriptor);
+    let integer = class_file.constant_pool.push(ConstantPoolInfo::Integer { bytes: 1 }).unwrap();
+    class_file.methods[1].descriptor_index = integer;
+    // A Class and a String that point at a Class
+    let class = class_file.constant_pool.push(ConstantPoolInfo::Class { name_index: 6 }).unwrap();
+    let string = class_file.constant_pool.push(ConstantPoolInfo::String { string_index: 5 }).unwrap();
+    let errors = violations(&class_file);
+    assert!(matches!(&errors[..], [
+        (class_context, ClassFormatErrorKind::WrongConstantKind { index: 6, expected: "Utf8" }),
+        (string_context, ClassFormatErrorKind::WrongConstantKind { index: 5, expected: "Utf8" }),
+        (field, ClassFormatErrorKind::WrongConstantKind { index: 5, expected: "Utf8" }),
+        (method, ClassFormatErrorKind::WrongConstantKind { index, expected: "Utf8" }),
+    ] if *class_context == format!("constant_pool[{}]", class) && *string_context == format!("constant_pool[{}]", string)
+        && field == "field #0" && method == "method #1" && *index == integer), "{:?}", errors);
+}
+
+#[test]
+fn bad_descriptors() {
+    let mut class_file = hello_world();
+    let name = utf8(&mut class_file, "x");
+    let descriptor = utf8(&mut class_file, "X");
+    add_field(&mut class_file, name, descriptor);
+    add_method(&mut class_file, 0x0001, "unclosed", "(I");
+    add_method(&mut class_file, 0x0001, "no_return", "()");
+    add_method(&mut class_file, 0x0001, "void_parameter", "(V)V");
+    let method_type = utf8(&mut class_file, "I");
+    let method_type = ConstantPoolInfo::MethodType { descriptor_index: method_type };
+    let method_type = class_file.constant_pool.push(method_type).unwrap();
+    let errors = violations(&class_file);
+    let contexts: Vec<&str> = errors.iter()
+        .filter(|(_, kind)| matches!(kind, ClassFormatErrorKind::InvalidDescriptor(_)))
+        .map(|(context, _)| context.as_str())
+        .collect();
+    assert_eq!(errors.len(), contexts.len(), "{:?}", errors);
+    assert_eq!(contexts[0], format!("constant_pool[{}]", method_type));
+    assert_eq!(contexts[1..], ["field #0", "method #2", "method #3", "method #4"]);
+}
+
+#[test]
+fn missing_and_wrong_superclasses() {
+    let mut class_file = hello_world();
+    class_file.super_class = 0;
+    let errors = violations(&class_file);
+    assert!(matches!(&errors[..], [(context, ClassFormatErrorKind::BadSuperclass(_))] if context == "super_class"),
+            "{:?}", errors);
+
+    // java/lang/Object itself must have none
+    let mut object = hello_world();
+    object.this_class = 6;
+    let errors = violations(&object);
+    assert!(matches!(&errors[..], [(_, ClassFormatErrorKind::BadSuperclass("java/lang/Object has no superclass"))]),
+            "{:?}", errors);
+
+    // An interface must extend java/lang/Object, and has no constructor
+    let mut interface = hello_world();
+    interface.access_flags = ClassAccessFlags(0x0601);
+    interface.super_class = 5;
+    interface.methods.remove(0);
+    let errors = violations(&interface);
+    assert!(matches!(&errors[..], [(_, ClassFormatErrorKind::BadSuperclass(_))]), "{:?}", errors);
+}
+
+#[test]
+fn duplicate_members() {
+    let mut class_file = hello_world();
+    let name = utf8(&mut class_file, "count");
+    let int = utf8(&mut class_file, "I");
+    let long = utf8(&mut class_file, "J");
+    add_field(&mut class_file, name, int);
+    add_field(&mut class_file, name, long);
+    add_field(&mut class_file, name, int);
+    add_method(&mut class_file, 0x0009, "main", "([Ljava/lang/String;)V");
+    add_method(&mut class_file, 0x0009, "main", "()V");
+    add_method(&mut class_file, 0x0001, "<init>", "()V");
+    let errors = violations(&class_file);
+    assert!(matches!(&errors[..], [
+        (field, ClassFormatErrorKind::DuplicateMember { name: field_name, descriptor: field_descriptor }),
+        (main, ClassFormatErrorKind::DuplicateMember { name: main_name, .. }),
+        (init, ClassFormatErrorKind::DuplicateMember { name: init_name, .. }),
+    ] if field == "field #2" && field_name == "count" && field_descriptor == "I" && main == "method #2"
+        && main_name == "main" && init == "method #4" && init_name == "<init>"), "{:?}", errors);
+}
+
+#[test]
+fn initialization_methods() {
+    let mut class_file = hello_world();
+    add_method(&mut class_file, 0x0001, "<init>", "(I)I");
+    add_method(&mut class_file, 0x0008, "<clinit>", "(I)V");
+    add_method(&mut class_file, 0x0000, "<clinit>", "()V");
+    add_method(&mut class_file, 0x0008, "<clinit>", "()I");
+    let errors = violations(&class_file);
+    assert!(matches!(&errors[..], [
+        (_, ClassFormatErrorKind::BadSpecialMethod("<init> must return void")),
+        (_, ClassFormatErrorKind::BadSpecialMethod("<clinit> must take no arguments")),
+        (_, ClassFormatErrorKind::BadSpecialMethod("<clinit> must be static")),
+        (_, ClassFormatErrorKind::BadSpecialMethod("<clinit> must return void")),
+    ]), "{:?}", errors);
+    let contexts: Vec<&str> = errors.iter().map(|(context, _)| context.as_str()).collect();
+    assert_eq!(contexts, ["method #2", "method #3", "method #4", "method #5"]);
+
+    // Before version 51 any method called <clinit> other than the class initializer is ignored
+    let mut old = hello_world();
+    old.major_version = 50;
+    add_method(&mut old, 0x0000, "<clinit>", "(I)V");
+    assert!(violations(&old).is_empty(), "{:?}", violations(&old));
+
+    let mut interface = hello_world();
+    interface.access_flags = ClassAccessFlags(0x0601);
+    interface.methods.remove(1);
+    let errors = violations(&interface);
+    assert!(matches!(&errors[..], [
+        (_, ClassFormatErrorKind::BadSpecialMethod("an interface must not have an <init> method")),
+        ..
+    ]), "{:?}", errors);
+}
+
+#[test]
+fn method_handles() {
+    let mut class_file = hello_world();
+    let interface_method = ConstantPoolInfo::InterfaceMethodRef { class_index: 19, name_and_type_index: 20 };
+    let interface_method = class_file.constant_pool.push(interface_method).unwrap();
+    let handles = [
+        (0, 4),
+        (10, 4),
+        // REF_getField of a method, REF_invokeVirtual of a field
+        (1, 4),
+        (5, 2),
+        // REF_invokeVirtual of <init>, REF_newInvokeSpecial of something else
+        (5, 1),
+        (8, 4),
+        // REF_invokeInterface of a class method
+        (9, 4),
+        // Fine: REF_newInvokeSpecial of <init>, REF_invokeStatic of an interface method from 52.0 on
+        (8, 1),
+        (6, interface_method),
+    ];
+    let indices: Vec<u16> = handles.into_iter()
+        .map(|(reference_kind, reference_index)| {
+            class_file.constant_pool.push(ConstantPoolInfo::MethodHandle { reference_kind, reference_index }).unwrap()
+        })
+        .collect();
+    let errors = violations(&class_file);
+    let reasons: Vec<&str> = errors.iter().map(|(_, kind)| match kind {
+        ClassFormatErrorKind::BadMethodHandle(reason) => *reason,
+        kind => panic!("{:?}", kind),
+    }).collect();
+    assert_eq!(reasons, [
+        "reference_kind must be between 1 and 9",
+        "reference_kind must be between 1 and 9",
+        "reference_index has the wrong kind for reference_kind",
+        "reference_index has the wrong kind for reference_kind",
+        "only REF_newInvokeSpecial may refer to <init>",
+        "REF_newInvokeSpecial must refer to <init>",
+        "reference_index has the wrong kind for reference_kind",
+    ]);
+
+    // Before 52.0 REF_invokeStatic can't refer to an interface method
+    class_file.major_version = 51;
+    let errors = violations(&class_file);
+    assert_eq!(errors.len(), 8, "{:?}", errors);
+    assert!(matches!(&errors[7], (context, ClassFormatErrorKind::BadMethodHandle(_))
+        if *context == format!("constant_pool[{}]", indices[8])), "{:?}", errors);
+}
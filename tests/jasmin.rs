@@ -0,0 +1,159 @@
+// A class printed as Jasmin and assembled again has to load, and print the same as the class it came from
+// but for the attributes that were left out. In particular the StackMapTable frames that classes from version
+// 50 on can't load without have to come back.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use jvmmy::attr::AttributeInfo;
+use jvmmy::class::ClassFile;
+use jvmmy::class::error::ClassFormatErrorKind;
+use jvmmy::class::format_check::check_format;
+use jvmmy::class::loading::ClassFileLoader;
+use jvmmy::jasmin::{self, AssemblyErrorKind};
+
+fn class_files(directory: &Path, paths: &mut Vec<String>) {
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        match path.is_dir() {
+            true => class_files(&path, paths),
+            false => paths.push(path.to_str().unwrap().to_string()),
+        }
+    }
+}
+
+fn frame_count(class_file: &ClassFile) -> usize {
+    class_file.methods.iter()
+        .flat_map(|method| &method.attributes)
+        .filter_map(|attribute| match &attribute.info {
+            AttributeInfo::Code { attributes, .. } => Some(attributes),
+            _ => None,
+        })
+        .flatten()
+        .map(|attribute| match &attribute.info {
+            AttributeInfo::StackMapTable { entries } => entries.len(),
+            _ => 0,
+        })
+        .sum()
+}
+
+// The class directives, whose order assembling doesn't keep, and the fields and methods after them. The
+// comments about attributes that were left out are left out too.
+fn comparable(source: &str) -> (Vec<&str>, Vec<&str>) {
+    let mut lines = source.lines().filter(|line| !line.ends_with("attribute left out"));
+    let mut header: Vec<&str> = lines.by_ref().take_while(|line| !line.is_empty()).collect();
+    header.sort();
+    (header, lines.collect())
+}
+
+#[test]
+fn printed_classes_assemble_and_load() {
+    let mut paths = vec!["HelloWorld.class".to_string(), "OnePlusOne.class".to_string()];
+    class_files(Path::new("tests/data/classes"), &mut paths);
+    let mut frames = 0;
+    for path in paths.iter().filter(|path| !path.ends_with("module-info.class")) {
+        let bytes = fs::read(path).unwrap();
+        let class_file = ClassFileLoader::new(&bytes).load().unwrap();
+        let source = jasmin::print(&class_file).unwrap();
+        let assembled = jasmin::assemble(&source).unwrap_or_else(|error| panic!("{}: {}", path, error));
+        let written = assembled.to_bytes().unwrap();
+        let loaded = ClassFileLoader::new(&written).load().unwrap_or_else(|error| panic!("{}: {}", path, error));
+        if let Err(errors) = check_format(&loaded) {
+            panic!("{}: {}", path, errors[0]);
+        }
+        let printed = jasmin::print(&loaded).unwrap();
+        assert_eq!(comparable(&printed), comparable(&source), "{} printed differently after assembling", path);
+        assert_eq!(frame_count(&loaded), frame_count(&class_file), "{}", path);
+        frames += frame_count(&loaded);
+    }
+    assert!(frames > 0);
+}
+
+#[test]
+fn module_info_is_not_printed() {
+    let bytes = fs::read("tests/data/classes/module-info.class").unwrap();
+    let class_file = ClassFileLoader::new(&bytes).load().unwrap();
+    let error = jasmin::print(&class_file).unwrap_err();
+    assert!(matches!(error.kind, ClassFormatErrorKind::NotPrintable(_)), "{}", error);
+}
+
+#[test]
+fn frames() {
+    let source = "
+        .bytecode 52.0
+        .class public super Frames
+        .super java/lang/Object
+
+        .method public static make(I)Ljava/lang/Object;
+            .limit stack 3
+          New:
+            new java/lang/Object
+            dup
+            iload_0
+            ifeq Init
+          Init:
+            .stack full locals int stack uninitialized New uninitialized New
+            invokespecial java/lang/Object/<init>()V
+            areturn
+        .end method
+    ";
+    let class_file = jasmin::assemble(source).unwrap();
+    let loaded = ClassFileLoader::new(&class_file.to_bytes().unwrap()).load().unwrap();
+    assert_eq!(frame_count(&loaded), 1);
+    let printed = jasmin::print(&loaded).unwrap();
+    assert!(printed.contains("L0:"), "{}", printed);
+    assert!(printed.contains(".stack full locals int stack uninitialized L0 uninitialized L0\n"), "{}", printed);
+
+    // A frame is for the instruction after it, so there has to be one, and only one frame for it
+    for (frames, line) in [(".stack same\n.stack same\nareturn", 2), ("areturn\n.stack same", 2)] {
+        let source = format!(".class Frames\n.method f()V\n.limit stack 1\naconst_null\n{}\n.end method", frames);
+        let error = jasmin::assemble(&source).unwrap_err();
+        assert!(matches!(error.kind, AssemblyErrorKind::Misplaced(_)), "{}", error);
+        assert_eq!(error.line, 4 + line, "{}", error);
+    }
+}
+
+#[test]
+fn locals_worked_out_from_too_many_parameters() {
+    // 32768 longs take up 65536 slots, one more than max_locals can hold
+    let source = format!(".class Locals\n.method static f({})V\n.limit stack 0\nreturn\n.end method", "J".repeat(32768));
+    let error = jasmin::assemble(&source).unwrap_err();
+    assert!(matches!(error.kind, AssemblyErrorKind::OutOfRange(_)), "{}", error);
+    assert_eq!(error.line, 2);
+}
+
+#[test]
+fn too_many_constants() {
+    // Each field adds its name to the constant pool, and the class and descriptor take up a few more entries
+    let source = |count: u16| {
+        let fields: String = (0..count).map(|i| format!(".field static f{} I\n", i)).collect();
+        format!(".class Constants\n.super java/lang/Object\n{}", fields)
+    };
+    let class_file = jasmin::assemble(&source(65500)).unwrap();
+    assert!(class_file.constant_pool.count() > 65500);
+    let error = jasmin::assemble(&source(65535)).unwrap_err();
+    assert!(matches!(error.kind, AssemblyErrorKind::TooManyConstants), "{}", error);
+}
+
+#[test]
+fn assembled_classes_stay_in_their_directory() {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("assembled");
+    let _ = fs::remove_dir_all(&directory);
+    let output = directory.join("classes");
+    fs::create_dir_all(&output).unwrap();
+    for (name, written) in [("a/Good", true), ("../Escaped", false), ("a/../../Escaped", false), ("/Absolute", false),
+                            ("a//Empty", false), ("a/./Dot", false)] {
+        let source_path = directory.join("Source.j");
+        fs::write(&source_path, format!(".class public {}\n.super java/lang/Object\n", name)).unwrap();
+        let result = Command::new(env!("CARGO_BIN_EXE_jvmmy"))
+            .args(["assemble", "-d", output.to_str().unwrap(), source_path.to_str().unwrap()])
+            .output()
+            .unwrap();
+        assert_eq!(result.status.success(), written, "{}", name);
+        let stderr = String::from_utf8(result.stderr).unwrap();
+        assert_eq!(stderr.contains("can't be written as a path"), !written, "{}", stderr);
+    }
+    assert!(output.join("a/Good.class").is_file());
+    assert!(!directory.join("Escaped.class").exists() && !output.join("Escaped.class").exists());
+}
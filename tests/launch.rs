@@ -0,0 +1,104 @@
+// Runs the programs in tests/data/classes/programs with the jvmmy binary, the way the java launcher would
+// run them, and checks what they print and the status they exit with. Their sources are in
+// tests/data/src/programs, and tests/data/lib/greeting.jar holds the one class of tests/data/src/lib.
+
+use std::process::{Command, Output};
+
+use jvmmy::classpath::SEPARATOR;
+
+fn jvmmy(args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_jvmmy"));
+    command.args(args).env_remove("CLASSPATH");
+    command
+}
+
+fn run(command: &mut Command) -> (Option<i32>, String, String) {
+    let Output { status, stdout, stderr } = command.output().unwrap();
+    (status.code(), String::from_utf8(stdout).unwrap(), String::from_utf8(stderr).unwrap())
+}
+
+#[test]
+fn args_reach_main() {
+    let (status, stdout, stderr) = run(&mut jvmmy(&["-cp", "tests/data/classes", "programs.Echo", "one", "two words", ""]));
+    assert_eq!(status, Some(0), "{}", stderr);
+    assert_eq!(stdout, "3 arguments\n[one] null\n[two words] null\n[] null\n");
+
+    let (_, stdout, _) = run(&mut jvmmy(&["-cp", "tests/data/classes", "programs.Echo"]));
+    assert_eq!(stdout, "0 arguments\n");
+}
+
+#[test]
+fn system_properties() {
+    // -Dname alone is an empty string, and a later -D for the same name wins. Options after the main class
+    // are the program's.
+    let (status, stdout, stderr) = run(&mut jvmmy(&[
+        "-Done=1", "-Dtwo", "-Dthree=a=b", "-Done=2", "-cp", "tests/data/classes", "programs.Echo",
+        "one", "two", "three", "java.class.path", "-Dfour=4", "four",
+    ]));
+    assert_eq!(status, Some(0), "{}", stderr);
+    assert_eq!(stdout, "6 arguments\n[one] 2\n[two] \n[three] a=b\n[java.class.path] tests/data/classes\n\
+        [-Dfour=4] null\n[four] null\n");
+}
+
+#[test]
+fn exit_status() {
+    for status in [0, 3, 255] {
+        let (code, stdout, stderr) = run(&mut jvmmy(&["-cp", "tests/data/classes", "programs.Exit", &status.to_string()]));
+        assert_eq!(code, Some(status), "{}", stderr);
+        // Nothing after System.exit runs, and what was printed before it isn't lost
+        assert_eq!(stdout, "exiting\n");
+        assert_eq!(stderr, "");
+    }
+}
+
+#[test]
+fn uncaught_exception() {
+    let (status, stdout, stderr) = run(&mut jvmmy(&["-cp", "tests/data/classes", "programs.Exit"]));
+    assert_eq!(status, Some(1));
+    assert_eq!(stdout, "exiting\n");
+    assert_eq!(stderr, "Exception in thread \"main\" java.lang.IllegalStateException: no status\n\
+        \tat programs.Exit.fail(Exit.java:15)\n\
+        \tat programs.Exit.main(Exit.java:8)\n");
+}
+
+#[test]
+fn missing_main_class() {
+    let (status, stdout, stderr) = run(&mut jvmmy(&["-cp", "tests/data/classes", "programs.Missing"]));
+    assert_eq!(status, Some(1));
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("Could not find or load main class programs.Missing"), "{}", stderr);
+}
+
+#[test]
+fn class_path_wildcards() {
+    let class_path = format!("tests/data/classes{}tests/data/lib/*", SEPARATOR);
+    let (status, stdout, stderr) = run(&mut jvmmy(&["-cp", &class_path, "programs.Greet"]));
+    assert_eq!(status, Some(0), "{}", stderr);
+    assert_eq!(stdout, "Hello from a JAR\n");
+
+    // The wildcard is only for JARs, not the directory itself or what is in its subdirectories
+    for class_path in ["tests/data/classes", "tests/data/*"] {
+        let class_path = format!("tests/data/classes{}{}", SEPARATOR, class_path);
+        let (status, _, stderr) = run(&mut jvmmy(&["-cp", &class_path, "programs.Greet"]));
+        assert_eq!(status, Some(1));
+        assert!(stderr.starts_with("Exception in thread \"main\" java.lang.NoClassDefFoundError: programs/Greeting"),
+                "{}", stderr);
+    }
+}
+
+#[test]
+fn class_path_environment_variable() {
+    let class_path = format!("tests/data/lib/*{}tests/data/classes", SEPARATOR);
+    let (status, stdout, stderr) = run(jvmmy(&["programs.Greet"]).env("CLASSPATH", &class_path));
+    assert_eq!(status, Some(0), "{}", stderr);
+    assert_eq!(stdout, "Hello from a JAR\n");
+
+    // -cp takes its place
+    let (status, _, _) = run(jvmmy(&["-cp", "tests/data/lib/greeting.jar", "programs.Greet"]).env("CLASSPATH", &class_path));
+    assert_eq!(status, Some(1));
+
+    // Without either, classes are looked for in the current directory
+    let (status, stdout, stderr) = run(jvmmy(&["programs.Echo", "java.class.path"]).current_dir("tests/data/classes"));
+    assert_eq!(status, Some(0), "{}", stderr);
+    assert_eq!(stdout, "1 arguments\n[java.class.path] .\n");
+}
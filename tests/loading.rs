@@ -0,0 +1,167 @@
+// The loader stops at the first thing wrong with a class file, and has to say what it is, where in the file
+// it is, and what was being read there. The class files are put together byte by byte, so the offsets can be
+// worked out.
+
+use jvmmy::class::error::{ClassFormatError, ClassFormatErrorKind};
+use jvmmy::class::loading::ClassFileLoader;
+
+fn utf8_entry(bytes: &mut Vec<u8>, string: &str) {
+    bytes.push(1);
+    bytes.extend((string.len() as u16).to_be_bytes());
+    bytes.extend(string.as_bytes());
+}
+
+// A version 52 class file for the class Test. Its constant pool is #1 Utf8 Test and #2 Class #1, followed
+// by names as Utf8 entries from #3 on. It has no interfaces or fields.
+fn class_file(names: &[&str], methods: &[Vec<u8>], attributes: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 52];
+    bytes.extend((names.len() as u16 + 3).to_be_bytes());
+    utf8_entry(&mut bytes, "Test");
+    bytes.extend([7, 0, 1]);
+    for name in names {
+        utf8_entry(&mut bytes, name);
+    }
+    bytes.extend([0, 0x21, 0, 2, 0, 0, 0, 0, 0, 0]);
+    bytes.extend((methods.len() as u16).to_be_bytes());
+    bytes.extend(methods.concat());
+    bytes.extend((attributes.len() as u16).to_be_bytes());
+    bytes.extend(attributes.concat());
+    bytes
+}
+
+fn attribute(name_index: u16, info: &[u8]) -> Vec<u8> {
+    let mut bytes = name_index.to_be_bytes().to_vec();
+    bytes.extend((info.len() as u32).to_be_bytes());
+    bytes.extend(info);
+    bytes
+}
+
+// The info of a Code attribute that only returns
+fn code(attributes: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = vec![0, 0, 0, 1, 0, 0, 0, 1, 0xb1, 0, 0];
+    bytes.extend((attributes.len() as u16).to_be_bytes());
+    bytes.extend(attributes.concat());
+    bytes
+}
+
+// A public static method
+fn method(name_index: u16, descriptor_index: u16, attributes: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = vec![0, 0x09];
+    bytes.extend(name_index.to_be_bytes());
+    bytes.extend(descriptor_index.to_be_bytes());
+    bytes.extend((attributes.len() as u16).to_be_bytes());
+    bytes.extend(attributes.concat());
+    bytes
+}
+
+fn load_error(bytes: &[u8]) -> ClassFormatError {
+    ClassFileLoader::new(bytes).load().err().unwrap()
+}
+
+#[test]
+fn test_class_loads() {
+    let bytes = class_file(&[], &[], &[]);
+    let class_file = ClassFileLoader::new(&bytes).load().unwrap();
+    assert_eq!(class_file.constant_pool.count(), 3);
+    assert_eq!(class_file.this_class, 2);
+}
+
+#[test]
+fn truncated() {
+    let bytes = class_file(&[], &[], &[]);
+    // Nothing at all, then the file ends in the middle of "Test", then in the middle of this_class
+    for (length, wanted, available, offset, context) in [
+        (0, 4, 0, 0, "magic"),
+        (15, 4, 2, 13, "constant_pool[1]"),
+        (23, 2, 1, 22, "this_class"),
+    ] {
+        let error = load_error(&bytes[..length]);
+        assert!(matches!(error.kind, ClassFormatErrorKind::Truncated { wanted: w, available: a }
+            if w == wanted && a == available), "{}", error);
+        assert_eq!((error.offset, error.context.as_str()), (Some(offset), context));
+    }
+}
+
+#[test]
+fn unknown_constant_pool_tag() {
+    let mut bytes = class_file(&[], &[], &[]);
+    // Tag 2 has never been used, it would be the Class entry at #2
+    bytes[17] = 2;
+    let error = load_error(&bytes);
+    assert!(matches!(error.kind, ClassFormatErrorKind::UnknownConstantPoolTag(2)), "{}", error);
+    assert_eq!((error.offset, error.context.as_str()), (Some(17), "constant_pool[2]"));
+}
+
+#[test]
+fn extra_bytes_at_the_end() {
+    let mut bytes = class_file(&[], &[], &[]);
+    let length = bytes.len();
+    bytes.extend([0, 0, 0]);
+    let error = load_error(&bytes);
+    assert!(matches!(error.kind, ClassFormatErrorKind::ExtraBytes(3)), "{}", error);
+    assert_eq!((error.offset, error.context.as_str()), (Some(length), ""));
+}
+
+#[test]
+fn bad_stack_map_frame_type() {
+    // A same frame, then 128, the first of the frame types reserved for future use
+    let stack_map_table = attribute(6, &[0, 2, 0, 128]);
+    let f = method(3, 4, &[attribute(5, &code(&[stack_map_table]))]);
+    let bytes = class_file(&["f", "()V", "Code", "StackMapTable"], &[f], &[]);
+    let error = load_error(&bytes);
+    assert!(matches!(error.kind, ClassFormatErrorKind::BadStackMapFrameType(128)), "{}", error);
+    // Only the class's attributes_count comes after it
+    assert_eq!(error.offset, Some(bytes.len() - 3));
+    assert_eq!(error.context, "method #0 attribute Code attribute StackMapTable entries[1]");
+}
+
+#[test]
+fn bad_element_value_tag() {
+    // An annotation with x = {a constant, a value with the tag 'x'}, and 'x' is none of the element value
+    // tags. The loader doesn't look at what the indexes in it point at.
+    let annotations = [0, 1, 0, 2, 0, 1, 0, 4, b'[', 0, 2, b'I', 0, 2, b'x'];
+    let bytes = class_file(&["RuntimeVisibleAnnotations", "x"], &[], &[attribute(3, &annotations)]);
+    let error = load_error(&bytes);
+    assert!(matches!(error.kind, ClassFormatErrorKind::BadElementValueTag(b'x')), "{}", error);
+    assert_eq!(error.offset, Some(bytes.len() - 1));
+    assert_eq!(error.context, "attribute RuntimeVisibleAnnotations annotations[0] element_value_pairs[0] values[1]");
+}
+
+#[test]
+fn attribute_length_mismatch() {
+    // A SourceFile attribute is a u16, so an attribute_length of 4 leaves two bytes that weren't read
+    let bytes = class_file(&["SourceFile"], &[], &[attribute(3, &[0, 1, 0, 0])]);
+    let error = load_error(&bytes);
+    assert!(matches!(error.kind, ClassFormatErrorKind::AttributeLengthMismatch { attribute_length: 4, consumed: 2 }),
+            "{}", error);
+    assert_eq!((error.offset, error.context.as_str()), (Some(bytes.len() - 4), "attribute SourceFile"));
+
+    // One of 1 has the u16 read past the end of the attribute, into the byte after it
+    let mut bytes = class_file(&["SourceFile"], &[], &[attribute(3, &[0])]);
+    bytes.push(1);
+    let error = load_error(&bytes);
+    assert!(matches!(error.kind, ClassFormatErrorKind::AttributeLengthMismatch { attribute_length: 1, consumed: 2 }),
+            "{}", error);
+    assert_eq!((error.offset, error.context.as_str()), (Some(bytes.len() - 2), "attribute SourceFile"));
+}
+
+#[test]
+fn attributes_nested_too_deeply() {
+    // Code attributes in each other's attributes, in a method. The method's attributes are the first level,
+    // so 7 of them fit in the 8 levels and 8 don't.
+    let nested = |depth: usize| {
+        let mut attributes = Vec::new();
+        for _ in 0..depth {
+            attributes = vec![attribute(5, &code(&attributes))];
+        }
+        class_file(&["f", "()V", "Code"], &[method(3, 4, &attributes)], &[])
+    };
+    assert!(ClassFileLoader::new(&nested(7)).load().is_ok());
+
+    let bytes = nested(8);
+    let error = load_error(&bytes);
+    assert!(matches!(error.kind, ClassFormatErrorKind::NestedTooDeeply { what: "attributes", max: 8 }), "{}", error);
+    // Right after the innermost attributes_count, which only the class's attributes_count comes after
+    assert_eq!(error.offset, Some(bytes.len() - 2));
+    assert_eq!(error.context, format!("method #0{}", " attribute Code".repeat(8)));
+}
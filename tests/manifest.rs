@@ -0,0 +1,48 @@
+// A manifest's lines are at most 72 bytes, so a long Class-Path goes on over continuation lines that start
+// with a space. Those have to come back together before the value is split into URLs.
+
+use std::path::{Path, PathBuf};
+
+use jvmmy::classpath::ClassPathEntry;
+use jvmmy::classpath::manifest::Manifest;
+
+#[test]
+fn continuation_lines() {
+    let bytes = b"Manifest-Version: 1.0\r\n\
+        Class-Path: lib/first.jar lib/sec\r\n \
+        ond.jar classes/ lib/thi\n rd.jar\r\n\
+        Main-Class: app.Main\r\n\
+        \r\n\
+        Name: app/Main.class\r\n\
+        Class-Path: ignored.jar\r\n";
+    let manifest = Manifest::parse(bytes);
+    assert_eq!(manifest.main_attributes, [
+        ("Manifest-Version".to_string(), "1.0".to_string()),
+        ("Class-Path".to_string(), "lib/first.jar lib/second.jar classes/ lib/third.jar".to_string()),
+        ("Main-Class".to_string(), "app.Main".to_string()),
+    ]);
+    assert_eq!(manifest.main_attribute("main-class"), Some("app.Main"));
+    assert_eq!(manifest.class_path(Path::new("app/app.jar")), [
+        ClassPathEntry::Archive(PathBuf::from("app/lib/first.jar")),
+        ClassPathEntry::Archive(PathBuf::from("app/lib/second.jar")),
+        ClassPathEntry::Directory(PathBuf::from("app/classes")),
+        ClassPathEntry::Archive(PathBuf::from("app/lib/third.jar")),
+    ]);
+}
+
+#[test]
+fn class_path_urls() {
+    let manifest = Manifest::parse(b"Class-Path: file:a%20b.jar http://example.com/c.jar C:d.jar bad%2.jar\n");
+    assert_eq!(manifest.class_path(Path::new("e.jar")), [
+        ClassPathEntry::Archive(PathBuf::from("a b.jar")),
+        ClassPathEntry::Archive(PathBuf::from("C:d.jar")),
+    ]);
+}
+
+#[test]
+fn characters_split_across_lines() {
+    // "ü" is c3 bc, and the line break comes between the two bytes, the way a writer that counts bytes puts it
+    let manifest = Manifest::parse(b"\xef\xbb\xbfClass-Path: lib/m\xc3\r\n \xbcller.jar\rMain-Class: \xe2\x82\n \xac\n");
+    assert_eq!(manifest.main_attribute("Class-Path"), Some("lib/müller.jar"));
+    assert_eq!(manifest.main_attribute("Main-Class"), Some("€"));
+}
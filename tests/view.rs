@@ -0,0 +1,78 @@
+// ClassFileView has to turn down the same malformed class files ClassFileLoader does, and read the same
+// strings from them.
+
+use std::borrow::Cow;
+
+use jvmmy::class::error::ClassFormatErrorKind;
+use jvmmy::class::view::ClassFileView;
+
+// The start of a version 52 class file, up to and including constant_pool_count
+fn header(constant_pool_count: u16) -> Vec<u8> {
+    let mut bytes = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 52];
+    bytes.extend(constant_pool_count.to_be_bytes());
+    bytes
+}
+
+fn long_entry(bytes: &mut Vec<u8>) {
+    bytes.push(5);
+    bytes.extend(1u64.to_be_bytes());
+}
+
+#[test]
+fn long_in_the_last_slot_is_an_error() {
+    let mut bytes = header(2);
+    long_entry(&mut bytes);
+    let error = ClassFileView::parse(&bytes).err().unwrap();
+    assert!(matches!(error.kind, ClassFormatErrorKind::ConstantPoolOverrun { index: 1, constant_pool_count: 2 }), "{}", error);
+}
+
+#[test]
+fn long_in_the_last_slot_of_the_largest_pool_is_an_error() {
+    let mut bytes = header(u16::MAX);
+    // Integers in slots 1 to 65533, which puts the Long at 65534 and its second slot at 65535
+    for _ in 1..u16::MAX - 1 {
+        bytes.push(3);
+        bytes.extend(0u32.to_be_bytes());
+    }
+    long_entry(&mut bytes);
+    let error = ClassFileView::parse(&bytes).err().unwrap();
+    assert!(matches!(error.kind, ClassFormatErrorKind::ConstantPoolOverrun { index: 65534, constant_pool_count: 65535 }), "{}", error);
+}
+
+
+fn utf8_entry(bytes: &mut Vec<u8>, string: &[u8]) {
+    bytes.push(1);
+    bytes.extend((string.len() as u16).to_be_bytes());
+    bytes.extend(string);
+}
+
+#[test]
+fn names_are_checked_as_modified_utf8() {
+    let strings: [&[u8]; 6] = [
+        b"plain",
+        // NUL and U+1F600 the modified UTF-8 way
+        b"a\xc0\x80b",
+        b"\xed\xa0\xbd\xed\xb8\x80",
+        // NUL and U+1F600 the UTF-8 way, which modified UTF-8 doesn't allow
+        b"a\x00b",
+        b"\xf0\x9f\x98\x80",
+        // Every byte below 0xf0, but not UTF-8 either
+        b"\xe0\x80",
+    ];
+    let mut bytes = header(strings.len() as u16 + 2);
+    for string in strings {
+        utf8_entry(&mut bytes, string);
+    }
+    // A Class entry for this_class, then no interfaces, fields, methods or attributes
+    bytes.extend([7, 0, 1]);
+    bytes.extend([0, 0x21, 0, strings.len() as u8 + 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    let view = ClassFileView::parse(&bytes).unwrap();
+
+    assert!(matches!(view.find_name(1).unwrap(), Cow::Borrowed("plain")));
+    assert!(matches!(view.find_name(2).unwrap(), Cow::Owned(string) if string == "a\0b"));
+    assert!(matches!(view.find_name(3).unwrap(), Cow::Owned(string) if string == "\u{1f600}"));
+    for index in 4..=6 {
+        let error = view.find_name(index).unwrap_err();
+        assert!(matches!(error.kind, ClassFormatErrorKind::InvalidModifiedUtf8 { .. }), "constant_pool[{}]: {}", index, error);
+    }
+}
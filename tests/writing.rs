@@ -0,0 +1,171 @@
+// Loading a class file and writing it back out has to give the same bytes, and loading those the same
+// ClassFile. tests/data/classes is compiled from tests/data/src with javac -g.
+
+use std::fs;
+
+use jvmmy::attr::{Attribute, AttributeInfo, StackMapFrameKind, VerificationTypeInfo};
+use jvmmy::class::ClassFile;
+use jvmmy::class::error::ClassFormatErrorKind;
+use jvmmy::class::loading::ClassFileLoader;
+use jvmmy::constant_pool::ConstantPoolInfo;
+use jvmmy::util::mutf8::ModifiedUtf8;
+
+fn round_trip(path: &str) -> ClassFile {
+    let bytes = fs::read(path).unwrap();
+    let class_file = ClassFileLoader::new(&bytes).load().unwrap();
+    let written = class_file.to_bytes().unwrap();
+    assert!(written == bytes, "{} was written back differently", path);
+    let reloaded = ClassFileLoader::new(&written).load().unwrap();
+    assert_eq!(reloaded, class_file, "{} loaded differently after writing", path);
+    class_file
+}
+
+// Every attribute of the class, its fields, methods, Code attributes and record components
+fn all_attributes(class_file: &ClassFile) -> Vec<&Attribute> {
+    let mut attributes: Vec<&Attribute> = class_file.attributes.iter()
+        .chain(class_file.fields.iter().flat_map(|field| &field.attributes))
+        .chain(class_file.methods.iter().flat_map(|method| &method.attributes))
+        .collect();
+    let mut i = 0;
+    while i < attributes.len() {
+        match &attributes[i].info {
+            AttributeInfo::Code { attributes: nested, .. } => attributes.extend(nested),
+            AttributeInfo::Record { components } =>
+                attributes.extend(components.iter().flat_map(|component| &component.attributes)),
+            _ => {}
+        }
+        i += 1;
+    }
+    attributes
+}
+
+fn has_attribute(class_file: &ClassFile, name: &str) -> bool {
+    all_attributes(class_file).iter().any(|attribute| attribute.info.name() == name)
+}
+
+#[test]
+fn hello_world() {
+    round_trip("HelloWorld.class");
+}
+
+#[test]
+fn one_plus_one() {
+    round_trip("OnePlusOne.class");
+}
+
+#[test]
+fn long_and_double_constants() {
+    let class_file = round_trip("tests/data/classes/features/Features.class");
+    let constants: Vec<_> = class_file.constant_pool.iter().map(|(_, info)| info).collect();
+    assert!(constants.iter().any(|info| matches!(info, ConstantPoolInfo::Long { .. })));
+    assert!(constants.iter().any(|info| matches!(info, ConstantPoolInfo::Double { .. })));
+}
+
+#[test]
+fn stack_map_table() {
+    let class_file = round_trip("tests/data/classes/features/Features.class");
+    assert!(has_attribute(&class_file, "StackMapTable"));
+}
+
+#[test]
+fn annotations() {
+    let class_file = round_trip("tests/data/classes/features/Features.class");
+    for name in ["RuntimeVisibleAnnotations", "RuntimeVisibleParameterAnnotations", "RuntimeInvisibleTypeAnnotations"] {
+        assert!(has_attribute(&class_file, name), "no {}", name);
+    }
+    let info = round_trip("tests/data/classes/features/Features$Info.class");
+    assert!(has_attribute(&info, "AnnotationDefault"));
+}
+
+#[test]
+fn module_info() {
+    let class_file = round_trip("tests/data/classes/module-info.class");
+    assert!(class_file.access_flags.is_module());
+    assert!(has_attribute(&class_file, "Module"));
+}
+
+#[test]
+fn nested_classes() {
+    for entry in fs::read_dir("tests/data/classes/features").unwrap() {
+        round_trip(entry.unwrap().path().to_str().unwrap());
+    }
+}
+
+#[test]
+fn chop_and_append_frames_hold_1_to_3_locals() {
+    let bytes = fs::read("tests/data/classes/features/Features.class").unwrap();
+    let class_file = ClassFileLoader::new(&bytes).load().unwrap();
+    let bad_frames = [
+        StackMapFrameKind::Chop { absent_locals: 0 },
+        StackMapFrameKind::Chop { absent_locals: 4 },
+        StackMapFrameKind::Chop { absent_locals: 255 },
+        StackMapFrameKind::Append { locals: Vec::new() },
+        StackMapFrameKind::Append { locals: vec![VerificationTypeInfo::Integer; 4] },
+        StackMapFrameKind::Append { locals: vec![VerificationTypeInfo::Integer; 300] },
+    ];
+    for kind in bad_frames {
+        let mut class_file = class_file.clone();
+        let frames = class_file.methods.iter_mut()
+            .flat_map(|method| &mut method.attributes)
+            .find_map(|attribute| match &mut attribute.info {
+                AttributeInfo::Code { attributes, .. } => attributes.iter_mut().find_map(|attribute| match &mut attribute.info {
+                    AttributeInfo::StackMapTable { entries } => Some(entries),
+                    _ => None,
+                }),
+                _ => None,
+            })
+            .unwrap();
+        frames[0].kind = kind;
+        let error = class_file.to_bytes().unwrap_err();
+        assert!(matches!(error.kind, ClassFormatErrorKind::BadStackMapFrame(_)), "{}", error);
+    }
+}
+
+#[test]
+fn lengths_and_counts_that_dont_fit_are_errors() {
+    let bytes = fs::read("HelloWorld.class").unwrap();
+    let class_file = ClassFileLoader::new(&bytes).load().unwrap();
+
+    let mut long_string = class_file.clone();
+    long_string.constant_pool.push(ConstantPoolInfo::Utf8 { string: ModifiedUtf8::from("x".repeat(65536).as_str()) }).unwrap();
+    let error = long_string.to_bytes().unwrap_err();
+    assert!(matches!(error.kind, ClassFormatErrorKind::CountOverflow { count: 65536, max: 65535 }), "{}", error);
+
+    let mut many_interfaces = class_file.clone();
+    many_interfaces.interfaces = vec![class_file.this_class; 65536];
+    let error = many_interfaces.to_bytes().unwrap_err();
+    assert!(matches!(error.kind, ClassFormatErrorKind::CountOverflow { count: 65536, max: 65535 }), "{}", error);
+
+    // Exactly at the limit still works
+    let mut longest_string = class_file.clone();
+    longest_string.constant_pool.push(ConstantPoolInfo::Utf8 { string: ModifiedUtf8::from("x".repeat(65535).as_str()) }).unwrap();
+    let written = longest_string.to_bytes().unwrap();
+    assert_eq!(ClassFileLoader::new(&written).load().unwrap(), longest_string);
+}
+
+#[test]
+fn attributes_keep_their_name_index() {
+    let bytes = fs::read("HelloWorld.class").unwrap();
+    let mut class_file = ClassFileLoader::new(&bytes).load().unwrap();
+    // A second Utf8 "Code" that the first method's Code attribute points at instead of the one javac used
+    let duplicate = class_file.constant_pool.push(ConstantPoolInfo::Utf8 { string: ModifiedUtf8::from("Code") }).unwrap();
+    let code = class_file.methods[0].attributes.iter_mut()
+        .find(|attribute| matches!(attribute.info, AttributeInfo::Code { .. }))
+        .unwrap();
+    assert_ne!(code.name_index, duplicate);
+    code.name_index = duplicate;
+    let written = class_file.to_bytes().unwrap();
+    let reloaded = ClassFileLoader::new(&written).load().unwrap();
+    assert_eq!(reloaded, class_file);
+
+    // Built in code, the name is looked up
+    let mut built = class_file.clone();
+    built.methods[0].attributes.iter_mut().for_each(|attribute| attribute.name_index = 0);
+    assert!(ClassFileLoader::new(&built.to_bytes().unwrap()).load().is_ok());
+
+    // Pointing at some other Utf8 is an error
+    let mut mismatched = class_file.clone();
+    mismatched.methods[0].attributes[0].name_index = class_file.methods[0].name_index;
+    let error = mismatched.to_bytes().unwrap_err();
+    assert!(matches!(error.kind, ClassFormatErrorKind::AttributeNameMismatch { .. }), "{}", error);
+}
@@ -0,0 +1,83 @@
+// Every entry of the test JARs has to read back as the class file it was made from, whether it is stored,
+// deflated, or in a zip64 archive. The JARs hold tests/data/classes/features, made with Info-ZIP's zip
+// -X -D, with -0 for stored.jar and -fz for zip64.jar.
+
+use std::fs;
+use std::path::Path;
+
+use jvmmy::zip::{CompressionMethod, ZipArchive, ZipErrorKind};
+
+fn archive(name: &str) -> ZipArchive<Vec<u8>> {
+    ZipArchive::new(fs::read(Path::new("tests/data/jars").join(name)).unwrap()).unwrap()
+}
+
+// Checks the archive holds exactly the class files, and gives back what they hold
+fn assert_same_as_classes(archive: &ZipArchive<Vec<u8>>, method: CompressionMethod) {
+    let mut count = 0;
+    for entry in fs::read_dir("tests/data/classes/features").unwrap() {
+        let path = entry.unwrap().path();
+        let name = format!("features/{}", path.file_name().unwrap().to_str().unwrap());
+        assert_eq!(archive.entry(&name).map(|entry| entry.method), Some(method), "{}", name);
+        assert_eq!(archive.read(&name).unwrap().as_deref(), Some(&fs::read(&path).unwrap()[..]), "{}", name);
+        count += 1;
+    }
+    assert_eq!(archive.entries.len(), count);
+}
+
+#[test]
+fn stored_entries() {
+    assert_same_as_classes(&archive("stored.jar"), CompressionMethod::Stored);
+}
+
+#[test]
+fn deflated_entries() {
+    assert_same_as_classes(&archive("deflated.jar"), CompressionMethod::Deflated);
+}
+
+#[test]
+fn zip64_entries() {
+    // The sizes are in zip64 extra fields, and the central directory is found through the zip64 end record
+    let bytes = fs::read("tests/data/jars/zip64.jar").unwrap();
+    assert!(bytes.windows(4).any(|window| window == [0x50, 0x4b, 0x06, 0x06]));
+    assert_same_as_classes(&archive("zip64.jar"), CompressionMethod::Deflated);
+}
+
+#[test]
+fn lookups_that_miss() {
+    let archive = archive("deflated.jar");
+    for name in ["features/Missing.class", "features/features.class", "Features.class", "features", "features/", ""] {
+        assert!(archive.entry(name).is_none(), "{}", name);
+        assert!(matches!(archive.read(name), Ok(None)), "{}", name);
+    }
+}
+
+// Sets the uncompressed size in the central directory entry of the first entry
+fn claim_size(bytes: &mut [u8], size: u32) {
+    let central = bytes.windows(4).position(|window| window == [0x50, 0x4b, 0x01, 0x02]).unwrap();
+    bytes[central + 24..central + 28].copy_from_slice(&size.to_le_bytes());
+}
+
+#[test]
+fn sizes_that_dont_match() {
+    let mut bytes = fs::read("tests/data/jars/deflated.jar").unwrap();
+    let actual = fs::metadata("tests/data/classes/features/Features$1.class").unwrap().len();
+    // Anything from a claimed size near the limit, which isn't allocated, to one byte too many
+    for size in [(1 << 30) - 1, 1 << 20, actual as u32 + 1] {
+        claim_size(&mut bytes, size);
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let error = archive.read_entry(&archive.entries[0]).unwrap_err();
+        assert_eq!(error.kind, ZipErrorKind::SizeMismatch { expected: size as u64, actual }, "{}", error);
+        assert_eq!(error.context, "entry features/Features$1.class");
+    }
+
+    // One byte too few stops decompressing there
+    claim_size(&mut bytes, actual as u32 - 1);
+    let archive = ZipArchive::new(&bytes[..]).unwrap();
+    let error = archive.read_entry(&archive.entries[0]).unwrap_err();
+    assert!(matches!(error.kind, ZipErrorKind::BadDeflate(_)), "{}", error);
+
+    claim_size(&mut bytes, (1 << 30) + 1);
+    let archive = ZipArchive::new(&bytes[..]).unwrap();
+    let error = archive.read_entry(&archive.entries[0]).unwrap_err();
+    assert_eq!(error.kind, ZipErrorKind::TooLarge((1 << 30) + 1));
+}